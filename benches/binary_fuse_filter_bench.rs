@@ -0,0 +1,85 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main, Throughput};
+use level_db_rust::traits::filter_policy_trait::FilterPolicy;
+use level_db_rust::util::filter_policy_binary_fuse::BinaryFuseFilterPolicy;
+use level_db_rust::util::filter_policy_blocked_bloom::BlockedBloomFilterPolicy;
+use level_db_rust::util::filter_policy_bloom::BloomFilterPolicy;
+
+const NUM_LOOKUPS: u64 = 100_000;
+
+fn policies() -> [(&'static str, Box<dyn FilterPolicy>); 4] {
+    [
+        ("bloom", Box::new(BloomFilterPolicy::new())),
+        ("blocked_bloom", Box::new(BlockedBloomFilterPolicy::new())),
+        ("binary_fuse", Box::new(BinaryFuseFilterPolicy::new())),
+        ("binary_fuse_8", Box::new(BinaryFuseFilterPolicy::new_with_fingerprint_bits(8))),
+    ]
+}
+
+fn keys(num_keys: usize) -> Vec<[u8; 8]> {
+    (0..num_keys as u64).map(|i| i.to_le_bytes()).collect()
+}
+
+/// 每个 key 占用的 bit 数及误判率
+///
+/// 误判率不是时间, 无法由 criterion 统计, 在 bench 开始前打印出来
+pub fn false_positive_rate_report(_c: &mut Criterion) {
+    println!("{:<16}{:>12}{:>16}{:>12}", "policy", "num_keys", "bits_per_key", "fpr(%)");
+    for num_keys in [1_000_usize, 100_000, 1_000_000] {
+        let keys = keys(num_keys);
+        let key_refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+        for (name, policy) in policies().iter() {
+            let mut filter = Vec::new();
+            policy.create_filter(&key_refs, &mut filter);
+            let false_positives = (0..NUM_LOOKUPS)
+                .filter(|i| policy.key_may_match(&(i + (1 << 40)).to_le_bytes(), &filter))
+                .count();
+            println!("{:<16}{:>12}{:>16.2}{:>12.3}", name, num_keys,
+                     filter.len() as f64 * 8.0 / num_keys as f64,
+                     false_positives as f64 * 100.0 / NUM_LOOKUPS as f64);
+        }
+    }
+}
+
+/// 构造 filter 的耗时
+pub fn create_filter_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("create_filter");
+    for num_keys in [1_000_usize, 100_000] {
+        let keys = keys(num_keys);
+        let key_refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+        group.throughput(Throughput::Elements(num_keys as u64));
+        for (name, policy) in policies().iter() {
+            group.bench_with_input(BenchmarkId::new(*name, num_keys), &key_refs, |b, key_refs| {
+                let mut result = Vec::new();
+                b.iter(|| {
+                    result.clear();
+                    policy.create_filter(key_refs, &mut result);
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+/// 查询的耗时, 查询的 key 一半存在, 一半不存在
+pub fn key_may_match_bench(c: &mut Criterion) {
+    const NUM_KEYS: usize = 1_000_000;
+    let keys = keys(NUM_KEYS);
+    let key_refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+    let lookups: Vec<[u8; 8]> = (0..NUM_LOOKUPS)
+        .map(|i| ((i * 7919) % (2 * NUM_KEYS as u64)).to_le_bytes())
+        .collect();
+
+    let mut group = c.benchmark_group("key_may_match");
+    group.throughput(Throughput::Elements(NUM_LOOKUPS));
+    for (name, policy) in policies().iter() {
+        let mut filter = Vec::new();
+        policy.create_filter(&key_refs, &mut filter);
+        group.bench_function(*name, |b| {
+            b.iter(|| lookups.iter().filter(|k| policy.key_may_match(k.as_slice(), &filter)).count());
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, false_positive_rate_report, create_filter_bench, key_may_match_bench);
+criterion_main!(benches);
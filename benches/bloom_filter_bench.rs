@@ -1,49 +1,72 @@
-use std::borrow::Borrow;
-use criterion::{Criterion, criterion_group, criterion_main};
-use rand::Rng;
-use level_db_rust::util::coding::Coding;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main, Throughput};
+use level_db_rust::traits::filter_policy_trait::FilterPolicy;
+use level_db_rust::util::filter_policy_blocked_bloom::BlockedBloomFilterPolicy;
 use level_db_rust::util::filter_policy_bloom::BloomFilterPolicy;
-use level_db_rust::util::slice::Slice;
-
-const KEY_SIZE: usize = 10_000_000;
-const BENCH_TIMES: usize = 128;
 
 /// BloomFilter bench Test
+///
+/// create_filter 追加到复用的 buffer 中, 与 FilterBlockBuilder 的用法相同
 pub fn bloom_filter_bench(c: &mut Criterion) {
-    let data: Vec<&Slice> = vec![&Slice::default(); KEY_SIZE];
-    for i in 0..KEY_SIZE {
-        data[i] = format!("{}", i).into();
-    }
+    let policy = BloomFilterPolicy::new();
+    let mut group = c.benchmark_group("bloom_filter");
+    for num_keys in [100_usize, 10_000] {
+        let keys: Vec<Vec<u8>> = (0..num_keys).map(|i| format!("key{:08}", i).into_bytes()).collect();
+        let key_refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+        group.throughput(Throughput::Elements(num_keys as u64));
+        group.bench_with_input(BenchmarkId::new("create_filter", num_keys), &key_refs, |b, key_refs| {
+            let mut result = Vec::new();
+            b.iter(|| {
+                result.clear();
+                policy.create_filter(key_refs, &mut result);
+            });
+        });
 
-    let mut every_bench_times = [0; BENCH_TIMES];
-    for i in 0..BENCH_TIMES {
-        every_bench_times[i] = rnd.gen_range(32..20480);
+        let mut filter = Vec::new();
+        policy.create_filter(&key_refs, &mut filter);
+        group.bench_with_input(BenchmarkId::new("key_may_match", num_keys), &key_refs, |b, key_refs| {
+            b.iter(|| key_refs.iter().filter(|k| policy.key_may_match(k, &filter)).count());
+        });
     }
+    group.finish();
+}
 
-    c.bench_function("default_test", |b| {
-        let mut i = 0;
-        b.iter(|| {
-            let filter = BloomFilterPolicy::new();
-            let bloom_filter_data = filter.create_filter_with_len(KEY_SIZE, data);
+/// BlockedBloomFilterPolicy 与 BloomFilterPolicy 的对比
+///
+/// 查询使用的 filter 约 5MB, 超过 L2 cache 的大小; 查询的 key 一半存在, 一半不存在
+pub fn filter_policy_compare_bench(c: &mut Criterion) {
+    const NUM_KEYS: usize = 4_000_000;
+    const NUM_LOOKUPS: usize = 10_000;
 
-            bench_default(filter, &bloom_filter_data, every_bench_times[i % BENCH_TIMES]);
-            i += 1;
-        });
-    });
-}
+    let keys: Vec<u8> = (0..NUM_KEYS as u64).flat_map(|i| i.to_le_bytes()).collect();
+    let key_refs: Vec<&[u8]> = keys.chunks(8).collect();
+    let lookups: Vec<[u8; 8]> = (0..NUM_LOOKUPS as u64)
+        .map(|i| ((i * 7919) % (2 * NUM_KEYS as u64)).to_le_bytes())
+        .collect();
 
-fn bench_default(filter: BloomFilterPolicy, bloom_filter_data: &Slice, record_count: usize) {
-    for j in 0..record_count {
-        let key_may_match = filter.key_may_match(format!("{}", i).into(), bloom_filter_data);
-        assert!(key_may_match)
-    }
+    let policies: [(&str, Box<dyn FilterPolicy>); 2] = [
+        ("bloom", Box::new(BloomFilterPolicy::new())),
+        ("blocked_bloom", Box::new(BlockedBloomFilterPolicy::new())),
+    ];
 
-    for j in (KEY_SIZE+1)..(KEY_SIZE+100) {
-        let key_may_match = filter.key_may_match(format!("{}", i).into(), bloom_filter_data);
-        // key_may_match 可能为 true， 可能为 false
-        println!("key_may_match:{}.", key_may_match)
+    let mut group = c.benchmark_group("filter_policy");
+    for (name, policy) in policies.iter() {
+        group.throughput(Throughput::Elements(NUM_LOOKUPS as u64));
+        group.bench_function(BenchmarkId::new("create_filter", name), |b| {
+            let mut result = Vec::new();
+            b.iter(|| {
+                result.clear();
+                policy.create_filter(&key_refs[..NUM_LOOKUPS], &mut result);
+            });
+        });
+
+        let mut filter = Vec::new();
+        policy.create_filter(&key_refs, &mut filter);
+        group.bench_function(BenchmarkId::new("key_may_match", name), |b| {
+            b.iter(|| lookups.iter().filter(|k| policy.key_may_match(k.as_slice(), &filter)).count());
+        });
     }
+    group.finish();
 }
 
-criterion_group!(benches, skiplist_bench);
-criterion_main!(benches);
\ No newline at end of file
+criterion_group!(benches, bloom_filter_bench, filter_policy_compare_bench);
+criterion_main!(benches);
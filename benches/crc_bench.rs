@@ -1,24 +1,28 @@
-use criterion::{Criterion, criterion_group, criterion_main};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main, Throughput};
 use rand::RngCore;
 use level_db_rust::util::crc::CRC;
 
-pub const SRC_DATA: [u8; 512] = [0; 512];
-
+/// CRC::value 在支持 crc32c 指令的 CPU 上使用硬件实现, CRC::extend_portable 为查表实现。
+/// crc32fast 计算的是 CRC32 (IEEE), 仅作为速度的参考
 pub fn default_crc_bench(c: &mut Criterion) {
     let mut rnd = rand::thread_rng();
-    c.bench_function("default_crc", |b| {
-        b.iter(|| {
-            rnd.fill_bytes(&mut SRC_DATA);
-            CRC::value(&SRC_DATA);
+    let mut group = c.benchmark_group("crc");
+    for size in [64_usize, 4096, 65536] {
+        let mut data = vec![0_u8; size];
+        rnd.fill_bytes(&mut data);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("default_crc", size), &data, |b, data| {
+            b.iter(|| CRC::value(data));
+        });
+        group.bench_with_input(BenchmarkId::new("portable_crc", size), &data, |b, data| {
+            b.iter(|| CRC::extend_portable(0, data));
         });
-    });
-    c.bench_function("crc32fast", |b| {
-        b.iter(|| {
-            rnd.fill_bytes(&mut SRC_DATA);
-            crc32fast::hash(&SRC_DATA);
+        group.bench_with_input(BenchmarkId::new("crc32fast", size), &data, |b, data| {
+            b.iter(|| crc32fast::hash(data));
         });
-    });
+    }
+    group.finish();
 }
 
 criterion_group!(benches, default_crc_bench);
-criterion_main!(benches);
\ No newline at end of file
+criterion_main!(benches);
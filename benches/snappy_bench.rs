@@ -0,0 +1,40 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main, Throughput};
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use level_db_rust::util::snappy::Snappy;
+
+/// 与 table 中 block 大小相近的数据
+const DATA_SIZE: usize = 4 * 1024;
+
+/// 随机字符串重复而成的数据, 压缩后的大小约为原来的 compressed_fraction
+fn compressible_data(rnd: &mut StdRng, compressed_fraction: f64, len: usize) -> Vec<u8> {
+    let raw_len = ((len as f64 * compressed_fraction) as usize).max(1);
+    let raw: Vec<u8> = (0..raw_len).map(|_| rnd.gen_range(b' '..=b'~')).collect();
+    raw.iter().cycle().take(len).cloned().collect()
+}
+
+pub fn snappy_bench(c: &mut Criterion) {
+    let mut rnd = StdRng::seed_from_u64(301);
+    let mut random = vec![0_u8; DATA_SIZE];
+    rnd.fill_bytes(&mut random);
+    let inputs = [
+        ("compressible", compressible_data(&mut rnd, 0.5, DATA_SIZE)),
+        ("random", random),
+    ];
+
+    let mut group = c.benchmark_group("snappy");
+    group.throughput(Throughput::Bytes(DATA_SIZE as u64));
+    for (name, input) in inputs.iter() {
+        group.bench_with_input(BenchmarkId::new("compress", name), input, |b, input| {
+            b.iter(|| Snappy::compress(input));
+        });
+        let compressed = Snappy::compress(input);
+        group.bench_with_input(BenchmarkId::new("decompress", name), &compressed, |b, compressed| {
+            b.iter(|| Snappy::decompress(compressed).unwrap());
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, snappy_bench);
+criterion_main!(benches);
@@ -0,0 +1,640 @@
+//! 与 LevelDB 的 db_bench 相同的基准测试
+//!
+//! ```text
+//! db_bench [--benchmarks=fillseq,readrandom,...] [--num=N] [--value_size=N] [--threads=N] ...
+//! ```
+//!
+//! 开始运行前会删除 --db 指定的数据库, fillseq、fillrandom、fillsync 及 fill100K 在写入前会重新创建数据库
+
+use std::path::PathBuf;
+use std::process::exit;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use level_db_rust::db::db::{destroy_db, DB};
+use level_db_rust::db::write_batch::WriteBatch;
+use level_db_rust::traits::filter_policy_trait::FilterPolicyPtr;
+use level_db_rust::util::cache::ShardLRUCache;
+use level_db_rust::util::crc::CRC;
+use level_db_rust::util::filter_policy_bloom::BloomFilterPolicy;
+use level_db_rust::util::histogram::Histogram;
+use level_db_rust::util::options::{Cache, Options, ReadOptions, WriteOptions};
+use level_db_rust::util::random::Random;
+use level_db_rust::util::slice::Slice;
+use level_db_rust::util::snappy::Snappy;
+
+const USAGE: &str = "\
+Usage: db_bench [flags]
+
+Flags:
+  --benchmarks=<a,b,...>     comma separated list of benchmarks to run, in order
+  --num=<n>                  number of key/values to place in the database (default 1000000)
+  --reads=<n>                number of read operations, default --num
+  --value_size=<n>           size of each value (default 100)
+  --compression_ratio=<f>    fraction of each value that stays after compression (default 0.5)
+  --threads=<n>              number of concurrent threads (default 1)
+  --cache_size=<n>           bytes of block cache, negative for the default cache (default -1)
+  --bloom_bits=<n>           bits per key of the bloom filter, negative for no filter (default -1)
+  --db=<path>                database directory (default /tmp/dbbench)
+
+Benchmarks:
+  fillseq        write N values in sequential key order in async mode
+  fillrandom     write N values in random key order in async mode
+  overwrite      overwrite N values in random key order in async mode
+  fillsync       write N/100 values in random key order in sync mode
+  fill100K       write N/1000 100K values in random order in async mode
+  readseq        read N times sequentially
+  readreverse    read N times in reverse order
+  readrandom     read N times in random order
+  readmissing    read N missing keys in random order
+  readhot        read N times in random order from 1% section of DB
+  seekrandom     N random seeks
+  deleteseq      delete N keys in sequential order
+  deleterandom   delete N keys in random order
+  compact        compact the entire DB
+  stats          print DB stats
+  crc32c         repeated crc32c of 4K of data
+  snappycomp     repeated snappy compression of 4K of data
+  snappyuncomp   repeated snappy uncompression of 4K of data
+";
+
+const DEFAULT_BENCHMARKS: &str = "fillseq,fillsync,fillrandom,overwrite,readrandom,readrandom,readseq,readreverse,\
+compact,readrandom,readseq,readreverse,crc32c,snappycomp,snappyuncomp";
+
+const KEY_SIZE: usize = 16;
+
+struct Flags {
+    benchmarks: Vec<String>,
+    num: usize,
+    reads: Option<usize>,
+    value_size: usize,
+    compression_ratio: f64,
+    threads: usize,
+    cache_size: i64,
+    bloom_bits: i32,
+    db: PathBuf,
+}
+
+fn parse_flags(args: impl Iterator<Item=String>) -> Result<Flags, String> {
+    let mut flags = Flags {
+        benchmarks: DEFAULT_BENCHMARKS.split(',').map(String::from).collect(),
+        num: 1000000,
+        reads: None,
+        value_size: 100,
+        compression_ratio: 0.5,
+        threads: 1,
+        cache_size: -1,
+        bloom_bits: -1,
+        db: PathBuf::from("/tmp/dbbench"),
+    };
+    fn parse<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+        value.parse().map_err(|_| format!("invalid value for --{}: {}", name, value))
+    }
+    for arg in args {
+        if arg == "-h" || arg == "--help" {
+            return Err(String::new());
+        }
+        let (name, value) = match arg.strip_prefix("--").and_then(|arg| arg.split_once('=')) {
+            Some(flag) => flag,
+            None => return Err(format!("invalid flag: {}", arg)),
+        };
+        match name {
+            "benchmarks" => flags.benchmarks = value.split(',').filter(|b| !b.is_empty()).map(String::from).collect(),
+            "num" => flags.num = parse(name, value)?,
+            "reads" => flags.reads = Some(parse(name, value)?),
+            "value_size" => flags.value_size = parse(name, value)?,
+            "compression_ratio" => flags.compression_ratio = parse(name, value)?,
+            "threads" => flags.threads = parse::<usize>(name, value)?.max(1),
+            "cache_size" => flags.cache_size = parse(name, value)?,
+            "bloom_bits" => flags.bloom_bits = parse(name, value)?,
+            "db" => flags.db = PathBuf::from(value),
+            _ => return Err(format!("invalid flag: {}", arg)),
+        }
+    }
+    Ok(flags)
+}
+
+/// 生成压缩率约为 compression_ratio 的数据
+struct RandomGenerator {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl RandomGenerator {
+    fn new(compression_ratio: f64) -> Self {
+        // We use a limited amount of data over and over again and ensure
+        // that it is larger than the compression window (32KB), and also
+        // large enough to serve all typical value sizes we want to write.
+        let mut rnd = Random::new(301);
+        let mut data = Vec::with_capacity(1048576 + 100);
+        while data.len() < 1048576 {
+            // Add a short fragment that is as compressible as specified
+            // by compression_ratio.
+            data.extend(compressible_bytes(&mut rnd, compression_ratio, 100));
+        }
+        Self { data, pos: 0 }
+    }
+
+    fn generate(&mut self, len: usize) -> &[u8] {
+        if self.pos + len > self.data.len() {
+            self.pos = 0;
+            assert!(len < self.data.len());
+        }
+        self.pos += len;
+        &self.data[self.pos - len..self.pos]
+    }
+}
+
+/// 长度为 len 的数据, 其中只有 len * compressed_fraction 个字节是随机的
+fn compressible_bytes(rnd: &mut Random, compressed_fraction: f64, len: usize) -> Vec<u8> {
+    let raw = ((len as f64 * compressed_fraction) as usize).max(1);
+    let raw_data: Vec<u8> = (0..raw).map(|_| b' ' + rnd.uniform(95) as u8).collect();
+    raw_data.iter().cycle().take(len).copied().collect()
+}
+
+/// 单个线程的统计, 结束后合并
+struct Stats {
+    start: Instant,
+    finish: Instant,
+    seconds: f64,
+    done: usize,
+    next_report: usize,
+    bytes: u64,
+    last_op_finish: Instant,
+    hist: Histogram,
+    message: String,
+}
+
+impl Stats {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            finish: now,
+            seconds: 0.0,
+            done: 0,
+            next_report: 100,
+            bytes: 0,
+            last_op_finish: now,
+            hist: Histogram::default(),
+            message: String::new(),
+        }
+    }
+
+    fn merge(&mut self, other: &Stats) {
+        self.hist.merge(&other.hist);
+        self.done += other.done;
+        self.bytes += other.bytes;
+        self.seconds += other.seconds;
+        self.start = self.start.min(other.start);
+        self.finish = self.finish.max(other.finish);
+
+        // Just keep the messages from one thread
+        if self.message.is_empty() {
+            self.message = other.message.clone();
+        }
+    }
+
+    fn stop(&mut self) {
+        self.finish = Instant::now();
+        self.seconds = (self.finish - self.start).as_secs_f64();
+    }
+
+    fn finished_single_op(&mut self) {
+        let now = Instant::now();
+        self.hist.add((now - self.last_op_finish).as_secs_f64() * 1e6);
+        self.last_op_finish = now;
+
+        self.done += 1;
+        if self.done >= self.next_report {
+            self.next_report += if self.next_report < 1000 { 100 }
+            else if self.next_report < 5000 { 500 }
+            else if self.next_report < 10000 { 1000 }
+            else if self.next_report < 50000 { 5000 }
+            else if self.next_report < 100000 { 10000 }
+            else if self.next_report < 500000 { 50000 }
+            else { 100000 };
+            eprint!("... finished {} ops{:30}\r", self.done, "");
+        }
+    }
+
+    fn report(&self, name: &str) {
+        // Pretend at least one op was done in case we are running a benchmark
+        // that does not call finished_single_op().
+        let done = self.done.max(1);
+
+        let mut extra = String::new();
+        if self.bytes > 0 {
+            // Rate is computed on actual elapsed time, not the sum of per-thread
+            // elapsed times.
+            let elapsed = (self.finish - self.start).as_secs_f64();
+            extra.push_str(&format!("{:6.1} MB/s", (self.bytes as f64 / 1048576.0) / elapsed));
+        }
+        if !self.message.is_empty() {
+            if !extra.is_empty() {
+                extra.push(' ');
+            }
+            extra.push_str(&self.message);
+        }
+
+        // On the other hand, per-op latency is computed on the sum of per-thread elapsed times.
+        let micros_per_op = self.seconds * 1e6 / done as f64;
+        let ops_per_sec = done as f64 / (self.finish - self.start).as_secs_f64();
+        println!("{:<12} : {:11.3} micros/op {:11.0} ops/sec;{}{}", name, micros_per_op, ops_per_sec,
+                 if extra.is_empty() { "" } else { " " }, extra);
+        println!("Microseconds per op:\n{}", self.hist);
+    }
+}
+
+/// 每个线程的状态
+struct ThreadState {
+    rand: Random,
+    stats: Stats,
+    gen: RandomGenerator,
+}
+
+type Method = fn(&Benchmark, &mut ThreadState);
+
+struct Benchmark {
+    flags: Flags,
+    cache: Option<Arc<Cache>>,
+    filter_policy: Option<FilterPolicyPtr>,
+    db: Option<DB>,
+    // 以下参数在每个测试开始前根据 flags 重置
+    num: usize,
+    value_size: usize,
+    entries_per_batch: usize,
+    reads: usize,
+    write_options: WriteOptions,
+}
+
+impl Benchmark {
+    fn new(flags: Flags) -> Self {
+        let cache = (flags.cache_size >= 0)
+            .then(|| ShardLRUCache::new_arc_with_capacity(flags.cache_size as usize, 0));
+        let filter_policy: Option<FilterPolicyPtr> = (flags.bloom_bits >= 0)
+            .then(|| Arc::new(Box::new(BloomFilterPolicy::new_with_bits_per_key(flags.bloom_bits as usize)) as _));
+        Self {
+            num: flags.num,
+            value_size: flags.value_size,
+            entries_per_batch: 1,
+            reads: flags.reads.unwrap_or(flags.num),
+            write_options: WriteOptions::default(),
+            flags,
+            cache,
+            filter_policy,
+            db: None,
+        }
+    }
+
+    /// 打开数据库使用的 Options, 所有的数据库共用同一个 block cache 及 filter policy
+    fn new_options(&self) -> Options {
+        Options {
+            create_if_missing: true,
+            block_cache: self.cache.clone(),
+            filter_policy: self.filter_policy.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn print_header(&self) {
+        let flags = &self.flags;
+        println!("Keys:       {} bytes each", KEY_SIZE);
+        println!("Values:     {} bytes each ({} bytes after compression)", flags.value_size,
+                 (flags.value_size as f64 * flags.compression_ratio + 0.5) as usize);
+        println!("Entries:    {}", flags.num);
+        println!("RawSize:    {:.1} MB (estimated)",
+                 ((KEY_SIZE + flags.value_size) * flags.num) as f64 / 1048576.0);
+        println!("FileSize:   {:.1} MB (estimated)",
+                 ((KEY_SIZE as f64 + flags.value_size as f64 * flags.compression_ratio) * flags.num as f64) / 1048576.0);
+        println!("Threads:    {}", flags.threads);
+        println!("Compression: {:?}", Options::default().compression);
+        match &self.cache {
+            Some(_) => println!("Block cache: {} bytes", flags.cache_size),
+            None => println!("Block cache: default (8388608 bytes)"),
+        }
+        match &self.filter_policy {
+            Some(policy) => println!("Filter:     {} ({} bits/key)", policy.name(), flags.bloom_bits),
+            None => println!("Filter:     none"),
+        }
+        if cfg!(debug_assertions) {
+            println!("WARNING: Optimization is disabled: benchmarks unnecessarily slow");
+        }
+        println!("------------------------------------------------");
+    }
+
+    fn run(&mut self) {
+        self.print_header();
+        if let Err(e) = destroy_db(&self.flags.db, &self.new_options()) {
+            eprintln!("destroy {} error: {}", self.flags.db.display(), e.get_msg());
+            exit(1);
+        }
+        self.open();
+
+        for name in self.flags.benchmarks.clone() {
+            // Reset parameters that may be overridden below
+            self.num = self.flags.num;
+            self.reads = self.flags.reads.unwrap_or(self.flags.num);
+            self.value_size = self.flags.value_size;
+            self.entries_per_batch = 1;
+            self.write_options = WriteOptions::default();
+
+            let mut fresh_db = false;
+            let method: Method = match name.as_str() {
+                "fillseq" => {
+                    fresh_db = true;
+                    Benchmark::write_seq
+                }
+                "fillrandom" => {
+                    fresh_db = true;
+                    Benchmark::write_random
+                }
+                "overwrite" => Benchmark::write_random,
+                "fillsync" => {
+                    fresh_db = true;
+                    self.num /= 100;
+                    self.write_options.sync = true;
+                    Benchmark::write_random
+                }
+                "fill100K" => {
+                    fresh_db = true;
+                    self.num /= 1000;
+                    self.value_size = 100 * 1000;
+                    Benchmark::write_random
+                }
+                "readseq" => Benchmark::read_sequential,
+                "readreverse" => Benchmark::read_reverse,
+                "readrandom" => Benchmark::read_random,
+                "readmissing" => Benchmark::read_missing,
+                "readhot" => Benchmark::read_hot,
+                "seekrandom" => Benchmark::seek_random,
+                "deleteseq" => Benchmark::delete_seq,
+                "deleterandom" => Benchmark::delete_random,
+                "compact" => Benchmark::compact,
+                "stats" => {
+                    self.print_stats("leveldb.stats");
+                    continue;
+                }
+                "crc32c" => Benchmark::crc32c,
+                "snappycomp" => Benchmark::snappy_compress,
+                "snappyuncomp" => Benchmark::snappy_uncompress,
+                name => {
+                    eprintln!("unknown benchmark '{}'", name);
+                    continue;
+                }
+            };
+
+            if fresh_db {
+                self.db = None;
+                if let Err(e) = destroy_db(&self.flags.db, &self.new_options()) {
+                    eprintln!("destroy {} error: {}", self.flags.db.display(), e.get_msg());
+                    exit(1);
+                }
+                self.open();
+            }
+            self.run_benchmark(&name, method);
+        }
+    }
+
+    fn open(&mut self) {
+        assert!(self.db.is_none());
+        match DB::open(self.new_options(), &self.flags.db) {
+            Ok(db) => self.db = Some(db),
+            Err(e) => {
+                eprintln!("open error: {}", e.get_msg());
+                exit(1);
+            }
+        }
+    }
+
+    fn db(&self) -> &DB {
+        self.db.as_ref().expect("db is not opened")
+    }
+
+    /// 以 flags.threads 个线程同时运行 method, 合并各个线程的统计后输出
+    fn run_benchmark(&self, name: &str, method: Method) {
+        let mut merged: Option<Stats> = None;
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.flags.threads).map(|index| scope.spawn(move || {
+                let mut state = ThreadState {
+                    rand: Random::new(1000 + index as u32),
+                    stats: Stats::new(),
+                    gen: RandomGenerator::new(self.flags.compression_ratio),
+                };
+                method(self, &mut state);
+                state.stats.stop();
+                state.stats
+            })).collect();
+            for handle in handles {
+                let stats = handle.join().expect("benchmark thread panicked");
+                match merged.as_mut() {
+                    Some(merged) => merged.merge(&stats),
+                    None => merged = Some(stats),
+                }
+            }
+        });
+        if let Some(stats) = merged {
+            stats.report(name);
+        }
+    }
+
+    fn write_seq(&self, thread: &mut ThreadState) {
+        self.do_write(thread, true);
+    }
+
+    fn write_random(&self, thread: &mut ThreadState) {
+        self.do_write(thread, false);
+    }
+
+    fn do_write(&self, thread: &mut ThreadState, seq: bool) {
+        if self.num != self.flags.num {
+            thread.stats.message = format!("({} ops)", self.num);
+        }
+
+        let mut bytes = 0;
+        for i in (0..self.num).step_by(self.entries_per_batch) {
+            let mut batch = WriteBatch::new();
+            for j in 0..self.entries_per_batch {
+                let k = if seq { i + j } else { thread.rand.uniform(self.flags.num as u32) as usize };
+                let key = format!("{:016}", k);
+                batch.put(key.as_bytes(), thread.gen.generate(self.value_size));
+                bytes += (self.value_size + key.len()) as u64;
+                thread.stats.finished_single_op();
+            }
+            if let Err(e) = self.db().write(&self.write_options, batch) {
+                eprintln!("put error: {}", e.get_msg());
+                exit(1);
+            }
+        }
+        thread.stats.bytes = bytes;
+    }
+
+    fn read_sequential(&self, thread: &mut ThreadState) {
+        let mut iter = self.db().new_iterator(&ReadOptions::default());
+        let mut i = 0;
+        let mut bytes = 0;
+        iter.seek_to_first();
+        while i < self.reads && iter.valid() {
+            bytes += (iter.key().len() + iter.value().len()) as u64;
+            thread.stats.finished_single_op();
+            i += 1;
+            iter.next();
+        }
+        thread.stats.bytes = bytes;
+    }
+
+    fn read_reverse(&self, thread: &mut ThreadState) {
+        let mut iter = self.db().new_iterator(&ReadOptions::default());
+        let mut i = 0;
+        let mut bytes = 0;
+        iter.seek_to_last();
+        while i < self.reads && iter.valid() {
+            bytes += (iter.key().len() + iter.value().len()) as u64;
+            thread.stats.finished_single_op();
+            i += 1;
+            iter.pre();
+        }
+        thread.stats.bytes = bytes;
+    }
+
+    fn read_random(&self, thread: &mut ThreadState) {
+        let options = ReadOptions::default();
+        let mut found = 0;
+        for _ in 0..self.reads {
+            let key = format!("{:016}", thread.rand.uniform(self.flags.num as u32));
+            if let Ok(Some(_)) = self.db().get(&options, key.as_bytes()) {
+                found += 1;
+            }
+            thread.stats.finished_single_op();
+        }
+        thread.stats.message = format!("({} of {} found)", found, self.num);
+    }
+
+    fn read_missing(&self, thread: &mut ThreadState) {
+        let options = ReadOptions::default();
+        for _ in 0..self.reads {
+            let key = format!("{:016}.", thread.rand.uniform(self.flags.num as u32));
+            let _ = self.db().get(&options, key.as_bytes());
+            thread.stats.finished_single_op();
+        }
+    }
+
+    fn read_hot(&self, thread: &mut ThreadState) {
+        let options = ReadOptions::default();
+        let range = (self.flags.num as u32).div_ceil(100).max(1);
+        for _ in 0..self.reads {
+            let key = format!("{:016}", thread.rand.uniform(range));
+            let _ = self.db().get(&options, key.as_bytes());
+            thread.stats.finished_single_op();
+        }
+    }
+
+    fn seek_random(&self, thread: &mut ThreadState) {
+        let options = ReadOptions::default();
+        let mut found = 0;
+        for _ in 0..self.reads {
+            let mut iter = self.db().new_iterator(&options);
+            let key = format!("{:016}", thread.rand.uniform(self.flags.num as u32));
+            iter.seek(&Slice::from(key.as_str()));
+            if iter.valid() && iter.key().as_ref() == key.as_bytes() {
+                found += 1;
+            }
+            thread.stats.finished_single_op();
+        }
+        thread.stats.message = format!("({} of {} found)", found, self.num);
+    }
+
+    fn delete_seq(&self, thread: &mut ThreadState) {
+        self.do_delete(thread, true);
+    }
+
+    fn delete_random(&self, thread: &mut ThreadState) {
+        self.do_delete(thread, false);
+    }
+
+    fn do_delete(&self, thread: &mut ThreadState, seq: bool) {
+        for i in (0..self.num).step_by(self.entries_per_batch) {
+            let mut batch = WriteBatch::new();
+            for j in 0..self.entries_per_batch {
+                let k = if seq { i + j } else { thread.rand.uniform(self.flags.num as u32) as usize };
+                batch.delete(format!("{:016}", k).as_bytes());
+                thread.stats.finished_single_op();
+            }
+            if let Err(e) = self.db().write(&self.write_options, batch) {
+                eprintln!("del error: {}", e.get_msg());
+                exit(1);
+            }
+        }
+    }
+
+    fn compact(&self, _thread: &mut ThreadState) {
+        if let Err(e) = self.db().compact_range(None, None) {
+            eprintln!("compact error: {}", e.get_msg());
+            exit(1);
+        }
+    }
+
+    fn print_stats(&self, key: &str) {
+        match self.db().get_property(Slice::from(key)) {
+            Some(stats) => println!("\n{}", stats.as_str()),
+            None => println!("\n(failed)"),
+        }
+    }
+
+    fn crc32c(&self, thread: &mut ThreadState) {
+        // Checksum about 500MB of data total
+        let size = 4096;
+        let data = vec![b'x'; size];
+        let mut bytes = 0;
+        let mut crc = 0;
+        while bytes < 500 * 1048576 {
+            crc = CRC::value(&data);
+            thread.stats.finished_single_op();
+            bytes += size as u64;
+        }
+        // Print so result is not dead
+        eprint!("... crc=0x{:x}\r", crc);
+        thread.stats.bytes = bytes;
+        thread.stats.message = "(4K per op)".to_string();
+    }
+
+    fn snappy_compress(&self, thread: &mut ThreadState) {
+        let input = thread.gen.generate(4096).to_vec();
+        let mut bytes = 0;
+        let mut produced = 0;
+        while bytes < 1024 * 1048576 {
+            produced += Snappy::compress(&input).len() as u64;
+            bytes += input.len() as u64;
+            thread.stats.finished_single_op();
+        }
+        thread.stats.message = format!("(output: {:.1}%)", (produced * 100) as f64 / bytes as f64);
+        thread.stats.bytes = bytes;
+    }
+
+    fn snappy_uncompress(&self, thread: &mut ThreadState) {
+        let input = thread.gen.generate(4096).to_vec();
+        let compressed = Snappy::compress(&input);
+        let mut bytes = 0;
+        while bytes < 1024 * 1048576 {
+            let uncompressed = Snappy::decompress(&compressed).expect("snappy decompress");
+            assert_eq!(input.len(), uncompressed.len());
+            bytes += input.len() as u64;
+            thread.stats.finished_single_op();
+        }
+        thread.stats.bytes = bytes;
+    }
+}
+
+fn main() {
+    let flags = match parse_flags(std::env::args().skip(1)) {
+        Ok(flags) => flags,
+        Err(msg) => {
+            if !msg.is_empty() {
+                eprintln!("{}", msg);
+            }
+            eprint!("{}", USAGE);
+            exit(1);
+        }
+    };
+    Benchmark::new(flags).run();
+}
@@ -0,0 +1,374 @@
+//! 数据库的命令行工具, 在数据库关闭时使用
+//!
+//! ```text
+//! ldb --db=<path> [--hex] <command> [args...]
+//! ```
+//!
+//! key 及 value 默认为 UTF-8 字符串, 以 0x 开头或者指定 --hex 时为十六进制
+
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use level_db_rust::db::db::DB;
+use level_db_rust::db::filename::{FileName, FileType};
+use level_db_rust::db::log_reader::LogReader;
+use level_db_rust::db::db_format::escape_string;
+use level_db_rust::db::repair::repair_db;
+use level_db_rust::db::version_edit::VersionEdit;
+use level_db_rust::db::write_batch::{WriteBatch, WriteBatchHandler};
+use level_db_rust::util::options::{Options, ReadOptions, WriteOptions};
+use level_db_rust::util::Result;
+use level_db_rust::util::slice::Slice;
+use level_db_rust::util::status::LevelError;
+
+const USAGE: &str = "\
+Usage: ldb --db=<path> [--hex] <command> [args...]
+
+Commands:
+  get <key>
+  put <key> <value>
+  delete <key>
+  scan [--from=<key>] [--to=<key>] [--limit=<n>]
+  batchput <key> <value> [<key> <value>...]
+  compact [--from=<key>] [--to=<key>]
+  property <name>
+  approxsize [--from=<key>] [--to=<key>]
+  dump_manifest [<MANIFEST file>]    default: the MANIFEST named by CURRENT
+  dump_wal [<log file>...]           default: every log file of the database
+  repair
+
+put and batchput create the database if it does not exist.
+Keys and values are UTF-8 strings, or hex when prefixed with 0x or when --hex is given.
+--hex also prints keys and values in hex.
+";
+
+struct Flags {
+    db: Option<PathBuf>,
+    hex: bool,
+    command: String,
+    args: Vec<String>,
+}
+
+fn parse_flags(args: impl Iterator<Item=String>) -> std::result::Result<Flags, String> {
+    let mut flags = Flags {
+        db: None,
+        hex: false,
+        command: String::new(),
+        args: vec![],
+    };
+    for arg in args {
+        if !flags.command.is_empty() {
+            flags.args.push(arg);
+        } else if let Some(db) = arg.strip_prefix("--db=") {
+            flags.db = Some(PathBuf::from(db));
+        } else if arg == "--hex" {
+            flags.hex = true;
+        } else if arg == "-h" || arg == "--help" {
+            return Err(String::new());
+        } else if arg.starts_with("--") {
+            return Err(format!("unknown option: {}", arg));
+        } else {
+            flags.command = arg;
+        }
+    }
+    if flags.command.is_empty() {
+        return Err("no command specified".to_string());
+    }
+    Ok(flags)
+}
+
+impl Flags {
+    fn db(&self) -> Result<&Path> {
+        self.db.as_deref().ok_or_else(|| LevelError::invalid_argument(
+            Slice::from(self.command.as_str()), Slice::from("--db=<path> is required")))
+    }
+
+    /// 解析 key 或 value, 以 0x 开头或者指定了 --hex 时按十六进制解析
+    fn parse_bytes(&self, arg: &str) -> Result<Vec<u8>> {
+        let hex = match arg.strip_prefix("0x") {
+            Some(hex) => hex,
+            None if self.hex => arg,
+            None => return Ok(arg.as_bytes().to_vec()),
+        };
+        if hex.len() % 2 != 0 {
+            return Err(LevelError::invalid_argument(Slice::from("odd length hex string"), Slice::from(arg)));
+        }
+        (0..hex.len()).step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| LevelError::invalid_argument(Slice::from("bad hex string"), Slice::from(arg))))
+            .collect()
+    }
+
+    fn format_bytes(&self, data: &[u8]) -> String {
+        if self.hex {
+            format!("0x{}", data.iter().map(|b| format!("{:02X}", b)).collect::<String>())
+        } else {
+            format!("'{}'", escape_string(data))
+        }
+    }
+
+    /// 检查参数的数量并解析其中的 key/value 及 --from/--to/--limit 选项
+    fn check_args(&self, min: usize, max: Option<usize>, options: &[&str]) -> Result<Args> {
+        let mut args = Args::default();
+        for arg in &self.args {
+            match arg.split_once('=') {
+                Some((name, value)) if arg.starts_with("--") => {
+                    if !options.contains(&name) {
+                        return Err(LevelError::invalid_argument(Slice::from("unknown option"), Slice::from(arg.as_str())));
+                    }
+                    match name {
+                        "--limit" => {
+                            args.limit = Some(value.parse::<usize>().map_err(|_| LevelError::invalid_argument(
+                                Slice::from("bad limit"), Slice::from(value)))?);
+                        }
+                        "--from" => args.from = Some(Slice::from_vec(self.parse_bytes(value)?)),
+                        _ => args.to = Some(Slice::from_vec(self.parse_bytes(value)?)),
+                    }
+                }
+                _ => args.positional.push(self.parse_bytes(arg)?),
+            }
+        }
+        if args.positional.len() < min || max.is_some_and(|max| args.positional.len() > max) {
+            return Err(LevelError::invalid_argument(Slice::from("wrong number of arguments"),
+                                                    Slice::from(self.command.as_str())));
+        }
+        Ok(args)
+    }
+}
+
+/// 解析后的命令参数
+#[derive(Default)]
+struct Args {
+    positional: Vec<Vec<u8>>,
+    from: Option<Slice>,
+    to: Option<Slice>,
+    limit: Option<usize>,
+}
+
+/// 输出 WriteBatch 中的每条记录
+struct BatchPrinter<'a> {
+    flags: &'a Flags,
+}
+
+impl WriteBatchHandler for BatchPrinter<'_> {
+    fn put(&mut self, key: &[u8], value: &[u8]) {
+        println!("  put {} => {}", self.flags.format_bytes(key), self.flags.format_bytes(value));
+    }
+
+    fn delete(&mut self, key: &[u8]) {
+        println!("  delete {}", self.flags.format_bytes(key));
+    }
+}
+
+fn dump_manifest(flags: &Flags) -> Result<()> {
+    let options = Options::default();
+    let path = match flags.args.as_slice() {
+        [path] => PathBuf::from(path),
+        [] => {
+            let db = flags.db()?;
+            let current = options.env.read_file_to_string(&FileName::current_file_name(db))?;
+            db.join(current.trim_end_matches('\n'))
+        }
+        _ => return Err(LevelError::invalid_argument(Slice::from("wrong number of arguments"),
+                                                     Slice::from(flags.command.as_str()))),
+    };
+    println!("--- {} ---", path.display());
+    let mut reader = LogReader::new(Box::new(options.env.new_sequential_file(&path)?), true, 0);
+    while let Some(record) = reader.read_next()? {
+        let mut edit = VersionEdit::new();
+        let status = edit.decode_from(&record);
+        if !status.is_ok() {
+            return Err(status);
+        }
+        print!("{}", edit.debug_string().as_str());
+    }
+    Ok(())
+}
+
+fn dump_wal(flags: &Flags) -> Result<()> {
+    let options = Options::default();
+    let paths: Vec<PathBuf> = if flags.args.is_empty() {
+        let db = flags.db()?;
+        let mut logs: Vec<(u64, PathBuf)> = options.env.get_children(db)?.into_iter()
+            .filter_map(|name| match FileName::parse_file_name(&name) {
+                Some((number, FileType::KLogFile)) => Some((number, db.join(name))),
+                _ => None,
+            })
+            .collect();
+        logs.sort();
+        logs.into_iter().map(|(_, path)| path).collect()
+    } else {
+        flags.args.iter().map(PathBuf::from).collect()
+    };
+
+    for path in paths {
+        println!("--- {} ---", path.display());
+        let mut reader = LogReader::new(Box::new(options.env.new_sequential_file(&path)?), true, 0);
+        let mut batch = WriteBatch::new();
+        loop {
+            let record = match reader.read_next() {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                // 读取文件的错误会一直重复出现, 只跳过损坏的记录
+                Err(e) if !e.is_corruption() => return Err(e),
+                Err(e) => {
+                    println!("  error: {}", e.get_msg());
+                    continue;
+                }
+            };
+            if record.len() < 12 {
+                println!("  error: log record too small: {} bytes", record.len());
+                continue;
+            }
+            batch.set_contents(&record);
+            println!("sequence {}, {} ops:", batch.sequence(), batch.count());
+            if let Err(e) = batch.iterate(&mut BatchPrinter { flags }) {
+                println!("  error: {}", e.get_msg());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn repair(flags: &Flags) -> Result<()> {
+    flags.check_args(0, Some(0), &[])?;
+    repair_db(flags.db()?, Options::default())?;
+    println!("OK");
+    Ok(())
+}
+
+/// 需要打开数据库的命令, 除 put 及 batchput 外数据库不存在时返回错误
+fn db_command(flags: &Flags) -> Result<()> {
+    let args = match flags.command.as_str() {
+        "get" | "delete" => flags.check_args(1, Some(1), &[])?,
+        "put" => flags.check_args(2, Some(2), &[])?,
+        "batchput" => {
+            let args = flags.check_args(2, None, &[])?;
+            if !args.positional.len().is_multiple_of(2) {
+                return Err(LevelError::invalid_argument(Slice::from("batchput needs key/value pairs"), Slice::from("")));
+            }
+            args
+        }
+        "scan" => flags.check_args(0, Some(0), &["--from", "--to", "--limit"])?,
+        "compact" | "approxsize" => flags.check_args(0, Some(0), &["--from", "--to"])?,
+        "property" => flags.check_args(1, Some(1), &[])?,
+        _ => unreachable!(),
+    };
+    // 写入的命令在数据库不存在时创建数据库
+    let options = Options {
+        create_if_missing: matches!(flags.command.as_str(), "put" | "batchput"),
+        ..Default::default()
+    };
+    let db = DB::open(options, flags.db()?)?;
+    let write_options = WriteOptions { sync: true };
+
+    match flags.command.as_str() {
+        "get" => {
+            let key = &args.positional[0];
+            match db.get(&ReadOptions::default(), key)? {
+                Some(value) => println!("{}", flags.format_bytes(&value)),
+                None => return Err(LevelError::not_found(Slice::from(flags.format_bytes(key)), Slice::from("not found"))),
+            }
+        }
+        "put" => {
+            db.put(&write_options, &args.positional[0], &args.positional[1])?;
+            println!("OK");
+        }
+        "delete" => {
+            db.delete(&write_options, &args.positional[0])?;
+            println!("OK");
+        }
+        "batchput" => {
+            let mut batch = WriteBatch::new();
+            for pair in args.positional.chunks(2) {
+                batch.put(&pair[0], &pair[1]);
+            }
+            db.write(&write_options, batch)?;
+            println!("OK");
+        }
+        "scan" => {
+            let mut iter = db.new_iterator(&ReadOptions { fill_cache: false, ..Default::default() });
+            match &args.from {
+                Some(from) => iter.seek(from),
+                None => iter.seek_to_first(),
+            }
+            let mut count = 0;
+            while iter.valid() && args.limit.is_none_or(|limit| count < limit) {
+                let key = iter.key();
+                if args.to.as_ref().is_some_and(|to| key.as_ref() >= to.as_ref()) {
+                    break;
+                }
+                println!("{} => {}", flags.format_bytes(key.as_ref()), flags.format_bytes(iter.value().as_ref()));
+                count += 1;
+                iter.next();
+            }
+            let status = iter.status();
+            if !status.is_ok() {
+                return Err(status);
+            }
+        }
+        "compact" => {
+            db.compact_range(args.from.as_ref(), args.to.as_ref())?;
+            println!("OK");
+        }
+        "property" => {
+            let name = &flags.args[0];
+            match db.get_property(Slice::from(name.as_str())) {
+                Some(value) => print!("{}", value.as_str()),
+                None => return Err(LevelError::not_found(Slice::from(name.as_str()), Slice::from("unknown property"))),
+            }
+            if !flags.args[0].ends_with("stats") && !flags.args[0].contains("table") {
+                println!();
+            }
+        }
+        "approxsize" => {
+            let from = args.from.unwrap_or_default();
+            // 未指定 --to 时统计到最后一个 key 为止
+            let to = match args.to {
+                Some(to) => to,
+                None => {
+                    let mut iter = db.new_iterator(&ReadOptions { fill_cache: false, ..Default::default() });
+                    iter.seek_to_last();
+                    if iter.valid() {
+                        let mut last = iter.key().as_ref().to_vec();
+                        last.push(0);
+                        Slice::from_vec(last)
+                    } else {
+                        from.clone()
+                    }
+                }
+            };
+            println!("{}", db.get_approximate_sizes(&[from..to])[0]);
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+fn main() {
+    let flags = match parse_flags(std::env::args().skip(1)) {
+        Ok(flags) => flags,
+        Err(msg) => {
+            if !msg.is_empty() {
+                eprintln!("{}", msg);
+            }
+            eprint!("{}", USAGE);
+            exit(1);
+        }
+    };
+
+    let result = match flags.command.as_str() {
+        "get" | "put" | "delete" | "scan" | "batchput" | "compact" | "property" | "approxsize" => db_command(&flags),
+        "dump_manifest" => dump_manifest(&flags),
+        "dump_wal" => dump_wal(&flags),
+        "repair" => repair(&flags),
+        command => {
+            eprintln!("unknown command: {}", command);
+            eprint!("{}", USAGE);
+            exit(1);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e.get_msg());
+        exit(1);
+    }
+}
@@ -0,0 +1,376 @@
+//! 查看、校验及遍历 SSTable (.ldb) 文件
+//!
+//! ```text
+//! sst_dump [--command=info|verify|scan] [options] <file>...
+//! ```
+//!
+//! * `info`: 输出 Footer、meta index block、index block 中的 BlockHandle、filter 的统计及 table 的属性 (默认)
+//! * `verify`: 校验每个 block 的 checksum
+//! * `scan`: 遍历 [--from, --to) 范围内的 key/value
+
+use std::path::PathBuf;
+use std::process::exit;
+use std::sync::Arc;
+use level_db_rust::db::db_format::{escape_string, InternalKey, InternalKeyComparator, K_MAX_SEQUENCE_NUMBER,
+                                   ParsedInternalKey, ValueType};
+use level_db_rust::table::block::Block;
+use level_db_rust::table::filter_block::{FILTER_BLOCK_KEY_PREFIX, FULL_FILTER_BLOCK_KEY_PREFIX};
+use level_db_rust::table::format::{BlockHandle, Footer, k_encoded_length, read_block, ToBlockHandle, ToFoot};
+use level_db_rust::table::table::Table;
+use level_db_rust::table::table_properties::{PROPERTIES_BLOCK_KEY, TableProperties};
+use level_db_rust::traits::comparator_trait::Comparator;
+use level_db_rust::util::coding::Decoder;
+use level_db_rust::util::comparator::BytewiseComparatorImpl;
+use level_db_rust::util::env::RandomAccessFile;
+use level_db_rust::util::options::{Options, OptionsPtr, ReadOptions};
+use level_db_rust::util::Result;
+use level_db_rust::util::slice::Slice;
+use level_db_rust::util::status::LevelError;
+
+const USAGE: &str = "\
+Usage: sst_dump [--command=info|verify|scan] [options] <file>...
+
+Commands:
+  info      print footer, metaindex, index block handles, filter stats and properties (default)
+  verify    verify the checksum of every block
+  scan      print key/value pairs
+
+Options:
+  --output_hex        print keys and values in hex instead of escaped strings
+  --user_key          print only the user key instead of 'user key' @ sequence : type
+  --from=<user key>   scan from this user key (inclusive)
+  --to=<user key>     scan up to this user key (exclusive)
+  --verify_checksums  verify block checksums while scanning
+";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Command {
+    Info,
+    Verify,
+    Scan,
+}
+
+struct Flags {
+    command: Command,
+    output_hex: bool,
+    user_key: bool,
+    verify_checksums: bool,
+    from: Option<Vec<u8>>,
+    to: Option<Vec<u8>>,
+    files: Vec<PathBuf>,
+}
+
+fn parse_flags(args: impl Iterator<Item=String>) -> std::result::Result<Flags, String> {
+    let mut flags = Flags {
+        command: Command::Info,
+        output_hex: false,
+        user_key: false,
+        verify_checksums: false,
+        from: None,
+        to: None,
+        files: vec![],
+    };
+    for arg in args {
+        if let Some(command) = arg.strip_prefix("--command=") {
+            flags.command = match command {
+                "info" => Command::Info,
+                "verify" => Command::Verify,
+                "scan" => Command::Scan,
+                _ => return Err(format!("unknown command: {}", command)),
+            };
+        } else if let Some(from) = arg.strip_prefix("--from=") {
+            flags.from = Some(from.as_bytes().to_vec());
+        } else if let Some(to) = arg.strip_prefix("--to=") {
+            flags.to = Some(to.as_bytes().to_vec());
+        } else {
+            match arg.as_str() {
+                "--output_hex" => flags.output_hex = true,
+                "--user_key" => flags.user_key = true,
+                "--verify_checksums" => flags.verify_checksums = true,
+                "-h" | "--help" => return Err(String::new()),
+                _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+                _ => flags.files.push(PathBuf::from(arg)),
+            }
+        }
+    }
+    if flags.files.is_empty() {
+        return Err("no file specified".to_string());
+    }
+    Ok(flags)
+}
+
+/// 打开的 SSTable 文件, 已经读取了 Footer、meta index block 及 properties block
+struct SstFile {
+    path: PathBuf,
+    options: OptionsPtr,
+    file: Box<dyn RandomAccessFile>,
+    file_size: u64,
+    footer: Footer,
+    meta_blocks: Vec<(String, BlockHandle)>,
+    properties: Option<TableProperties>,
+    // table 中的 key 是否为 internal key, 由 properties 中的比较器判断, 没有 properties 时视为 internal key
+    internal_keys: bool,
+}
+
+impl SstFile {
+    fn open(path: PathBuf) -> Result<SstFile> {
+        let options = Options::default();
+        let file = options.env.new_random_access_file(&path)?;
+        let file_size = options.env.get_file_size(&path)?;
+        let mut sst = SstFile {
+            path,
+            options: Arc::new(Box::new(options)),
+            file: Box::new(file),
+            file_size,
+            footer: Footer::default(),
+            meta_blocks: vec![],
+            properties: None,
+            internal_keys: true,
+        };
+        if file_size < k_encoded_length as u64 {
+            return Err(LevelError::corruption_string("file is too short to be an sstable", ""));
+        }
+        let footer_input = sst.file.read(file_size - k_encoded_length as u64, k_encoded_length as usize)?;
+        sst.footer.decode_from(&footer_input)?;
+
+        let meta_index = sst.read_block(&sst.footer.meta_index_handle(), false)?;
+        for_each_entry(&meta_index, |key, value| {
+            let handle = decode_handle(value)?;
+            sst.meta_blocks.push((String::from_utf8_lossy(key).to_string(), handle));
+            Ok(())
+        })?;
+        if let Some(handle) = sst.meta_block(PROPERTIES_BLOCK_KEY) {
+            let properties = TableProperties::decode_from(&sst.read_block(&handle, false)?)?;
+            sst.internal_keys = properties.comparator_name == InternalKeyComparator::default().get_name();
+            sst.properties = Some(properties);
+        }
+        Ok(sst)
+    }
+
+    fn meta_block(&self, name: &str) -> Option<BlockHandle> {
+        self.meta_blocks.iter().find(|(key, _)| key == name).map(|(_, handle)| *handle)
+    }
+
+    fn read_block(&self, handle: &BlockHandle, verify_checksums: bool) -> Result<Block> {
+        let read_options = ReadOptions {
+            verify_checksums,
+            ..ReadOptions::default()
+        };
+        let contents = read_block(self.file.as_ref(), &self.options, &read_options, self.footer.checksum(), handle)?;
+        Ok(Block::new(contents))
+    }
+
+    /// 以 table 中 key 的比较器打开 Table
+    fn open_table(&self) -> Result<Arc<Table>> {
+        let mut options = Options::default();
+        if self.internal_keys {
+            options.cmp = Arc::new(InternalKeyComparator::default());
+        }
+        let file = options.env.new_random_access_file(&self.path)?;
+        Ok(Arc::new(Table::open(Arc::new(Box::new(options)), Box::new(file), self.file_size)?))
+    }
+}
+
+/// 按顺序遍历 block 中的 entry
+fn for_each_entry<F>(block: &Block, mut f: F) -> Result<()>
+    where F: FnMut(&[u8], &[u8]) -> Result<()> {
+    let mut iter = block.new_iterator(Arc::new(BytewiseComparatorImpl::default()));
+    iter.seek_to_first();
+    while iter.valid() {
+        f(iter.key().as_ref(), iter.value().as_ref())?;
+        iter.next();
+    }
+    let status = iter.status();
+    if status.is_ok() { Ok(()) } else { Err(status) }
+}
+
+fn decode_handle(value: &[u8]) -> Result<BlockHandle> {
+    let mut handle = BlockHandle::default();
+    handle.decode_from(&mut Decoder::with_buf(value))?;
+    Ok(handle)
+}
+
+fn format_handle(handle: &BlockHandle) -> String {
+    format!("offset {}, size {}", handle.offset(), handle.size())
+}
+
+fn format_bytes(data: &[u8], hex: bool) -> String {
+    if hex {
+        data.iter().map(|b| format!("{:02X}", b)).collect()
+    } else {
+        escape_string(data)
+    }
+}
+
+fn format_key(key: &[u8], flags: &Flags, internal_keys: bool) -> String {
+    if !internal_keys {
+        return format_bytes(key, flags.output_hex);
+    }
+    match ParsedInternalKey::parse_internal_key(key) {
+        Some(_) if flags.user_key => format_bytes(ParsedInternalKey::extract_user_key(key), flags.output_hex),
+        Some(parsed) => format!("'{}' @ {} : {}", format_bytes(ParsedInternalKey::extract_user_key(key), flags.output_hex),
+                                parsed.sequence(), parsed.value_type().get_value()),
+        None => format!("(bad){}", format_bytes(key, flags.output_hex)),
+    }
+}
+
+/// 输出 filter block 的统计: 类型、filter 的数量及每个 key 占用的 bit 数
+fn print_filter_stats(sst: &SstFile, name: &str, handle: &BlockHandle) -> Result<()> {
+    let block_size = handle.size() as usize;
+    let contents = sst.file.read(handle.offset(), block_size)?;
+    let contents: &[u8] = &contents;
+    let bits_per_key = match &sst.properties {
+        Some(properties) if properties.num_entries > 0 => {
+            format!("{:.2}", (block_size * 8) as f64 / properties.num_entries as f64)
+        }
+        _ => "unknown".to_string(),
+    };
+    if name.starts_with(FULL_FILTER_BLOCK_KEY_PREFIX) {
+        println!("  {}: full filter, {} bytes, {} bits/key", name, block_size, bits_per_key);
+    } else if name.starts_with(FILTER_BLOCK_KEY_PREFIX) {
+        // filter block 的格式: filter 0 .. filter N-1 | offset of filter 0 .. N-1 (fixed32) | array offset (fixed32) | base_lg (1 byte)
+        if block_size < 5 {
+            println!("  {}: bad filter block, {} bytes", name, block_size);
+            return Ok(());
+        }
+        let base_lg = contents[block_size - 1];
+        let array_offset = Decoder::with_buf(&contents[block_size - 5..]).get_fixed32()? as usize;
+        if array_offset > block_size - 5 {
+            println!("  {}: bad filter block, {} bytes", name, block_size);
+            return Ok(());
+        }
+        let num_filters = (block_size - 5 - array_offset) / 4;
+        let mut decoder = Decoder::with_buf(&contents[array_offset..block_size - 1]);
+        let mut offsets = Vec::with_capacity(num_filters + 1);
+        for _ in 0..=num_filters {
+            offsets.push(decoder.get_fixed32()?);
+        }
+        let empty_filters = offsets.windows(2).filter(|w| w[0] == w[1]).count();
+        println!("  {}: partitioned filter, base_lg {}, {} filters ({} empty), {} bytes, {} bits/key",
+                 name, base_lg, num_filters, empty_filters, block_size, bits_per_key);
+    }
+    Ok(())
+}
+
+fn info(sst: &SstFile, flags: &Flags) -> Result<()> {
+    println!("Footer:");
+    println!("  metaindex handle: {}", format_handle(&sst.footer.meta_index_handle()));
+    println!("  index handle: {}", format_handle(&sst.footer.index_handle()));
+    println!("  checksum: {:?}", sst.footer.checksum());
+
+    println!("Metaindex:");
+    for (name, handle) in &sst.meta_blocks {
+        println!("  {}: {}", name, format_handle(handle));
+    }
+
+    println!("Index:");
+    let index = sst.read_block(&sst.footer.index_handle(), false)?;
+    for_each_entry(&index, |key, value| {
+        println!("  {}: {}", format_key(key, flags, sst.internal_keys), format_handle(&decode_handle(value)?));
+        Ok(())
+    })?;
+
+    println!("Filter:");
+    for (name, handle) in &sst.meta_blocks {
+        print_filter_stats(sst, name, handle)?;
+    }
+
+    println!("Properties:");
+    match &sst.properties {
+        Some(properties) => {
+            for line in properties.debug_string().lines() {
+                println!("  {}", line);
+            }
+        }
+        None => println!("  (none)"),
+    }
+    Ok(())
+}
+
+/// 校验所有 block 的 checksum, 返回校验失败的 block 数量
+fn verify(sst: &SstFile) -> Result<usize> {
+    let mut handles = vec![sst.footer.meta_index_handle(), sst.footer.index_handle()];
+    handles.extend(sst.meta_blocks.iter().map(|(_, handle)| *handle));
+    let index = sst.read_block(&sst.footer.index_handle(), false)?;
+    for_each_entry(&index, |_, value| {
+        handles.push(decode_handle(value)?);
+        Ok(())
+    })?;
+    handles.sort_by_key(|handle| handle.offset());
+
+    let mut failures = 0;
+    for handle in &handles {
+        if let Err(e) = sst.read_block(handle, true) {
+            println!("  block at {}: {}", format_handle(handle), e.get_msg());
+            failures += 1;
+        }
+    }
+    println!("  verified {} blocks, {} corrupted", handles.len(), failures);
+    Ok(failures)
+}
+
+fn scan(sst: &SstFile, flags: &Flags) -> Result<()> {
+    let table = sst.open_table()?;
+    let read_options = ReadOptions {
+        verify_checksums: flags.verify_checksums,
+        ..ReadOptions::default()
+    };
+    let mut iter = table.new_iterator(&read_options);
+    match &flags.from {
+        Some(from) if sst.internal_keys => {
+            let target = InternalKey::new(Slice::from_buf(from), K_MAX_SEQUENCE_NUMBER as u64, ValueType::KTypeValue);
+            iter.seek(&Slice::from_buf(target.encode()));
+        }
+        Some(from) => iter.seek(&Slice::from_buf(from)),
+        None => iter.seek_to_first(),
+    }
+
+    while iter.valid() {
+        let key = iter.key();
+        let user_key = match sst.internal_keys && key.as_ref().len() >= 8 {
+            true => ParsedInternalKey::extract_user_key(key.as_ref()),
+            false => key.as_ref(),
+        };
+        if matches!(&flags.to, Some(to) if user_key >= to.as_slice()) {
+            break;
+        }
+        println!("{} => {}", format_key(key.as_ref(), flags, sst.internal_keys),
+                 format_bytes(iter.value().as_ref(), flags.output_hex));
+        iter.next();
+    }
+    let status = iter.status();
+    if status.is_ok() { Ok(()) } else { Err(status) }
+}
+
+fn main() {
+    let flags = match parse_flags(std::env::args().skip(1)) {
+        Ok(flags) => flags,
+        Err(msg) => {
+            if !msg.is_empty() {
+                eprintln!("{}", msg);
+            }
+            eprint!("{}", USAGE);
+            exit(1);
+        }
+    };
+
+    let mut ok = true;
+    for path in &flags.files {
+        println!("from [{}]", path.display());
+        let result = SstFile::open(path.clone()).and_then(|sst| match flags.command {
+            Command::Info => info(&sst, &flags).map(|_| true),
+            Command::Verify => verify(&sst).map(|failures| failures == 0),
+            Command::Scan => scan(&sst, &flags).map(|_| true),
+        });
+        match result {
+            Ok(file_ok) => ok &= file_ok,
+            Err(e) => {
+                println!("  error: {}", e.get_msg());
+                ok = false;
+            }
+        }
+    }
+    if !ok {
+        exit(1);
+    }
+}
@@ -1,19 +1,13 @@
-use std::error::Error;
-use std::fs::File;
-use std::io;
-use std::ops::Deref;
-use std::sync::Arc;
+use std::path::Path;
+use crate::db::db_format::InternalKey;
 use crate::db::file_meta_data::FileMetaData;
 use crate::db::filename::FileName;
 use crate::db::table_cache::TableCache;
-use crate::table::table::Table;
 use crate::table::table_builder::TableBuilder;
 use crate::traits::DataIterator;
 use crate::util::env::Env;
-use crate::util::options::{Options, OptionsPtr, ReadOptions};
+use crate::util::options::{OptionsPtr, ReadOptions};
 use crate::util::Result;
-use crate::util::slice::Slice;
-use crate::util::status::{LevelError, Status};
 
 pub struct BuildTable {}
 
@@ -29,51 +23,54 @@ impl BuildTable {
     ///
     /// # Arguments
     ///
-    /// * `dbname`:
-    /// * `env`:
-    /// * `options`:
-    /// * `table_cache`:
-    /// * `iter`:
-    /// * `meta`:
+    /// * `dbname`: 数据库目录
+    /// * `env`: 运行环境
+    /// * `options`: 配置, 其中的 cmp 为 InternalKeyComparator
+    /// * `table_cache`: 用于检查生成的 table 是否可用
+    /// * `iter`: 按 internal key 有序的数据
+    /// * `meta`: 输入时需要设置文件号, 成功后会填充文件大小及 key 的范围
+    /// * `level`: table 将要写入的层, 决定使用的压缩类型
     ///
-    /// returns: Result<FileMetaData, Status>
+    /// returns: Result<(), Status>
     ///
     /// # Examples
     ///
+    /// ```ignore
+    /// let mut meta = FileMetaData::default();
+    /// meta.set_number(versions.new_file_number());
+    /// BuildTable::build_table(&dbname, &options.env, &options, &table_cache, mem.new_iterator().as_mut(), &mut meta, 0)?;
     /// ```
-    ///
-    /// ```
-    pub fn build_table(dbname: &Slice, env: &Env, options: OptionsPtr,
-                       table_cache: &TableCache, mut iter: Box<dyn DataIterator>,
-                       meta: &mut FileMetaData) -> Result<FileMetaData> {
+    pub fn build_table(dbname: &Path, env: &Env, options: &OptionsPtr,
+                       table_cache: &TableCache, iter: &mut dyn DataIterator,
+                       meta: &mut FileMetaData, level: usize) -> Result<()> {
         meta.set_file_size(0);
         // 迭代器移动到第一个节点
         iter.seek_to_first();
         // 生成一个 SSTable 文件名
-        let file_name = FileName::table_file_name(dbname, meta.get_number());
-
-        let mut s : Status = Status::default();
+        let fname = FileName::table_file_name(dbname, meta.get_number());
 
+        let mut s: Result<()> = Ok(());
         if iter.valid() {
-            let fileRS: Result<File> = env.new_writable_file(&file_name);
-            if(!fileRS.is_ok()){
-                return Err(fileRS.err().unwrap());
-            }
+            let file = env.new_writable_file(&fname)?;
 
-            let writable_file = Arc::new(fileRS.unwrap());
             // 生成一个 TableBuilder
-            let builder: TableBuilder = TableBuilder::new_with_writable_file(options, writable_file.clone());
-
-            meta.get_smallest().decode_from(&iter.key());
+            let mut builder = TableBuilder::new_with_writable_file(options.clone(), Box::new(file));
+            builder.set_compression(options.compression_for_level(level));
+            let mut smallest = InternalKey::default();
+            smallest.decode_from(iter.key().as_ref());
+            meta.set_smallest(smallest);
 
             // 调用迭代器，依次将每个键-值对加入 TableBuilder
+            let mut key = vec![];
             while iter.valid() {
+                key.clear();
+                key.extend_from_slice(iter.key().as_ref());
+                builder.add(&key, iter.value().as_ref());
                 iter.next();
-
-                let key = iter.key();
-                meta.get_largest().decode_from(&key);
-                builder.add(&key, &iter.value());
             }
+            let mut largest = InternalKey::default();
+            largest.decode_from(&key);
+            meta.set_largest(largest);
 
             // Finish and check for builder errors
             // 调用 TableBuilder 的 Finish 函数生成 SSTable 文件
@@ -86,48 +83,32 @@ impl BuildTable {
             // Finish and check for file errors
             // 将文件刷新到磁盘
             if s.is_ok() {
-                let rs:io::Result<()> = writable_file.sync_data();
-                if rs.is_ok() {
-                    s = Status::default();
-                }else{
-                    s = Status::wrapper_str(LevelError::KIOError, rs.unwrap_err().to_string().as_str());
-                }
+                s = builder.sync();
             }
             // 关闭文件
-            // if s.is_ok() {
-            //     writableFile.close
-            // }
+            drop(builder);
 
             if s.is_ok() {
-                let readOptions = ReadOptions::default();
                 // Verify that the table is usable
-                let it: Box<dyn DataIterator> = table_cache.new_iterator(&readOptions,
-                                         meta.get_number(),
-                                         meta.get_file_size() as usize,
-                                         &Table::new())
-                    .expect("table_cache.new_iterator error");
-                s = it.status();
+                let it = table_cache.new_iterator(&ReadOptions::default(), meta.get_number(), meta.get_file_size());
+                let status = it.status();
+                if !status.is_ok() {
+                    s = Err(status);
+                }
             }
-        } // if end
+        }
 
         // Check for input iterator errors
-        if !iter.status().is_ok() {
-            s = iter.status();
+        let status = iter.status();
+        if !status.is_ok() {
+            s = Err(status);
         }
 
         if s.is_ok() && meta.get_file_size() > 0 {
             // Keep it
         } else {
-            // DeleteFile fname
-            // todo
-        }
-
-        if s.is_ok() {
-            // todo
-            // return Ok(meta);
-            return Ok(FileMetaData::default());
-        }else{
-            return Err(s);
+            let _ = env.remove_file(&fname);
         }
+        s
     }
-}
\ No newline at end of file
+}
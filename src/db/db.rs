@@ -1,42 +1,447 @@
-use std::ops::Range;
+use std::collections::BTreeSet;
+use std::ops::{Deref, Range};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::db::builder::BuildTable;
+use crate::db::db_format::{Config, InternalKey, InternalKeyComparator, K_MAX_SEQUENCE_NUMBER, LookupKey, ParsedInternalKey, ValueType};
+use crate::db::db_iter::new_db_iterator;
+use crate::db::file_meta_data::FileMetaData;
+use crate::db::filename::{FileName, FileType};
+use crate::db::log_reader::LogReader;
+use crate::db::log_writer::LogWriter;
+use crate::db::table_cache::TableCache;
+use crate::db::version_edit::VersionEdit;
+use crate::db::version_set::{Compaction, GetStats, Version, VersionSet};
+use crate::db::write_batch::WriteBatch;
+use crate::db::mem_table::MemTable;
+use crate::{log_debug, log_error, log_info, log_warn};
+use crate::table::merger::new_merging_iterator;
+use crate::table::prefix_iterator::new_prefix_iterator;
+use crate::table::table_builder::TableBuilder;
+use crate::table::table_properties::TableProperties;
 use crate::traits::DataIterator;
-use crate::util::options::{Options, ReadOptions, WriteOptions};
+use crate::traits::logger_trait::{InfoLogLevel, LoggerPtr};
+use crate::util::cache::ShardLRUCache;
+use crate::util::env::{Env, FileLock};
+use crate::util::filter_policy_internal::InternalFilterPolicy;
+use crate::util::filter_policy_prefix::PrefixFilterPolicy;
+use crate::util::slice_transform::InternalSliceTransform;
+use crate::util::options::{Options, OptionsPtr, ReadOptions, WriteOptions};
 use crate::util::Result;
 use crate::util::slice::Slice;
+use crate::util::status::{LevelError, Status};
 
-struct DB {
+/// 为 TableCache 之外的用途(LOCK、LOG、MANIFEST、预写日志等)预留的文件句柄数
+const K_NUM_NON_TABLE_CACHE_FILES: usize = 10;
 
+pub struct DB {
+    pub(crate) inner: Arc<DBInner>,
+}
+
+/// DB 的共享部分, 由用户线程与后台 compaction 线程共同持有
+pub struct DBInner {
+    pub(crate) dbname: PathBuf,
+    pub(crate) options: OptionsPtr,
+    internal_comparator: InternalKeyComparator,
+    pub(crate) table_cache: Arc<TableCache>,
+
+    /// 保护 DB 的可变状态
+    pub(crate) mutex: Mutex<DBState>,
+    /// 后台任务完成时发出通知
+    /// Signalled when background work finishes
+    bg_cv: Condvar,
+    pub(crate) shutting_down: AtomicBool,
+    /// So bg thread can detect non-null imm_
+    has_imm: AtomicBool,
+}
+
+/// 由 DBInner::mutex 保护的状态
+pub(crate) struct DBState {
+    /// 打开数据库时锁定的 LOCK 文件
+    db_lock: Option<FileLock>,
+
+    pub(crate) versions: VersionSet,
+
+    /// 正在写入的 memtable
+    pub(crate) mem: Option<MemTable>,
+    /// Memtable being compacted
+    /// 正在落盘的 memtable
+    pub(crate) imm: Option<Arc<MemTable>>,
+    /// 当前 memtable 对应的预写日志
+    pub(crate) logfile_number: u64,
+    pub(crate) log: Option<LogWriter>,
+
+    // Set of table files to protect from deletion because they are part of ongoing compactions.
+    // 正在生成的 table 文件号(compaction 或者 memtable 落盘的输出), 不能被当作过期文件删除
+    pub(crate) pending_outputs: BTreeSet<u64>,
+
+    /// Has a background compaction been scheduled or is running?
+    pub(crate) bg_compaction_scheduled: bool,
+
+    /// 正在等待执行的 manual compaction
+    pub(crate) manual_compaction: Option<ManualCompaction>,
+
+    /// Have we encountered a background error in paranoid mode?
+    pub(crate) bg_error: Status,
+
+    /// 每一层的 compaction 统计, 用于 leveldb.stats 属性
+    pub(crate) stats: [CompactionStats; Config::K_NUM_LEVELS],
+}
+
+/// Information for a manual compaction
+///
+/// 一次 manual compaction 的进度, 每次后台任务处理一部分 key 范围后更新 begin
+pub(crate) struct ManualCompaction {
+    level: usize,
+    done: bool,
+    /// None means beginning of key range
+    begin: Option<InternalKey>,
+    /// None means end of key range
+    end: Option<InternalKey>,
+}
+
+/// 一次 compaction 的执行状态
+struct CompactionState {
+    compaction: Compaction,
+
+    /// Sequence numbers < smallest_snapshot are not significant since we
+    /// will never have to service a snapshot below smallest_snapshot.
+    /// Therefore if we have seen a sequence number S <= smallest_snapshot,
+    /// we can drop all entries for the same key with sequence numbers < S.
+    smallest_snapshot: u64,
+
+    outputs: Vec<CompactionOutput>,
+
+    /// State kept for output being generated
+    builder: Option<TableBuilder>,
+
+    total_bytes: u64,
+}
+
+/// Files produced by compaction
+struct CompactionOutput {
+    number: u64,
+    file_size: u64,
+    smallest: InternalKey,
+    largest: InternalKey,
+}
+
+type StateGuard<'a> = MutexGuard<'a, DBState>;
+
+/// Per level compaction stats.  stats_[level] stores the stats for
+/// compactions that produced data for the specified "level".
+///
+/// 输出到某一层的 compaction 的统计
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CompactionStats {
+    /// compaction 耗时, 微秒
+    pub(crate) micros: u64,
+    /// 读取的字节数
+    pub(crate) bytes_read: u64,
+    /// 写入的字节数
+    pub(crate) bytes_written: u64,
+}
+
+impl CompactionStats {
+    /// 累加另一次 compaction 的统计
+    pub(crate) fn add(&mut self, c: &CompactionStats) {
+        self.micros += c.micros;
+        self.bytes_read += c.bytes_read;
+        self.bytes_written += c.bytes_written;
+    }
 }
 
-//TODO temp
-struct WriteBatchOptions {}
-struct WriteBatch {}
 pub trait Snapshot {}
 
 impl DB {
+    /// 创建 DB 对象, 除了创建运行日志 LOG (未指定 Options::info_log 时) 之外不会读写磁盘上的任何文件
+    pub(crate) fn new<P: AsRef<Path>>(mut options: Options, dbname: P) -> Self {
+        let dbname = dbname.as_ref().to_path_buf();
+        if options.info_log.is_none() {
+            options.info_log = open_info_log(&options.env, &dbname, options.info_log_level);
+        }
+        // 与 LevelDB 的 SanitizeOptions 相同, 将 max_open_files 限制在 [64 + K_NUM_NON_TABLE_CACHE_FILES, 50000] 之间
+        options.max_open_files = options.max_open_files.clamp(64 + K_NUM_NON_TABLE_CACHE_FILES as u32, 50000);
+        let table_cache_size = (options.max_open_files as usize) - K_NUM_NON_TABLE_CACHE_FILES;
+        // 未指定 block cache 时使用 8MB 的 cache
+        if options.block_cache.is_none() {
+            options.block_cache = Some(ShardLRUCache::new_arc_with_capacity(8 << 20, 0));
+        }
+        let icmp = InternalKeyComparator::new(options.cmp.clone());
+        // table 中保存的是 internal key, DB 内部使用的 options 以 InternalKeyComparator 作为比较器
+        options.cmp = Arc::new(icmp.clone());
+        // filter 中保存的是 user key 及其前缀, 前缀提取器同样作用于 user key
+        options.filter_policy = InternalFilterPolicy::wrap(
+            PrefixFilterPolicy::wrap(options.filter_policy.take(), options.prefix_extractor.clone()));
+        options.prefix_extractor = InternalSliceTransform::wrap(options.prefix_extractor.take());
+        let options: OptionsPtr = Arc::new(Box::new(options));
+        let table_cache = Arc::new(TableCache::new(&dbname, options.clone(), table_cache_size));
+        let versions = VersionSet::new(&dbname, options.clone(), table_cache.clone(), icmp.clone());
 
-    async fn open(_opt: Options, _name: String) -> Result<Self> {
-        todo!()
+        let state = DBState {
+            db_lock: None,
+            versions,
+            mem: None,
+            imm: None,
+            logfile_number: 0,
+            log: None,
+            pending_outputs: BTreeSet::new(),
+            bg_compaction_scheduled: false,
+            manual_compaction: None,
+            bg_error: Status::default(),
+            stats: [CompactionStats::default(); Config::K_NUM_LEVELS],
+        };
+
+        Self {
+            inner: Arc::new(DBInner {
+                dbname,
+                options,
+                internal_comparator: icmp,
+                table_cache,
+                mutex: Mutex::new(state),
+                bg_cv: Condvar::new(),
+                shutting_down: AtomicBool::new(false),
+                has_imm: AtomicBool::new(false),
+            })
+        }
     }
 
-    async fn put(&mut self, _opt: WriteOptions, _key: Slice, _value: Slice) -> Result<()> {
-        todo!()
+    /// 打开数据库, 返回的 DB 可以在多个线程间共享
+    ///
+    /// 数据库不存在时, options.create_if_missing 为 true 则创建新的数据库, 否则返回错误;
+    /// 数据库已存在且 options.error_if_exists 为 true 时返回错误。
+    /// 打开时会锁定 LOCK 文件, 并将上次未落盘的预写日志恢复为 level 0 之后的 table
+    ///
+    /// Open the database with the specified "name".
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 配置
+    /// * `dbname`: 数据库目录
+    ///
+    /// returns: Result<DB, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut options = Options::default();
+    /// options.create_if_missing = true;
+    /// let db = DB::open(options, "/tmp/testdb")?;
+    /// ```
+    pub fn open<P: AsRef<Path>>(options: Options, dbname: P) -> Result<DB> {
+        let db = DB::new(options, dbname);
+        let mut state = db.mutex.lock().unwrap();
+        let mut edit = VersionEdit::new();
+        // Recover handles create_if_missing, error_if_exists
+        state = db.recover(state, &mut edit)?;
+
+        // Create new log and a corresponding memtable.
+        let new_log_number = state.versions.new_file_number();
+        let file = db.options.env.new_writable_file(&FileName::log_file_name(&db.dbname, new_log_number))?;
+        state.log = Some(LogWriter::new(Box::new(file)));
+        state.logfile_number = new_log_number;
+        state.mem = Some(MemTable::create(db.internal_comparator.user_comparator()));
+
+        // No older logs needed after recovery.
+        edit.set_prev_log_number(0);
+        edit.set_log_number(new_log_number);
+        state.versions.log_and_apply(&mut edit)?;
+        db.delete_obsolete_files(&mut state);
+        db.inner.maybe_schedule_compaction(&mut state);
+        drop(state);
+        Ok(db)
     }
 
-    async fn delete(&mut self, _key: Slice) -> Result<bool> {
-        todo!()
+    /// 写入 key, 已存在时覆盖原来的 value
+    ///
+    /// Set the database entry for "key" to "value".  Returns OK on success,
+    /// and a non-OK status on error.
+    /// Note: consider setting options.sync = true.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 写入的配置
+    /// * `key`: user key
+    /// * `value`: value
+    ///
+    /// returns: Result<(), Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// db.put(&WriteOptions::default(), b"foo", b"v1")?;
+    /// ```
+    pub fn put(&self, options: &WriteOptions, key: &[u8], value: &[u8]) -> Result<()> {
+        let mut batch = WriteBatch::new();
+        batch.put(key, value);
+        self.write(options, batch)
     }
 
-    async fn write(&mut self, _opt: WriteBatchOptions, _updates: WriteBatch) -> Result<()> {
-        todo!()
+    /// 删除 key, key 不存在时同样返回成功
+    ///
+    /// Remove the database entry (if any) for "key".  Returns OK on
+    /// success, and a non-OK status on error.  It is not an error if "key"
+    /// did not exist in the database.
+    /// Note: consider setting options.sync = true.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 写入的配置
+    /// * `key`: user key
+    ///
+    /// returns: Result<(), Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// db.delete(&WriteOptions::default(), b"foo")?;
+    /// ```
+    pub fn delete(&self, options: &WriteOptions, key: &[u8]) -> Result<()> {
+        let mut batch = WriteBatch::new();
+        batch.delete(key);
+        self.write(options, batch)
     }
 
-    async fn get(&mut self, _opt: ReadOptions, _key: Slice) -> Result<Option<Slice>> {
-        todo!()
+    /// 原子地写入 WriteBatch 中的所有修改
+    ///
+    /// 写入期间一直持有锁: 先写入预写日志(options.sync 为 true 时同步到磁盘), 再写入 memtable。
+    /// 同步预写日志失败时, 日志中是否包含本次写入是不确定的, 之后所有的写入都会返回该错误
+    ///
+    /// Apply the specified updates to the database.
+    /// Returns OK on success, non-OK on failure.
+    /// Note: consider setting options.sync = true.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 写入的配置
+    /// * `updates`: 批量修改
+    ///
+    /// returns: Result<(), Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut batch = WriteBatch::new();
+    /// batch.delete(b"key1");
+    /// batch.put(b"key2", b"value");
+    /// db.write(&WriteOptions::default(), batch)?;
+    /// ```
+    pub fn write(&self, options: &WriteOptions, mut updates: WriteBatch) -> Result<()> {
+        let state = self.mutex.lock().unwrap();
+        let mut state = self.inner.make_room_for_write(state, false)?;
+        let state = &mut *state;
+        let mut last_sequence = state.versions.last_sequence();
+        updates.set_sequence(last_sequence + 1);
+        last_sequence += updates.count() as u64;
+
+        // Add to log and apply to memtable.
+        let log = state.log.as_mut().unwrap();
+        let mut status = log.add_record(Slice::from_buf(updates.contents()));
+        let mut sync_error = false;
+        if status.is_ok() && options.sync {
+            status = log.sync();
+            sync_error = status.is_err();
+        }
+        if status.is_ok() {
+            status = updates.insert_into(state.mem.as_mut().unwrap());
+        }
+        if let (true, Err(e)) = (sync_error, &status) {
+            // The state of the log file is indeterminate: the log record we
+            // just added may or may not show up when the DB is re-opened.
+            // So we force the DB into a mode where all future writes fail.
+            self.record_background_error(state, e.clone());
+        }
+        state.versions.set_last_sequence(last_sequence);
+        status
     }
 
-    fn new_iterator(&mut self, _opt: ReadOptions) -> Result<Box<dyn DataIterator>> {
-        todo!()
+    /// 读取 key 对应的 value, key 不存在(或者已被删除)时返回 Ok(None)
+    ///
+    /// 依次查找 memtable、immutable memtable 及各层的 table, 查找 table 时不持有锁。
+    /// 目前不支持快照, 总是读取最新的数据
+    ///
+    /// If the database contains an entry for "key" store the
+    /// corresponding value in *value and return OK.
+    ///
+    /// If there is no entry for "key" leave *value unchanged and return
+    /// a status for which Status::IsNotFound() returns true.
+    ///
+    /// May return some other Status on an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 读取的配置
+    /// * `key`: user key
+    ///
+    /// returns: Result<Option<Slice>, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// if let Some(value) = db.get(&ReadOptions::default(), b"foo")? {
+    ///     println!("{}", value.as_str());
+    /// }
+    /// ```
+    pub fn get(&self, options: &ReadOptions, key: &[u8]) -> Result<Option<Slice>> {
+        let mut state = self.mutex.lock().unwrap();
+        let snapshot = state.versions.last_sequence();
+        let current = state.versions.current();
+        let lkey = LookupKey::new(Slice::from_buf(key), snapshot as usize);
+
+        // First look in the memtable, then in the immutable memtable (if any).
+        let mut result = state.mem.as_ref().map_or(Ok(None), |mem| mem.get(&lkey));
+        if let (Ok(None), Some(imm)) = (&result, &state.imm) {
+            result = imm.get(&lkey);
+        }
+        if let Ok(None) = result {
+            // Unlock while reading from files
+            drop(state);
+            let mut stats = GetStats::default();
+            result = current.get(&self.table_cache, options, &lkey, &mut stats);
+            state = self.mutex.lock().unwrap();
+            if current.update_stats(&stats) {
+                self.inner.maybe_schedule_compaction(&mut state);
+            }
+        }
+        drop(state);
+
+        match result {
+            Err(e) if e.is_not_found() => Ok(None),
+            result => result,
+        }
+    }
+
+    /// 创建数据库的迭代器, key 为 user key。
+    ///
+    /// 迭代器只能看到创建时已经写入的数据, 并在使用期间保持当时的 memtable 及 table 文件有效。
+    /// options.prefix_same_as_start 为 true 且配置了 prefix_extractor 时, seek 之后只迭代与目标前缀相同的 key
+    ///
+    /// Return a heap-allocated iterator over the contents of the database.
+    /// The result of NewIterator() is initially invalid (caller must
+    /// call one of the Seek methods on the iterator before using it).
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 读取的配置
+    ///
+    /// returns: Box<dyn DataIterator, Global>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut iter = db.new_iterator(&ReadOptions::default());
+    /// iter.seek_to_first();
+    /// while iter.valid() {
+    ///     println!("{} => {}", iter.key(), iter.value());
+    ///     iter.next();
+    /// }
+    /// ```
+    pub fn new_iterator(&self, options: &ReadOptions) -> Box<dyn DataIterator> {
+        let (iter, version, latest_snapshot) = self.new_internal_iterator(options);
+        new_db_iterator(self.inner.clone(), self.internal_comparator.user_comparator(), iter, version, latest_snapshot)
     }
 
     fn get_snapshot(&self) -> Box<dyn Snapshot> {
@@ -47,16 +452,1175 @@ impl DB {
         todo!()
     }
 
-    fn get_property(&self, _key: Slice) -> Option<Slice> {
-        todo!()
+    /// 获取 DB 的属性, 不存在的属性返回 None
+    ///
+    /// 支持的属性:
+    ///  "leveldb.num-files-at-level<N>" - 第 N 层的文件数量, N 为十进制数字
+    ///  "leveldb.stats" - 每一层的文件数、大小及 compaction 的统计
+    ///  "leveldb.sstables" - 每一层所有 table 文件的 key 范围
+    ///  "leveldb.approximate-memory-usage" - memtable 及 block 缓存使用的内存近似值(字节)
+    ///  "leveldb.aggregated-table-properties" - 所有 table 的数值属性之和, 见 TableProperties::add
+    ///  "leveldb.aggregated-table-properties-at-level<N>" - 第 N 层所有 table 的数值属性之和
+    ///  "leveldb.table-properties" - 每个 table 的全部属性, 包括 TablePropertiesCollector 收集的属性
+    ///
+    /// # Arguments
+    ///
+    /// * `property`: 属性名
+    ///
+    /// returns: Option<Slice>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let num_files = db.get_property(Slice::from("leveldb.num-files-at-level0"));
+    /// ```
+    pub fn get_property(&self, property: Slice) -> Option<Slice> {
+        let state = self.mutex.lock().unwrap();
+        let property = std::str::from_utf8(&property).ok()?;
+        let input = property.strip_prefix("leveldb.")?;
+
+        if let Some(level) = input.strip_prefix("num-files-at-level") {
+            if level.is_empty() || !level.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let level = level.parse::<usize>().ok()?;
+            if level >= Config::K_NUM_LEVELS {
+                return None;
+            }
+            return Some(Slice::from(state.versions.num_level_files(level).to_string()));
+        }
+        if let Some(level) = input.strip_prefix("aggregated-table-properties-at-level") {
+            if level.is_empty() || !level.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let level = level.parse::<usize>().ok()?;
+            if level >= Config::K_NUM_LEVELS {
+                return None;
+            }
+            let v = state.versions.current();
+            return Some(Slice::from(self.aggregate_table_properties(&v, level..level + 1).debug_string()));
+        }
+
+        match input {
+            "stats" => {
+                let mut value = String::new();
+                value.push_str("                               Compactions\n");
+                value.push_str("Level  Files Size(MB) Time(sec) Read(MB) Write(MB)\n");
+                value.push_str("--------------------------------------------------\n");
+                for (level, stats) in state.stats.iter().enumerate() {
+                    let files = state.versions.num_level_files(level);
+                    if stats.micros > 0 || files > 0 {
+                        value.push_str(&format!("{:3} {:8} {:8.0} {:9.0} {:8.0} {:9.0}\n",
+                                                level,
+                                                files,
+                                                state.versions.num_level_bytes(level) as f64 / 1048576.0,
+                                                stats.micros as f64 / 1e6,
+                                                stats.bytes_read as f64 / 1048576.0,
+                                                stats.bytes_written as f64 / 1048576.0));
+                    }
+                }
+                Some(Slice::from(value))
+            }
+            "sstables" => Some(Slice::from(state.versions.current().debug_string())),
+            "approximate-memory-usage" => {
+                let mut total_usage = 0;
+                if let Some(block_cache) = &self.options.block_cache {
+                    total_usage += block_cache.total_charge().ok()?;
+                }
+                if let Some(mem) = &state.mem {
+                    total_usage += mem.approximate_memory_usage();
+                }
+                if let Some(imm) = &state.imm {
+                    total_usage += imm.approximate_memory_usage();
+                }
+                Some(Slice::from(total_usage.to_string()))
+            }
+            "aggregated-table-properties" => {
+                let v = state.versions.current();
+                Some(Slice::from(self.aggregate_table_properties(&v, 0..Config::K_NUM_LEVELS).debug_string()))
+            }
+            "table-properties" => {
+                let v = state.versions.current();
+                let mut value = String::new();
+                for level in 0..Config::K_NUM_LEVELS {
+                    value.push_str(&format!("--- level {} ---\n", level));
+                    for f in v.files(level) {
+                        value.push_str(&format!("{}:\n", f.get_number()));
+                        if let Some(properties) = self.table_properties(f) {
+                            value.push_str(&properties.debug_string());
+                        }
+                    }
+                }
+                Some(Slice::from(value))
+            }
+            _ => None,
+        }
     }
 
-    fn get_approximate_sizes(&self, _rng: Range<Slice>, _n: u32) -> Result<Vec<usize>> {
-        todo!()
+    /// 返回每个 user key 范围 [start, end) 在文件系统中大致占用的字节数
+    ///
+    /// 结果只包含已经写入 table 文件的数据, 不包含 memtable 中的数据。
+    /// 数据经过压缩时, 返回的是压缩后的大小
+    ///
+    /// For each i in [0,n-1], store in "sizes[i]", the approximate
+    /// file system space used by keys in "[range[i].start .. range[i].limit)".
+    ///
+    /// # Arguments
+    ///
+    /// * `ranges`: user key 范围
+    ///
+    /// returns: Vec<u64>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let sizes = db.get_approximate_sizes(&[Slice::from("a")..Slice::from("k"), Slice::from("k")..Slice::from("z")]);
+    /// ```
+    pub fn get_approximate_sizes(&self, ranges: &[Range<Slice>]) -> Vec<u64> {
+        let state = self.mutex.lock().unwrap();
+        let v = state.versions.current();
+        ranges.iter().map(|range| {
+            // Convert user_key into a corresponding internal key.
+            let k1 = InternalKey::new(range.start.clone(), K_MAX_SEQUENCE_NUMBER as u64, Config::K_VALUE_TYPE_FOR_SEEK);
+            let k2 = InternalKey::new(range.end.clone(), K_MAX_SEQUENCE_NUMBER as u64, Config::K_VALUE_TYPE_FOR_SEEK);
+            let start = state.versions.approximate_offset_of(&v, &k1);
+            let limit = state.versions.approximate_offset_of(&v, &k2);
+            limit.saturating_sub(start)
+        }).collect()
     }
 
-    async fn compact_range(&mut self, _rng: Range<Slice>) -> Result<()> {
-        todo!()
+    /// 对 user key 范围 [begin, end] 进行 compaction
+    ///
+    /// 先将 memtable 落盘并等待 immutable memtable 落盘完成, 然后从 level 0 开始,
+    /// 逐层 compact 到与该范围重叠的最深的层。每一层按照 max_file_size 分多次进行, 避免一次 compaction 过大。
+    /// 被删除或者被覆盖的数据会在 compaction 中丢弃, 从而回收磁盘空间
+    ///
+    /// Compact the underlying storage for the key range [*begin,*end].
+    /// In particular, deleted and overwritten versions are discarded,
+    /// and the data is rearranged to reduce the cost of operations
+    /// needed to access the data.  This operation should typically only
+    /// be invoked by users who understand the underlying implementation.
+    ///
+    /// begin==nullptr is treated as a key before all keys in the database.
+    /// end==nullptr is treated as a key after all keys in the database.
+    /// Therefore the following call will compact the entire database:
+    ///    db->CompactRange(nullptr, nullptr);
+    ///
+    /// # Arguments
+    ///
+    /// * `begin`: None 表示比所有的 key 都小
+    /// * `end`: None 表示比所有的 key 都大
+    ///
+    /// returns: Result<(), Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// // compact 整个数据库
+    /// db.compact_range(None, None)?;
+    /// db.compact_range(Some(&Slice::from("a")), Some(&Slice::from("k")))?;
+    /// ```
+    pub fn compact_range(&self, begin: Option<&Slice>, end: Option<&Slice>) -> Result<()> {
+        let mut max_level_with_files = 1;
+        {
+            let state = self.mutex.lock().unwrap();
+            let base = state.versions.current();
+            for level in 1..Config::K_NUM_LEVELS {
+                if base.overlap_in_level(level, begin.map(|k| k.as_ref()), end.map(|k| k.as_ref())) {
+                    max_level_with_files = level;
+                }
+            }
+        }
+
+        // 正在落盘的 immutable memtable 中也可能有该范围的数据, 总是切换 memtable 并等待两者都写入 table
+        self.inner.flush_mem_table()?;
+        for level in 0..max_level_with_files {
+            self.inner.compact_level_range(level, begin, end)?;
+        }
+        Ok(())
+    }
+}
+
+impl Deref for DB {
+    type Target = DBInner;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl Drop for DB {
+    fn drop(&mut self) {
+        // Wait for background work to finish.
+        let mut state = self.mutex.lock().unwrap();
+        self.shutting_down.store(true, Ordering::Release);
+        while state.bg_compaction_scheduled {
+            state = self.bg_cv.wait(state).unwrap();
+        }
+        if let Some(lock) = state.db_lock.take() {
+            let _ = self.options.env.unlock_file(lock);
+        }
+    }
+}
+
+/// 删除数据库
+///
+/// 删除前会先锁定 LOCK 文件, 数据库正在被使用时返回错误。只会删除文件名能被识别为数据库文件的文件,
+/// 删除完成后释放锁, 目录为空时一并删除目录
+///
+/// Destroy the contents of the specified database.
+/// Be very careful using this method.
+///
+/// # Arguments
+///
+/// * `dbname`: 数据库目录
+/// * `options`: 配置
+///
+/// returns: Result<(), Status>
+///
+/// # Examples
+///
+/// ```ignore
+/// destroy_db("/tmp/db", &Options::default())?;
+/// ```
+pub fn destroy_db<P: AsRef<Path>>(dbname: P, options: &Options) -> Result<()> {
+    let dbname = dbname.as_ref();
+    let env = &options.env;
+    let filenames = match env.get_children(dbname) {
+        Ok(filenames) => filenames,
+        // Ignore error in case directory does not exist
+        Err(_) => return Ok(()),
+    };
+
+    let lockname = FileName::lock_file_name(dbname);
+    let lock = env.lock_file(&lockname)?;
+    let mut result = Ok(());
+    for filename in filenames {
+        match FileName::parse_file_name(&filename) {
+            // Lock file will be deleted at end
+            Some((_, FileType::KDBLockFile)) | None => {}
+            Some(_) => {
+                let del = env.remove_file(&dbname.join(&filename));
+                if result.is_ok() && del.is_err() {
+                    result = del;
+                }
+            }
+        }
+    }
+    // Ignore error since state is already gone
+    let _ = env.unlock_file(lock);
+    let _ = env.remove_file(&lockname);
+    // Ignore error in case dir contains other files
+    let _ = env.remove_dir(dbname);
+    result
+}
+
+/// 在数据库目录下创建运行日志 LOG, 上一次的 LOG 被重命名为 LOG.old。
+/// 目录不存在时会先创建目录, 创建失败时不写入运行日志
+///
+/// # Arguments
+///
+/// * `env`: 运行环境
+/// * `dbname`: 数据库目录
+/// * `level`: 写入的最低日志级别
+///
+/// returns: Option<LoggerPtr>
+///
+/// # Examples
+///
+/// ```ignore
+/// options.info_log = open_info_log(&options.env, &dbname, options.info_log_level);
+/// ```
+pub(crate) fn open_info_log(env: &Env, dbname: &Path, level: InfoLogLevel) -> Option<LoggerPtr> {
+    // In case it does not exist
+    let _ = env.create_dir(dbname);
+    let _ = env.rename_file(&FileName::info_log_file_name(dbname), &FileName::old_info_log_file_name(dbname));
+    // No place suitable for logging
+    env.new_logger(&FileName::info_log_file_name(dbname), level).ok()
+}
+
+impl DBInner {
+    /// 读取 table 的属性, 打开 table 失败或者 table 没有 properties block 时返回 None
+    fn table_properties(&self, f: &FileMetaData) -> Option<TableProperties> {
+        let table = self.table_cache.find_table(f.get_number(), f.get_file_size()).ok()?;
+        table.properties().cloned()
+    }
+
+    /// 累加 Version v 中 levels 层所有 table 的属性
+    fn aggregate_table_properties(&self, v: &Version, levels: Range<usize>) -> TableProperties {
+        let mut aggregated = TableProperties::default();
+        for level in levels {
+            for f in v.files(level) {
+                if let Some(properties) = self.table_properties(f) {
+                    aggregated.add(&properties);
+                }
+            }
+        }
+        aggregated
+    }
+
+    /// 创建新的数据库: 写入只包含比较器名称等基本信息的 MANIFEST-000001, 并将 CURRENT 指向它
+    fn new_db(&self) -> Result<()> {
+        let mut new_db = VersionEdit::new();
+        new_db.set_comparator_name(self.internal_comparator.user_comparator().get_name().into());
+        new_db.set_log_number(0);
+        new_db.set_next_file(2);
+        new_db.set_last_sequence(0);
+
+        let manifest = FileName::descriptor_file_name(&self.dbname, 1);
+        let file = self.options.env.new_writable_file(&manifest)?;
+        let mut status = {
+            let mut log = LogWriter::new(Box::new(file));
+            let mut record = vec![];
+            new_db.encode_to(&mut record)
+                .and_then(|_| log.add_record(Slice::from_vec(record)))
+                .and_then(|_| log.sync())
+        };
+        if status.is_ok() {
+            // Make "CURRENT" file that points to the new manifest file.
+            status = FileName::set_current_file(&self.options.env, &self.dbname, 1);
+        }
+        if status.is_err() {
+            let _ = self.options.env.remove_file(&manifest);
+        }
+        status
+    }
+
+    /// 锁定数据库, 读取 MANIFEST 恢复 VersionSet, 并将比 MANIFEST 中记录的日志更新的预写日志写入 table。
+    /// 恢复过程中对 VersionSet 的修改记录在 edit 中
+    ///
+    /// Recover the descriptor from persistent storage.  May do a significant
+    /// amount of work to recover recently logged updates.  Any changes to
+    /// be made to the descriptor are added to *edit.
+    fn recover<'a>(&'a self, mut state: StateGuard<'a>, edit: &mut VersionEdit) -> Result<StateGuard<'a>> {
+        let env = &self.options.env;
+        // Ignore error from CreateDir since the creation of the DB is
+        // committed only when the descriptor is created, and this directory
+        // may already exist from a previous failed creation attempt.
+        let _ = env.create_dir(&self.dbname);
+        assert!(state.db_lock.is_none());
+        state.db_lock = Some(env.lock_file(&FileName::lock_file_name(&self.dbname))?);
+
+        if !env.file_exists(&FileName::current_file_name(&self.dbname)) {
+            if self.options.create_if_missing {
+                log_info!(self.options.info_log, "Creating DB {} since it was missing.", self.dbname.display());
+                self.new_db()?;
+            } else {
+                return Err(LevelError::invalid_argument(Slice::from(self.dbname.display().to_string()),
+                                                        Slice::from("does not exist (create_if_missing is false)")));
+            }
+        } else if self.options.error_if_exists {
+            return Err(LevelError::invalid_argument(Slice::from(self.dbname.display().to_string()),
+                                                    Slice::from("exists (error_if_exists is true)")));
+        }
+
+        state.versions.recover()?;
+
+        // Recover from all newer log files than the ones named in the
+        // descriptor (new log files may have been added by the previous
+        // incarnation without registering them in the descriptor).
+        //
+        // Note that PrevLogNumber() is no longer used, but we pay
+        // attention to it in case we are recovering a database
+        // produced by an older version of leveldb.
+        let min_log = state.versions.log_number();
+        let prev_log = state.versions.prev_log_number();
+        let filenames = env.get_children(&self.dbname)?;
+        let mut expected = BTreeSet::new();
+        state.versions.add_live_files(&mut expected);
+        let mut logs = vec![];
+        for filename in filenames {
+            if let Some((number, file_type)) = FileName::parse_file_name(&filename) {
+                expected.remove(&number);
+                if file_type == FileType::KLogFile && (number >= min_log || number == prev_log) {
+                    logs.push(number);
+                }
+            }
+        }
+        if let Some(missing) = expected.first() {
+            return Err(LevelError::corruption_string(
+                &format!("{} missing files; e.g.", expected.len()),
+                &FileName::table_file_name(&self.dbname, *missing).display().to_string()));
+        }
+
+        // Recover in the order in which the logs were generated
+        logs.sort();
+        let mut max_sequence = 0;
+        for log in logs {
+            state = self.recover_log_file(state, log, edit, &mut max_sequence)?;
+
+            // The previous incarnation may not have written any MANIFEST
+            // records after allocating this log number.  So we manually
+            // update the file number allocation counter in VersionSet.
+            state.versions.mark_file_number_used(log);
+        }
+
+        if state.versions.last_sequence() < max_sequence {
+            state.versions.set_last_sequence(max_sequence);
+        }
+        Ok(state)
+    }
+
+    /// 将预写日志中的数据写入 memtable, memtable 写满及读取完成时写入 level 0 之后的 table。
+    /// 损坏的记录会被跳过, options.paranoid_checks 为 true 时返回错误; 读取日志文件失败时总是返回错误
+    fn recover_log_file<'a>(&'a self, mut state: StateGuard<'a>, log_number: u64, edit: &mut VersionEdit,
+                            max_sequence: &mut u64) -> Result<StateGuard<'a>> {
+        // Open the log file
+        let fname = FileName::log_file_name(&self.dbname, log_number);
+        let file = self.options.env.new_sequential_file(&fname)?;
+
+        // We intentionally make log::Reader do checksumming even if
+        // paranoid_checks==false so that corruptions cause entire commits
+        // to be skipped instead of propagating bad information (like overly
+        // large sequence numbers).
+        let mut reader = LogReader::new(Box::new(file), true, 0);
+        log_info!(self.options.info_log, "Recovering log #{}", log_number);
+
+        // Read all the records and add to a memtable
+        let mut batch = WriteBatch::new();
+        let mut mem: Option<MemTable> = None;
+        loop {
+            let status = match reader.read_next() {
+                Ok(Some(record)) if record.len() < 12 => {
+                    Err(LevelError::corruption_string("log record too small", ""))
+                }
+                Ok(Some(record)) => {
+                    batch.set_contents(&record);
+                    let m = mem.get_or_insert_with(|| MemTable::create(self.internal_comparator.user_comparator()));
+                    batch.insert_into(m)
+                }
+                Ok(None) => break,
+                // 只跳过损坏的记录, 读取文件的错误会一直重复出现
+                Err(e) if !e.is_corruption() => return Err(e),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = status {
+                log_warn!(self.options.info_log, "Log #{}: dropping record; {}", log_number, e.get_msg());
+                if self.options.paranoid_checks {
+                    return Err(e);
+                }
+                continue;
+            }
+
+            let last_seq = batch.sequence() + batch.count() as u64 - 1;
+            if last_seq > *max_sequence {
+                *max_sequence = last_seq;
+            }
+
+            if mem.as_ref().is_some_and(|m| m.approximate_memory_usage() > self.options.write_buffer_size) {
+                let m = mem.take().unwrap();
+                let (s, status) = self.write_level0_table(state, &m, edit, None);
+                state = s;
+                // Reflect errors immediately so that conditions like full
+                // file-systems cause the DB::Open() to fail.
+                status?;
+            }
+        }
+
+        if let Some(m) = mem {
+            let (s, status) = self.write_level0_table(state, &m, edit, None);
+            state = s;
+            status?;
+        }
+        Ok(state)
+    }
+
+    /// 创建合并了 memtable、immutable memtable 及当前 Version 中所有 table 的内部迭代器,
+    /// 同时返回当前 Version 及最新的序列号
+    fn new_internal_iterator(&self, options: &ReadOptions) -> (Box<dyn DataIterator>, Arc<Version>, u64) {
+        let state = self.mutex.lock().unwrap();
+        let latest_snapshot = state.versions.last_sequence();
+
+        // Collect together all needed child iterators
+        let mut list = vec![];
+        if let Some(mem) = &state.mem {
+            list.push(mem.new_iterator());
+        }
+        if let Some(imm) = &state.imm {
+            list.push(imm.new_iterator());
+        }
+        let version = state.versions.current();
+        version.add_iterators(&self.table_cache, options, &mut list);
+        let mut internal_iter = new_merging_iterator(Arc::new(self.internal_comparator.clone()), list);
+        // memtable 中没有 filter, 在合并之后按前缀截断
+        if let (true, Some(prefix_extractor)) = (options.prefix_same_as_start, &self.options.prefix_extractor) {
+            internal_iter = new_prefix_iterator(internal_iter, prefix_extractor.clone(), Box::new(|_| true));
+        }
+        (internal_iter, version, latest_snapshot)
+    }
+
+    /// 记录迭代器在 internal key 处读取的样本, 需要时触发 seek compaction
+    ///
+    /// Record a sample of bytes read at the specified internal key.
+    /// Samples are taken approximately once every config::kReadBytesPeriod
+    /// bytes.
+    pub(crate) fn record_read_sample(self: &Arc<Self>, key: &[u8]) {
+        let mut state = self.mutex.lock().unwrap();
+        if state.versions.current().record_read_sample(key) {
+            self.maybe_schedule_compaction(&mut state);
+        }
+    }
+
+    /// 删除数据库目录下所有不再需要的文件, 返回删除的文件数量
+    ///
+    /// 需要保留的文件:
+    /// 1. 当前及上一个(正在 compact 的 memtable 对应的)预写日志
+    /// 2. 当前的 MANIFEST
+    /// 3. 任一存活的 Version 引用的 table
+    /// 4. 正在进行的 compaction 的输出文件(pending_outputs)
+    /// 5. CURRENT、LOCK、LOG 等文件
+    ///
+    /// 被删除的 table 会同时从 TableCache 中移除
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let deleted = db.delete_obsolete_files(&mut state);
+    /// ```
+    pub(crate) fn delete_obsolete_files(&self, state: &mut DBState) -> usize {
+        if !state.bg_error.is_ok() {
+            // After a background error, we don't know whether a new version may
+            // or may not have been committed, so we cannot safely garbage collect.
+            return 0;
+        }
+
+        // Make a set of all of the live files
+        let mut live = state.pending_outputs.clone();
+        state.versions.add_live_files(&mut live);
+
+        // Ignoring errors on purpose
+        let filenames = self.options.env.get_children(&self.dbname).unwrap_or_default();
+        let mut files_to_delete = vec![];
+        for filename in filenames {
+            if let Some((number, file_type)) = FileName::parse_file_name(&filename) {
+                let keep = match file_type {
+                    FileType::KLogFile => {
+                        number >= state.versions.log_number() || number == state.versions.prev_log_number()
+                    }
+                    FileType::KDescriptorFile => {
+                        // Keep my manifest file, and any newer incarnations'
+                        // (in case there is a race that allows other incarnations)
+                        number >= state.versions.manifest_file_number()
+                    }
+                    FileType::KTableFile => live.contains(&number),
+                    // Any temp files that are currently being written to must
+                    // be recorded in pending_outputs_, which is inserted into "live"
+                    FileType::KTempFile => live.contains(&number),
+                    FileType::KCurrentFile | FileType::KDBLockFile | FileType::KInfoLogFile => true,
+                };
+
+                if !keep {
+                    if file_type == FileType::KTableFile {
+                        let _ = self.table_cache.evict(number);
+                    }
+                    log_info!(self.options.info_log, "Delete type={:?} #{}", file_type, number);
+                    files_to_delete.push(filename);
+                }
+            }
+        }
+
+        let mut deleted = 0;
+        for filename in files_to_delete {
+            match self.options.env.remove_file(&self.dbname.join(&filename)) {
+                Ok(_) => deleted += 1,
+                Err(e) => log_warn!(self.options.info_log, "Delete {} failed: {}", filename, e.to_string()),
+            }
+        }
+        log_debug!(self.options.info_log, "Deleted {} obsolete files", deleted);
+
+        deleted
+    }
+
+    /// 将当前的 memtable 切换为 immutable memtable, 并等待后台线程将其写入 level 0 之后的某一层
+    ///
+    /// Force current memtable contents to be compacted.
+    fn flush_mem_table(self: &Arc<Self>) -> Result<()> {
+        let mut state = self.mutex.lock().unwrap();
+        state = self.make_room_for_write(state, true)?;
+
+        // Wait until the compaction completes
+        while state.imm.is_some() && state.bg_error.is_ok() {
+            state = self.bg_cv.wait(state).unwrap();
+        }
+        if state.imm.is_some() {
+            return Err(state.bg_error.clone());
+        }
+        Ok(())
+    }
+
+    /// 对第 level 层中与 user key 范围 [begin, end] 重叠的文件进行 compaction, 并等待完成
+    ///
+    /// Compact any files in the named level that overlap [*begin,*end]
+    fn compact_level_range(self: &Arc<Self>, level: usize, begin: Option<&Slice>, end: Option<&Slice>) -> Result<()> {
+        assert!(level + 1 < Config::K_NUM_LEVELS);
+
+        let manual = ManualCompaction {
+            level,
+            done: false,
+            begin: begin.map(|k| InternalKey::new(k.clone(), K_MAX_SEQUENCE_NUMBER as u64, Config::K_VALUE_TYPE_FOR_SEEK)),
+            end: end.map(|k| InternalKey::new(k.clone(), 0, ValueType::KTypeDeletion)),
+        };
+
+        let mut state = self.mutex.lock().unwrap();
+        // 同一时间只执行一个 manual compaction
+        while state.manual_compaction.is_some() && !self.shutting_down.load(Ordering::Acquire) && state.bg_error.is_ok() {
+            state = self.bg_cv.wait(state).unwrap();
+        }
+        if state.manual_compaction.is_none() {
+            state.manual_compaction = Some(manual);
+            self.maybe_schedule_compaction(&mut state);
+        }
+        while state.manual_compaction.as_ref().is_some_and(|m| !m.done)
+            && !self.shutting_down.load(Ordering::Acquire) && state.bg_error.is_ok() {
+            state = self.bg_cv.wait(state).unwrap();
+        }
+
+        if state.manual_compaction.as_ref().is_some_and(|m| !m.done) {
+            // Finish current background compaction in the case where
+            // the condition variable was signalled due to an error.
+            while state.bg_compaction_scheduled {
+                state = self.bg_cv.wait(state).unwrap();
+            }
+        }
+        // 取消(或者移除已完成的)本次 manual compaction, 并唤醒等待中的其他 manual compaction
+        state.manual_compaction = None;
+        self.bg_cv.notify_all();
+
+        if !state.bg_error.is_ok() {
+            return Err(state.bg_error.clone());
+        }
+        if self.shutting_down.load(Ordering::Acquire) {
+            return Err(LevelError::io_error(Slice::from("Deleting DB during compaction"), Slice::default()));
+        }
+        Ok(())
+    }
+
+    /// 保证 memtable 有空间写入新的数据, force 为 true 时总是将当前 memtable 切换为 immutable memtable
+    ///
+    /// REQUIRES: mutex_ is held
+    fn make_room_for_write<'a>(self: &'a Arc<Self>, mut state: StateGuard<'a>, mut force: bool) -> Result<StateGuard<'a>> {
+        let mut allow_delay = !force;
+        loop {
+            if !state.bg_error.is_ok() {
+                // Yield previous error
+                return Err(state.bg_error.clone());
+            } else if allow_delay && state.versions.num_level_files(0) >= Config::KL0_SLOWDOWN_WRITES_TRIGGER {
+                // We are getting close to hitting a hard limit on the number of
+                // L0 files.  Rather than delaying a single write by several
+                // seconds when we hit the hard limit, start delaying each
+                // individual write by 1ms to reduce latency variance.  Also,
+                // this delay hands over some CPU to the compaction thread in
+                // case it is sharing the same core as the writer.
+                log_debug!(self.options.info_log, "Too many L0 files; delaying write by 1ms");
+                drop(state);
+                thread::sleep(Duration::from_millis(1));
+                // Do not delay a single write more than once
+                allow_delay = false;
+                state = self.mutex.lock().unwrap();
+            } else if !force && state.mem.as_ref()
+                .is_some_and(|mem| mem.approximate_memory_usage() <= self.options.write_buffer_size) {
+                // There is room in current memtable
+                break;
+            } else if state.imm.is_some() {
+                // We have filled up the current memtable, but the previous
+                // one is still being compacted, so we wait.
+                log_info!(self.options.info_log, "Current memtable full; waiting...");
+                state = self.bg_cv.wait(state).unwrap();
+            } else if state.versions.num_level_files(0) >= Config::K_L0_STOP_WRITES_TRIGGER {
+                // There are too many level-0 files.
+                log_info!(self.options.info_log, "Too many L0 files; waiting...");
+                state = self.bg_cv.wait(state).unwrap();
+            } else {
+                // Attempt to switch to a new memtable and trigger compaction of old
+                let new_log_number = state.versions.new_file_number();
+                let file = match self.options.env.new_writable_file(&FileName::log_file_name(&self.dbname, new_log_number)) {
+                    Ok(file) => file,
+                    Err(e) => {
+                        // Avoid chewing through file number space in a tight loop.
+                        state.versions.reuse_file_number(new_log_number);
+                        return Err(e);
+                    }
+                };
+                state.log = Some(LogWriter::new(Box::new(file)));
+                state.logfile_number = new_log_number;
+                state.imm = state.mem.take().map(Arc::new);
+                self.has_imm.store(state.imm.is_some(), Ordering::Release);
+                state.mem = Some(MemTable::create(self.internal_comparator.user_comparator()));
+                // Do not force another compaction if have room
+                force = false;
+                self.maybe_schedule_compaction(&mut state);
+            }
+        }
+        Ok(state)
     }
 
-}
\ No newline at end of file
+    /// 记录后台任务的错误, 之后所有的写入及 compaction 都会返回该错误
+    fn record_background_error(&self, state: &mut DBState, s: Status) {
+        if state.bg_error.is_ok() {
+            state.bg_error = s;
+            self.bg_cv.notify_all();
+        }
+    }
+
+    /// 有需要执行的 compaction 时启动后台线程, 同一时间最多只有一个后台线程
+    ///
+    /// REQUIRES: mutex_ is held
+    pub(crate) fn maybe_schedule_compaction(self: &Arc<Self>, state: &mut DBState) {
+        if state.bg_compaction_scheduled {
+            // Already scheduled
+        } else if self.shutting_down.load(Ordering::Acquire) {
+            // DB is being deleted; no more background compactions
+        } else if !state.bg_error.is_ok() {
+            // Already got an error; no more changes
+        } else if state.imm.is_none() && !state.has_manual_compaction() && !state.versions.needs_compaction() {
+            // No work to be done
+        } else {
+            state.bg_compaction_scheduled = true;
+            let db = self.clone();
+            thread::spawn(move || db.background_call());
+        }
+    }
+
+    /// 后台线程的入口
+    fn background_call(self: &Arc<Self>) {
+        let mut state = self.mutex.lock().unwrap();
+        assert!(state.bg_compaction_scheduled);
+        if self.shutting_down.load(Ordering::Acquire) {
+            // No more background work when shutting down.
+        } else if !state.bg_error.is_ok() {
+            // No more background work after a background error.
+        } else {
+            state = self.background_compaction(state);
+        }
+
+        state.bg_compaction_scheduled = false;
+
+        // Previous compaction may have produced too many files in a level,
+        // so reschedule another compaction if needed.
+        self.maybe_schedule_compaction(&mut state);
+        self.bg_cv.notify_all();
+    }
+
+    /// 执行一次后台任务: immutable memtable 落盘, 或者一次(manual 或者自动选取的) compaction
+    fn background_compaction<'a>(&'a self, mut state: StateGuard<'a>) -> StateGuard<'a> {
+        if state.imm.is_some() {
+            return self.compact_mem_table(state);
+        }
+
+        let is_manual = state.has_manual_compaction();
+        let mut manual_end = None;
+        let c = if is_manual {
+            let m = state.manual_compaction.as_ref().unwrap();
+            let (level, begin, end) = (m.level, m.begin.clone(), m.end.clone());
+            let c = state.versions.compact_range(level, begin.as_ref(), end.as_ref());
+            if let Some(c) = &c {
+                manual_end = Some(c.input(0, c.num_input_files(0) - 1).get_largest().clone());
+            }
+            let key_string = |k: Option<&InternalKey>, default: &str| {
+                k.map_or(default.to_string(), |k| k.debug_string().to_string())
+            };
+            log_info!(self.options.info_log, "Manual compaction at level-{} from {} .. {}; will stop at {}",
+                      level, key_string(begin.as_ref(), "(begin)"), key_string(end.as_ref(), "(end)"),
+                      key_string(manual_end.as_ref(), "(end)"));
+            state.manual_compaction.as_mut().unwrap().done = c.is_none();
+            c
+        } else {
+            state.versions.pick_compaction()
+        };
+
+        let mut status: Result<()> = Ok(());
+        if let Some(mut c) = c {
+            if !is_manual && c.is_trivial_move() {
+                // Move file to next level
+                assert_eq!(1, c.num_input_files(0));
+                let f = c.input(0, 0).clone();
+                let level = c.level() as u32;
+                c.edit().delete_file(level, f.get_number());
+                c.edit().add_file(level + 1, f.get_number(), f.get_file_size(),
+                                  f.get_smallest().clone(), f.get_largest().clone());
+                status = state.versions.log_and_apply(c.edit());
+                if let Err(e) = &status {
+                    self.record_background_error(&mut state, e.clone());
+                }
+                log_info!(self.options.info_log, "Moved #{} to level-{} {} bytes: {}",
+                          f.get_number(), level + 1, f.get_file_size(), state.versions.level_summary());
+            } else {
+                let mut compact = CompactionState::new(c, state.versions.last_sequence());
+                (state, status) = self.do_compaction_work(state, &mut compact);
+                if let Err(e) = &status {
+                    self.record_background_error(&mut state, e.clone());
+                }
+                self.cleanup_compaction(&mut state, compact);
+                self.delete_obsolete_files(&mut state);
+            }
+        }
+
+        if let Err(e) = &status {
+            if !self.shutting_down.load(Ordering::Acquire) {
+                log_error!(self.options.info_log, "Compaction error: {}", e.get_msg());
+            }
+            // Ignore compaction errors found during shutting down
+        }
+
+        if is_manual {
+            let m = state.manual_compaction.as_mut().unwrap();
+            if status.is_err() {
+                m.done = true;
+            }
+            if !m.done {
+                // We only compacted part of the requested range.  Update *m
+                // to the range that is left to be compacted.
+                m.begin = manual_end;
+            }
+        }
+        state
+    }
+
+    /// 将 immutable memtable 写入 table 文件, 并记录到新的 Version 中
+    ///
+    /// Compact the in-memory write buffer to disk.  Switches to a new
+    /// log-file/memtable and writes a new descriptor iff successful.
+    /// Errors are recorded in bg_error_.
+    fn compact_mem_table<'a>(&'a self, state: StateGuard<'a>) -> StateGuard<'a> {
+        let imm = state.imm.clone().unwrap();
+
+        // Save the contents of the memtable as a new Table
+        let mut edit = VersionEdit::new();
+        let base = state.versions.current();
+        let (mut state, mut s) = self.write_level0_table(state, &imm, &mut edit, Some(&base));
+        drop(base);
+
+        if s.is_ok() && self.shutting_down.load(Ordering::Acquire) {
+            s = Err(LevelError::io_error(Slice::from("Deleting DB during memtable compaction"), Slice::default()));
+        }
+
+        // Replace immutable memtable with the generated Table
+        if s.is_ok() {
+            edit.set_prev_log_number(0);
+            // Earlier logs no longer needed
+            edit.set_log_number(state.logfile_number);
+            s = state.versions.log_and_apply(&mut edit);
+        }
+
+        match s {
+            Ok(_) => {
+                // Commit to the new state
+                state.imm = None;
+                self.has_imm.store(false, Ordering::Release);
+                self.delete_obsolete_files(&mut state);
+            }
+            Err(e) => self.record_background_error(&mut state, e),
+        }
+        state
+    }
+
+    /// 将 mem 写入新的 table 文件, 写入的层级由 base 中与其重叠的文件决定
+    fn write_level0_table<'a>(&'a self, mut state: StateGuard<'a>, mem: &MemTable,
+                              edit: &mut VersionEdit, base: Option<&Arc<Version>>) -> (StateGuard<'a>, Result<()>) {
+        let start = Instant::now();
+        let mut meta = FileMetaData::default();
+        meta.set_number(state.versions.new_file_number());
+        state.pending_outputs.insert(meta.get_number());
+        let mut iter = mem.new_iterator();
+        log_info!(self.options.info_log, "Level-0 table #{}: started", meta.get_number());
+
+        // 生成 table 之前按 memtable 的 key 范围选择写入的层, table 使用该层的压缩类型
+        let mut level = 0;
+        if let Some(base) = base {
+            iter.seek_to_first();
+            if iter.valid() {
+                let smallest = ParsedInternalKey::extract_user_key(iter.key().as_ref()).to_vec();
+                iter.seek_to_last();
+                let largest = ParsedInternalKey::extract_user_key(iter.key().as_ref()).to_vec();
+                level = base.pick_level_for_memtable_output(&smallest, &largest);
+            }
+        }
+
+        drop(state);
+        let s = BuildTable::build_table(&self.dbname, &self.options.env, &self.options, &self.table_cache,
+                                        iter.as_mut(), &mut meta, level);
+        let mut state = self.mutex.lock().unwrap();
+
+        log_info!(self.options.info_log, "Level-0 table #{}: {} bytes {}", meta.get_number(), meta.get_file_size(),
+                  s.as_ref().map_or_else(|e| e.get_msg(), |_| "OK".to_string()));
+        state.pending_outputs.remove(&meta.get_number());
+
+        // Note that if file_size is zero, the file has been deleted and
+        // should not be added to the manifest.
+        if s.is_ok() && meta.get_file_size() > 0 {
+            edit.add_file(level as u32, meta.get_number(), meta.get_file_size(),
+                          meta.get_smallest().clone(), meta.get_largest().clone());
+        }
+
+        let stats = CompactionStats {
+            micros: start.elapsed().as_micros() as u64,
+            bytes_read: 0,
+            bytes_written: meta.get_file_size(),
+        };
+        state.stats[level].add(&stats);
+        (state, s)
+    }
+
+    /// 合并 compaction 的输入文件, 丢弃被覆盖及已删除的数据, 生成下一层的文件并安装新的 Version
+    ///
+    /// 执行期间释放锁, 并优先处理 immutable memtable 的落盘
+    fn do_compaction_work<'a>(&'a self, state: StateGuard<'a>,
+                              compact: &mut CompactionState) -> (StateGuard<'a>, Result<()>) {
+        let start = Instant::now();
+        // Micros spent doing imm_ compactions
+        let mut imm_micros = 0;
+
+        let c = &compact.compaction;
+        log_info!(self.options.info_log, "Compacting {}@{} + {}@{} files", c.num_input_files(0), c.level(),
+                  c.num_input_files(1), c.level() + 1);
+        assert!(state.versions.num_level_files(c.level()) > 0);
+        assert!(compact.builder.is_none());
+        assert!(compact.outputs.is_empty());
+
+        let mut input = state.versions.make_input_iterator(c);
+
+        // Release mutex while we're actually doing the compaction work
+        drop(state);
+
+        input.seek_to_first();
+        let mut status: Result<()> = Ok(());
+        let ucmp = self.internal_comparator.user_comparator();
+        let mut current_user_key: Option<Vec<u8>> = None;
+        let mut last_sequence_for_key = K_MAX_SEQUENCE_NUMBER as u64;
+        let mut key = vec![];
+        while input.valid() && !self.shutting_down.load(Ordering::Acquire) {
+            // Prioritize immutable compaction work
+            if self.has_imm.load(Ordering::Relaxed) {
+                let imm_start = Instant::now();
+                let mut state = self.mutex.lock().unwrap();
+                if state.imm.is_some() {
+                    state = self.compact_mem_table(state);
+                    // Wake up MakeRoomForWrite() if necessary.
+                    self.bg_cv.notify_all();
+                }
+                drop(state);
+                imm_micros += imm_start.elapsed().as_micros() as u64;
+            }
+
+            key.clear();
+            key.extend_from_slice(input.key().as_ref());
+            if compact.compaction.should_stop_before(&key) && compact.builder.is_some() {
+                status = self.finish_compaction_output_file(compact, input.as_ref());
+                if status.is_err() {
+                    break;
+                }
+            }
+
+            // Handle key/value, add to state, etc.
+            let mut drop_entry = false;
+            match ParsedInternalKey::parse_internal_key(&key) {
+                None => {
+                    // Do not hide error keys
+                    current_user_key = None;
+                    last_sequence_for_key = K_MAX_SEQUENCE_NUMBER as u64;
+                }
+                Some(ikey) => {
+                    let user_key: &[u8] = ikey.user_key().as_ref();
+                    if current_user_key.as_ref()
+                        .is_none_or(|k| ucmp.compare(user_key, k) != Some(std::cmp::Ordering::Equal)) {
+                        // First occurrence of this user key
+                        current_user_key = Some(user_key.to_vec());
+                        last_sequence_for_key = K_MAX_SEQUENCE_NUMBER as u64;
+                    }
+
+                    if last_sequence_for_key <= compact.smallest_snapshot {
+                        // Hidden by an newer entry for same user key
+                        drop_entry = true; // (A)
+                    } else if ikey.value_type() == ValueType::KTypeDeletion
+                        && ikey.sequence() <= compact.smallest_snapshot
+                        && compact.compaction.is_base_level_for_key(user_key) {
+                        // For this user key:
+                        // (1) there is no data in higher levels
+                        // (2) data in lower levels will have larger sequence numbers
+                        // (3) data in layers that are being compacted here and have
+                        //     smaller sequence numbers will be dropped in the next
+                        //     few iterations of this loop (by rule (A) above).
+                        // Therefore this deletion marker is obsolete and can be dropped.
+                        drop_entry = true;
+                    }
+
+                    last_sequence_for_key = ikey.sequence();
+                }
+            }
+
+            if !drop_entry {
+                // Open output file if necessary
+                if compact.builder.is_none() {
+                    status = self.open_compaction_output_file(compact);
+                    if status.is_err() {
+                        break;
+                    }
+                }
+                let builder = compact.builder.as_mut().unwrap();
+                let output = compact.outputs.last_mut().unwrap();
+                if builder.get_num_entries() == 0 {
+                    output.smallest.decode_from(&key);
+                }
+                output.largest.decode_from(&key);
+                builder.add(&key, input.value().as_ref());
+
+                // Close output file if it is big enough
+                if builder.get_file_size() >= compact.compaction.max_output_file_size() {
+                    status = self.finish_compaction_output_file(compact, input.as_ref());
+                    if status.is_err() {
+                        break;
+                    }
+                }
+            }
+
+            input.next();
+        }
+
+        if status.is_ok() && self.shutting_down.load(Ordering::Acquire) {
+            status = Err(LevelError::io_error(Slice::from("Deleting DB during compaction"), Slice::default()));
+        }
+        if status.is_ok() && compact.builder.is_some() {
+            status = self.finish_compaction_output_file(compact, input.as_ref());
+        }
+        if status.is_ok() {
+            let s = input.status();
+            if !s.is_ok() {
+                status = Err(s);
+            }
+        }
+        drop(input);
+
+        let mut stats = CompactionStats {
+            micros: (start.elapsed().as_micros() as u64).saturating_sub(imm_micros),
+            ..Default::default()
+        };
+        for which in 0..2 {
+            for f in compact.compaction.inputs(which) {
+                stats.bytes_read += f.get_file_size();
+            }
+        }
+        for output in &compact.outputs {
+            stats.bytes_written += output.file_size;
+        }
+
+        let mut state = self.mutex.lock().unwrap();
+        state.stats[compact.compaction.level() + 1].add(&stats);
+
+        if status.is_ok() {
+            status = self.install_compaction_results(&mut state, compact);
+        }
+        log_info!(self.options.info_log, "compacted to: {}", state.versions.level_summary());
+        (state, status)
+    }
+
+    /// 分配新的文件号并创建 compaction 的输出文件
+    fn open_compaction_output_file(&self, compact: &mut CompactionState) -> Result<()> {
+        assert!(compact.builder.is_none());
+        let file_number = {
+            let mut state = self.mutex.lock().unwrap();
+            let file_number = state.versions.new_file_number();
+            state.pending_outputs.insert(file_number);
+            file_number
+        };
+        compact.outputs.push(CompactionOutput {
+            number: file_number,
+            file_size: 0,
+            smallest: InternalKey::default(),
+            largest: InternalKey::default(),
+        });
+
+        // Make the output file
+        let file = self.options.env.new_writable_file(&FileName::table_file_name(&self.dbname, file_number))?;
+        let mut builder = TableBuilder::new_with_writable_file(self.options.clone(), Box::new(file));
+        builder.set_compression(self.options.compression_for_level(compact.compaction.level() + 1));
+        compact.builder = Some(builder);
+        Ok(())
+    }
+
+    /// 完成当前的输出文件, 并检查生成的 table 是否可用
+    fn finish_compaction_output_file(&self, compact: &mut CompactionState, input: &dyn DataIterator) -> Result<()> {
+        let mut builder = compact.builder.take().unwrap();
+        let output = compact.outputs.last_mut().unwrap();
+        let output_number = output.number;
+        assert_ne!(0, output_number);
+
+        // Check for iterator errors
+        let input_status = input.status();
+        let current_entries = builder.get_num_entries();
+        let mut s = if input_status.is_ok() {
+            builder.finish()
+        } else {
+            builder.abandon();
+            Err(input_status)
+        };
+        let current_bytes = builder.get_file_size();
+        output.file_size = current_bytes;
+        compact.total_bytes += current_bytes;
+
+        // Finish and check for file errors
+        if s.is_ok() {
+            s = builder.sync();
+        }
+        drop(builder);
+
+        if s.is_ok() && current_entries > 0 {
+            // Verify that the table is usable
+            let iter = self.table_cache.new_iterator(&ReadOptions::default(), output_number, current_bytes);
+            let status = iter.status();
+            if status.is_ok() {
+                log_info!(self.options.info_log, "Generated table #{}@{}: {} keys, {} bytes", output_number,
+                          compact.compaction.level(), current_entries, current_bytes);
+            } else {
+                s = Err(status);
+            }
+        }
+        s
+    }
+
+    /// 删除 compaction 的输入文件, 将输出文件加入下一层
+    fn install_compaction_results(&self, state: &mut DBState, compact: &mut CompactionState) -> Result<()> {
+        let c = &mut compact.compaction;
+        log_info!(self.options.info_log, "Compacted {}@{} + {}@{} files => {} bytes",
+                  c.num_input_files(0), c.level(), c.num_input_files(1), c.level() + 1, compact.total_bytes);
+
+        // Add compaction outputs
+        c.add_input_deletions();
+        let level = c.level() as u32;
+        for output in &compact.outputs {
+            c.edit().add_file(level + 1, output.number, output.file_size,
+                              output.smallest.clone(), output.largest.clone());
+        }
+        state.versions.log_and_apply(c.edit())
+    }
+
+    /// 放弃未完成的输出文件, 并释放 compaction 输出文件的文件号
+    fn cleanup_compaction(&self, state: &mut DBState, mut compact: CompactionState) {
+        if let Some(mut builder) = compact.builder.take() {
+            // May happen if we get a shutdown call in the middle of compaction
+            builder.abandon();
+        }
+        for output in &compact.outputs {
+            state.pending_outputs.remove(&output.number);
+        }
+        compact.compaction.release_inputs();
+    }
+}
+
+impl DBState {
+    /// 是否有未完成的 manual compaction
+    fn has_manual_compaction(&self) -> bool {
+        self.manual_compaction.as_ref().is_some_and(|m| !m.done)
+    }
+}
+
+impl CompactionState {
+    fn new(compaction: Compaction, smallest_snapshot: u64) -> Self {
+        Self {
+            compaction,
+            smallest_snapshot,
+            outputs: vec![],
+            builder: None,
+            total_bytes: 0,
+        }
+    }
+}
@@ -1,13 +1,13 @@
 use std::cmp::Ordering;
-use std::io::Write;
+use std::fmt::Write;
 use std::sync::Arc;
 use crate::db::db_format::ValueType::{KTypeDeletion, KTypeValue};
-use crate::db::file_meta_data::FileMetaData;
 use crate::traits::comparator_trait::Comparator;
-use crate::util::coding::{Encoder, varint_length};
+use crate::util::coding::Encoder;
+use crate::util::comparator::BytewiseComparatorImpl;
 use crate::util::slice::Slice;
-use crate::util::unsafe_slice::UnsafeSlice;
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ValueType {
     /// 0x0
     KTypeDeletion,
@@ -22,12 +22,14 @@ pub struct ParsedInternalKey {
     value_type: ValueType,
 }
 
-#[derive(Debug)]
+/// 内部键: |user key|sequence number(7 bytes)|value type(1 byte)|
+#[derive(Debug, Clone)]
 pub struct InternalKey {
     rep_: Slice,
 }
 
 /// InternalKeyComparator
+#[derive(Clone)]
 pub struct InternalKeyComparator {
     user_comparator_: Arc<dyn Comparator>,
 }
@@ -93,7 +95,8 @@ impl Default for ParsedInternalKey {
 
 impl ParsedInternalKey {
     pub fn debug_string(&self) -> Slice {
-        Slice::default()
+        Slice::from(format!("'{}' @ {} : {}",
+                            escape_string(&self.user_key), self.sequence, self.value_type.get_value()))
     }
 
     /// Return the length of the encoding of "key".
@@ -101,9 +104,10 @@ impl ParsedInternalKey {
         key.user_key.size() + 8
     }
 
-    // 将 self 的数据追加到 result 中
-    pub fn append_internal_key(&self, result: Slice) {
-        todo!()
+    /// 将 self 编码为内部键并追加到 result 中
+    pub fn append_internal_key(&self, result: &mut Vec<u8>) {
+        result.extend_from_slice(&self.user_key);
+        result.extend_from_slice(&pack_sequence_and_type(self.sequence as usize, self.value_type).to_le_bytes());
     }
 
     pub fn new(user_key: Slice, sequence: u64, value_type: ValueType) -> Self {
@@ -114,17 +118,56 @@ impl ParsedInternalKey {
         }
     }
 
-    /// Attempt to parse an internal key from "internal_key".  On success,
-    /// stores the parsed data in "*result", and returns true.
-    /// On error, returns false, leaves "*result" in an undefined state.
-    pub fn parse_internal_key(internal_key: Slice, target: ParsedInternalKey) -> bool {
-        // line 173
-        todo!()
+    pub fn user_key(&self) -> &Slice {
+        &self.user_key
+    }
+
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    pub fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    /// 解析内部键, 内部键长度不足 8 字节或者 value type 不合法时返回 None
+    ///
+    /// Attempt to parse an internal key from "internal_key".
+    ///
+    /// # Arguments
+    ///
+    /// * `internal_key`: 编码后的内部键
+    ///
+    /// returns: Option<ParsedInternalKey>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::db::db_format::{InternalKey, ParsedInternalKey, ValueType};
+    /// use level_db_rust::util::slice::Slice;
+    ///
+    /// let key = InternalKey::new(Slice::from("foo"), 100, ValueType::KTypeValue);
+    /// let parsed = ParsedInternalKey::parse_internal_key(key.encode()).unwrap();
+    /// assert_eq!(100, parsed.sequence());
+    /// ```
+    pub fn parse_internal_key(internal_key: &[u8]) -> Option<ParsedInternalKey> {
+        let n = internal_key.len();
+        if n < 8 {
+            return None;
+        }
+        let num = u64::from_le_bytes(internal_key[n - 8..].try_into().ok()?);
+        let value_type = ValueType::try_from((num & 0xff) as i32).ok()?;
+        Some(ParsedInternalKey {
+            user_key: Slice::from_buf(&internal_key[..n - 8]),
+            sequence: num >> 8,
+            value_type,
+        })
     }
 
     /// Returns the user key portion of an internal key.
-    pub fn extract_user_key(internal_key: Slice) -> Slice {
-        todo!()
+    pub fn extract_user_key(internal_key: &[u8]) -> &[u8] {
+        assert!(internal_key.len() >= 8);
+        &internal_key[..internal_key.len() - 8]
     }
 }
 
@@ -147,44 +190,46 @@ impl PartialEq for InternalKey {
 
 impl InternalKey {
     pub fn new(user_key: Slice, sequence: u64, value_type: ValueType) -> Self {
-        // line 145
-        let result: Slice = Slice::default();
+        let mut result = Vec::with_capacity(user_key.size() + 8);
         ParsedInternalKey::new(user_key, sequence, value_type)
-            .append_internal_key(result);
+            .append_internal_key(&mut result);
 
         Self {
-            // rep_: result
-            // todo result值如何赋值
-            rep_: Slice::default()
+            rep_: Slice::from_vec(result)
         }
     }
 
-    ///
-    /// xxx
+    /// 从编码后的内部键中解码, 返回解码后的内部键是否非空
     ///
     /// # Arguments
-    /// * `input`:
+    /// * `input`: 编码后的内部键
     ///
     /// # Examples
     ///
     /// ```
+    /// use level_db_rust::db::db_format::{InternalKey, ValueType};
+    /// use level_db_rust::util::slice::Slice;
     ///
+    /// let other = InternalKey::new(Slice::from("foo"), 100, ValueType::KTypeValue);
+    /// let mut key = InternalKey::default();
+    /// assert!(key.decode_from(other.encode()));
     /// ```
-    pub fn decode_from(&self, input: &UnsafeSlice) {
-        todo!()
-
-        // wangbo
-        // self.rep_.assign(input.borrow_data().bytes());
+    pub fn decode_from(&mut self, input: &[u8]) -> bool {
+        self.rep_ = Slice::from_buf(input);
+        !self.rep_.empty()
     }
 
     /// 输出 InternalKey 调试信息
     pub fn debug_string(&self) -> Slice {
-        // line 164
-        todo!()
+        match ParsedInternalKey::parse_internal_key(&self.rep_) {
+            Some(parsed) => parsed.debug_string(),
+            None => Slice::from(format!("(bad){}", escape_string(&self.rep_)))
+        }
     }
 
-    pub fn encode(self) -> Slice {
-        self.rep_
+    pub fn encode(&self) -> &[u8] {
+        assert!(!self.rep_.empty());
+        &self.rep_
     }
 
     /// 取得  Slice的长度
@@ -192,50 +237,67 @@ impl InternalKey {
         self.rep_.size()
     }
 
-    pub fn user_key(self) -> Slice {
-        ParsedInternalKey::extract_user_key(self.rep_)
+    pub fn user_key(&self) -> &[u8] {
+        ParsedInternalKey::extract_user_key(&self.rep_)
     }
 
-    pub fn set_from(self, p: ParsedInternalKey) {
-        // self.rep_.clear();
-        p.append_internal_key(self.rep_);
+    pub fn set_from(&mut self, p: &ParsedInternalKey) {
+        let mut result = Vec::with_capacity(p.user_key.size() + 8);
+        p.append_internal_key(&mut result);
+        self.rep_ = Slice::from_vec(result);
     }
 
-    pub fn clear(self) {
-        // self.rep_.clear();
+    pub fn clear(&mut self) {
+        self.rep_ = Slice::default();
     }
 }
 
 impl Default for InternalKeyComparator {
     fn default() -> Self {
-        todo!()
+        Self::new(Arc::new(BytewiseComparatorImpl::default()))
     }
 }
 
 impl InternalKeyComparator {
-    pub fn create(_cmp: Box<dyn Comparator>) -> Box<Self> {
-        todo!()
+    /// 使用用户提供的比较器构造 InternalKeyComparator
+    pub fn new(user_comparator: Arc<dyn Comparator>) -> Self {
+        Self {
+            user_comparator_: user_comparator
+        }
     }
 
-    pub fn user_comparator(&self) -> Box<dyn Comparator> {
-        todo!()
+    pub fn user_comparator(&self) -> Arc<dyn Comparator> {
+        self.user_comparator_.clone()
     }
 
-    pub fn compare_internal_key(&self, key1: &InternalKey, key2: &InternalKey) -> u32 {
-        // line 122, 167
-        todo!()
+    pub fn compare_internal_key(&self, key1: &InternalKey, key2: &InternalKey) -> Ordering {
+        self.compare_key(key1.encode(), key2.encode())
+    }
+
+    /// 比较两个编码后的内部键
+    ///
+    /// Order by:
+    ///    increasing user key (according to user-supplied comparator)
+    ///    decreasing sequence number
+    ///    decreasing type (though sequence# should be enough to disambiguate)
+    pub fn compare_key(&self, akey: &[u8], bkey: &[u8]) -> Ordering {
+        let r = self.user_comparator_.compare(ParsedInternalKey::extract_user_key(akey),
+                                              ParsedInternalKey::extract_user_key(bkey))
+            .unwrap_or(Ordering::Equal);
+        if r != Ordering::Equal {
+            return r;
+        }
+        let anum = u64::from_le_bytes(akey[akey.len() - 8..].try_into().unwrap());
+        let bnum = u64::from_le_bytes(bkey[bkey.len() - 8..].try_into().unwrap());
+        bnum.cmp(&anum)
     }
 }
 
 /// InternalKeyComparator 比较器: 用来比较内部键（Internal Key）。
 /// 内部键值是为了方便处理，将原普通键、序列号和值类型组成的新键。
 impl Comparator for InternalKeyComparator {
-    // fn new(c: Box<ComparatorTrait>) -> InternalKeyComparator {
-    //     todo!()
-    // }
-
-    fn compare(&self, _a: &[u8], _b: &[u8]) -> Option<Ordering> {
-        todo!()
+    fn compare(&self, a: &[u8], b: &[u8]) -> Option<Ordering> {
+        Some(self.compare_key(a, b))
     }
 
     fn get_name(&self) -> String {
@@ -251,6 +313,19 @@ impl Comparator for InternalKeyComparator {
     }
 }
 
+/// 将 key 转为可读的字符串, 不可打印的字符以 \x.. 的形式输出
+pub fn escape_string(key: &[u8]) -> String {
+    let mut result = String::with_capacity(key.len());
+    for &c in key {
+        if (b' '..=b'~').contains(&c) {
+            result.push(c as char);
+        } else {
+            let _ = write!(result, "\\x{:02x}", c);
+        }
+    }
+    result
+}
+
 impl LookupKey {
     /// Initialize *this for looking up user_key at a snapshot with
     /// the specified sequence number.
@@ -258,13 +333,13 @@ impl LookupKey {
         let user_key_size = user_key.size();
         let need = user_key_size + 13; // A conservative estimate
         let mut data = Vec::with_capacity(need);
-        let buf = data.as_mut_slice();
-        let mut encoder = Encoder::with_buf(buf);
-        // write key size
-        let klength = varint_length((user_key_size + 8) as u64);
-        // 需要保证写入的数据不会超过buf.len(), 否则会溢出
+        let mut encoder = Encoder::with_vec(&mut data);
+        // vec 会自动扩容, 写入不会溢出
+        let user_key_start;
         unsafe {
-            encoder.uncheck_put_varint32(klength as u32);
+            // write key size
+            encoder.uncheck_put_varint32((user_key_size + 8) as u32);
+            user_key_start = encoder.offset();
             // write key slice
             encoder.uncheck_put_buf(user_key.as_ref());
             // write sequence number and value type
@@ -273,7 +348,7 @@ impl LookupKey {
 
         LookupKey {
             data: Slice::from_vec(data),
-            user_key_start: klength
+            user_key_start
         }
     }
 
@@ -293,7 +368,8 @@ impl LookupKey {
     /// Return the user key
     pub fn user_key(&self) -> Slice {
         // line 207
-        todo!()
+        let buf = self.data.as_ref();
+        Slice::from_buf(&buf[self.user_key_start..buf.len() - 8])
     }
 }
 
@@ -316,7 +392,7 @@ impl LookupKey {
 //     }
 // }
 
-const K_MAX_SEQUENCE_NUMBER: usize = (1 << 56) - 1;
+pub const K_MAX_SEQUENCE_NUMBER: usize = (1 << 56) - 1;
 
 #[inline]
 pub fn pack_sequence_and_type(seq_no: usize, v_type: ValueType) -> u64 {
@@ -1,6 +1,27 @@
-use crate::db::db_format::Config;
+#![cfg(test)]
+use crate::db::db_format::{Config, LookupKey, ParsedInternalKey, ValueType};
+use crate::util::slice::Slice;
 
 #[test]
 fn test_db_format() {
     Config::K_NUM_LEVELS;
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_lookup_key() {
+    let key = LookupKey::new(Slice::from("foo"), 100);
+    assert_eq!(b"foo", key.user_key().as_ref());
+    // memtable key 以 internal key 的长度作为前缀
+    assert_eq!(11, key.mem_table_key().as_ref()[0]);
+    assert_eq!(&key.mem_table_key().as_ref()[1..], key.internal_key().as_ref());
+    let parsed = ParsedInternalKey::parse_internal_key(key.internal_key().as_ref()).unwrap();
+    assert_eq!(b"foo", parsed.user_key().as_ref());
+    assert_eq!(100, parsed.sequence());
+    assert_eq!(ValueType::KTypeValue, parsed.value_type());
+
+    // 长度前缀超过一个字节
+    let user_key = "k".repeat(200);
+    let key = LookupKey::new(Slice::from(user_key.as_str()), 7);
+    assert_eq!(user_key.as_bytes(), key.user_key().as_ref());
+    assert_eq!(2 + 208, key.mem_table_key().size());
+}
@@ -0,0 +1,334 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+use rand::Rng;
+use crate::db::db::DBInner;
+use crate::db::db_format::{Config, ParsedInternalKey, ValueType};
+use crate::db::version_set::Version;
+use crate::traits::comparator_trait::Comparator;
+use crate::traits::DataIterator;
+use crate::util::slice::Slice;
+use crate::util::status::{LevelError, Status};
+use crate::util::unsafe_slice::UnsafeSlice;
+
+/// Which direction is the iterator currently moving?
+/// (1) When moving forward, the internal iterator is positioned at
+///     the exact entry that yields this->key(), this->value()
+/// (2) When moving backwards, the internal iterator is positioned
+///     just before all entries whose user key == this->key().
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Direction {
+    Forward,
+    Reverse,
+}
+
+/// DB 的迭代器, 将内部迭代器中的 (internal key, value) 转换为用户看到的 (user key, value)。
+///
+/// 同一个 user key 只返回序列号不大于 sequence 的最新的一条记录, 最新的记录为删除时跳过该 user key
+///
+/// Memtables and sstables that make the DB representation contain
+/// (userkey,seq,type) => uservalue entries.  DBIter
+/// combines multiple entries for the same userkey found in the DB
+/// representation into a single entry while accounting for sequence
+/// numbers, deletion markers, overwrites, etc.
+struct DBIter {
+    db: Arc<DBInner>,
+    user_comparator: Arc<dyn Comparator>,
+    iter: Box<dyn DataIterator>,
+    sequence: u64,
+    status: Status,
+    // == current key when direction_==kReverse
+    saved_key: Vec<u8>,
+    // == current raw value when direction_==kReverse
+    saved_value: Vec<u8>,
+    direction: Direction,
+    valid: bool,
+    bytes_until_read_sampling: usize,
+    // 迭代期间 Version 引用的 table 文件不会被删除
+    _version: Arc<Version>,
+}
+
+/// 创建 DB 的迭代器
+///
+/// Return a new iterator that converts internal keys (yielded by
+/// "*internal_iter") that were live at the specified "sequence" number
+/// into appropriate user keys.
+///
+/// # Arguments
+///
+/// * `db`: 迭代器读取的 DB, 用于记录读取的样本以触发 seek compaction
+/// * `user_comparator`: user key 的比较器
+/// * `internal_iter`: 合并了 memtable 及所有 table 的内部迭代器
+/// * `version`: 创建 internal_iter 时的 Version
+/// * `sequence`: 只返回序列号不大于 sequence 的数据
+///
+/// returns: Box<dyn DataIterator, Global>
+///
+/// # Examples
+///
+/// ```ignore
+/// let iter = new_db_iterator(db.clone(), ucmp, internal_iter, version, last_sequence);
+/// ```
+pub(crate) fn new_db_iterator(db: Arc<DBInner>, user_comparator: Arc<dyn Comparator>,
+                              internal_iter: Box<dyn DataIterator>, version: Arc<Version>,
+                              sequence: u64) -> Box<dyn DataIterator> {
+    Box::new(DBIter {
+        db,
+        user_comparator,
+        iter: internal_iter,
+        sequence,
+        status: Status::default(),
+        saved_key: vec![],
+        saved_value: vec![],
+        direction: Direction::Forward,
+        valid: false,
+        bytes_until_read_sampling: random_compaction_period(),
+        _version: version,
+    })
+}
+
+/// Picks the number of bytes that can be read until a compaction is scheduled.
+fn random_compaction_period() -> usize {
+    rand::thread_rng().gen_range(0..2 * Config::K_READ_BYTES_PERIOD)
+}
+
+/// 将 k 复制到 dst 中
+#[inline]
+fn save_key(k: &[u8], dst: &mut Vec<u8>) {
+    dst.clear();
+    dst.extend_from_slice(k);
+}
+
+impl DBIter {
+    #[inline]
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        self.user_comparator.compare(a, b).unwrap_or(Ordering::Equal)
+    }
+
+    /// 解析内部迭代器当前的 key, 并按照读取的字节数采样
+    fn parse_key(&mut self) -> Option<ParsedInternalKey> {
+        let k = self.iter.key();
+        let bytes_read = k.len() + self.iter.value().len();
+        while self.bytes_until_read_sampling < bytes_read {
+            self.bytes_until_read_sampling += random_compaction_period();
+            self.db.record_read_sample(k.as_ref());
+        }
+        self.bytes_until_read_sampling -= bytes_read;
+
+        let parsed = ParsedInternalKey::parse_internal_key(k.as_ref());
+        if parsed.is_none() {
+            self.status = LevelError::corruption_string("corrupted internal key in DBIter", "");
+        }
+        parsed
+    }
+
+    /// 从内部迭代器的当前位置向后查找第一个可见的记录。
+    /// skipping 为 true 时跳过 user key 不大于 saved_key 的记录
+    fn find_next_user_entry(&mut self, mut skipping: bool) {
+        // Loop until we hit an acceptable entry to yield
+        assert!(self.iter.valid());
+        assert_eq!(self.direction, Direction::Forward);
+        loop {
+            if let Some(ikey) = self.parse_key() {
+                if ikey.sequence() <= self.sequence {
+                    match ikey.value_type() {
+                        ValueType::KTypeDeletion => {
+                            // Arrange to skip all upcoming entries for this key since
+                            // they are hidden by this deletion.
+                            save_key(ikey.user_key(), &mut self.saved_key);
+                            skipping = true;
+                        }
+                        ValueType::KTypeValue => {
+                            if skipping && self.compare(ikey.user_key(), &self.saved_key) != Ordering::Greater {
+                                // Entry hidden
+                            } else {
+                                self.valid = true;
+                                self.saved_key.clear();
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+            self.iter.next();
+            if !self.iter.valid() {
+                break;
+            }
+        }
+        self.saved_key.clear();
+        self.valid = false;
+    }
+
+    /// 从内部迭代器的当前位置向前查找 user key 最大的可见记录, 保存到 saved_key 及 saved_value 中
+    fn find_prev_user_entry(&mut self) {
+        assert_eq!(self.direction, Direction::Reverse);
+
+        let mut value_type = ValueType::KTypeDeletion;
+        if self.iter.valid() {
+            loop {
+                if let Some(ikey) = self.parse_key() {
+                    if ikey.sequence() <= self.sequence {
+                        if value_type != ValueType::KTypeDeletion
+                            && self.compare(ikey.user_key(), &self.saved_key) == Ordering::Less {
+                            // We encountered a non-deleted value in entries for previous keys,
+                            break;
+                        }
+                        value_type = ikey.value_type();
+                        if value_type == ValueType::KTypeDeletion {
+                            self.saved_key.clear();
+                            self.saved_value.clear();
+                        } else {
+                            save_key(ikey.user_key(), &mut self.saved_key);
+                            save_key(self.iter.value().as_ref(), &mut self.saved_value);
+                        }
+                    }
+                }
+                self.iter.pre();
+                if !self.iter.valid() {
+                    break;
+                }
+            }
+        }
+
+        if value_type == ValueType::KTypeDeletion {
+            // End
+            self.valid = false;
+            self.saved_key.clear();
+            self.saved_value.clear();
+            self.direction = Direction::Forward;
+        } else {
+            self.valid = true;
+        }
+    }
+}
+
+impl DataIterator for DBIter {
+    fn valid(&self) -> bool {
+        self.valid
+    }
+
+    fn seek_to_first(&mut self) {
+        self.direction = Direction::Forward;
+        self.saved_value.clear();
+        self.iter.seek_to_first();
+        if self.iter.valid() {
+            self.find_next_user_entry(false);
+        } else {
+            self.valid = false;
+        }
+    }
+
+    fn seek_to_last(&mut self) {
+        self.direction = Direction::Reverse;
+        self.saved_value.clear();
+        self.iter.seek_to_last();
+        self.find_prev_user_entry();
+    }
+
+    /// target 为 user key
+    fn seek(&mut self, target: &Slice) {
+        self.direction = Direction::Forward;
+        self.saved_value.clear();
+        self.saved_key.clear();
+        ParsedInternalKey::new(target.clone(), self.sequence, Config::K_VALUE_TYPE_FOR_SEEK)
+            .append_internal_key(&mut self.saved_key);
+        self.iter.seek(&Slice::from_buf(&self.saved_key));
+        if self.iter.valid() {
+            self.find_next_user_entry(false);
+        } else {
+            self.valid = false;
+        }
+    }
+
+    fn next(&mut self) {
+        assert!(self.valid);
+
+        if self.direction == Direction::Reverse {
+            // Switch directions?
+            self.direction = Direction::Forward;
+            // iter_ is pointing just before the entries for this->key(),
+            // so advance into the range of entries for this->key() and then
+            // use the normal skipping code below.
+            if !self.iter.valid() {
+                self.iter.seek_to_first();
+            } else {
+                self.iter.next();
+            }
+            if !self.iter.valid() {
+                self.valid = false;
+                self.saved_key.clear();
+                return;
+            }
+            // saved_key_ already contains the key to skip past.
+        } else {
+            // Store in saved_key_ the current key so we skip it below.
+            save_key(ParsedInternalKey::extract_user_key(self.iter.key().as_ref()), &mut self.saved_key);
+
+            // iter_ is pointing to current key. We can now safely move to the next to
+            // avoid checking current key.
+            self.iter.next();
+            if !self.iter.valid() {
+                self.valid = false;
+                self.saved_key.clear();
+                return;
+            }
+        }
+
+        self.find_next_user_entry(true);
+    }
+
+    fn pre(&mut self) {
+        assert!(self.valid);
+
+        if self.direction == Direction::Forward {
+            // Switch directions?
+            // iter_ is pointing at the current entry.  Scan backwards until
+            // the key changes so we can use the normal reverse scanning code.
+            assert!(self.iter.valid());
+            save_key(ParsedInternalKey::extract_user_key(self.iter.key().as_ref()), &mut self.saved_key);
+            loop {
+                self.iter.pre();
+                if !self.iter.valid() {
+                    self.valid = false;
+                    self.saved_key.clear();
+                    self.saved_value.clear();
+                    return;
+                }
+                let key = self.iter.key();
+                if self.compare(ParsedInternalKey::extract_user_key(key.as_ref()), &self.saved_key) == Ordering::Less {
+                    break;
+                }
+            }
+            self.direction = Direction::Reverse;
+        }
+
+        self.find_prev_user_entry();
+    }
+
+    /// 返回 user key
+    fn key(&self) -> UnsafeSlice {
+        assert!(self.valid);
+        match self.direction {
+            Direction::Forward => {
+                let key = self.iter.key();
+                unsafe { key.sub_slice(0, key.len() - 8) }
+            }
+            Direction::Reverse => unsafe { UnsafeSlice::from_raw_parts(self.saved_key.as_ptr(), self.saved_key.len()) },
+        }
+    }
+
+    fn value(&self) -> UnsafeSlice {
+        assert!(self.valid);
+        match self.direction {
+            Direction::Forward => self.iter.value(),
+            Direction::Reverse => unsafe { UnsafeSlice::from_raw_parts(self.saved_value.as_ptr(), self.saved_value.len()) },
+        }
+    }
+
+    fn status(&self) -> Status {
+        if self.status.is_ok() {
+            self.iter.status()
+        } else {
+            self.status.clone()
+        }
+    }
+}
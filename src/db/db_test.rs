@@ -0,0 +1,1194 @@
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::fs::File;
+    use std::path::Path;
+    use std::cmp::Ordering;
+    use std::fmt::Arguments;
+    use std::sync::{Arc, Mutex};
+    use crate::db::db::{destroy_db, CompactionStats, DB};
+    use crate::db::db_format::{Config, InternalKey, ParsedInternalKey, ValueType};
+    use crate::db::filename::FileName;
+    use crate::db::version_edit::VersionEdit;
+    use crate::db::write_batch::WriteBatch;
+    use crate::db::mem_table::MemTable;
+    use crate::table::block::Block;
+    use crate::table::format::{BlockHandle, Footer, k_encoded_length, read_block, ToBlockHandle, ToFoot};
+    use crate::table::table_builder::TableBuilder;
+    use crate::traits::comparator_trait::Comparator;
+    use crate::traits::DataIterator;
+    use crate::traits::logger_trait::{InfoLogLevel, Logger};
+    use crate::traits::table_properties_collector_trait::{TablePropertiesCollector, UserCollectedProperties};
+    use crate::util::cache::ShardLRUCache;
+    use crate::util::coding::Decoder;
+    use crate::util::comparator::BytewiseComparatorImpl;
+    use crate::util::options::{CompressionType, Options, OptionsPtr, ReadOptions, WriteOptions};
+    use crate::util::slice::Slice;
+    use crate::util::Result;
+    use crate::log_info;
+
+    #[test]
+    fn test_delete_obsolete_files() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_delete_obsolete_files_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+
+        let db = DB::new(Options::default(), &dbname);
+        let mut state = db.mutex.lock().unwrap();
+        // 正在生成中的 table 及临时文件
+        state.pending_outputs.insert(7);
+        state.pending_outputs.insert(9);
+
+        let keep = [
+            FileName::current_file_name(&dbname),
+            FileName::lock_file_name(&dbname),
+            FileName::info_log_file_name(&dbname),
+            FileName::old_info_log_file_name(&dbname),
+            FileName::log_file_name(&dbname, 3),
+            FileName::descriptor_file_name(&dbname, 2),
+            FileName::table_file_name(&dbname, 7),
+            FileName::temp_file_name(&dbname, 9),
+            // 不属于数据库的文件
+            dbname.join("foo.txt"),
+        ];
+        let obsolete = [
+            FileName::table_file_name(&dbname, 5),
+            FileName::sst_table_file_name(&dbname, 6),
+            FileName::temp_file_name(&dbname, 8),
+        ];
+        for fname in keep.iter().chain(obsolete.iter()) {
+            File::create(fname)?;
+        }
+
+        assert_eq!(obsolete.len(), db.delete_obsolete_files(&mut state));
+        for fname in keep.iter() {
+            assert!(fname.exists(), "{:?}", fname);
+        }
+        for fname in obsolete.iter() {
+            assert!(!fname.exists(), "{:?}", fname);
+        }
+
+        // 第二次执行时没有需要删除的文件
+        assert_eq!(0, db.delete_obsolete_files(&mut state));
+        drop(state);
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_obsolete_files_keeps_live_versions() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_delete_obsolete_files_live_versions_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+
+        let db = DB::new(Options::default(), &dbname);
+        let mut state = db.mutex.lock().unwrap();
+        state.versions.mark_file_number_used(12);
+        let mut edit = VersionEdit::new();
+        edit.set_prev_log_number(4);
+        edit.set_log_number(5);
+        edit.add_file(0, 10, 100, ikey("a", 1), ikey("b", 2));
+        state.versions.log_and_apply(&mut edit)?;
+        // 仍在被读取的旧版本
+        let old = state.versions.current();
+
+        let mut edit = VersionEdit::new();
+        edit.delete_file(0, 10);
+        edit.add_file(1, 11, 100, ikey("a", 1), ikey("b", 2));
+        state.versions.log_and_apply(&mut edit)?;
+
+        let keep = [
+            // 前一个及当前的预写日志, 以及更新的日志
+            FileName::log_file_name(&dbname, 4),
+            FileName::log_file_name(&dbname, 5),
+            FileName::log_file_name(&dbname, 6),
+            FileName::table_file_name(&dbname, 11),
+        ];
+        let obsolete = [
+            FileName::log_file_name(&dbname, 3),
+            FileName::table_file_name(&dbname, 12),
+        ];
+        let old_table = FileName::table_file_name(&dbname, 10);
+        for fname in keep.iter().chain(obsolete.iter()).chain([&old_table]) {
+            File::create(fname)?;
+        }
+
+        assert_eq!(obsolete.len(), db.delete_obsolete_files(&mut state));
+        for fname in keep.iter().chain([&old_table]) {
+            assert!(fname.exists(), "{:?}", fname);
+        }
+        for fname in obsolete.iter() {
+            assert!(!fname.exists(), "{:?}", fname);
+        }
+
+        // 旧版本释放之后, 只被它引用的 table 才会被删除
+        drop(old);
+        assert_eq!(1, db.delete_obsolete_files(&mut state));
+        assert!(!old_table.exists());
+        for fname in keep.iter() {
+            assert!(fname.exists(), "{:?}", fname);
+        }
+        drop(state);
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_sanitize_max_open_files() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_sanitize_max_open_files_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+
+        for (max_open_files, expected) in [(0, 74), (10, 74), (1000, 1000), (u32::MAX, 50000)] {
+            let mut options = Options::default();
+            options.max_open_files = max_open_files;
+            let db = DB::new(options, &dbname);
+            assert_eq!(expected, db.options.max_open_files);
+        }
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    fn property(db: &DB, name: &str) -> Option<String> {
+        db.get_property(Slice::from(name)).map(|v| String::from_utf8(v.to_vec()).unwrap())
+    }
+
+    fn ikey(user_key: &str, seq: u64) -> InternalKey {
+        InternalKey::new(Slice::from(user_key), seq, ValueType::KTypeValue)
+    }
+
+    #[test]
+    fn test_get_property() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_get_property_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+
+        let db = DB::new(Options::default(), &dbname);
+        let mut edit = VersionEdit::new();
+        edit.add_file(0, 10, 3 * 1048576, ikey("a", 1), ikey("c", 2));
+        edit.add_file(0, 11, 1048576, ikey("b", 3), ikey("d", 4));
+        edit.add_file(2, 12, 100, ikey("e", 5), ikey("f", 6));
+        db.mutex.lock().unwrap().versions.log_and_apply(&mut edit)?;
+        db.mutex.lock().unwrap().stats[1].add(&CompactionStats { micros: 2_000_000, bytes_read: 5 * 1048576, bytes_written: 4 * 1048576 });
+
+        assert_eq!(Some("2".to_string()), property(&db, "leveldb.num-files-at-level0"));
+        assert_eq!(Some("0".to_string()), property(&db, "leveldb.num-files-at-level1"));
+        assert_eq!(Some("1".to_string()), property(&db, "leveldb.num-files-at-level2"));
+        assert_eq!(Some("0".to_string()), property(&db, "leveldb.num-files-at-level6"));
+
+        // 只输出有文件或者有 compaction 统计的层
+        let stats = property(&db, "leveldb.stats").unwrap();
+        assert_eq!("                               Compactions\n\
+                    Level  Files Size(MB) Time(sec) Read(MB) Write(MB)\n\
+                    --------------------------------------------------\n  \
+                    0        2        4         0        0         0\n  \
+                    1        0        0         2        5         4\n  \
+                    2        1        0         0        0         0\n", stats);
+
+        let sstables = property(&db, "leveldb.sstables").unwrap();
+        assert_eq!(db.mutex.lock().unwrap().versions.current().debug_string(), sstables);
+        assert!(sstables.contains("--- level 2 ---\n 12:100['e' @ 5 : 1 .. 'f' @ 6 : 1]\n"), "{}", sstables);
+
+        assert_eq!(Some("0".to_string()), property(&db, "leveldb.approximate-memory-usage"));
+        let mem = MemTable::create(Arc::new(BytewiseComparatorImpl::default()));
+        let usage = mem.approximate_memory_usage();
+        db.mutex.lock().unwrap().mem = Some(mem);
+        assert_eq!(Some(usage.to_string()), property(&db, "leveldb.approximate-memory-usage"));
+
+        for unknown in ["", "leveldb.", "leveldb.foo", "rocksdb.stats", "leveldb.num-files-at-level",
+            "leveldb.num-files-at-level7", "leveldb.num-files-at-level+1", "leveldb.num-files-at-levelx"] {
+            assert_eq!(None, property(&db, unknown), "{}", unknown);
+        }
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_approximate_memory_usage_block_cache() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_approximate_memory_usage_test");
+        let _ = fs::remove_dir_all(&dbname);
+        let cache = ShardLRUCache::new_arc_with_capacity(1 << 20, 0);
+        let mut options = open_options();
+        options.block_cache = Some(cache.clone());
+
+        let db = DB::open(options, &dbname)?;
+        let value = "x".repeat(1000);
+        for i in 0..100 {
+            db.put(&WriteOptions::default(), format!("key{:03}", i).as_bytes(), value.as_bytes())?;
+        }
+        db.compact_range(None, None)?;
+        let mem_usage = db.mutex.lock().unwrap().mem.as_ref().unwrap().approximate_memory_usage();
+        assert_eq!(0, cache.total_charge()?);
+        assert_eq!(Some(mem_usage.to_string()), property(&db, "leveldb.approximate-memory-usage"));
+
+        // 读取的 data block 放入 block cache, 计入内存使用量
+        for i in 0..100 {
+            assert_eq!(Some(value.clone()), get(&db, &format!("key{:03}", i)));
+        }
+        let charge = cache.total_charge()?;
+        assert!(charge > 100 * 1000, "{}", charge);
+        assert_eq!(Some((mem_usage + charge).to_string()), property(&db, "leveldb.approximate-memory-usage"));
+
+        drop(db);
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    /// 生成包含 keys 的 table, 每个 value 大小为 value_size, 返回文件大小
+    fn build_table(db: &DB, number: u64, keys: &[&str], value_size: usize) -> Result<u64> {
+        let options: OptionsPtr = db.options.clone();
+        let file = options.env.new_writable_file(&FileName::table_file_name(&db.dbname, number))?;
+        let mut builder = TableBuilder::new_with_writable_file(options, Box::new(file));
+        for (i, key) in keys.iter().enumerate() {
+            builder.add(ikey(key, i as u64 + 1).encode(), "x".repeat(value_size).as_bytes());
+        }
+        builder.finish()?;
+        builder.sync()?;
+        Ok(builder.get_file_size())
+    }
+
+    fn between(val: u64, low: u64, high: u64) -> bool {
+        let result = val >= low && val <= high;
+        if !result {
+            println!("Value {} is not in range [{}, {}]", val, low, high);
+        }
+        result
+    }
+
+    #[test]
+    fn test_get_approximate_sizes() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_get_approximate_sizes_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+
+        let mut options = Options::default();
+        options.block_size = 1024;
+        options.compression = CompressionType::NoCompression;
+        let db = DB::new(options, &dbname);
+
+        // level 1 两个 table, level 2 一个 table, 每个 key 约 10000 字节
+        let size_10 = build_table(&db, 10, &["a", "b", "c", "d"], 10000)?;
+        let size_11 = build_table(&db, 11, &["e", "f"], 10000)?;
+        let size_12 = build_table(&db, 12, &["m", "n", "o"], 10000)?;
+        let mut edit = VersionEdit::new();
+        edit.add_file(1, 10, size_10, ikey("a", 1), ikey("d", 4));
+        edit.add_file(1, 11, size_11, ikey("e", 1), ikey("f", 2));
+        edit.add_file(2, 12, size_12, ikey("m", 1), ikey("o", 3));
+        db.mutex.lock().unwrap().versions.log_and_apply(&mut edit)?;
+
+        let range = |start: &str, limit: &str| Slice::from(start)..Slice::from(limit);
+        let sizes = db.get_approximate_sizes(&[
+            range("", "a"),
+            range("a", "b"),
+            range("a", "c"),
+            range("a", "e"),
+            range("a", "z"),
+            range("g", "l"),
+            range("m", "o"),
+            range("z", "zz"),
+            range("e", "a"),
+        ]);
+        assert!(between(sizes[0], 0, 0));
+        assert!(between(sizes[1], 10000, 11000));
+        assert!(between(sizes[2], 20000, 21000));
+        assert!(between(sizes[3], 40000, 42000));
+        assert_eq!(size_10 + size_11 + size_12, sizes[4]);
+        assert!(between(sizes[5], 0, 0));
+        assert!(between(sizes[6], 20000, 21000));
+        assert!(between(sizes[7], 0, 0));
+        // 起始位置大于结束位置时返回 0
+        assert_eq!(0, sizes[8]);
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    /// 生成包含 entries(key, sequence, type) 的 table 并加入 level 层
+    fn add_table(db: &DB, level: u32, number: u64, entries: &[(&str, u64, ValueType)]) -> Result<()> {
+        let file = db.options.env.new_writable_file(&FileName::table_file_name(&db.dbname, number))?;
+        let mut builder = TableBuilder::new_with_writable_file(db.options.clone(), Box::new(file));
+        for (key, seq, value_type) in entries {
+            let value = if *value_type == ValueType::KTypeValue { format!("{}@{}", key, seq) } else { String::new() };
+            builder.add(InternalKey::new(Slice::from(*key), *seq, *value_type).encode(), value.as_bytes());
+        }
+        builder.finish()?;
+        builder.sync()?;
+
+        let (first, last) = (entries[0], entries[entries.len() - 1]);
+        let mut edit = VersionEdit::new();
+        edit.add_file(level, number, builder.get_file_size(),
+                      InternalKey::new(Slice::from(first.0), first.1, first.2),
+                      InternalKey::new(Slice::from(last.0), last.1, last.2));
+        let mut state = db.mutex.lock().unwrap();
+        state.versions.mark_file_number_used(number);
+        let last_sequence = entries.iter().map(|e| e.1).max().unwrap().max(state.versions.last_sequence());
+        state.versions.set_last_sequence(last_sequence);
+        state.versions.log_and_apply(&mut edit)
+    }
+
+    /// 统计 table 中 value 的数量及最大的 value 长度
+    #[derive(Default)]
+    struct ValueStatsCollector {
+        num_values: u64,
+        max_value_size: usize,
+    }
+
+    impl TablePropertiesCollector for ValueStatsCollector {
+        fn name(&self) -> String {
+            "ValueStatsCollector".to_string()
+        }
+
+        fn add(&mut self, _user_key: &[u8], value: &[u8], value_type: ValueType, _sequence: u64) {
+            if value_type == ValueType::KTypeValue {
+                self.num_values += 1;
+                self.max_value_size = self.max_value_size.max(value.len());
+            }
+        }
+
+        fn finish(&mut self, properties: &mut UserCollectedProperties) {
+            properties.insert("test.num.values".to_string(), self.num_values.to_string().into_bytes());
+            properties.insert("test.max.value.size".to_string(), self.max_value_size.to_string().into_bytes());
+        }
+    }
+
+    #[test]
+    fn test_table_properties() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_table_properties_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+
+        let mut options = Options::default();
+        options.table_properties_collectors.push(Arc::new(|| Box::new(ValueStatsCollector::default())));
+        let db = DB::new(options, &dbname);
+        add_table(&db, 0, 10, &[("a", 5, ValueType::KTypeValue), ("b", 3, ValueType::KTypeDeletion),
+            ("c", 4, ValueType::KTypeValue)])?;
+        add_table(&db, 2, 11, &[("d", 1, ValueType::KTypeValue), ("e", 2, ValueType::KTypeDeletion)])?;
+
+        // 通过 Table 读取单个 table 的属性
+        let current = db.mutex.lock().unwrap().versions.current();
+        let f = &current.files(0)[0];
+        let table = db.table_cache.find_table(f.get_number(), f.get_file_size())?;
+        let properties = table.properties().unwrap();
+        assert_eq!(3, properties.num_entries);
+        assert_eq!(1, properties.num_deletions);
+        assert_eq!(1, properties.num_data_blocks);
+        assert_eq!(3 * 9, properties.raw_key_size);
+        assert_eq!(("a@5".len() + "c@4".len()) as u64, properties.raw_value_size);
+        assert_eq!(3, properties.smallest_seqno);
+        assert_eq!(5, properties.largest_seqno);
+        assert!(properties.data_size > 0 && properties.index_size > 0);
+        assert_eq!(0, properties.filter_size);
+        assert_eq!("leveldb.InternalKeyComparator", properties.comparator_name);
+        assert_eq!(format!("{:?}", db.options.compression), properties.compression);
+        assert_eq!(Some(&b"2".to_vec()), properties.user_collected_properties.get("test.num.values"));
+        assert_eq!(Some(&b"3".to_vec()), properties.user_collected_properties.get("test.max.value.size"));
+
+        // DB 中所有 table 的属性之和
+        let aggregated = property(&db, "leveldb.aggregated-table-properties").unwrap();
+        assert!(aggregated.starts_with("leveldb.num.entries: 5
+leveldb.num.deletions: 2
+leveldb.num.data.blocks: 2
+"),
+                "{}", aggregated);
+        assert!(aggregated.contains("leveldb.smallest.seqno: 1
+leveldb.largest.seqno: 5
+"), "{}", aggregated);
+        let level2 = property(&db, "leveldb.aggregated-table-properties-at-level2").unwrap();
+        assert!(level2.starts_with("leveldb.num.entries: 2
+"), "{}", level2);
+        let level1 = property(&db, "leveldb.aggregated-table-properties-at-level1").unwrap();
+        assert!(level1.starts_with("leveldb.num.entries: 0
+"), "{}", level1);
+        assert_eq!(None, property(&db, "leveldb.aggregated-table-properties-at-level7"));
+
+        let per_table = property(&db, "leveldb.table-properties").unwrap();
+        assert!(per_table.contains("--- level 2 ---\n11:\nleveldb.num.entries: 2\n"), "{}", per_table);
+        assert!(per_table.contains("test.num.values: 1\n"), "{}", per_table);
+
+        drop(db);
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    /// level 层所有 table 中的数据
+    fn level_entries(db: &DB, level: usize) -> Vec<(String, u64, ValueType)> {
+        let current = db.mutex.lock().unwrap().versions.current();
+        let mut entries = vec![];
+        for f in current.files(level) {
+            let mut iter = db.table_cache.new_iterator(&ReadOptions::default(), f.get_number(), f.get_file_size());
+            iter.seek_to_first();
+            while iter.valid() {
+                let key = ParsedInternalKey::parse_internal_key(iter.key().as_ref()).unwrap();
+                entries.push((String::from_utf8(key.user_key().to_vec()).unwrap(), key.sequence(), key.value_type()));
+                iter.next();
+            }
+            assert!(iter.status().is_ok());
+        }
+        entries
+    }
+
+    fn num_files_at_level(db: &DB, level: usize) -> usize {
+        db.mutex.lock().unwrap().versions.num_level_files(level)
+    }
+
+    #[test]
+    fn test_compact_range_drops_deleted() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_compact_range_deleted_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+
+        let db = DB::new(Options::default(), &dbname);
+        add_table(&db, 2, 11, &[("a", 1, ValueType::KTypeValue), ("b", 2, ValueType::KTypeValue),
+            ("c", 3, ValueType::KTypeValue), ("d", 4, ValueType::KTypeValue)])?;
+        add_table(&db, 1, 10, &[("a", 5, ValueType::KTypeDeletion), ("b", 6, ValueType::KTypeValue),
+            ("c", 7, ValueType::KTypeDeletion)])?;
+
+        db.compact_range(None, None)?;
+
+        // 被删除及被覆盖的数据都被丢弃, 最底层的删除标记也不再需要
+        assert_eq!(0, num_files_at_level(&db, 1));
+        assert_eq!(1, num_files_at_level(&db, 2));
+        assert_eq!(vec![("b".to_string(), 6, ValueType::KTypeValue), ("d".to_string(), 4, ValueType::KTypeValue)],
+                   level_entries(&db, 2));
+
+        // 输入文件已经被删除
+        assert!(!FileName::table_file_name(&dbname, 10).exists());
+        assert!(!FileName::table_file_name(&dbname, 11).exists());
+        let state = db.mutex.lock().unwrap();
+        assert!(state.pending_outputs.is_empty());
+        assert!(state.stats[2].bytes_read > 0);
+        assert!(state.stats[2].bytes_written > 0);
+        drop(state);
+
+        // 再次 compact 时没有需要处理的数据
+        db.compact_range(None, None)?;
+        assert_eq!(1, num_files_at_level(&db, 2));
+
+        drop(db);
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_range_partial() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_compact_range_partial_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+
+        // 每次 compaction 只处理一个文件
+        let mut options = Options::default();
+        options.max_file_size = 1;
+        let db = DB::new(options, &dbname);
+        add_table(&db, 2, 13, &[("a", 1, ValueType::KTypeValue)])?;
+        add_table(&db, 1, 10, &[("a", 2, ValueType::KTypeValue), ("b", 3, ValueType::KTypeValue)])?;
+        add_table(&db, 1, 11, &[("c", 4, ValueType::KTypeValue), ("d", 5, ValueType::KTypeValue)])?;
+        add_table(&db, 1, 12, &[("e", 6, ValueType::KTypeValue), ("f", 7, ValueType::KTypeValue)])?;
+
+        db.compact_range(Some(&Slice::from("a")), Some(&Slice::from("d")))?;
+
+        // 范围之外的文件不受影响
+        let current = db.mutex.lock().unwrap().versions.current();
+        assert_eq!(vec![12], current.files(1).iter().map(|f| f.get_number()).collect::<Vec<_>>());
+        assert_eq!(2, current.num_files(2));
+        assert_eq!(vec![("a".to_string(), 2, ValueType::KTypeValue), ("b".to_string(), 3, ValueType::KTypeValue),
+                        ("c".to_string(), 4, ValueType::KTypeValue), ("d".to_string(), 5, ValueType::KTypeValue)],
+                   level_entries(&db, 2));
+        assert!(db.mutex.lock().unwrap().manual_compaction.is_none());
+
+        drop(db);
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_range_memtable() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_compact_range_memtable_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+
+        let db = DB::new(Options::default(), &dbname);
+        {
+            let mut state = db.mutex.lock().unwrap();
+            let mut mem = MemTable::create(Arc::new(BytewiseComparatorImpl::default()));
+            mem.add(1, ValueType::KTypeValue, &"b", &"b@1")?;
+            mem.add(2, ValueType::KTypeValue, &"c", &"c@2")?;
+            mem.add(3, ValueType::KTypeDeletion, &"b", &"")?;
+            state.mem = Some(mem);
+            state.versions.set_last_sequence(3);
+        }
+
+        // memtable 中没有范围内的数据时同样落盘
+        db.compact_range(Some(&Slice::from("d")), Some(&Slice::from("z")))?;
+        let state = db.mutex.lock().unwrap();
+        assert!(state.mem.as_ref().unwrap().is_empty());
+        assert!(state.imm.is_none());
+        // 切换 memtable 时创建了新的预写日志
+        assert!(FileName::log_file_name(&dbname, state.logfile_number).exists());
+        assert_eq!(state.logfile_number, state.versions.log_number());
+        drop(state);
+
+        // 没有重叠的文件时直接写入 K_MAX_MEM_COMPACT_LEVEL 层
+        assert_eq!(1, num_files_at_level(&db, Config::K_MAX_MEM_COMPACT_LEVEL));
+        assert_eq!(vec![("b".to_string(), 3, ValueType::KTypeDeletion), ("b".to_string(), 1, ValueType::KTypeValue),
+                        ("c".to_string(), 2, ValueType::KTypeValue)],
+                   level_entries(&db, Config::K_MAX_MEM_COMPACT_LEVEL));
+
+        drop(db);
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_range_waits_for_imm() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_compact_range_imm_test");
+        let _ = fs::remove_dir_all(&dbname);
+
+        let db = DB::open(open_options(), &dbname)?;
+        add_table(&db, 2, 100, &[("b", 1, ValueType::KTypeValue), ("c", 2, ValueType::KTypeValue)])?;
+        {
+            // 后台线程即将落盘的 immutable memtable 中删除了 b
+            let mut state = db.mutex.lock().unwrap();
+            let mut imm = MemTable::create(Arc::new(BytewiseComparatorImpl::default()));
+            imm.add(3, ValueType::KTypeDeletion, &"b", &"")?;
+            state.imm = Some(Arc::new(imm));
+            state.versions.set_last_sequence(3);
+            state.bg_compaction_scheduled = true;
+        }
+
+        std::thread::scope(|scope| {
+            let compaction = scope.spawn(|| db.compact_range(None, None));
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            // 等待 immutable memtable 落盘之后才 compact 各层
+            assert!(!compaction.is_finished());
+
+            let mut state = db.mutex.lock().unwrap();
+            state.bg_compaction_scheduled = false;
+            db.inner.maybe_schedule_compaction(&mut state);
+            drop(state);
+            compaction.join().unwrap()
+        })?;
+
+        assert!(db.mutex.lock().unwrap().imm.is_none());
+        // b 的删除与 level 2 中的 b 合并后一起被丢弃
+        let entries: Vec<_> = (0..Config::K_NUM_LEVELS).flat_map(|level| level_entries(&db, level)).collect();
+        assert_eq!(vec![("c".to_string(), 2, ValueType::KTypeValue)], entries);
+        assert_eq!(None, get(&db, "b"));
+        assert_eq!(Some("c@2".to_string()), get(&db, "c"));
+
+        drop(db);
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    /// 按字节逆序比较 user key
+    struct ReverseComparator {}
+
+    impl Comparator for ReverseComparator {
+        fn compare(&self, a: &[u8], b: &[u8]) -> Option<Ordering> {
+            Some(b.cmp(a))
+        }
+
+        fn get_name(&self) -> String {
+            String::from("leveldb.ReverseBytewiseComparator")
+        }
+
+        fn find_shortest_separator(&self, start: &String, _limit: &Slice) -> String {
+            start.clone()
+        }
+
+        fn find_short_successor(&self, key: &String) -> String {
+            key.clone()
+        }
+    }
+
+    #[test]
+    fn test_new_mem_table_uses_user_comparator() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_new_mem_table_comparator_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+
+        let mut options = Options::default();
+        options.cmp = Arc::new(ReverseComparator {});
+        let db = DB::new(options, &dbname);
+        {
+            let mut state = db.mutex.lock().unwrap();
+            let mut mem = MemTable::create(Arc::new(ReverseComparator {}));
+            mem.add(1, ValueType::KTypeValue, &"a", &"a@1")?;
+            state.mem = Some(mem);
+            state.versions.set_last_sequence(1);
+        }
+        // 落盘时会切换到新的 memtable
+        db.compact_range(None, None)?;
+
+        let mut state = db.mutex.lock().unwrap();
+        let mem = state.mem.as_mut().unwrap();
+        for (i, key) in ["a", "b", "c"].iter().enumerate() {
+            mem.add(i + 2, ValueType::KTypeValue, key, key)?;
+        }
+        let mut iter = mem.new_iterator();
+        iter.seek_to_first();
+        let mut keys = vec![];
+        while iter.valid() {
+            keys.push(String::from_utf8(ParsedInternalKey::extract_user_key(iter.key().as_ref()).to_vec()).unwrap());
+            iter.next();
+        }
+        assert_eq!(vec!["c", "b", "a"], keys);
+        drop(iter);
+        drop(state);
+
+        drop(db);
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_destroy_db() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_destroy_db_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+        let options = Options::default();
+
+        let db_files = [
+            FileName::current_file_name(&dbname),
+            FileName::lock_file_name(&dbname),
+            FileName::info_log_file_name(&dbname),
+            FileName::log_file_name(&dbname, 3),
+            FileName::descriptor_file_name(&dbname, 2),
+            FileName::table_file_name(&dbname, 5),
+            FileName::sst_table_file_name(&dbname, 6),
+            FileName::temp_file_name(&dbname, 7),
+        ];
+        for fname in db_files.iter() {
+            File::create(fname)?;
+        }
+        let foreign = dbname.join("foo.txt");
+        File::create(&foreign)?;
+
+        // 数据库正在使用时不能删除
+        let lock = options.env.lock_file(&FileName::lock_file_name(&dbname))?;
+        assert!(destroy_db(&dbname, &options).err().unwrap().is_io_error());
+        for fname in db_files.iter() {
+            assert!(fname.exists(), "{:?}", fname);
+        }
+        options.env.unlock_file(lock)?;
+
+        // 不属于数据库的文件以及目录会被保留
+        destroy_db(&dbname, &options)?;
+        for fname in db_files.iter() {
+            assert!(!fname.exists(), "{:?}", fname);
+        }
+        assert!(foreign.exists());
+
+        // 目录为空时一并删除
+        fs::remove_file(&foreign)?;
+        File::create(FileName::table_file_name(&dbname, 8))?;
+        destroy_db(&dbname, &options)?;
+        assert!(!dbname.exists());
+
+        // 目录不存在时直接返回
+        destroy_db(&dbname, &options)?;
+        Ok(())
+    }
+
+    /// table 中第一个 data block 的压缩类型
+    fn first_block_type(fname: &Path) -> u8 {
+        let contents = fs::read(fname).unwrap();
+        let mut footer = Footer::default();
+        footer.decode_from(&contents[contents.len() - k_encoded_length as usize..]).unwrap();
+        let index = read_block(&contents, &Options::default(), &ReadOptions::default(), footer.checksum(), &footer.index_handle()).unwrap();
+        let mut iter = Block::new(index).new_iterator(Arc::new(BytewiseComparatorImpl::default()));
+        iter.seek_to_first();
+        let mut handle = BlockHandle::default();
+        handle.decode_from(&mut Decoder::with_buf(iter.value().as_ref())).unwrap();
+        contents[(handle.offset() + handle.size()) as usize]
+    }
+
+    #[test]
+    fn test_compression_per_level() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_compression_per_level_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+
+        // 默认不压缩
+        assert_eq!(CompressionType::NoCompression, Options::default().compression);
+        let mut options = Options::default();
+        options.compression = CompressionType::SnappyCompression;
+        options.compression_per_level = vec![CompressionType::NoCompression, CompressionType::NoCompression,
+                                             CompressionType::ZstdCompression];
+        let db = DB::new(options, &dbname);
+
+        let keys: Vec<String> = (0..100).map(|i| format!("key{:05}", i)).collect();
+        let entries: Vec<(&str, u64, ValueType)> = keys.iter().enumerate()
+            .map(|(i, k)| (k.as_str(), i as u64 + 2, ValueType::KTypeValue))
+            .collect();
+        // 直接使用 TableBuilder 生成的 table 使用 options.compression
+        add_table(&db, 2, 11, &[("key00050", 1, ValueType::KTypeValue)])?;
+        add_table(&db, 1, 10, &entries)?;
+        assert_eq!(CompressionType::SnappyCompression as u8, first_block_type(&FileName::table_file_name(&dbname, 10)));
+
+        // compaction 输出到 level 2, 使用 Zstd 压缩, 被覆盖的 key00050@1 被丢弃
+        db.compact_range(None, None)?;
+        let current = db.mutex.lock().unwrap().versions.current();
+        assert_eq!(0, current.num_files(1));
+        assert_eq!(1, current.num_files(2));
+        let output = FileName::table_file_name(&dbname, current.files(2)[0].get_number());
+        assert_eq!(CompressionType::ZstdCompression as u8, first_block_type(&output));
+        assert_eq!(entries.iter().map(|(k, seq, t)| (k.to_string(), *seq, *t)).collect::<Vec<_>>(),
+                   level_entries(&db, 2));
+        drop(current);
+
+        // memtable 落盘时直接写入 level 2, 同样使用 level 2 的压缩类型
+        {
+            let mut state = db.mutex.lock().unwrap();
+            let mut mem = MemTable::create(Arc::new(BytewiseComparatorImpl::default()));
+            mem.add(200, ValueType::KTypeValue, &"zzz".to_string(), &"v".repeat(1000))?;
+            state.mem = Some(mem);
+            state.versions.set_last_sequence(200);
+        }
+        db.compact_range(Some(&Slice::from("zzz")), Some(&Slice::from("zzz")))?;
+        let current = db.mutex.lock().unwrap().versions.current();
+        assert_eq!(0, current.num_files(0));
+        assert_eq!(2, current.num_files(2));
+        let flushed = FileName::table_file_name(&dbname, current.files(2)[1].get_number());
+        assert_eq!(CompressionType::ZstdCompression as u8, first_block_type(&flushed));
+
+        drop(current);
+        drop(db);
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    /// 收集所有日志的 Logger
+    #[derive(Default)]
+    struct CollectingLogger {
+        lines: Mutex<Vec<(InfoLogLevel, String)>>,
+    }
+
+    impl Logger for CollectingLogger {
+        fn log(&self, level: InfoLogLevel, args: Arguments) {
+            self.lines.lock().unwrap().push((level, args.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_info_log() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_info_log_test");
+        let _ = fs::remove_dir_all(&dbname);
+
+        // 目录不存在时会被创建
+        let db = DB::new(Options::default(), &dbname);
+        log_info!(db.options.info_log, "first {}", 1);
+        drop(db);
+        let log = fs::read_to_string(FileName::info_log_file_name(&dbname))?;
+        assert!(log.ends_with(" [INFO] first 1\n"), "{}", log);
+
+        // 上一次的 LOG 被重命名为 LOG.old
+        let db = DB::new(Options::default(), &dbname);
+        log_info!(db.options.info_log, "second {}", 2);
+        drop(db);
+        let log = fs::read_to_string(FileName::info_log_file_name(&dbname))?;
+        assert!(log.ends_with(" [INFO] second 2\n"), "{}", log);
+        let old_log = fs::read_to_string(FileName::old_info_log_file_name(&dbname))?;
+        assert!(old_log.ends_with(" [INFO] first 1\n"), "{}", old_log);
+        fs::remove_dir_all(&dbname)?;
+
+        // 指定了 info_log 时不创建 LOG
+        fs::create_dir_all(&dbname)?;
+        let logger = Arc::new(CollectingLogger::default());
+        let mut options = Options::default();
+        options.info_log = Some(logger.clone());
+        let db = DB::new(options, &dbname);
+        assert!(!FileName::info_log_file_name(&dbname).exists());
+
+        File::create(FileName::table_file_name(&dbname, 5))?;
+        assert_eq!(1, db.delete_obsolete_files(&mut db.mutex.lock().unwrap()));
+        let lines = logger.lines.lock().unwrap().clone();
+        assert!(lines.contains(&(InfoLogLevel::Info, "Delete type=KTableFile #5".to_string())), "{:?}", lines);
+        assert!(lines.contains(&(InfoLogLevel::Debug, "Deleted 1 obsolete files".to_string())), "{:?}", lines);
+
+        drop(db);
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    fn open_options() -> Options {
+        let mut options = Options::default();
+        options.create_if_missing = true;
+        options
+    }
+
+    fn get(db: &DB, key: &str) -> Option<String> {
+        db.get(&ReadOptions::default(), key.as_bytes()).unwrap()
+            .map(|v| String::from_utf8(v.to_vec()).unwrap())
+    }
+
+    /// 迭代器当前位置的 "key->value"
+    fn iter_entry(iter: &dyn DataIterator) -> String {
+        if !iter.valid() {
+            return "(invalid)".to_string();
+        }
+        format!("{}->{}", iter.key().as_str(), iter.value().as_str())
+    }
+
+    #[test]
+    fn test_open() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_open_test");
+        let _ = fs::remove_dir_all(&dbname);
+
+        // 数据库不存在
+        let err = DB::open(Options::default(), &dbname).err().unwrap();
+        assert!(err.is_invalid_argument(), "{}", err.get_msg());
+
+        let db = DB::open(open_options(), &dbname)?;
+        assert!(FileName::current_file_name(&dbname).exists());
+        // 数据库已被锁定
+        assert!(DB::open(open_options(), &dbname).err().unwrap().is_io_error());
+        drop(db);
+
+        let mut options = open_options();
+        options.error_if_exists = true;
+        assert!(DB::open(options, &dbname).err().unwrap().is_invalid_argument());
+        drop(DB::open(Options::default(), &dbname)?);
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_put_get_delete() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_put_get_delete_test");
+        let _ = fs::remove_dir_all(&dbname);
+        let write_options = WriteOptions::default();
+
+        let db = DB::open(open_options(), &dbname)?;
+        db.put(&write_options, b"foo", b"v1")?;
+        db.put(&write_options, b"bar", b"v2")?;
+        db.put(&write_options, b"foo", b"v3")?;
+        db.delete(&write_options, b"bar")?;
+        // 删除不存在的 key 不是错误
+        db.delete(&write_options, b"none")?;
+        let mut batch = WriteBatch::new();
+        batch.put(b"baz", b"v4");
+        batch.delete(b"foo");
+        batch.put(b"foo", b"v5");
+        db.write(&WriteOptions { sync: true }, batch)?;
+        assert_eq!(Some("v5".to_string()), get(&db, "foo"));
+        assert_eq!(None, get(&db, "bar"));
+        assert_eq!(Some("v4".to_string()), get(&db, "baz"));
+        assert_eq!(8, db.mutex.lock().unwrap().versions.last_sequence());
+
+        // 从预写日志中恢复
+        drop(db);
+        let db = DB::open(open_options(), &dbname)?;
+        assert_eq!(8, db.mutex.lock().unwrap().versions.last_sequence());
+        // 恢复的数据写入 level 0
+        assert_eq!(1, num_files_at_level(&db, 0));
+        assert_eq!(Some("v5".to_string()), get(&db, "foo"));
+        assert_eq!(None, get(&db, "bar"));
+        assert_eq!(Some("v4".to_string()), get(&db, "baz"));
+
+        // 从 table 中读取, memtable 中的删除覆盖 table 中的数据
+        db.delete(&write_options, b"baz")?;
+        db.put(&write_options, b"bar", b"v6")?;
+        assert_eq!(None, get(&db, "baz"));
+        assert_eq!(Some("v6".to_string()), get(&db, "bar"));
+        db.compact_range(None, None)?;
+        assert_eq!(None, get(&db, "baz"));
+        assert_eq!(Some("v6".to_string()), get(&db, "bar"));
+        assert_eq!(Some("v5".to_string()), get(&db, "foo"));
+
+        drop(db);
+        let db = DB::open(open_options(), &dbname)?;
+        assert_eq!(10, db.mutex.lock().unwrap().versions.last_sequence());
+        assert_eq!(None, get(&db, "baz"));
+        assert_eq!(Some("v6".to_string()), get(&db, "bar"));
+        assert_eq!(Some("v5".to_string()), get(&db, "foo"));
+        drop(db);
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_many_writes() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_many_writes_test");
+        let _ = fs::remove_dir_all(&dbname);
+        let mut options = open_options();
+        // memtable 写满后切换并在后台落盘
+        options.write_buffer_size = 10000;
+
+        let db = DB::open(options, &dbname)?;
+        let value = "x".repeat(100);
+        for i in 0..2000 {
+            db.put(&WriteOptions::default(), format!("key{:06}", i).as_bytes(), value.as_bytes())?;
+        }
+        for i in (0..2000).step_by(3) {
+            db.delete(&WriteOptions::default(), format!("key{:06}", i).as_bytes())?;
+        }
+        let check = |db: &DB| {
+            for i in 0..2000 {
+                let expected = if i % 3 == 0 { None } else { Some(value.clone()) };
+                assert_eq!(expected, get(db, &format!("key{:06}", i)), "key{:06}", i);
+            }
+        };
+        check(&db);
+        // 后台 compaction 可能已经把落盘的文件合并成一个
+        let files: usize = (0..Config::K_NUM_LEVELS).map(|level| num_files_at_level(&db, level)).sum();
+        assert!(files > 0, "{}", files);
+
+        drop(db);
+        let mut options = open_options();
+        options.write_buffer_size = 10000;
+        let db = DB::open(options, &dbname)?;
+        check(&db);
+        db.compact_range(None, None)?;
+        check(&db);
+        drop(db);
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_iterator() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_iterator_test");
+        let _ = fs::remove_dir_all(&dbname);
+        let write_options = WriteOptions::default();
+
+        let db = DB::open(open_options(), &dbname)?;
+        let mut iter = db.new_iterator(&ReadOptions::default());
+        iter.seek_to_first();
+        assert_eq!("(invalid)", iter_entry(iter.as_ref()));
+        iter.seek_to_last();
+        assert_eq!("(invalid)", iter_entry(iter.as_ref()));
+
+        db.put(&write_options, b"a", b"va")?;
+        db.put(&write_options, b"b", b"vb")?;
+        db.put(&write_options, b"c", b"vc")?;
+        db.put(&write_options, b"d", b"vd")?;
+        // a、b 写入 table, 其余在 memtable 中
+        db.compact_range(Some(&Slice::from("a")), Some(&Slice::from("b")))?;
+        db.put(&write_options, b"b", b"vb2")?;
+        db.delete(&write_options, b"c")?;
+
+        let mut iter = db.new_iterator(&ReadOptions::default());
+        // 创建迭代器之后的写入不可见
+        db.put(&write_options, b"e", b"ve")?;
+        db.delete(&write_options, b"a")?;
+
+        iter.seek_to_first();
+        assert_eq!("a->va", iter_entry(iter.as_ref()));
+        iter.next();
+        assert_eq!("b->vb2", iter_entry(iter.as_ref()));
+        iter.next();
+        assert_eq!("d->vd", iter_entry(iter.as_ref()));
+        iter.next();
+        assert_eq!("(invalid)", iter_entry(iter.as_ref()));
+
+        iter.seek_to_last();
+        assert_eq!("d->vd", iter_entry(iter.as_ref()));
+        iter.pre();
+        assert_eq!("b->vb2", iter_entry(iter.as_ref()));
+        iter.pre();
+        assert_eq!("a->va", iter_entry(iter.as_ref()));
+        iter.pre();
+        assert_eq!("(invalid)", iter_entry(iter.as_ref()));
+
+        // 切换方向
+        iter.seek(&Slice::from("c"));
+        assert_eq!("d->vd", iter_entry(iter.as_ref()));
+        iter.pre();
+        assert_eq!("b->vb2", iter_entry(iter.as_ref()));
+        iter.next();
+        assert_eq!("d->vd", iter_entry(iter.as_ref()));
+        iter.seek(&Slice::from("b"));
+        assert_eq!("b->vb2", iter_entry(iter.as_ref()));
+        iter.seek(&Slice::from("z"));
+        assert_eq!("(invalid)", iter_entry(iter.as_ref()));
+        assert!(iter.status().is_ok());
+        drop(iter);
+
+        let mut iter = db.new_iterator(&ReadOptions::default());
+        iter.seek_to_first();
+        let mut entries = vec![];
+        while iter.valid() {
+            entries.push(iter_entry(iter.as_ref()));
+            iter.next();
+        }
+        assert_eq!(vec!["b->vb2", "d->vd", "e->ve"], entries);
+        drop(iter);
+
+        drop(db);
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    /// 最新的预写日志
+    fn last_log_file(dbname: &Path) -> std::path::PathBuf {
+        let mut logs: Vec<_> = fs::read_dir(dbname).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+            .collect();
+        logs.sort();
+        logs.pop().unwrap()
+    }
+
+    #[test]
+    fn test_recover() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_recover_test");
+        let _ = fs::remove_dir_all(&dbname);
+        let write_options = WriteOptions { sync: true };
+
+        let db = DB::open(open_options(), &dbname)?;
+        db.put(&write_options, b"a", b"va")?;
+        db.put(&write_options, b"b", b"vb")?;
+        db.put(&write_options, b"c", b"vc")?;
+        drop(db);
+
+        // 破坏最后一条记录的数据, 校验失败
+        let log = last_log_file(&dbname);
+        let mut contents = fs::read(&log)?;
+        let n = contents.len();
+        contents[n - 1] ^= 0xff;
+        fs::write(&log, contents)?;
+
+        let mut options = open_options();
+        options.paranoid_checks = true;
+        assert!(DB::open(options, &dbname).err().unwrap().is_corruption());
+
+        // 跳过损坏的记录
+        let db = DB::open(open_options(), &dbname)?;
+        assert_eq!(Some("va".to_string()), get(&db, "a"));
+        assert_eq!(Some("vb".to_string()), get(&db, "b"));
+        assert_eq!(None, get(&db, "c"));
+        assert_eq!(2, db.mutex.lock().unwrap().versions.last_sequence());
+
+        // 恢复时 memtable 写满会生成多个 level 0 的文件
+        let value = "x".repeat(1000);
+        for i in 0..100 {
+            db.put(&WriteOptions::default(), format!("key{:03}", i).as_bytes(), value.as_bytes())?;
+        }
+        drop(db);
+        let mut options = open_options();
+        options.write_buffer_size = 10000;
+        let db = DB::open(options, &dbname)?;
+        assert!(num_files_at_level(&db, 0) > 1, "{}", num_files_at_level(&db, 0));
+        assert_eq!(102, db.mutex.lock().unwrap().versions.last_sequence());
+        for i in 0..100 {
+            assert_eq!(Some(value.clone()), get(&db, &format!("key{:03}", i)));
+        }
+        assert_eq!(Some("va".to_string()), get(&db, "a"));
+        drop(db);
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_iterator_mem_imm_levels() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_iterator_mem_imm_levels_test");
+        let _ = fs::remove_dir_all(&dbname);
+        let write_options = WriteOptions::default();
+
+        // level 0 之后的层: a、d、e
+        let db = DB::open(open_options(), &dbname)?;
+        db.put(&write_options, b"a", b"a1")?;
+        db.put(&write_options, b"d", b"d1")?;
+        db.put(&write_options, b"e", b"e1")?;
+        db.compact_range(None, None)?;
+        // level 0: 恢复时写入 b 及 e 的删除
+        db.put(&write_options, b"b", b"b1")?;
+        db.delete(&write_options, b"e")?;
+        drop(db);
+        let db = DB::open(open_options(), &dbname)?;
+        assert_eq!(1, num_files_at_level(&db, 0));
+        assert!((1..Config::K_NUM_LEVELS).any(|level| num_files_at_level(&db, level) > 0));
+
+        // immutable memtable: c 及 d 的删除
+        {
+            let mut state = db.mutex.lock().unwrap();
+            let mut imm = MemTable::create(Arc::new(BytewiseComparatorImpl::default()));
+            imm.add(6, ValueType::KTypeValue, &"c", &"c1")?;
+            imm.add(7, ValueType::KTypeDeletion, &"d", &"")?;
+            state.imm = Some(Arc::new(imm));
+            state.versions.set_last_sequence(7);
+        }
+        // memtable: 覆盖 a
+        db.put(&write_options, b"a", b"a2")?;
+        assert!(db.mutex.lock().unwrap().imm.is_some());
+
+        assert_eq!(Some("a2".to_string()), get(&db, "a"));
+        assert_eq!(Some("b1".to_string()), get(&db, "b"));
+        assert_eq!(Some("c1".to_string()), get(&db, "c"));
+        assert_eq!(None, get(&db, "d"));
+        assert_eq!(None, get(&db, "e"));
+
+        let mut iter = db.new_iterator(&ReadOptions::default());
+        let mut entries = vec![];
+        iter.seek_to_first();
+        while iter.valid() {
+            entries.push(iter_entry(iter.as_ref()));
+            iter.next();
+        }
+        assert_eq!(vec!["a->a2", "b->b1", "c->c1"], entries);
+        let mut entries = vec![];
+        iter.seek_to_last();
+        while iter.valid() {
+            entries.push(iter_entry(iter.as_ref()));
+            iter.pre();
+        }
+        assert_eq!(vec!["c->c1", "b->b1", "a->a2"], entries);
+        iter.seek(&Slice::from("cc"));
+        assert_eq!("(invalid)", iter_entry(iter.as_ref()));
+        assert!(iter.status().is_ok());
+        drop(iter);
+
+        drop(db);
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_stall() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_write_stall_test");
+        let _ = fs::remove_dir_all(&dbname);
+        let logger = Arc::new(CollectingLogger::default());
+        let mut options = open_options();
+        options.write_buffer_size = 5000;
+        options.info_log = Some(logger.clone());
+        options.info_log_level = InfoLogLevel::Debug;
+
+        let db = DB::open(options, &dbname)?;
+        for i in 0..Config::K_L0_STOP_WRITES_TRIGGER {
+            let key = format!("k{:02}", i);
+            add_table(&db, 0, 100 + i as u64, &[(&key, i as u64 + 1, ValueType::KTypeValue)])?;
+        }
+        // level 0 的文件数量达到 KL0_SLOWDOWN_WRITES_TRIGGER 时延迟写入, memtable 仍有空间
+        db.put(&WriteOptions::default(), b"a", "x".repeat(6000).as_bytes())?;
+        assert!(logger.lines.lock().unwrap()
+            .contains(&(InfoLogLevel::Debug, "Too many L0 files; delaying write by 1ms".to_string())));
+
+        std::thread::scope(|scope| {
+            // memtable 已满且 level 0 的文件数量达到 K_L0_STOP_WRITES_TRIGGER, 写入等待 compaction
+            let writer = scope.spawn(|| db.put(&WriteOptions::default(), b"b", b"vb"));
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            assert!(!writer.is_finished());
+            assert!(logger.lines.lock().unwrap()
+                .contains(&(InfoLogLevel::Info, "Too many L0 files; waiting...".to_string())));
+
+            db.inner.maybe_schedule_compaction(&mut db.mutex.lock().unwrap());
+            writer.join().unwrap()
+        })?;
+        assert!(num_files_at_level(&db, 0) < Config::K_L0_STOP_WRITES_TRIGGER);
+        assert_eq!(Some("vb".to_string()), get(&db, "b"));
+        assert_eq!(Some("k00@1".to_string()), get(&db, "k00"));
+        drop(db);
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+}
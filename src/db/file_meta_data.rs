@@ -1,15 +1,15 @@
-use std::cmp;
 use std::cmp::Ordering;
-use std::collections::HashMap;
-use crate::db::db_format::{InternalKey, InternalKeyComparator};
+use std::sync::atomic::{AtomicI32, Ordering as AtomicOrdering};
+use crate::db::db_format::InternalKey;
 
 /// @see version_edit FileMetaData
+///
+/// 同一个文件的 FileMetaData 以 Arc<FileMetaData> 的形式被多个 Version 共享,
+/// 不再需要手动维护引用计数。allowed_seeks 会在读取时被修改, 因此使用原子类型
 #[derive(Debug)]
 pub struct FileMetaData {
-    // todo  参考rc的实现
-    refs: i32,
     // Seeks allowed until compaction
-    allowed_seeks: i32,
+    allowed_seeks: AtomicI32,
     number: u64,
     // File size in bytes
     file_size: u64,
@@ -23,8 +23,7 @@ impl Default for FileMetaData {
     #[inline]
     fn default() -> Self {
         Self {
-            refs: 0,
-            allowed_seeks: 1 << 30,
+            allowed_seeks: AtomicI32::new(1 << 30),
             number: 0,
             file_size: 0,
             smallest: InternalKey::default(),
@@ -33,34 +32,40 @@ impl Default for FileMetaData {
     }
 }
 
-impl FileMetaData {
-    #[inline]
-    pub fn new_with_refs(refs: i32) -> Self {
-        FileMetaData::new_with_refs_allowed_seeks(refs, 1 << 30)
+impl Clone for FileMetaData {
+    fn clone(&self) -> Self {
+        Self {
+            allowed_seeks: AtomicI32::new(self.get_allowed_seeks()),
+            number: self.number,
+            file_size: self.file_size,
+            smallest: self.smallest.clone(),
+            largest: self.largest.clone()
+        }
     }
+}
 
+impl FileMetaData {
     #[inline]
-    pub fn new_with_refs_allowed_seeks(refs: i32, allowed_seeks: i32) -> Self {
-        FileMetaData::new_with_refs_allowed_seeks_file_size(refs, allowed_seeks, 0)
+    pub fn new_with_allowed_seeks(allowed_seeks: i32) -> Self {
+        FileMetaData::new_with_allowed_seeks_file_size(allowed_seeks, 0)
     }
 
-    pub fn new_with_refs_allowed_seeks_file_size(refs: i32, allowed_seeks: i32, file_size: u64) -> Self {
-        FileMetaData::new_with_refs_allowed_seeks_file_size_internal_key(refs, allowed_seeks, file_size, InternalKey::default(), InternalKey::default())
+    pub fn new_with_allowed_seeks_file_size(allowed_seeks: i32, file_size: u64) -> Self {
+        FileMetaData::new_with_allowed_seeks_file_size_internal_key(allowed_seeks, file_size, InternalKey::default(), InternalKey::default())
     }
 
-    pub fn new_with_refs_allowed_seeks_file_size_internal_key(refs: i32, allowed_seeks: i32, file_size: u64,
-                                                            smallest: InternalKey, largest: InternalKey) -> Self {
-        FileMetaData::new(refs, allowed_seeks, 0, file_size, smallest, largest)
+    pub fn new_with_allowed_seeks_file_size_internal_key(allowed_seeks: i32, file_size: u64,
+                                                         smallest: InternalKey, largest: InternalKey) -> Self {
+        FileMetaData::new(allowed_seeks, 0, file_size, smallest, largest)
     }
 
     pub fn new_with_number_file_size_internal_key(number: u64, file_size: u64, smallest: InternalKey, largest: InternalKey) -> Self {
-        FileMetaData::new(0, 1 << 30, number, file_size, smallest, largest)
+        FileMetaData::new(1 << 30, number, file_size, smallest, largest)
     }
 
-    pub fn new(refs: i32, allowed_seeks: i32, number: u64, file_size: u64, smallest: InternalKey, largest: InternalKey) -> Self {
+    pub fn new(allowed_seeks: i32, number: u64, file_size: u64, smallest: InternalKey, largest: InternalKey) -> Self {
         Self {
-            refs,
-            allowed_seeks,
+            allowed_seeks: AtomicI32::new(allowed_seeks),
             number,
             file_size,
             smallest,
@@ -72,6 +77,10 @@ impl FileMetaData {
         self.number
     }
 
+    pub fn set_number(&mut self, number: u64) {
+        self.number = number;
+    }
+
     /// File size in bytes
     pub fn get_file_size(&self) -> u64 {
         self.file_size
@@ -91,12 +100,26 @@ impl FileMetaData {
         &self.largest
     }
 
-    pub fn get_refs(&self) -> i32 {
-        self.refs
+    pub fn set_smallest(&mut self, smallest: InternalKey) {
+        self.smallest = smallest;
     }
 
-    pub fn add_refs(&mut self, num: i32) {
-        self.refs += num;
+    pub fn set_largest(&mut self, largest: InternalKey) {
+        self.largest = largest;
+    }
+
+    /// Seeks allowed until compaction
+    pub fn get_allowed_seeks(&self) -> i32 {
+        self.allowed_seeks.load(AtomicOrdering::Relaxed)
+    }
+
+    pub fn set_allowed_seeks(&self, allowed_seeks: i32) {
+        self.allowed_seeks.store(allowed_seeks, AtomicOrdering::Relaxed);
+    }
+
+    /// allowed_seeks 减一, 返回减少后的值
+    pub fn decrease_allowed_seeks(&self) -> i32 {
+        self.allowed_seeks.fetch_sub(1, AtomicOrdering::Relaxed) - 1
     }
 }
 
@@ -104,8 +127,7 @@ impl PartialEq for FileMetaData {
     /// 判断两个 FileMetaData 是否相同
     #[inline]
     fn eq(&self, other: &Self) -> bool {
-        self.refs == other.refs
-        && self.allowed_seeks == other.allowed_seeks
+        self.get_allowed_seeks() == other.get_allowed_seeks()
         && self.number == other.number
         && self.file_size == other.file_size
         && self.smallest.eq(&other.smallest)
@@ -115,9 +137,9 @@ impl PartialEq for FileMetaData {
 
 impl PartialOrd for FileMetaData {
     /// 判断两个 FileMetaData 的大小关系
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    fn partial_cmp(&self, _other: &Self) -> Option<Ordering> {
         // todo
         // InternalKeyComparator::compare()
         Option::Some(Ordering::Equal)
     }
-}
\ No newline at end of file
+}
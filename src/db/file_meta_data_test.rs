@@ -7,8 +7,8 @@ mod test {
     fn test_eq() {
         let meta1: FileMetaData = FileMetaData::default();
         let meta2: FileMetaData = FileMetaData::default();
-        let meta3: FileMetaData = FileMetaData::new_with_refs(6);
-        let meta3_1: FileMetaData = FileMetaData::new_with_refs(6);
+        let meta3: FileMetaData = FileMetaData::new_with_allowed_seeks(6);
+        let meta3_1: FileMetaData = FileMetaData::new_with_allowed_seeks(6);
 
         assert!(meta1.eq(&meta2));
         assert!(!meta1.eq(&meta3));
@@ -19,11 +19,22 @@ mod test {
     fn test_partial_ord() {
         let meta0: FileMetaData = FileMetaData::default();
         let meta0_1: FileMetaData = FileMetaData::default();
-        let meta3: FileMetaData = FileMetaData::new_with_refs(3);
-        let meta6: FileMetaData = FileMetaData::new_with_refs(6);
-        let meta7: FileMetaData = FileMetaData::new_with_refs(7);
+        let meta3: FileMetaData = FileMetaData::new_with_allowed_seeks(3);
+        let meta6: FileMetaData = FileMetaData::new_with_allowed_seeks(6);
+        let meta7: FileMetaData = FileMetaData::new_with_allowed_seeks(7);
 
         assert!(meta0.partial_cmp(&meta0_1).is_some());
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_allowed_seeks() {
+        let meta = FileMetaData::new_with_allowed_seeks(2);
+        assert_eq!(1, meta.decrease_allowed_seeks());
+        assert_eq!(0, meta.decrease_allowed_seeks());
+
+        meta.set_allowed_seeks(100);
+        let cloned = meta.clone();
+        assert_eq!(100, cloned.get_allowed_seeks());
+        assert!(meta.eq(&cloned));
+    }
+}
@@ -1,12 +1,233 @@
-use crate::util::slice::Slice;
-
-// TODo 参考 PathBuf
-pub struct FileName {
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::util::env::Env;
+use crate::util::Result;
 
+/// 数据库目录下的文件类型
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FileType {
+    /// 预写日志, %06d.log
+    KLogFile,
+    /// 数据库锁文件, LOCK
+    KDBLockFile,
+    /// SSTable 文件, %06d.ldb (兼容旧版本的 %06d.sst)
+    KTableFile,
+    /// MANIFEST 文件, MANIFEST-%06d
+    KDescriptorFile,
+    /// 记录当前 MANIFEST 文件名的 CURRENT 文件
+    KCurrentFile,
+    /// 临时文件, %06d.dbtmp
+    KTempFile,
+    /// 运行日志, LOG 及 LOG.old
+    KInfoLogFile,
 }
 
+/// 数据库目录下各类文件的文件名生成与解析
+///
+/// 所有生成文件名的方法都以 dbname 为目录, 返回完整的文件路径
+pub struct FileName {}
+
 impl FileName {
-    pub fn table_file_name(dbname: &Slice, number : u64) -> Slice{
-        todo!()
+    /// 预写日志的文件名, dbname/%06d.log
+    ///
+    /// # Arguments
+    ///
+    /// * `dbname`: 数据库目录
+    /// * `number`: 文件号, 必须大于 0
+    ///
+    /// returns: PathBuf
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::db::filename::FileName;
+    /// // foo/000192.log
+    /// let path = FileName::log_file_name("foo", 192);
+    /// ```
+    pub fn log_file_name<P: AsRef<Path>>(dbname: P, number: u64) -> PathBuf {
+        assert!(number > 0);
+        Self::make_file_name(dbname, number, "log")
+    }
+
+    /// SSTable 的文件名, dbname/%06d.ldb
+    ///
+    /// # Arguments
+    ///
+    /// * `dbname`: 数据库目录
+    /// * `number`: 文件号, 必须大于 0
+    ///
+    /// returns: PathBuf
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::db::filename::FileName;
+    /// // foo/000200.ldb
+    /// let path = FileName::table_file_name("foo", 200);
+    /// ```
+    pub fn table_file_name<P: AsRef<Path>>(dbname: P, number: u64) -> PathBuf {
+        assert!(number > 0);
+        Self::make_file_name(dbname, number, "ldb")
+    }
+
+    /// 旧版本 SSTable 的文件名, dbname/%06d.sst。 读取 table 时, 如果 .ldb 文件不存在则尝试该文件名
+    pub fn sst_table_file_name<P: AsRef<Path>>(dbname: P, number: u64) -> PathBuf {
+        assert!(number > 0);
+        Self::make_file_name(dbname, number, "sst")
+    }
+
+    /// MANIFEST 的文件名, dbname/MANIFEST-%06d
+    ///
+    /// # Arguments
+    ///
+    /// * `dbname`: 数据库目录
+    /// * `number`: 文件号, 必须大于 0
+    ///
+    /// returns: PathBuf
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::db::filename::FileName;
+    /// // foo/MANIFEST-000100
+    /// let path = FileName::descriptor_file_name("foo", 100);
+    /// ```
+    pub fn descriptor_file_name<P: AsRef<Path>>(dbname: P, number: u64) -> PathBuf {
+        assert!(number > 0);
+        dbname.as_ref().join(format!("MANIFEST-{:06}", number))
+    }
+
+    /// CURRENT 文件的文件名, 该文件中记录了当前 MANIFEST 文件的文件名
+    pub fn current_file_name<P: AsRef<Path>>(dbname: P) -> PathBuf {
+        dbname.as_ref().join("CURRENT")
+    }
+
+    /// 数据库锁文件的文件名, 持有该文件锁的进程拥有数据库
+    pub fn lock_file_name<P: AsRef<Path>>(dbname: P) -> PathBuf {
+        dbname.as_ref().join("LOCK")
+    }
+
+    /// 临时文件的文件名, dbname/%06d.dbtmp
+    pub fn temp_file_name<P: AsRef<Path>>(dbname: P, number: u64) -> PathBuf {
+        assert!(number > 0);
+        Self::make_file_name(dbname, number, "dbtmp")
+    }
+
+    /// 运行日志的文件名, dbname/LOG
+    pub fn info_log_file_name<P: AsRef<Path>>(dbname: P) -> PathBuf {
+        dbname.as_ref().join("LOG")
     }
-}
\ No newline at end of file
+
+    /// 上一个运行日志的文件名, dbname/LOG.old
+    pub fn old_info_log_file_name<P: AsRef<Path>>(dbname: P) -> PathBuf {
+        dbname.as_ref().join("LOG.old")
+    }
+
+    /// 解析数据库目录下的文件名(不含目录部分), 得到文件号及文件类型。
+    /// 不属于 LevelDB 的文件返回 None
+    ///
+    /// Owned filenames have the form:
+    ///    dbname/CURRENT
+    ///    dbname/LOCK
+    ///    dbname/LOG
+    ///    dbname/LOG.old
+    ///    dbname/MANIFEST-[0-9]+
+    ///    dbname/[0-9]+.(log|sst|ldb|dbtmp)
+    ///
+    /// # Arguments
+    ///
+    /// * `fname`: 文件名
+    ///
+    /// returns: Option<(u64, FileType)>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::db::filename::{FileName, FileType};
+    /// let (number, file_type) = FileName::parse_file_name("000100.log").unwrap();
+    /// assert_eq!(100, number);
+    /// assert_eq!(FileType::KLogFile, file_type);
+    /// ```
+    pub fn parse_file_name(fname: &str) -> Option<(u64, FileType)> {
+        match fname {
+            "CURRENT" => return Some((0, FileType::KCurrentFile)),
+            "LOCK" => return Some((0, FileType::KDBLockFile)),
+            "LOG" | "LOG.old" => return Some((0, FileType::KInfoLogFile)),
+            _ => {}
+        }
+
+        if let Some(rest) = fname.strip_prefix("MANIFEST-") {
+            let (number, suffix) = Self::consume_decimal_number(rest)?;
+            if !suffix.is_empty() {
+                return None;
+            }
+            return Some((number, FileType::KDescriptorFile));
+        }
+
+        let (number, suffix) = Self::consume_decimal_number(fname)?;
+        let file_type = match suffix {
+            ".log" => FileType::KLogFile,
+            ".sst" | ".ldb" => FileType::KTableFile,
+            ".dbtmp" => FileType::KTempFile,
+            _ => return None
+        };
+        Some((number, file_type))
+    }
+
+    /// 将 CURRENT 文件指向 descriptor_number 对应的 MANIFEST 文件。
+    ///
+    /// 先将内容写入临时文件并同步到磁盘, 再通过 rename 原子地替换 CURRENT,
+    /// 保证任何时刻 CURRENT 中都是一个完整的 MANIFEST 文件名
+    ///
+    /// # Arguments
+    ///
+    /// * `env`: 运行环境
+    /// * `dbname`: 数据库目录
+    /// * `descriptor_number`: MANIFEST 文件号
+    ///
+    /// returns: Result<(), Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// FileName::set_current_file(&env, "foo", 5)?;
+    /// ```
+    pub fn set_current_file<P: AsRef<Path>>(env: &Env, dbname: P, descriptor_number: u64) -> Result<()> {
+        let dbname = dbname.as_ref();
+        let manifest = Self::descriptor_file_name(dbname, descriptor_number);
+        // 去掉 "dbname/" 前缀, CURRENT 中只记录文件名
+        let contents = manifest.file_name()
+            .expect("descriptor file name")
+            .to_string_lossy()
+            .into_owned();
+        let tmp = Self::temp_file_name(dbname, descriptor_number);
+
+        let rs = Self::write_string_to_file_sync(env, &tmp, format!("{}\n", contents).as_bytes())
+            .and_then(|_| env.rename_file(&tmp, &Self::current_file_name(dbname)));
+        if rs.is_err() {
+            let _ = env.remove_file(&tmp);
+        }
+        rs
+    }
+
+    fn write_string_to_file_sync(env: &Env, fname: &Path, data: &[u8]) -> Result<()> {
+        let mut file = env.new_writable_file(fname)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    fn make_file_name<P: AsRef<Path>>(dbname: P, number: u64, suffix: &str) -> PathBuf {
+        dbname.as_ref().join(format!("{:06}.{}", number, suffix))
+    }
+
+    /// 解析字符串开头的十进制数字, 返回数字及剩余的字符串。没有数字或者数字溢出时返回 None
+    fn consume_decimal_number(input: &str) -> Option<(u64, &str)> {
+        let digits = input.bytes().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        let number = input[..digits].parse::<u64>().ok()?;
+        Some((number, &input[digits..]))
+    }
+}
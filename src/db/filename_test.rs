@@ -0,0 +1,124 @@
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::db::filename::{FileName, FileType};
+    use crate::util::env::Env;
+    use crate::util::Result;
+
+    #[test]
+    fn test_parse() {
+        let cases: [(&str, u64, FileType); 14] = [
+            ("100.log", 100, FileType::KLogFile),
+            ("0.log", 0, FileType::KLogFile),
+            ("0.sst", 0, FileType::KTableFile),
+            ("0.ldb", 0, FileType::KTableFile),
+            ("CURRENT", 0, FileType::KCurrentFile),
+            ("LOCK", 0, FileType::KDBLockFile),
+            ("MANIFEST-2", 2, FileType::KDescriptorFile),
+            ("MANIFEST-7", 7, FileType::KDescriptorFile),
+            ("LOG", 0, FileType::KInfoLogFile),
+            ("LOG.old", 0, FileType::KInfoLogFile),
+            ("18446744073709551615.log", 18446744073709551615, FileType::KLogFile),
+            ("000123.dbtmp", 123, FileType::KTempFile),
+            ("000200.ldb", 200, FileType::KTableFile),
+            ("MANIFEST-000001", 1, FileType::KDescriptorFile),
+        ];
+        for (fname, number, file_type) in cases {
+            let parsed = FileName::parse_file_name(fname);
+            assert_eq!(Some((number, file_type)), parsed, "{}", fname);
+        }
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let errors = [
+            "",
+            "foo",
+            "foo-dx-100.log",
+            ".log",
+            "",
+            "manifest",
+            "CURREN",
+            "CURRENTX",
+            "MANIFES",
+            "MANIFEST",
+            "MANIFEST-",
+            "XMANIFEST-3",
+            "MANIFEST-3x",
+            "LOC",
+            "LOCKx",
+            "LO",
+            "LOGx",
+            "18446744073709551616.log",
+            "184467440737095516150.log",
+            "100",
+            "100.",
+            "100.lop",
+        ];
+        for fname in errors {
+            assert_eq!(None, FileName::parse_file_name(fname), "{}", fname);
+        }
+    }
+
+    #[test]
+    fn test_construction() {
+        let fname = FileName::current_file_name("foo");
+        assert_eq!(PathBuf::from("foo/CURRENT"), fname);
+
+        let fname = FileName::lock_file_name("foo");
+        assert_eq!(PathBuf::from("foo/LOCK"), fname);
+
+        let fname = FileName::log_file_name("foo", 192);
+        assert_eq!(PathBuf::from("foo/000192.log"), fname);
+
+        let fname = FileName::table_file_name("bar", 200);
+        assert_eq!(PathBuf::from("bar/000200.ldb"), fname);
+
+        let fname = FileName::sst_table_file_name("bar", 200);
+        assert_eq!(PathBuf::from("bar/000200.sst"), fname);
+
+        let fname = FileName::descriptor_file_name("bar", 100);
+        assert_eq!(PathBuf::from("bar/MANIFEST-000100"), fname);
+
+        let fname = FileName::temp_file_name("tmp", 999);
+        assert_eq!(PathBuf::from("tmp/000999.dbtmp"), fname);
+
+        let fname = FileName::info_log_file_name("foo");
+        assert_eq!(PathBuf::from("foo/LOG"), fname);
+
+        let fname = FileName::old_info_log_file_name("foo");
+        assert_eq!(PathBuf::from("foo/LOG.old"), fname);
+
+        // 生成的文件名都可以被重新解析
+        for (fname, number, file_type) in [
+            (FileName::log_file_name("foo", 7), 7, FileType::KLogFile),
+            (FileName::table_file_name("foo", 8), 8, FileType::KTableFile),
+            (FileName::descriptor_file_name("foo", 9), 9, FileType::KDescriptorFile),
+            (FileName::temp_file_name("foo", 10), 10, FileType::KTempFile),
+        ] {
+            let name = fname.file_name().unwrap().to_str().unwrap();
+            assert_eq!(Some((number, file_type)), FileName::parse_file_name(name));
+        }
+    }
+
+    #[test]
+    fn test_set_current_file() -> Result<()> {
+        let dbname = std::env::temp_dir().join("level_db_rust_filename_test");
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname)?;
+        let env = Env {};
+
+        FileName::set_current_file(&env, &dbname, 5)?;
+        let current = fs::read_to_string(FileName::current_file_name(&dbname))?;
+        assert_eq!("MANIFEST-000005\n", current);
+        // 临时文件已经被重命名
+        assert!(!FileName::temp_file_name(&dbname, 5).exists());
+
+        FileName::set_current_file(&env, &dbname, 12)?;
+        let current = fs::read_to_string(FileName::current_file_name(&dbname))?;
+        assert_eq!("MANIFEST-000012\n", current);
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@ use std::fs::File;
 use std::io::{Read, Seek, Write};
 use std::io::SeekFrom::Start;
 
-use crate::db::log_writer::{K_BLOCK_SIZE, K_FIRST_TYPE, K_FULL_TYPE, K_LAST_TYPE, K_MIDDLE_TYPE};
+use crate::db::log_writer::{K_BLOCK_SIZE, K_FIRST_TYPE, K_FULL_TYPE, K_HEADER_SIZE, K_LAST_TYPE, K_MIDDLE_TYPE, K_ZERO_TYPE};
 use crate::util::coding::Decoder;
 use crate::util::crc::{AsCrc, CRC};
 use crate::util::Result;
@@ -43,39 +43,51 @@ impl LogReader {
         }
     }
 
+    /// 读取下一条完整的记录, 日志读取完毕时返回 None
+    ///
+    /// 被分成多个分片(First/Middle/Last)写入的记录会在这里重新拼接。
+    /// 文件末尾不完整的记录(写入过程中进程退出)被视为日志结束
     pub fn read_next(&mut self) -> Result<Option<Slice>> {
         let mut tmp: Option<Vec<u8>> = None;
         loop {
-            if self.buf_read_idx >= self.buf_len {
-                self.read_buf()?;
-            }
-            let data_len = (self.buf[self.buf_read_idx + 4] as usize) + ((self.buf[self.buf_read_idx + 5] as usize) << 8);
-            let record_type = self.buf[self.buf_read_idx + 6];
-            self.buf_read_idx += 7;
-            // CRC check
-            self.check_crc(data_len)?;
+            let (record_type, start, end) = match self.read_physical_record()? {
+                Some(record) => record,
+                None => return Ok(None)
+            };
+            let data = &self.buf[start..end];
             match record_type {
                 K_FULL_TYPE => {
-                    let end_idx = self.buf_read_idx + data_len;
-                    let data = &self.buf[self.buf_read_idx..end_idx];
-                    self.buf_read_idx += data_len;
+                    if tmp.is_some() {
+                        return Err(Status::wrapper(LevelError::KCorruption,
+                                                   "partial record without end".into()));
+                    }
                     return Ok(Some(Slice::from_buf(data)));
                 }
                 K_FIRST_TYPE => {
-                    tmp = Some(Vec::with_capacity(K_BLOCK_SIZE));
-                    let partial_data = &self.buf[self.buf_read_idx..];
-                    tmp.as_mut().unwrap().write(partial_data)?;
+                    if tmp.is_some() {
+                        return Err(Status::wrapper(LevelError::KCorruption,
+                                                   "partial record without end".into()));
+                    }
+                    let mut partial = Vec::with_capacity(K_BLOCK_SIZE);
+                    partial.write_all(data)?;
+                    tmp = Some(partial);
                 }
                 K_MIDDLE_TYPE => {
-                    tmp.as_mut().unwrap().write(self.buf.as_ref())?;
+                    match tmp.as_mut() {
+                        Some(partial) => partial.write_all(data)?,
+                        None => return Err(Status::wrapper(LevelError::KCorruption,
+                                                           "missing start of fragmented record".into()))
+                    }
                 }
                 K_LAST_TYPE => {
-                    let end_idx = self.buf_read_idx + data_len;
-                    let partial_data = &self.buf[self.buf_read_idx..end_idx];
-                    self.buf_read_idx += data_len;
-                    tmp.as_mut().unwrap().write(partial_data)?;
-                    let data = tmp.unwrap();
-                    return Ok(Some(Slice::from_vec(data)));
+                    return match tmp.take() {
+                        Some(mut partial) => {
+                            partial.write_all(data)?;
+                            Ok(Some(Slice::from_vec(partial)))
+                        }
+                        None => Err(Status::wrapper(LevelError::KCorruption,
+                                                    "missing start of fragmented record".into()))
+                    };
                 }
                 _ => {
                     return Err(Status::wrapper(LevelError::KCorruption,
@@ -85,9 +97,65 @@ impl LogReader {
         }
     }
 
+    /// 读取一条物理记录, 返回记录类型以及数据在 buf 中的起止位置
+    fn read_physical_record(&mut self) -> Result<Option<(u8, usize, usize)>> {
+        loop {
+            if self.buf_len - self.buf_read_idx < K_HEADER_SIZE {
+                if self.eof {
+                    // Note that if buffer is non-empty, we have a truncated header at the end of the file,
+                    // which can be caused by the writer crashing in the middle of writing the header.
+                    // Instead of considering this an error, just report EOF.
+                    return Ok(None);
+                }
+                // Last read was a full read, so this is a trailer to skip
+                self.read_buf()?;
+                continue;
+            }
+            let header = &self.buf[self.buf_read_idx..];
+            let data_len = (header[4] as usize) + ((header[5] as usize) << 8);
+            let record_type = header[6];
+            if K_HEADER_SIZE + data_len > self.buf_len - self.buf_read_idx {
+                if self.eof {
+                    // the writer died in the middle of writing the record
+                    return Ok(None);
+                }
+                self.buf_read_idx = self.buf_len;
+                return Err(Status::wrapper(LevelError::KCorruption, "bad record length".into()));
+            }
+            self.buf_read_idx += K_HEADER_SIZE;
+            if record_type == K_ZERO_TYPE && data_len == 0 {
+                // Skip zero length record, which may be produced by mmap based writing code that
+                // preallocates file regions.
+                self.buf_read_idx = self.buf_len;
+                continue;
+            }
+            // CRC check
+            if let Err(e) = self.check_crc(data_len) {
+                // Drop the rest of the buffer since "length" itself may have
+                // been corrupted and if we trust it, we could find some
+                // fragment of a real log record that just happens to look
+                // like a valid log record.
+                self.buf_read_idx = self.buf_len;
+                return Err(e);
+            }
+            let start = self.buf_read_idx;
+            self.buf_read_idx += data_len;
+            return Ok(Some((record_type, start, self.buf_read_idx)));
+        }
+    }
+
     fn read_buf(&mut self) -> Result<()> {
         self.buf_read_idx = 0;
-        self.buf_len = self.file_reader.read(self.buf.as_mut())?;
+        self.buf_len = 0;
+        // read 可能只读到部分数据, 需要一直读满一个 block 或者读到文件末尾
+        while self.buf_len < K_BLOCK_SIZE {
+            let n = self.file_reader.read(&mut self.buf[self.buf_len..])?;
+            if n == 0 {
+                break;
+            }
+            self.buf_len += n;
+        }
+        self.read_pos += self.buf_len;
         if self.buf_len < K_BLOCK_SIZE {
             self.eof = true;
         }
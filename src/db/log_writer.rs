@@ -1,3 +1,5 @@
+use std::fs::File;
+use std::io;
 use std::io::Write;
 use crate::util::coding::Encoder;
 use crate::util::crc::{AsCrc, CRC};
@@ -20,15 +22,33 @@ pub const K_HEADER_SIZE: usize = 4 + 2 + 1;
 const K_EMPTY_BYTES: [u8; 6] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
 
 pub struct LogWriter {
-    file_writer: Box<dyn Write>,
+    file_writer: Box<dyn WritableFile>,
     /// Offset in current block
     block_offset: usize,
 
     type_crc: [u32; K_MAX_RECORD_TYPE + 1],
 }
 
+/// 日志的写入目标, 除了顺序写入外还需要支持将数据同步到磁盘
+pub trait WritableFile: Write + Send + Sync {
+    /// 将已写入的数据同步到磁盘
+    fn sync(&mut self) -> io::Result<()>;
+}
+
+impl WritableFile for File {
+    fn sync(&mut self) -> io::Result<()> {
+        self.sync_data()
+    }
+}
+
+impl WritableFile for Vec<u8> {
+    fn sync(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 impl LogWriter {
-    pub fn new(file_writer: Box<dyn Write>) -> LogWriter {
+    pub fn new(file_writer: Box<dyn WritableFile>) -> LogWriter {
         let mut type_crc = [0_u32; K_MAX_RECORD_TYPE + 1];
         init_type_crc(&mut type_crc);
         Self {
@@ -39,15 +59,15 @@ impl LogWriter {
     }
 
     pub fn add_record(&mut self, slice: Slice) -> Result<()> {
-        let left_over = K_BLOCK_SIZE - self.block_offset;
-        assert!(left_over >= 0);
         let mut left = slice.len();
         let mut begin = true;
         let mut start_idx = 0;
         while begin || left > 0 {
+            let left_over = K_BLOCK_SIZE - self.block_offset;
             if left_over < K_HEADER_SIZE {
+                // Switch to a new block, fill the trailer with zeroes
                 if left_over > 0 {
-                    self.file_writer.write(&K_EMPTY_BYTES[0..left_over])?;
+                    self.file_writer.write_all(&K_EMPTY_BYTES[0..left_over])?;
                 }
                 self.block_offset = 0;
             }
@@ -71,18 +91,24 @@ impl LogWriter {
         Ok(())
     }
 
+    /// 将已写入的日志同步到磁盘
+    pub fn sync(&mut self) -> Result<()> {
+        self.file_writer.sync()?;
+        Ok(())
+    }
+
     fn emit_physical_record(&mut self, record_type: u8, data: &[u8]) -> Result<()> {
         let mut crc = CRC::extend(self.type_crc[record_type as usize], data);
         crc = CRC::mask(crc);
         let mut header = [0_u8; K_HEADER_SIZE];
-        Encoder::with_buf(&mut header).put_varint32(crc);
+        Encoder::with_buf(&mut header).put_fixed32(crc)?;
         header[4] = (data.len() & 0xff) as u8;
         header[5] = (data.len() >> 8) as u8;
         header[6] = record_type;
-        self.file_writer.write(header.as_ref())?;
+        self.file_writer.write_all(header.as_ref())?;
         self.block_offset += K_HEADER_SIZE;
         if !data.is_empty() {
-            self.file_writer.write(data)?;
+            self.file_writer.write_all(data)?;
             self.block_offset += data.len();
         }
         self.file_writer.flush()?;
@@ -1,28 +1,55 @@
-use std::io::Write;
+use std::cmp::Ordering;
 use std::sync::{Arc, Mutex};
-use crate::db::db_format::{LookupKey, ValueType};
-use crate::db::skip_list::SkipList;
+use crate::db::db_format::{InternalKeyComparator, LookupKey, ParsedInternalKey, ValueType};
+use crate::db::skip_list::{Iter, SkipList};
 use crate::traits::comparator_trait::Comparator;
 use crate::traits::DataIterator;
 use crate::util::arena::ArenaRef;
 use crate::util::slice::Slice;
 use crate::util::{Arena, Result};
-use crate::util::coding::{Encoder, varint_length};
+use crate::util::coding::{Decoder, Encoder, varint_length};
+use crate::util::status::{LevelError, Status};
 use crate::util::unsafe_slice::UnsafeSlice;
 
 /// 内存表
-pub struct MemTable<Cmp: Comparator> {
-    cmp: Arc<Cmp>,
-    list: SkipList<Cmp>,
+///
+/// 跳表中的每个元素的格式为:
+///     key_size     : varint32 of internal_key.size()
+///     key bytes    : char[internal_key.size()]
+///     value_size   : varint32 of value.size()
+///     value bytes  : char[value.size()]
+/// 元素之间按照其中的 internal key 排序
+pub struct MemTable {
+    comparator: Arc<KeyComparator>,
+    list: SkipList<KeyComparator>,
     arena: ArenaRef,
 }
 
-impl<Cmp: Comparator> MemTable<Cmp> {
+/// 内存表只会在持有 DB 锁时写入, 成为 imm 之后只读, 因此可以在线程间共享
+unsafe impl Send for MemTable {}
+
+unsafe impl Sync for MemTable {}
+
+/// 比较跳表中的元素, 只比较元素中带长度前缀的 internal key
+pub struct KeyComparator {
+    comparator: InternalKeyComparator,
+}
+
+/// 内存表迭代器, key 为 internal key
+struct MemTableIterator {
+    iter: Iter<KeyComparator>,
+    // 跳表中的 key 及节点都分配在 arena 中, 持有 arena 保证迭代期间内存有效
+    _arena: ArenaRef,
+    // For passing to EncodeKey
+    tmp: Vec<u8>,
+}
+
+impl MemTable {
     /// 创建内存表
     ///
     /// # Arguments
     ///
-    /// * `_comparator`: 比较器
+    /// * `cmp`: user key 的比较器, 即 Options::cmp
     ///
     /// returns: MemTable
     ///
@@ -31,11 +58,14 @@ impl<Cmp: Comparator> MemTable<Cmp> {
     /// ```
     /// let mt = MemTable::create(cmp);
     /// ```
-    pub fn create(cmp: Arc<Cmp>) -> Self {
+    pub fn create(cmp: Arc<dyn Comparator>) -> Self {
         let arena = Arc::new(Mutex::new(Arena::default()));
-        let list = SkipList::create(cmp.clone(), arena.clone());
+        let comparator = Arc::new(KeyComparator {
+            comparator: InternalKeyComparator::new(cmp),
+        });
+        let list = SkipList::create(comparator.clone(), arena.clone());
         Self {
-            cmp,
+            comparator,
             list,
             arena,
         }
@@ -47,30 +77,56 @@ impl<Cmp: Comparator> MemTable<Cmp> {
         self.arena.lock().unwrap().memory_usage()
     }
 
-    /// 创建内存表迭代器
+    /// 内存表中的元素数量
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    /// 内存表是否为空
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.list.len() == 0
+    }
+
+    /// 创建内存表迭代器, 迭代器的 key 为 internal key, value 为写入的值。
     ///
-    /// # Arguments
+    /// 迭代器持有内存表的 arena, 迭代期间可以继续(由持有 DB 锁的唯一写线程)向内存表中写入数据
     ///
-    /// returns: MemTable
+    /// Return an iterator that yields the contents of the memtable.
+    ///
+    /// The caller must ensure that the underlying MemTable remains live
+    /// while the returned iterator is live.  The keys returned by this
+    /// iterator are internal keys encoded by AppendInternalKey in the
+    /// db/format.{h,cc} module.
     ///
     /// # Examples
     ///
     /// ```
     /// let mem = MemTable::create(comp);
-    /// let it = mem.new_new_iterator()?;
+    /// let mut iter = mem.new_iterator();
+    /// iter.seek_to_first();
     /// ```
-    pub fn new_iterator(&self) -> Result<Box<dyn DataIterator>> {
-        todo!()
+    pub fn new_iterator(&self) -> Box<dyn DataIterator> {
+        Box::new(MemTableIterator {
+            iter: self.list.iter(),
+            _arena: self.arena.clone(),
+            tmp: vec![],
+        })
     }
 
     /// 像内存表中写入或删除一个元素
+    ///
+    /// Add an entry into memtable that maps key to value at the
+    /// specified sequence number and with the specified type.
+    /// Typically value will be empty if type==kTypeDeletion.
     pub fn add<R: AsRef<[u8]>>(&mut self, seq_no: usize, v_type: ValueType, key: &R, value: &R) -> Result<()> {
         let key_buf = key.as_ref();
         let value_buf = value.as_ref();
         let key_size = key_buf.len();
         let value_size = value_buf.len();
         let internal_key_size = key_size + 8;
-        let encoded_len = varint_length(key_size as u64)
+        let encoded_len = varint_length(internal_key_size as u64)
             + internal_key_size
             + varint_length(value_size as u64)
             + value_size;
@@ -85,20 +141,143 @@ impl<Cmp: Comparator> MemTable<Cmp> {
             encoder.uncheck_put_buf(key_buf);
             // write seq_no and type
             encoder.uncheck_put_fixed64((seq_no << 8 | v_type.get_value()) as u64);
+            // write value size
+            encoder.uncheck_put_varint32(value_size as u32);
             // write value slice
             encoder.uncheck_put_buf(value_buf);
         }
-        // let slice = Slice::from_buf(buf);
-        self.list.insert(UnsafeSlice::new_with_arena(buf, self.arena.clone())?)
+        debug_assert_eq!(encoded_len, encoder.offset());
+        let entry = unsafe { UnsafeSlice::from_raw_parts(buf.as_ptr(), encoded_len) };
+        drop(lock);
+        self.list.insert(entry)
     }
 
     /// 通过 key 查找结果
-    pub fn get(&self, _key: &LookupKey) -> Result<Option<Slice>> {
-        todo!()
+    ///
+    /// If memtable contains a value for key, returns it.
+    /// If memtable contains a deletion for key, returns a NotFound() error.
+    /// Else, returns None.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: 查找键, 只会找到序列号不大于 key 中序列号的数据
+    ///
+    /// returns: Result<Option<Slice>, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let key = LookupKey::new(Slice::from("foo"), seq);
+    /// match mem.get(&key) {
+    ///     // 找到 value
+    ///     Ok(Some(value)) => {},
+    ///     // key 已被删除
+    ///     Err(e) if e.is_not_found() => {},
+    ///     // 内存表中没有该 key, 需要继续查找 imm 及 table
+    ///     _ => {},
+    /// }
+    /// ```
+    pub fn get(&self, key: &LookupKey) -> Result<Option<Slice>> {
+        let mut iter = self.new_iterator();
+        iter.seek(&key.internal_key());
+        if !iter.valid() {
+            return Ok(None);
+        }
+        // Check that it belongs to same user key.  We do not check the
+        // sequence number since the Seek() call above should have skipped
+        // all entries with overly large sequence numbers.
+        let internal_key = iter.key();
+        let parsed = match ParsedInternalKey::parse_internal_key(internal_key.as_ref()) {
+            Some(parsed) => parsed,
+            None => return Err(LevelError::corruption_string("corrupted memtable key", "")),
+        };
+        let ucmp = self.comparator.comparator.user_comparator();
+        if ucmp.compare(parsed.user_key(), &key.user_key()) != Some(Ordering::Equal) {
+            return Ok(None);
+        }
+        match parsed.value_type() {
+            ValueType::KTypeValue => Ok(Some(Slice::from_buf(iter.value().as_ref()))),
+            ValueType::KTypeDeletion => Err(LevelError::not_found(key.user_key(), Slice::default())),
+        }
     }
 }
 
-mod test {
-    #[test]
-    fn test() {}
-}
\ No newline at end of file
+/// 读取 data 开头带 varint32 长度前缀的数据
+fn get_length_prefixed_slice(data: &[u8]) -> &[u8] {
+    let mut decoder = Decoder::with_buf(data);
+    let len = decoder.get_varint32().unwrap_or_default() as usize;
+    let offset = decoder.offset();
+    &data[offset..offset + len]
+}
+
+impl Comparator for KeyComparator {
+    /// Internal keys are encoded as length-prefixed strings.
+    fn compare(&self, a: &[u8], b: &[u8]) -> Option<Ordering> {
+        let a = get_length_prefixed_slice(a);
+        let b = get_length_prefixed_slice(b);
+        Some(self.comparator.compare_key(a, b))
+    }
+
+    fn get_name(&self) -> String {
+        String::from("leveldb.MemTable.KeyComparator")
+    }
+
+    /// 跳表中不需要缩短 key, 原样返回
+    fn find_shortest_separator(&self, start: &String, _limit: &Slice) -> String {
+        start.clone()
+    }
+
+    /// 跳表中不需要缩短 key, 原样返回
+    fn find_short_successor(&self, key: &String) -> String {
+        key.clone()
+    }
+}
+
+impl DataIterator for MemTableIterator {
+    fn valid(&self) -> bool {
+        self.iter.valid()
+    }
+
+    fn seek_to_first(&mut self) {
+        self.iter.seek_to_first()
+    }
+
+    fn seek_to_last(&mut self) {
+        self.iter.seek_to_last()
+    }
+
+    /// target 为 internal key
+    fn seek(&mut self, target: &Slice) {
+        // Encode a suitable internal key target for "target" and return it.
+        self.tmp.clear();
+        Encoder::with_vec_append(&mut self.tmp).put_varint32(target.size() as u32).expect("put key size");
+        self.tmp.extend_from_slice(target);
+        self.iter.seek(&Slice::from_buf(&self.tmp))
+    }
+
+    fn next(&mut self) {
+        DataIterator::next(&mut self.iter)
+    }
+
+    fn pre(&mut self) {
+        self.iter.pre()
+    }
+
+    fn key(&self) -> UnsafeSlice {
+        let entry = self.iter.key();
+        let key = get_length_prefixed_slice(entry.as_ref());
+        unsafe { UnsafeSlice::from_raw_parts(key.as_ptr(), key.len()) }
+    }
+
+    fn value(&self) -> UnsafeSlice {
+        let entry = self.iter.key();
+        let key = get_length_prefixed_slice(entry.as_ref());
+        let value_start = (key.as_ptr() as usize - entry.as_ref().as_ptr() as usize) + key.len();
+        let value = get_length_prefixed_slice(&entry.as_ref()[value_start..]);
+        unsafe { UnsafeSlice::from_raw_parts(value.as_ptr(), value.len()) }
+    }
+
+    fn status(&self) -> Status {
+        Status::default()
+    }
+}
@@ -1,6 +1,4 @@
-use crate::db::db_format::InternalKeyComparator;
 use crate::db::skip_list::SkipList;
-use crate::db::mem_table::MemTable;
 use crate::util::comparator::BytewiseComparatorImpl;
 
 pub mod log_writer;
@@ -10,6 +8,8 @@ mod log_wr_test;
 pub mod skip_list;
 pub mod mem_table;
 pub mod db;
+mod db_test;
+pub mod db_iter;
 mod skip_list_test;
 pub mod db_format;
 mod db_format_test;
@@ -23,8 +23,10 @@ pub mod builder;
 mod builder_test;
 pub mod filename;
 mod filename_test;
+pub mod write_batch;
+mod write_batch_test;
+pub mod repair;
+mod repair_test;
 
 /// 默认调表
 pub type DefaultSkipList = SkipList<BytewiseComparatorImpl>;
-/// 默认内存表
-pub type DefaultMemTable = MemTable<BytewiseComparatorImpl>;
@@ -0,0 +1,423 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::db::builder::BuildTable;
+use crate::db::db::open_info_log;
+use crate::db::db_format::{InternalKeyComparator, ParsedInternalKey};
+use crate::db::file_meta_data::FileMetaData;
+use crate::db::filename::{FileName, FileType};
+use crate::db::log_reader::LogReader;
+use crate::db::log_writer::LogWriter;
+use crate::db::table_cache::TableCache;
+use crate::db::version_edit::VersionEdit;
+use crate::db::write_batch::WriteBatch;
+use crate::db::mem_table::MemTable;
+use crate::{log_info, log_warn};
+use crate::table::table_builder::TableBuilder;
+use crate::traits::DataIterator;
+use crate::util::filter_policy_internal::InternalFilterPolicy;
+use crate::util::filter_policy_prefix::PrefixFilterPolicy;
+use crate::util::slice_transform::InternalSliceTransform;
+use crate::util::options::{Options, OptionsPtr, ReadOptions};
+use crate::util::Result;
+use crate::util::slice::Slice;
+use crate::util::status::LevelError;
+
+// We recover the contents of the descriptor from the other files we find.
+// (1) Any log files are first converted to tables
+// (2) We scan every table to compute
+//     (a) smallest/largest for the table
+//     (b) largest sequence number in the table
+// (3) We generate descriptor contents:
+//      - log number is set to zero
+//      - next-file-number is set to 1 + largest file number we found
+//      - last-sequence-number is set to largest sequence# found across
+//        all tables (see 2c)
+//      - compaction pointers are cleared
+//      - every table file is added at level 0
+//
+// Possible optimization 1:
+//   (a) Compute total size and use to pick appropriate max-level M
+//   (b) Sort tables by largest sequence# in the table
+//   (c) For each table: if it overlaps earlier table, place in level-0,
+//       else place in level-M.
+// Possible optimization 2:
+//   Store per-table metadata (smallest, largest, largest-seq#, ...)
+//   in the table's meta section to speed up ScanTable.
+
+/// MANIFEST 损坏或丢失时, 根据数据库目录下的预写日志及 table 文件重建 MANIFEST
+struct Repairer {
+    dbname: PathBuf,
+    icmp: InternalKeyComparator,
+    options: OptionsPtr,
+    table_cache: Arc<TableCache>,
+    edit: VersionEdit,
+
+    manifests: Vec<String>,
+    table_numbers: Vec<u64>,
+    logs: Vec<u64>,
+    tables: Vec<TableInfo>,
+    next_file_number: u64,
+}
+
+/// 扫描 table 得到的元数据
+struct TableInfo {
+    meta: FileMetaData,
+    max_sequence: u64,
+}
+
+/// 修复数据库
+///
+/// 所有的预写日志会被转换为 table, 无法读取的文件会被移动到 `lost/` 目录,
+/// 然后根据所有可以读取的 table 重新生成 MANIFEST, 所有 table 都放在 level 0。
+/// 修复后部分数据可能丢失, 调用方需要谨慎使用
+///
+/// If a DB cannot be opened, you may attempt to call this method to
+/// resurrect as much of the contents of the database as possible.
+/// Some data may be lost, so be careful when calling this function
+/// on a database that contains important information.
+///
+/// # Arguments
+///
+/// * `dbname`: 数据库目录
+/// * `options`: 配置, 比较器需要与数据库创建时的一致
+///
+/// returns: Result<(), Status>
+///
+/// # Examples
+///
+/// ```ignore
+/// repair_db("/tmp/db", Options::default())?;
+/// ```
+pub fn repair_db<P: AsRef<Path>>(dbname: P, options: Options) -> Result<()> {
+    Repairer::new(dbname.as_ref(), options).run()
+}
+
+impl Repairer {
+    fn new(dbname: &Path, mut options: Options) -> Self {
+        if options.info_log.is_none() {
+            options.info_log = open_info_log(&options.env, dbname, options.info_log_level);
+        }
+        let icmp = InternalKeyComparator::new(options.cmp.clone());
+        options.cmp = Arc::new(icmp.clone());
+        options.filter_policy = InternalFilterPolicy::wrap(
+            PrefixFilterPolicy::wrap(options.filter_policy.take(), options.prefix_extractor.clone()));
+        options.prefix_extractor = InternalSliceTransform::wrap(options.prefix_extractor.take());
+        let options: OptionsPtr = Arc::new(Box::new(options));
+        // TableCache can be small since we expect each table to be opened once.
+        let table_cache = Arc::new(TableCache::new(dbname, options.clone(), 10));
+        Self {
+            dbname: dbname.to_path_buf(),
+            icmp,
+            options,
+            table_cache,
+            edit: VersionEdit::new(),
+            manifests: vec![],
+            table_numbers: vec![],
+            logs: vec![],
+            tables: vec![],
+            next_file_number: 1,
+        }
+    }
+
+    fn run(mut self) -> Result<()> {
+        self.find_files()?;
+        self.convert_log_files_to_tables();
+        self.extract_meta_data();
+        self.write_descriptor()?;
+
+        let bytes: u64 = self.tables.iter().map(|t| t.meta.get_file_size()).sum();
+        log_warn!(self.options.info_log,
+                  "**** Repaired leveldb {}; recovered {} files; {} bytes. Some data may have been lost. ****",
+                  self.dbname.display(), self.tables.len(), bytes);
+        Ok(())
+    }
+
+    /// 按文件类型收集数据库目录下的文件
+    fn find_files(&mut self) -> Result<()> {
+        let filenames = self.options.env.get_children(&self.dbname)?;
+        // 运行日志 LOG 及 LOG.old 由 Repairer 自己创建, 不算作数据库的文件
+        if filenames.iter().all(|name| matches!(FileName::parse_file_name(name), Some((_, FileType::KInfoLogFile)))) {
+            return Err(LevelError::io_error(Slice::from(self.dbname.to_string_lossy().as_ref()),
+                                            Slice::from("repair found no files")));
+        }
+
+        for filename in filenames {
+            if let Some((number, file_type)) = FileName::parse_file_name(&filename) {
+                if file_type == FileType::KDescriptorFile {
+                    self.manifests.push(filename);
+                } else {
+                    if number + 1 > self.next_file_number {
+                        self.next_file_number = number + 1;
+                    }
+                    match file_type {
+                        FileType::KLogFile => self.logs.push(number),
+                        FileType::KTableFile => self.table_numbers.push(number),
+                        // Ignore other files
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn convert_log_files_to_tables(&mut self) {
+        for log in self.logs.clone() {
+            let logname = FileName::log_file_name(&self.dbname, log);
+            if let Err(e) = self.convert_log_to_table(log) {
+                log_warn!(self.options.info_log, "Log #{}: ignoring conversion error: {}", log, e.get_msg());
+            }
+            self.archive_file(&logname);
+        }
+    }
+
+    /// 将预写日志中的所有记录写入 memtable, 再生成一个新的 table
+    fn convert_log_to_table(&mut self, log: u64) -> Result<()> {
+        // Open the log file
+        let logname = FileName::log_file_name(&self.dbname, log);
+        let file = self.options.env.new_sequential_file(&logname)?;
+
+        // We intentionally make log::Reader do checksumming so that
+        // corruptions cause entire commits to be skipped instead of
+        // propagating bad information (like overly large sequence
+        // numbers).
+        let mut reader = LogReader::new(Box::new(file), true, 0);
+
+        // Read all the records and add to a memtable
+        let mut batch = WriteBatch::new();
+        let mut mem = MemTable::create(self.icmp.user_comparator());
+        let mut counter = 0;
+        loop {
+            let record = match reader.read_next() {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                Err(e) => {
+                    // 跳过损坏的记录, 继续读取之后的数据
+                    log_warn!(self.options.info_log, "Log #{}: dropping record; {}", log, e.get_msg());
+                    continue;
+                }
+            };
+            if record.len() < 12 {
+                log_warn!(self.options.info_log, "Log #{}: dropping {} bytes; log record too small", log, record.len());
+                continue;
+            }
+            batch.set_contents(&record);
+            match batch.insert_into(&mut mem) {
+                Ok(_) => counter += batch.count(),
+                // Keep going with rest of file
+                Err(e) => log_warn!(self.options.info_log, "Log #{}: ignoring {}", log, e.get_msg()),
+            }
+        }
+
+        // Do not record a version edit for this conversion to a Table
+        // since ExtractMetaData() will also generate edits.
+        let mut meta = FileMetaData::default();
+        meta.set_number(self.next_file_number);
+        self.next_file_number += 1;
+        let mut iter = mem.new_iterator();
+        let status = BuildTable::build_table(&self.dbname, &self.options.env, &self.options, &self.table_cache,
+                                             iter.as_mut(), &mut meta, 0);
+        if status.is_ok() && meta.get_file_size() > 0 {
+            self.table_numbers.push(meta.get_number());
+        }
+        log_info!(self.options.info_log, "Log #{}: {} ops saved to Table #{} {}", log, counter, meta.get_number(),
+                  status.as_ref().map_or_else(|e| e.get_msg(), |_| "OK".to_string()));
+        status
+    }
+
+    fn extract_meta_data(&mut self) {
+        for number in self.table_numbers.clone() {
+            self.scan_table(number);
+        }
+    }
+
+    fn new_table_iterator(&self, meta: &FileMetaData) -> Box<dyn DataIterator> {
+        // Same as compaction iterators: if paranoid_checks are on, turn
+        // on checksum verification.
+        let options = ReadOptions {
+            verify_checksums: self.options.paranoid_checks,
+            ..Default::default()
+        };
+        self.table_cache.new_iterator(&options, meta.get_number(), meta.get_file_size())
+    }
+
+    /// 遍历 table 得到 key 的范围及最大的序列号, 无法完整读取的 table 会尝试修复
+    fn scan_table(&mut self, number: u64) {
+        let mut t = TableInfo {
+            meta: FileMetaData::default(),
+            max_sequence: 0,
+        };
+        t.meta.set_number(number);
+
+        let mut fname = FileName::table_file_name(&self.dbname, number);
+        let mut file_size = self.options.env.get_file_size(&fname);
+        if file_size.is_err() {
+            // Try alternate file name.
+            fname = FileName::sst_table_file_name(&self.dbname, number);
+            if let Ok(size) = self.options.env.get_file_size(&fname) {
+                file_size = Ok(size);
+            }
+        }
+        match file_size {
+            Ok(size) => t.meta.set_file_size(size),
+            Err(e) => {
+                self.archive_file(&FileName::table_file_name(&self.dbname, number));
+                self.archive_file(&FileName::sst_table_file_name(&self.dbname, number));
+                log_warn!(self.options.info_log, "Table #{}: dropped: {}", number, e.get_msg());
+                return;
+            }
+        }
+
+        // Extract metadata by scanning through table.
+        let mut counter = 0;
+        let mut iter = self.new_table_iterator(&t.meta);
+        let mut empty = true;
+        iter.seek_to_first();
+        while iter.valid() {
+            let key = iter.key();
+            match ParsedInternalKey::parse_internal_key(key.as_ref()) {
+                None => {
+                    log_warn!(self.options.info_log, "Table #{}: unparsable key {:?}", number, key.as_ref());
+                }
+                Some(parsed) => {
+                    counter += 1;
+                    if empty {
+                        empty = false;
+                        let mut smallest = t.meta.get_smallest().clone();
+                        smallest.decode_from(key.as_ref());
+                        t.meta.set_smallest(smallest);
+                    }
+                    let mut largest = t.meta.get_largest().clone();
+                    largest.decode_from(key.as_ref());
+                    t.meta.set_largest(largest);
+                    t.max_sequence = t.max_sequence.max(parsed.sequence());
+                }
+            }
+            iter.next();
+        }
+        let status = iter.status();
+        drop(iter);
+        log_info!(self.options.info_log, "Table #{}: {} entries {}", number, counter,
+                  if status.is_ok() { "OK".to_string() } else { status.get_msg() });
+
+        if status.is_ok() {
+            self.tables.push(t);
+        } else {
+            // RepairTable archives input file.
+            self.repair_table(&fname, t);
+        }
+    }
+
+    /// 将 table 中可以读取的数据复制到新的 table, 然后替换原来的文件
+    fn repair_table(&mut self, src: &Path, mut t: TableInfo) {
+        // We will copy src contents to a new table and then rename the
+        // new table over the source.
+
+        // Create builder.
+        let copy = FileName::table_file_name(&self.dbname, self.next_file_number);
+        self.next_file_number += 1;
+        let file = match self.options.env.new_writable_file(&copy) {
+            Ok(file) => file,
+            Err(_) => return,
+        };
+        let mut builder = TableBuilder::new_with_writable_file(self.options.clone(), Box::new(file));
+
+        // Copy data.
+        let mut iter = self.new_table_iterator(&t.meta);
+        let mut counter = 0;
+        iter.seek_to_first();
+        while iter.valid() {
+            builder.add(iter.key().as_ref(), iter.value().as_ref());
+            counter += 1;
+            iter.next();
+        }
+        drop(iter);
+
+        self.archive_file(src);
+        let mut s = Ok(());
+        if counter == 0 {
+            // Nothing to save
+            builder.abandon();
+        } else {
+            s = builder.finish();
+            if s.is_ok() {
+                t.meta.set_file_size(builder.get_file_size());
+                s = builder.sync();
+            }
+        }
+        drop(builder);
+
+        if counter > 0 && s.is_ok() {
+            let orig = FileName::table_file_name(&self.dbname, t.meta.get_number());
+            s = self.options.env.rename_file(&copy, &orig);
+            if s.is_ok() {
+                log_info!(self.options.info_log, "Table #{}: {} entries repaired", t.meta.get_number(), counter);
+                self.tables.push(t);
+            }
+        }
+        if counter == 0 || s.is_err() {
+            let _ = self.options.env.remove_file(&copy);
+        }
+    }
+
+    /// 生成新的 MANIFEST 并更新 CURRENT, 旧的 MANIFEST 被移动到 lost 目录
+    fn write_descriptor(&mut self) -> Result<()> {
+        let tmp = FileName::temp_file_name(&self.dbname, 1);
+        let file = self.options.env.new_writable_file(&tmp)?;
+
+        let max_sequence = self.tables.iter().map(|t| t.max_sequence).max().unwrap_or(0);
+
+        self.edit.set_comparator_name(self.icmp.user_comparator().get_name().into());
+        self.edit.set_log_number(0);
+        self.edit.set_next_file(self.next_file_number);
+        self.edit.set_last_sequence(max_sequence);
+
+        for t in &self.tables {
+            // TODO(opt): separate out into multiple levels
+            self.edit.add_file(0, t.meta.get_number(), t.meta.get_file_size(),
+                               t.meta.get_smallest().clone(), t.meta.get_largest().clone());
+        }
+
+        let mut status = {
+            let mut log = LogWriter::new(Box::new(file));
+            let mut record = vec![];
+            self.edit.encode_to(&mut record)
+                .and_then(|_| log.add_record(Slice::from_vec(record)))
+                .and_then(|_| log.sync())
+        };
+
+        if status.is_err() {
+            let _ = self.options.env.remove_file(&tmp);
+        } else {
+            // Discard older manifests
+            for manifest in self.manifests.clone() {
+                self.archive_file(&self.dbname.join(manifest));
+            }
+
+            // Install new manifest
+            status = self.options.env.rename_file(&tmp, &FileName::descriptor_file_name(&self.dbname, 1));
+            if status.is_ok() {
+                status = FileName::set_current_file(&self.options.env, &self.dbname, 1);
+            } else {
+                let _ = self.options.env.remove_file(&tmp);
+            }
+        }
+        status
+    }
+
+    /// 将文件移动到同一目录下的 lost 子目录中
+    ///
+    /// Move into another directory.  E.g., for
+    ///    dir/foo
+    /// rename to
+    ///    dir/lost/foo
+    fn archive_file(&self, fname: &Path) {
+        let new_dir = fname.parent().unwrap_or(Path::new("")).join("lost");
+        // Ignore error
+        let _ = self.options.env.create_dir(&new_dir);
+        let new_file = new_dir.join(fname.file_name().unwrap_or_default());
+        let s = self.options.env.rename_file(fname, &new_file);
+        log_info!(self.options.info_log, "Archiving {}: {}", fname.display(),
+                  s.map_or_else(|e| e.get_msg(), |_| "OK".to_string()));
+    }
+}
@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use crate::db::db_format::{InternalKey, InternalKeyComparator, ValueType};
+    use crate::db::filename::FileName;
+    use crate::db::log_writer::LogWriter;
+    use crate::db::repair::repair_db;
+    use crate::db::table_cache::TableCache;
+    use crate::db::version_set::VersionSet;
+    use crate::db::write_batch::WriteBatch;
+    use crate::table::table_builder::TableBuilder;
+    use crate::util::options::{Options, OptionsPtr};
+    use crate::util::slice::Slice;
+    use crate::util::Result;
+
+    fn new_db_dir(name: &str) -> PathBuf {
+        let dbname = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname).unwrap();
+        dbname
+    }
+
+    /// DB 内部使用的 options, 以 InternalKeyComparator 作为比较器
+    fn internal_options() -> OptionsPtr {
+        let mut options = Options::default();
+        options.cmp = Arc::new(InternalKeyComparator::new(options.cmp.clone()));
+        Arc::new(Box::new(options))
+    }
+
+    fn ikey(user_key: &str, seq: u64, value_type: ValueType) -> InternalKey {
+        InternalKey::new(Slice::from(user_key), seq, value_type)
+    }
+
+    fn recover(dbname: &Path) -> Result<VersionSet> {
+        let options = Options::default();
+        let icmp = InternalKeyComparator::new(options.cmp.clone());
+        let options: OptionsPtr = Arc::new(Box::new(options));
+        let table_cache = Arc::new(TableCache::new(dbname, options.clone(), 100));
+        let mut versions = VersionSet::new(dbname, options, table_cache, icmp);
+        versions.recover()?;
+        Ok(versions)
+    }
+
+    fn children(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir).unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_repair_db() -> Result<()> {
+        let dbname = new_db_dir("level_db_rust_repair_test");
+        let options = internal_options();
+
+        // 完好的 table
+        let file = options.env.new_writable_file(&FileName::table_file_name(&dbname, 5))?;
+        let mut builder = TableBuilder::new_with_writable_file(options.clone(), Box::new(file));
+        for (i, key) in ["a", "b", "c"].iter().enumerate() {
+            builder.add(ikey(key, i as u64 + 1, ValueType::KTypeValue).encode(), b"v");
+        }
+        builder.finish()?;
+        builder.sync()?;
+        let table_size = builder.get_file_size();
+        drop(builder);
+
+        // 预写日志
+        let file = options.env.new_writable_file(&FileName::log_file_name(&dbname, 7))?;
+        let mut log = LogWriter::new(Box::new(file));
+        let mut batch = WriteBatch::new();
+        batch.set_sequence(10);
+        batch.put(b"d", b"vd");
+        batch.delete(b"a");
+        log.add_record(Slice::from_buf(batch.contents()))?;
+        batch.clear();
+        batch.set_sequence(12);
+        batch.put(b"e", b"ve");
+        log.add_record(Slice::from_buf(batch.contents()))?;
+        // 太短的记录被忽略
+        log.add_record(Slice::from("short"))?;
+        log.sync()?;
+        drop(log);
+
+        // 无法读取的 table, 损坏的 MANIFEST
+        fs::write(FileName::table_file_name(&dbname, 8), "not a table".repeat(10))?;
+        fs::write(FileName::descriptor_file_name(&dbname, 2), "garbage")?;
+        FileName::set_current_file(&options.env, &dbname, 2)?;
+        fs::write(dbname.join("foo.txt"), "bar")?;
+
+        repair_db(&dbname, Options::default())?;
+
+        assert_eq!(vec!["000005.ldb", "000009.ldb", "CURRENT", "LOG", "MANIFEST-000001", "foo.txt", "lost"],
+                   children(&dbname));
+        let log = fs::read_to_string(FileName::info_log_file_name(&dbname))?;
+        assert!(log.contains("[WARN] Log #7: dropping 5 bytes; log record too small"), "{}", log);
+        assert!(log.contains("[WARN] **** Repaired leveldb"), "{}", log);
+        assert_eq!(vec!["000007.log", "000008.ldb", "MANIFEST-000002"], children(&dbname.join("lost")));
+
+        let versions = recover(&dbname)?;
+        let current = versions.current();
+        let mut files: Vec<_> = current.files(0).iter().collect();
+        files.sort_by_key(|f| f.get_number());
+        assert_eq!(2, files.len());
+        for level in 1..7 {
+            assert_eq!(0, current.num_files(level));
+        }
+
+        assert_eq!(5, files[0].get_number());
+        assert_eq!(table_size, files[0].get_file_size());
+        assert_eq!(ikey("a", 1, ValueType::KTypeValue).encode(), files[0].get_smallest().encode());
+        assert_eq!(ikey("c", 3, ValueType::KTypeValue).encode(), files[0].get_largest().encode());
+
+        // 预写日志转换成的 table
+        assert_eq!(9, files[1].get_number());
+        assert_eq!(ikey("a", 11, ValueType::KTypeDeletion).encode(), files[1].get_smallest().encode());
+        assert_eq!(ikey("e", 12, ValueType::KTypeValue).encode(), files[1].get_largest().encode());
+
+        assert_eq!(12, versions.last_sequence());
+        assert_eq!(0, versions.log_number());
+        // 日志转换使用了文件号 9, 修复损坏的 table 使用了文件号 10, recover 时以 next file number 作为新的 MANIFEST 文件号
+        assert_eq!(11, versions.manifest_file_number());
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_repair_db_errors() -> Result<()> {
+        let dbname = new_db_dir("level_db_rust_repair_errors_test");
+        let status = repair_db(&dbname, Options::default()).err().unwrap();
+        assert!(status.is_io_error());
+        // 只有 Repairer 创建的运行日志
+        assert_eq!(vec!["LOG"], children(&dbname));
+
+        fs::remove_dir_all(&dbname)?;
+        assert!(repair_db(&dbname, Options::default()).is_err());
+        let _ = fs::remove_dir_all(&dbname);
+        Ok(())
+    }
+}
@@ -1,8 +1,9 @@
 use std::cmp::Ordering;
 use std::mem;
-use std::mem::size_of;
-use std::ptr::null_mut;
+use std::mem::{align_of, size_of};
+use std::ptr::{NonNull, null_mut};
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicPtr, Ordering as AtomicOrdering};
 
 use rand::prelude::*;
 
@@ -52,11 +53,19 @@ pub struct Iter<Cmp: Comparator> {
 
 impl<Cmp: Comparator> SkipList<Cmp> {
     pub fn create(comparator: Arc<Cmp>, arena: ArenaRef) -> Self {
+        let head = Node::create_head(arena.clone());
+        let tail = Node::create_tail();
+        // 空表的每一层都直接指向尾指针, 迭代器可以从任意一层开始查找
+        unsafe {
+            for l in 0..MAX_LEVEL {
+                (&mut *head).set_node(l, tail);
+            }
+        }
         Self {
             height: 0,
             num: 0,
-            head: Node::create_head(arena.clone()),
-            tail: Node::create_tail(),
+            head,
+            tail,
             cmp: comparator,
             arena,
         }
@@ -87,8 +96,8 @@ impl<Cmp: Comparator> SkipList<Cmp> {
             (&mut *node).level = level;
             (&mut *self.head).level = level;
             for l in 0..level {
-                (&mut *self.head).set_node(l, node);
                 (&mut *node).set_node(l, self.tail);
+                (&mut *self.head).set_node(l, node);
             }
         }
         self.height = level;
@@ -111,8 +120,8 @@ impl<Cmp: Comparator> SkipList<Cmp> {
                 if ele.is_tail() {
                     if l <= node_top_level {
                         // ele is tail node, add node to last
-                        (&mut *current).set_node(l, node_ptr);
                         node.set_node(l, self.tail);
+                        (&mut *current).set_node(l, node_ptr);
                         debug!("bind: {} before: {}, after: <tail>, at level: {}",
                                         node.key.unwrap(),
                                         (&*current).key.unwrap(),
@@ -124,8 +133,8 @@ impl<Cmp: Comparator> SkipList<Cmp> {
                         Some(Ordering::Less) => {
                             // node higher than current level at ele
                             if node_top_level >= l {
-                                (&mut *current).set_node(l, node_ptr);
                                 node.set_node(l, ele_ptr);
+                                (&mut *current).set_node(l, node_ptr);
                                 if (&*current).is_head() {
                                     debug!("bind: {} before: <head>, after: {}, at level: {}",
                                         node.key.unwrap(),
@@ -158,8 +167,8 @@ impl<Cmp: Comparator> SkipList<Cmp> {
         // if head level is less than new node, then fix head node height
         if self.height < node_height {
             for l in (self.height()..node_height).rev() {
-                (&mut *self.head).set_node(l, node_ptr);
                 node.set_node(l, self.tail);
+                (&mut *self.head).set_node(l, node_ptr);
             }
             self.height = node_height;
         }
@@ -338,39 +347,20 @@ impl Node {
         self.level
     }
 
+    /// 读取第 level 层的下一个节点, 与 set_node 配对, 保证读到的节点已经完整初始化
     #[inline]
     #[must_use]
     unsafe fn get_node(&self, level: usize) -> RawNode {
         assert!(level < MAX_LEVEL);
-        self.next_elems.offset(level as isize).read()
+        AtomicPtr::from_ptr(self.next_elems.add(level)).load(AtomicOrdering::Acquire)
     }
 
+    /// 设置第 level 层的下一个节点。
+    /// 插入时先设置新节点的 next, 再将其链接到前一个节点, 读线程可以与唯一的写线程并发遍历跳表
     #[inline]
     unsafe fn set_node(&mut self, level: usize, node: RawNode) {
         assert!(level < MAX_LEVEL);
-        self.next_elems.offset(level as isize).write(node);
-    }
-
-    /// 找到最后一个数据元素
-    unsafe fn seek_to_last(&self) -> Option<RawNode> {
-        if self.is_tail() {
-            return None;
-        }
-        let mut pre = self;
-        let mut cur = &*self.next_top_node();
-        loop {
-            if cur.is_tail() {
-                return Some(pre as *const Node as *mut Node);
-            }
-            pre = cur;
-            cur = &*cur.next_top_node();
-        }
-    }
-
-    /// 找到最上层的下一个元素
-    #[inline]
-    unsafe fn next_top_node(&self) -> RawNode {
-        self.get_node(self.level - 1)
+        AtomicPtr::from_ptr(self.next_elems.add(level)).store(node, AtomicOrdering::Release);
     }
 }
 
@@ -391,7 +381,7 @@ fn allocate_next_elems(arena: ArenaRef) -> *mut RawNode {
     // allocate next_elems to 8 capacity array
     let elems_size = size_of::<RawNode>() * MAX_LEVEL;
     let mut lock = arena.lock().expect("lock arena");
-    let elems_ptr = lock.allocate(elems_size);
+    let elems_ptr = lock.allocate_align(elems_size, align_of::<RawNode>());
     // transmute raw ptr to RawNode ptr
     unsafe {
         mem::transmute(elems_ptr.as_ptr())
@@ -417,6 +407,27 @@ impl<Cmp: Comparator> Iter<Cmp> {
             cmp: list.cmp.clone(),
         }
     }
+
+    /// 从最高层开始查找, 返回最后一个满足 go_right(key) 的节点, 不存在时返回头指针
+    unsafe fn find_last_where<F: Fn(&UnsafeSlice) -> bool>(&self, go_right: F) -> RawNode {
+        let mut x = self.head;
+        for l in (0..MAX_LEVEL).rev() {
+            loop {
+                let next = (&*x).get_node(l);
+                if (&*next).is_tail() || !go_right(&(&*next).key.unwrap()) {
+                    break;
+                }
+                x = next;
+            }
+        }
+        x
+    }
+
+    /// 头指针或者尾指针都视为无效位置, 统一使用尾指针表示
+    #[inline]
+    fn set_current(&mut self, node: RawNode) {
+        self.current = if unsafe { (&*node).is_head_or_tail() } { self.tail } else { node };
+    }
 }
 
 impl<Cmp: Comparator> Iterator for Iter<Cmp> {
@@ -435,57 +446,71 @@ impl<Cmp: Comparator> Iterator for Iter<Cmp> {
     }
 }
 
+/// 跳表只保存 key, value 需要由调用方从 key 中解析(@see MemTableIterator)
 impl<Cmp: Comparator> DataIterator for Iter<Cmp> {
     #[inline]
     fn valid(&self) -> bool {
         unsafe {
-            (&*self.current).is_head_or_tail()
+            !(&*self.current).is_head_or_tail()
         }
     }
 
     #[inline]
     fn seek_to_first(&mut self) {
-        self.current = unsafe {
-            (&*self.head).get_node(0)
-        }
+        let first = unsafe { (&*self.head).get_node(0) };
+        self.set_current(first);
     }
 
     #[inline]
     fn seek_to_last(&mut self) {
-        unsafe {
-            self.current = (&*self.current).seek_to_last().unwrap_or(self.tail)
-        }
-    }
-
-    fn seek(&mut self, key: &Slice) {
-        todo!()
+        let last = unsafe { self.find_last_where(|_| true) };
+        self.set_current(last);
+    }
+
+    /// 定位到第一个 >= target 的节点
+    fn seek(&mut self, target: &Slice) {
+        let cmp = self.cmp.clone();
+        let node = unsafe {
+            let before = self.find_last_where(|key| {
+                cmp.compare(key.as_ref(), target.as_ref()) == Some(Ordering::Less)
+            });
+            (&*before).get_node(0)
+        };
+        self.set_current(node);
     }
 
     fn next(&mut self) {
-        unsafe {
-            if (&*self.current).is_tail() {
-                return;
-            }
-            self.current = (&*self.current).get_node(0);
-        }
+        assert!(self.valid());
+        let next = unsafe { (&*self.current).get_node(0) };
+        self.set_current(next);
     }
 
+    /// 定位到最后一个 < key() 的节点
     fn pre(&mut self) {
-        todo!()
+        assert!(self.valid());
+        let cmp = self.cmp.clone();
+        let current = self.key();
+        let node = unsafe {
+            self.find_last_where(|key| {
+                cmp.compare(key.as_ref(), current.as_ref()) == Some(Ordering::Less)
+            })
+        };
+        self.set_current(node);
     }
 
     fn key(&self) -> UnsafeSlice {
-        let mem_key = unsafe {
+        assert!(self.valid());
+        unsafe {
             (&*self.current).key.unwrap()
-        };
-        mem_key
+        }
     }
 
     fn value(&self) -> UnsafeSlice {
-        todo!()
+        assert!(self.valid());
+        unsafe { UnsafeSlice::from_raw_parts(NonNull::<u8>::dangling().as_ptr(), 0) }
     }
 
     fn status(&self) -> Status {
-        todo!()
+        Status::default()
     }
-}
\ No newline at end of file
+}
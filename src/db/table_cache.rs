@@ -1,75 +1,161 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::db::filename::FileName;
+use crate::table::iterator::new_error_iterator;
 use crate::table::table::Table;
 use crate::traits::DataIterator;
-use crate::util::options::ReadOptions;
+use crate::util::cache::ShardLRUCache;
+use crate::util::options::{OptionsPtr, ReadOptions};
 use crate::util::slice::Slice;
 use crate::util::Result;
+use crate::util::status::LevelError;
 
-pub struct Saver {}
-
-pub struct TableCache {}
+/// 已打开的 Table 的缓存, 以文件号为 key
+pub struct TableCache {
+    dbname: PathBuf,
+    options: OptionsPtr,
+    cache: ShardLRUCache<Table>,
+}
 
 impl TableCache {
-    pub fn new() -> Self {
+    /// 创建 TableCache
+    ///
+    /// # Arguments
+    ///
+    /// * `dbname`: 数据库目录
+    /// * `options`: 配置
+    /// * `entries`: 最多缓存的 Table 数量
+    ///
+    /// returns: TableCache
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let table_cache = TableCache::new("/tmp/db", options, 990);
+    /// ```
+    pub fn new<P: AsRef<Path>>(dbname: P, options: OptionsPtr, entries: usize) -> Self {
         Self {
-
+            dbname: dbname.as_ref().to_path_buf(),
+            options,
+            // 每个 Table 的 charge 为 1, 容量即为可缓存的 Table 数量
+            cache: ShardLRUCache::new_with_capacity(entries, entries),
         }
     }
-    /// 从缓存中获取Table
+
+    /// 在文件号对应的 Table 中查找 key, 找到 key 之后的第一条记录时调用 handle_result(key, value)
+    ///
+    /// If a seek to internal key "k" in specified file finds an entry,
+    /// call (*handle_result)(arg, found_key, found_value).
     ///
     /// # Arguments
     ///
     /// * `options`: 读取的配置
     /// * `file_number`: 文件号
     /// * `file_size`: 文件大小
-    /// * `k`: key
-    /// * `handle_result`: 回调函数
+    /// * `k`: 编码后的 internal key
+    /// * `handle_result`: 回调函数, 参数为找到的 key 及 value
     ///
-    /// returns: ()
+    /// returns: Result<(), Status>
     ///
     /// # Examples
     ///
+    /// ```ignore
+    /// table_cache.get(&options, f.get_number(), f.get_file_size(), ikey, |k, v| {
+    ///     // 比较 user key, 保存 value
+    /// })?;
     /// ```
+    pub fn get<F>(&self, options: &ReadOptions, file_number: u64, file_size: u64,
+                  k: &[u8], handle_result: F) -> Result<()>
+        where F: FnMut(&[u8], &[u8]) {
+        let table = self.find_table(file_number, file_size)?;
+        table.internal_get(options, k, handle_result)
+    }
+
+    /// 获取文件号对应的 Table, 缓存中不存在时打开文件并加入缓存。
+    /// 打开失败时不会缓存错误, 下次调用会重新尝试打开
+    ///
+    /// # Arguments
     ///
+    /// * `file_number`: 文件号
+    /// * `file_size`: 文件大小
+    ///
+    /// returns: Result<Arc<Table>, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let table = table_cache.find_table(f.get_number(), f.get_file_size())?;
     /// ```
-    pub fn get<F>(&self, _options: &ReadOptions, _file_number: u64, _file_size: usize,
-                  _k: &Slice, _arg: &mut Saver, _handle_result: F)
-        where F: FnMut(&mut Saver, &Slice, &Slice)  {
-        todo!()
+    pub fn find_table(&self, file_number: u64, file_size: u64) -> Result<Arc<Table>> {
+        let key = Self::cache_key(file_number);
+        if let Some(table) = self.cache.lookup(&key)? {
+            return Ok(table);
+        }
+
+        let env = &self.options.env;
+        let fname = FileName::table_file_name(&self.dbname, file_number);
+        let file = match env.new_random_access_file(&fname) {
+            Ok(file) => file,
+            Err(e) => {
+                // 兼容旧版本的 .sst 文件名
+                let old_fname = FileName::sst_table_file_name(&self.dbname, file_number);
+                env.new_random_access_file(&old_fname).map_err(|_| e)?
+            }
+        };
+        let table = Table::open_with_file_number(self.options.clone(), file_number, Box::new(file), file_size)?;
+        self.cache.insert(&key, table, 1)?;
+        match self.cache.lookup(&key)? {
+            Some(table) => Ok(table),
+            None => Err(LevelError::corruption_string("table cache", "inserted table not found")),
+        }
     }
+
     /// 根据文件号消除缓存
     ///
     /// # Arguments
     ///
     /// * `file_number`: 文件号
     ///
-    /// returns: ()
+    /// returns: Result<(), Status>
     ///
     /// # Examples
     ///
     /// ```
     ///
     /// ```
-    pub fn evict(&mut self, _file_number: u64) {
-        todo!()
+    pub fn evict(&self, file_number: u64) -> Result<()> {
+        self.cache.erase(&Self::cache_key(file_number))
     }
 
-    /// 获取一个迭代器
+    /// 返回文件号对应的 table 的迭代器, 打开 table 失败时返回携带错误状态的空迭代器
+    ///
+    /// Return an iterator for the specified file number (the corresponding
+    /// file length must be exactly "file_size" bytes).
     ///
     /// # Arguments
     ///
     /// * `options`: 读取的配置
     /// * `file_number`: 文件号
     /// * `file_size`: 文件大小
-    /// * `table`: 表
     ///
-    /// returns: Result<dyn DataIterator<Item=<unknown>>, Status>
+    /// returns: Box<dyn DataIterator, Global>
     ///
     /// # Examples
     ///
+    /// ```ignore
+    /// let mut iter = table_cache.new_iterator(&ReadOptions::default(), f.get_number(), f.get_file_size());
+    /// iter.seek_to_first();
     /// ```
-    ///
-    /// ```
-    pub fn new_iterator(&self, _options: &ReadOptions, _file_number: u64, _file_size: usize, _table: &Table) -> Result<Box<dyn DataIterator>> {
-        todo!()
+    pub fn new_iterator(&self, options: &ReadOptions, file_number: u64, file_size: u64) -> Box<dyn DataIterator> {
+        match self.find_table(file_number, file_size) {
+            Ok(table) => table.new_iterator(options),
+            Err(e) => new_error_iterator(e),
+        }
+    }
+
+    /// 缓存的 key, 为文件号的 fixed64 编码
+    #[inline]
+    fn cache_key(file_number: u64) -> Slice {
+        Slice::from_buf(&file_number.to_le_bytes())
     }
-}
\ No newline at end of file
+}
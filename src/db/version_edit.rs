@@ -1,9 +1,6 @@
-use std::fs::read;
-use std::iter::Map;
-use crate::db::db_format::InternalKey;
+use crate::db::db_format::{Config, InternalKey};
 use crate::db::file_meta_data::FileMetaData;
-use crate::db::version_edit;
-use crate::util::coding::Encoder;
+use crate::util::coding::{Decoder, Encoder};
 use crate::util::slice::Slice;
 use crate::util::Result;
 use crate::util::status::{LevelError, Status};
@@ -188,26 +185,82 @@ impl VersionEdit {
         self.deleted_files_.push((level, file));
     }
 
+    pub fn has_comparator(&self) -> bool {
+        self.has_comparator_
+    }
+
+    pub fn comparator_name(&self) -> &str {
+        &self.comparator_
+    }
+
+    pub fn has_log_number(&self) -> bool {
+        self.has_log_number_
+    }
+
+    pub fn log_number(&self) -> u64 {
+        self.log_number_
+    }
+
+    pub fn has_prev_log_number(&self) -> bool {
+        self.has_prev_log_number_
+    }
+
+    pub fn prev_log_number(&self) -> u64 {
+        self.prev_log_number_
+    }
+
+    pub fn has_next_file_number(&self) -> bool {
+        self.has_next_file_number_
+    }
+
+    pub fn next_file_number(&self) -> u64 {
+        self.next_file_number_
+    }
+
+    pub fn has_last_sequence(&self) -> bool {
+        self.has_last_sequence_
+    }
+
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence_
+    }
+
+    /// left: level;  right: 下次 compaction 开始的 key
+    pub fn compact_pointers(&self) -> &Vec<(u32, InternalKey)> {
+        &self.compact_pointers_
+    }
+
+    /// left: level;  right: file number
+    pub fn deleted_files(&self) -> &Vec<(u32, u64)> {
+        &self.deleted_files_
+    }
+
+    /// left: level;  right: FileMetaData
+    pub fn new_files(&self) -> &Vec<(u32, FileMetaData)> {
+        &self.new_files_
+    }
+
     /// 将 VersionEdit 对象编码至 target 中
     ///
     /// # Arguments
     ///
-    /// * `target`:
+    /// * `target`: 编码结果
     ///
-    /// returns: ()
+    /// returns: Result<(), Status>
     ///
     /// # Examples
     ///
     /// ```
+    /// use level_db_rust::db::version_edit::VersionEdit;
     ///
+    /// let mut target = vec![];
+    /// VersionEdit::new_with_log_number(6).encode_to(&mut target).unwrap();
     /// ```
     pub fn encode_to(&self, target: &mut Vec<u8>) -> Result<()> {
         let mut encoder = Encoder::with_vec(target);
         if self.has_comparator_ {
             encoder.put_varint32(Tag::k_comparator.get_value() as u32)?;
-            encoder.put_varint32(self.comparator_.len() as u32)?;
-            // fixme 需要正确使用 put_length_prefixed_slice  将slice长度及slice内容编码到target
-            // encoder.put_length_prefixed_slice(self.comparator_.len())?;
+            encoder.put_length_prefixed_slice(&Slice::from(self.comparator_.as_str()))?;
         }
         if self.has_log_number_ {
             encoder.put_varint32(Tag::kLogNumber.get_value() as u32)?;
@@ -229,29 +282,25 @@ impl VersionEdit {
             encoder.put_varint64(self.last_sequence_)?;
         }
 
-        for i in 0..self.compact_pointers_.len() {
+        for (level, key) in &self.compact_pointers_ {
             encoder.put_varint32(Tag::kCompactPointer.get_value() as u32)?;
-            encoder.put_varint32(self.compact_pointers_[i].0)?;
-            // fixme 需要正确使用put_length_prefixed_slice
-            // encoder.put_length_prefixed_slice( self.compact_pointers_[i].1.encode_len())?;
+            encoder.put_varint32(*level)?;
+            encoder.put_length_prefixed_slice(&Slice::from_buf(key.encode()))?;
         }
 
-        for i in 0..self.deleted_files_.len() {
+        for (level, number) in &self.deleted_files_ {
             encoder.put_varint32(Tag::kDeletedFile.get_value() as u32)?;
-            encoder.put_varint32(self.deleted_files_[i].0)?;
-            encoder.put_varint64(self.deleted_files_[i].1)?;
+            encoder.put_varint32(*level)?;
+            encoder.put_varint64(*number)?;
         }
 
-        for i in 0..self.new_files_.len() {
-            let f: &FileMetaData = &self.new_files_[i].1;
+        for (level, f) in &self.new_files_ {
             encoder.put_varint32(Tag::kNewFile.get_value() as u32)?;
-            // level
-            encoder.put_varint32(self.new_files_[i].0)?;
+            encoder.put_varint32(*level)?;
             encoder.put_varint64(f.get_number())?;
             encoder.put_varint64(f.get_file_size())?;
-            // fixme 需要正确使用put_length_prefixed_slice
-            // encoder.put_length_prefixed_slice( f.get_smallest().encode_len())?;
-            // encoder.put_length_prefixed_slice( f.get_largest().encode_len())?;
+            encoder.put_length_prefixed_slice(&Slice::from_buf(f.get_smallest().encode()))?;
+            encoder.put_length_prefixed_slice(&Slice::from_buf(f.get_largest().encode()))?;
         }
         Ok(())
     }
@@ -260,88 +309,169 @@ impl VersionEdit {
     ///
     /// # Arguments
     ///
-    /// * `source`:
+    /// * `source`: encode_to 编码的数据
     ///
-    /// returns: ()
+    /// returns: Status, 数据不合法时返回 Corruption
     ///
     /// # Examples
     ///
     /// ```
+    /// use level_db_rust::db::version_edit::VersionEdit;
+    /// use level_db_rust::util::slice::Slice;
     ///
+    /// let mut edit = VersionEdit::new();
+    /// let status = edit.decode_from(&Slice::from("a"));
+    /// assert_eq!("VersionEdit: unknown tag", status.get_msg());
     /// ```
     pub fn decode_from(&mut self, source: &Slice) -> Status {
         self.clear();
 
-        let version_edit = VersionEdit::new();
+        let mut decoder = Decoder::with_slice(source);
+        let mut msg: Option<&str> = None;
+
+        while msg.is_none() && decoder.can_get() {
+            let tag = match decoder.get_varint32() {
+                Ok(tag) => tag,
+                Err(_) => break
+            };
+
+            match Tag::from_value(tag) {
+                Some(Tag::k_comparator) => match decoder.get_length_prefixed_slice() {
+                    Ok(name) => {
+                        self.comparator_ = name.into();
+                        self.has_comparator_ = true;
+                    }
+                    Err(_) => msg = Some("comparator name")
+                },
+                Some(Tag::kLogNumber) => match decoder.get_varint64() {
+                    Ok(num) => self.set_log_number(num),
+                    Err(_) => msg = Some("log number")
+                },
+                Some(Tag::kPrevLogNumber) => match decoder.get_varint64() {
+                    Ok(num) => self.set_prev_log_number(num),
+                    Err(_) => msg = Some("previous log number")
+                },
+                Some(Tag::kNextFileNumber) => match decoder.get_varint64() {
+                    Ok(num) => self.set_next_file(num),
+                    Err(_) => msg = Some("next file number")
+                },
+                Some(Tag::kLastSequence) => match decoder.get_varint64() {
+                    Ok(seq) => self.set_last_sequence(seq),
+                    Err(_) => msg = Some("last sequence number")
+                },
+                Some(Tag::kCompactPointer) => {
+                    match Self::get_level(&mut decoder)
+                        .and_then(|level| Ok((level, Self::get_internal_key(&mut decoder)?))) {
+                        Ok((level, key)) => self.set_compact_pointer(level, key),
+                        Err(_) => msg = Some("compaction pointer")
+                    }
+                }
+                Some(Tag::kDeletedFile) => {
+                    match Self::get_level(&mut decoder)
+                        .and_then(|level| Ok((level, decoder.get_varint64()?))) {
+                        Ok((level, number)) => self.delete_file(level, number),
+                        Err(_) => msg = Some("deleted file")
+                    }
+                }
+                Some(Tag::kNewFile) => {
+                    let new_file = Self::get_level(&mut decoder).and_then(|level| {
+                        let number = decoder.get_varint64()?;
+                        let file_size = decoder.get_varint64()?;
+                        let smallest = Self::get_internal_key(&mut decoder)?;
+                        let largest = Self::get_internal_key(&mut decoder)?;
+                        Ok((level, number, file_size, smallest, largest))
+                    });
+                    match new_file {
+                        Ok((level, number, file_size, smallest, largest)) =>
+                            self.add_file(level, number, file_size, smallest, largest),
+                        Err(_) => msg = Some("new-file entry")
+                    }
+                }
+                None => msg = Some("unknown tag")
+            }
+        }
 
-        let msg: Option<Tag> = Option::None;
+        if msg.is_none() && decoder.can_get() {
+            msg = Some("invalid tag");
+        }
 
-        // todo Coding::get_varint32 存在问题。开发暂停
-        // while msg.is_none() && Coding::get_varint32(source) != 0_u32 {
-        //     let tag_value = Coding::get_varint32(source);
-        //     let tag = Tag::from_value(tag_value);
-        //
-        //     if tag.is_none() {
-        //         return LevelError::corruption_string("VersionEdit", "unknown tag");
-        //     }
-        //
-        // }
-        todo!()
+        match msg {
+            Some(msg) => LevelError::corruption_string("VersionEdit", msg),
+            None => Status::default()
+        }
     }
 
     /// VersionEdit 输出调试信息
     pub fn debug_string(&self) -> Slice {
-        let debug_str = String::from("VersionEdit {");
-
-        let mut has_comparator_str = String::default();
-        if (self.has_comparator_) {
-            has_comparator_str.push_str(format!("\n Comparator: {}", self.comparator_.as_str()).as_str());
+        let mut r = String::from("VersionEdit {");
+        if self.has_comparator_ {
+            r.push_str(&format!("\n  Comparator: {}", self.comparator_));
         }
+        if self.has_log_number_ {
+            r.push_str(&format!("\n  LogNumber: {}", self.log_number_));
+        }
+        if self.has_prev_log_number_ {
+            r.push_str(&format!("\n  PrevLogNumber: {}", self.prev_log_number_));
+        }
+        if self.has_next_file_number_ {
+            r.push_str(&format!("\n  NextFile: {}", self.next_file_number_));
+        }
+        if self.has_last_sequence_ {
+            r.push_str(&format!("\n  LastSeq: {}", self.last_sequence_));
+        }
+        for (level, key) in &self.compact_pointers_ {
+            r.push_str(&format!("\n  CompactPointer: {} {}", level, key.debug_string()));
+        }
+        for (level, number) in &self.deleted_files_ {
+            r.push_str(&format!("\n  RemoveFile: {} {}", level, number));
+        }
+        for (level, f) in &self.new_files_ {
+            r.push_str(&format!("\n  AddFile: {} {} {} {} .. {}", level, f.get_number(), f.get_file_size(),
+                                f.get_smallest().debug_string(), f.get_largest().debug_string()));
+        }
+        r.push_str("\n}\n");
 
-        let mut has_log_number__str = String::default();
-        // if(self.has_log_number_){
-        // todo
-        //     // let append_log_number = logging.AppendNumberTo(&r, self.log_number_);
-        //     let append_log_number = self.log_number_ + "".as_ref();
-        //     has_log_number__str.push_str(format!("\n LogNumber: {}", append_log_number).as_str());
-        // }
-
-        let rs = format!("{}{}{}", debug_str, has_log_number__str, "\n}\n");
-
-        Slice::from(rs)
+        Slice::from(r)
     }
 }
 
 /// 静态方法
-impl<'a> VersionEdit {
-    pub fn get_internal_key(input: Slice) -> Result<InternalKey> {
-        let key = InternalKey::default();
-
-        todo!()
-
-        // Slice str;
-        // if (GetLengthPrefixedSlice(input, &str)) {
-        //     dst->DecodeFrom(str);
-        //     return true;
-        // } else {
-        //     return false;
-        // }
+impl VersionEdit {
+    /// 从 decoder 中解出以长度为前缀编码的 InternalKey
+    pub fn get_internal_key(decoder: &mut Decoder) -> Result<InternalKey> {
+        let mut key = InternalKey::default();
+        let encoded = decoder.get_length_prefixed_slice()?;
+        if key.decode_from(&encoded) {
+            Ok(key)
+        } else {
+            Err(LevelError::corruption_string("VersionEdit", "empty internal key"))
+        }
     }
 
-    /// 从 Slice 中解出 level 值
+    /// 从 decoder 中解出 level 值, level 必须小于 Config::K_NUM_LEVELS
     ///
     /// # Arguments
     ///
-    /// * `input`:
+    /// * `decoder`: 解码器
     ///
-    /// returns: bool
+    /// returns: Result<u32, Status>
     ///
     /// # Examples
     ///
     /// ```
+    /// use level_db_rust::db::version_edit::VersionEdit;
+    /// use level_db_rust::util::coding::Decoder;
     ///
+    /// let data = [3u8];
+    /// let mut decoder = Decoder::with_buf(&data);
+    /// assert_eq!(3, VersionEdit::get_level(&mut decoder).unwrap());
     /// ```
-    pub fn get_level(input: Slice) -> Result<u32> {
-        todo!()
+    pub fn get_level(decoder: &mut Decoder) -> Result<u32> {
+        let level = decoder.get_varint32()?;
+        if (level as usize) < Config::K_NUM_LEVELS {
+            Ok(level)
+        } else {
+            Err(LevelError::corruption_string("VersionEdit", "level"))
+        }
     }
-}
\ No newline at end of file
+}
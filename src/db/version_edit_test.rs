@@ -1,5 +1,6 @@
 
 mod test {
+    use crate::db::db_format::{InternalKey, ValueType};
     use crate::db::version_edit;
     use crate::db::version_edit::{Tag, VersionEdit};
     use crate::util::slice::Slice;
@@ -54,4 +55,59 @@ mod test {
         let debug_str = version_edit.debug_string();
         println!("debug_str: \n {}", debug_str);
     }
-}
\ No newline at end of file
+
+    fn test_encode_decode(edit: &VersionEdit) {
+        let mut encoded = vec![];
+        edit.encode_to(&mut encoded).unwrap();
+        let mut parsed = VersionEdit::new();
+        let status = parsed.decode_from(&Slice::from_vec(encoded.clone()));
+        assert!(status.is_ok(), "{}", status.get_msg());
+        let mut encoded2 = vec![];
+        parsed.encode_to(&mut encoded2).unwrap();
+        assert_eq!(encoded, encoded2);
+    }
+
+    #[test]
+    fn test_version_edit_encode_decode() {
+        const K_BIG: u64 = 1 << 50;
+
+        let mut edit = VersionEdit::new();
+        for i in 0..4_u64 {
+            test_encode_decode(&edit);
+            edit.add_file(3, K_BIG + 300 + i, K_BIG + 400 + i,
+                          InternalKey::new(Slice::from("foo"), K_BIG + 500 + i, ValueType::KTypeValue),
+                          InternalKey::new(Slice::from("zoo"), K_BIG + 600 + i, ValueType::KTypeDeletion));
+            edit.delete_file(4, K_BIG + 700 + i);
+            edit.set_compact_pointer(i as u32, InternalKey::new(Slice::from("x"), K_BIG + 900 + i, ValueType::KTypeValue));
+        }
+
+        edit.set_comparator_name(Slice::from("foo"));
+        edit.set_log_number(K_BIG + 100);
+        edit.set_next_file(K_BIG + 200);
+        edit.set_last_sequence(K_BIG + 1000);
+        test_encode_decode(&edit);
+    }
+
+    #[test]
+    fn test_version_edit_decode_corruption() {
+        let mut edit = VersionEdit::new();
+        edit.add_file(3, 300, 400,
+                      InternalKey::new(Slice::from("foo"), 500, ValueType::KTypeValue),
+                      InternalKey::new(Slice::from("zoo"), 600, ValueType::KTypeValue));
+        let mut encoded = vec![];
+        edit.encode_to(&mut encoded).unwrap();
+
+        // 截断的数据
+        encoded.truncate(encoded.len() - 2);
+        let mut parsed = VersionEdit::new();
+        let status = parsed.decode_from(&Slice::from_vec(encoded));
+        assert!(status.is_corruption());
+        assert_eq!("VersionEdit: new-file entry", status.get_msg());
+
+        // level 超过 K_NUM_LEVELS
+        let mut parsed = VersionEdit::new();
+        let status = parsed.decode_from(&Slice::from_vec(vec![6, 9, 1]));
+        assert!(status.is_corruption());
+        assert_eq!("VersionEdit: deleted file", status.get_msg());
+    }
+}
@@ -1,34 +1,42 @@
-use std::collections::{BTreeMap, BTreeSet};
-use std::rc::Rc;
-use crate::db::db_format::{Config, InternalKeyComparator, LookupKey};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use crate::db::db_format::{Config, InternalKey, InternalKeyComparator, K_MAX_SEQUENCE_NUMBER, LookupKey, ParsedInternalKey, ValueType};
 use crate::db::file_meta_data::FileMetaData;
+use crate::db::filename::FileName;
+use crate::db::log_reader::LogReader;
+use crate::db::log_writer::{K_HEADER_SIZE, LogWriter};
 use crate::db::table_cache::TableCache;
 use crate::db::version_edit::VersionEdit;
+use crate::{log_error, log_info};
+use crate::table::iterator::new_error_iterator;
+use crate::table::merger::new_merging_iterator;
+use crate::table::two_level_iterator::new_two_level_iterator;
 use crate::traits::comparator_trait::Comparator;
-use crate::util::env::Env;
-use crate::util::options::{Options, ReadOptions};
+use crate::traits::DataIterator;
+use crate::util::options::{OptionsPtr, ReadOptions};
 use crate::util::slice::Slice;
 use crate::util::Result;
+use crate::util::status::{LevelError, Status};
+use crate::util::unsafe_slice::UnsafeSlice;
 
+/// 数据库在某一时刻的文件布局, 创建后不再修改。
+///
+/// Version 以 Arc 共享, 迭代器、快照读、compaction 等通过持有 Arc<Version> 保证读取期间
+/// Version 及其引用的文件不会被回收, 不再需要手动维护引用计数
 // .h  line 58 - 162
 pub struct Version {
-    // todo  链表实现，前驱后继
-    // VersionSet to which this Version belongs
-    vset_: Rc<VersionSet>,
-    // Next version in linked list
-    next_: Rc<Version>,
-    // Previous version in linked list
-    prev_: Rc<Version>,
-
-    // Number of live refs to this version
-    refs_: i32,
+    icmp_: InternalKeyComparator,
+    options_: OptionsPtr,
 
     // List of files per level, 内部vec 初始化长度 config::kNumLevels
-    files_: Vec<Vec<FileMetaData>>,
+    // 同一个文件会被多个 Version 共享, 最后一个引用它的 Version 释放后 FileMetaData 随之释放
+    files_: Vec<Vec<Arc<FileMetaData>>>,
 
     // Next file to compact based on seek stats.
-    file_to_compact_: FileMetaData,
-    file_to_compact_level_: i32,
+    // 读取时由 update_stats 设置, Version 以 Arc 共享, 因此使用 Mutex. 元素为 (文件, 层级)
+    file_to_compact_: Mutex<Option<(Arc<FileMetaData>, usize)>>,
 
     // Level that should be compacted next and its compaction score.
     // Score < 1 means compaction is not strictly needed.  These fields are initialized by Finalize().
@@ -38,10 +46,9 @@ pub struct Version {
 
 // .h  line 164 - 320
 pub struct VersionSet {
-    env_: Env,
-    dbname_: Slice,
-    options_: Options,
-    table_cache_: TableCache,
+    dbname_: PathBuf,
+    options_: OptionsPtr,
+    table_cache_: Arc<TableCache>,
     icmp_: InternalKeyComparator,
     next_file_number_: u64,
     manifest_file_number_: u64,
@@ -51,76 +58,99 @@ pub struct VersionSet {
     prev_log_number_: u64,
 
     // Opened lazily
-    // todo WritableFile
-    // descriptor_file_: tokio.File,
-    // todo log::Writer
-    // descriptor_log_:  log::Writer,
+    // 当前 MANIFEST 的写入器, 第一次 log_and_apply 时创建
+    descriptor_log_: Option<LogWriter>,
+    // 已写入当前 MANIFEST 的字节数(近似值), 超过 max_file_size 时切换到新的 MANIFEST
+    descriptor_size_: u64,
 
-    // Head of circular doubly-linked list of versions.
-    dummy_versions_: Version,
-    // dummy_versions_.prev_
-    current_: Version,
+    // 所有仍被引用的 Version, 对应 C++ 实现中以 dummy_versions_ 为头的双向链表。
+    // 只持有弱引用, Version 的生命周期由持有它的读操作、迭代器、compaction 决定,
+    // 最后一个 Arc<Version> 释放时 Version 即被回收, 不会形成引用环
+    versions_: Vec<Weak<Version>>,
+    // versions_ 中最新的 Version
+    current_: Arc<Version>,
 
     // Per-level key at which the next compaction at that level should start.
     // Either an empty string, or a valid InternalKey.
-    compact_pointer_: [String; Config::K_NUM_LEVELS]
+    compact_pointer_: [Slice; Config::K_NUM_LEVELS]
 }
 
+/// 一次 compaction 的信息, 将 level 层与 level + 1 层的输入文件合并后输出到 level + 1 层
+///
+/// A Compaction encapsulates information about a compaction.
 // .h  line 323 - 393
 pub struct Compaction {
-    level_: u32,
+    level_: usize,
     max_output_file_size_: u64,
-    input_version_: Version,
-    edit_: VersionEdit
+    input_version_: Option<Arc<Version>>,
+    edit_: VersionEdit,
 
-    // // Each compaction reads inputs from "level_" and "level_+1"
-    // std::vector<FileMetaData*> inputs_[2];      // The two sets of inputs
-    //
-    // // State used to check for number of overlapping grandparent files
-    // // (parent == level_ + 1, grandparent == level_ + 2)
-    // std::vector<FileMetaData*> grandparents_;
-    // size_t grandparent_index_;  // Index in grandparent_starts_
-    // bool seen_key_;             // Some output key has been seen
-    // int64_t overlapped_bytes_;  // Bytes of overlap between current output
-    // // and grandparent files
-    //
-    // // State for implementing IsBaseLevelForKey
-    //
-    // // level_ptrs_ holds indices into input_version_->levels_: our state
-    // // is that we are positioned at one of the file ranges for each
-    // // higher level than the ones involved in this compaction (i.e. for
-    // // all L >= level_ + 2).
-    // size_t level_ptrs_[config::kNumLevels];
+    // Each compaction reads inputs from "level_" and "level_+1"
+    // The two sets of inputs
+    inputs_: [Vec<Arc<FileMetaData>>; 2],
+
+    // State used to check for number of overlapping grandparent files
+    // (parent == level_ + 1, grandparent == level_ + 2)
+    grandparents_: Vec<Arc<FileMetaData>>,
+    // Index in grandparent_starts_
+    grandparent_index_: usize,
+    // Some output key has been seen
+    seen_key_: bool,
+    // Bytes of overlap between current output and grandparent files
+    overlapped_bytes_: u64,
+
+    // State for implementing IsBaseLevelForKey
+
+    // level_ptrs_ holds indices into input_version_->levels_: our state
+    // is that we are positioned at one of the file ranges for each
+    // higher level than the ones involved in this compaction (i.e. for
+    // all L >= level_ + 2).
+    level_ptrs_: [usize; Config::K_NUM_LEVELS],
+}
+
+/// Version::get 的统计信息, 记录第一个被读取但没有找到 key 的文件, 用于触发 seek compaction
+#[derive(Default)]
+pub struct GetStats {
+    seek_file: Option<Arc<FileMetaData>>,
+    seek_file_level: usize
 }
 
-/// Lookup the value for key.  If found, store it in *val and
-/// return OK.  Else return a non-OK status.  Fills *stats.
-/// REQUIRES: lock is not held
-struct GetStats {
-    seek_file: Rc<FileMetaData>,
-    seek_file_level: i32
+/// Version::get 在一个文件中查找的结果
+enum SaverState {
+    NotFound,
+    Found(Slice),
+    Deleted,
+    Corrupt,
 }
 
+/// 遍历某一层(level > 0)的所有文件, key 为文件的 largest, value 为 16 字节的文件号及文件大小
+///
+/// An internal iterator.  For a given version/level pair, yields
+/// information about the files in the level.  For a given entry, key()
+/// is the largest key that occurs in the file, and value() is an
+/// 16-byte value containing the file number and file size, both
+/// encoded using EncodeFixed64.
 // ,cc line 163
 struct LevelFileNumIterator {
     icmp_: InternalKeyComparator,
-    flist_: Vec<FileMetaData>,
-    index_: u32,
+    flist_: Vec<Arc<FileMetaData>>,
+    // 等于 flist_.len() 时无效
+    index_: usize,
 
-    // // Backing store for value().  Holds the file number and size.
-    // mutable char value_buf_[16];
-    value_buf_: [u8]
+    // Backing store for value().  Holds the file number and size.
+    value_buf_: [u8; 16]
 }
 
+/// 将一系列 VersionEdit 应用到 base Version 上, 得到新的 Version, 避免为每个 VersionEdit 都创建一个中间 Version
+///
+/// A helper class so we can efficiently apply a whole sequence
+/// of edits to a particular state without creating intermediate
+/// Versions that contain full copies of the intermediate state.
 // line 604
 pub struct Builder {
-    vset_: Rc<VersionSet>,
-
-    // VersionSet* ;
-    // Version* base_;
-    // LevelState levels_[config::kNumLevels];
-
-    // a : BTreeSet<FileMetaData>,
+    cmp_: BySmallestKey,
+    base_: Arc<Version>,
+    levels_: Vec<LevelState>,
 }
 
 struct BySmallestKey {
@@ -128,32 +158,56 @@ struct BySmallestKey {
 }
 
 struct LevelState {
-    deleted_files: Vec<u64>,
+    deleted_files: BTreeSet<u64>,
 
     // replace std::set<FileMetaData*, BySmallestKey> FileSet -> added_files
-    added_files: BTreeSet<FileMetaData>
+    // 按照 BySmallestKey 的顺序排列
+    added_files: Vec<Arc<FileMetaData>>
 }
 
 impl Version {
-    fn new(vset: Rc<VersionSet>) -> Self {
-        todo!()
+    fn new(icmp: InternalKeyComparator, options: OptionsPtr) -> Self {
+        Self {
+            icmp_: icmp,
+            options_: options,
+            files_: (0..Config::K_NUM_LEVELS).map(|_| vec![]).collect(),
+            file_to_compact_: Mutex::new(None),
+            compaction_score_: -1.0,
+            compaction_level_: -1
+        }
     }
 
-    fn set_next(&mut self, data: &Rc<Version>) -> bool {
-        todo!()
+    /// level 层的文件数量
+    pub fn num_files(&self, level: usize) -> usize {
+        self.files_[level].len()
     }
 
-    fn set_prev(&mut self, data: &Rc<Version>) -> bool {
-        todo!()
+    /// level 层的所有文件, level > 0 时按照 smallest key 排序且互不重叠
+    pub fn files(&self, level: usize) -> &Vec<Arc<FileMetaData>> {
+        &self.files_[level]
     }
 
-    fn get_refs(&self) -> i32 {
-        self.refs_
+    /// 输出每一层的文件信息
+    ///
+    /// Return a human readable string that describes this version's contents.
+    pub fn debug_string(&self) -> String {
+        let mut r = String::new();
+        for (level, files) in self.files_.iter().enumerate() {
+            // E.g.,
+            //   --- level 1 ---
+            //   17:123['a' .. 'd']
+            //   20:43['e' .. 'g']
+            r.push_str(&format!("--- level {} ---\n", level));
+            for f in files {
+                r.push_str(&format!(" {}:{}[{} .. {}]\n", f.get_number(), f.get_file_size(),
+                                    f.get_smallest().debug_string(), f.get_largest().debug_string()));
+            }
+        }
+        r
     }
 
-    /// todo 等待 Iterator 接口
-    ///
-    /// 通过self.versionSet中的TableCache.NewIterator, 将 self 对象数据追加到 iters 中
+    /// 将该 Version 中每个 level 0 文件的迭代器, 以及其他每一层的连接迭代器追加到 iters 中,
+    /// 合并这些迭代器即可得到该 Version 的全部数据
     ///
     /// Append to *iters a sequence of iterators that will yield the contents of this Version when merged together.
     ///
@@ -161,39 +215,179 @@ impl Version {
     ///
     /// # Arguments
     ///
-    /// * `options`:
-    /// * `iters`:
+    /// * `table_cache`: 打开 table 使用的缓存
+    /// * `options`: 读取的配置
+    /// * `iters`: 迭代器列表
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
+    /// ```ignore
+    /// let mut list = vec![];
+    /// current.add_iterators(&table_cache, &ReadOptions::default(), &mut list);
+    /// let iter = new_merging_iterator(Arc::new(icmp), list);
     /// ```
-    ///
-    /// ```
-    fn add_iterators(&self, options: ReadOptions, /* mut iters: Vec<Iterator> */) {
-        todo!()
+    pub fn add_iterators(&self, table_cache: &Arc<TableCache>, options: &ReadOptions,
+                         iters: &mut Vec<Box<dyn DataIterator>>) {
+        // Merge all level zero files together since they may overlap
+        for f in &self.files_[0] {
+            iters.push(table_cache.new_iterator(options, f.get_number(), f.get_file_size()));
+        }
+
+        // For levels > 0, we can use a concatenating iterator that sequentially
+        // walks through the non-overlapping files in the level, opening them
+        // lazily.
+        for level in 1..Config::K_NUM_LEVELS {
+            if self.files_[level].is_empty() {
+                continue;
+            }
+            let table_cache = table_cache.clone();
+            let (verify_checksums, fill_cache, prefix_same_as_start) =
+                (options.verify_checksums, options.fill_cache, options.prefix_same_as_start);
+            iters.push(new_two_level_iterator(
+                Box::new(LevelFileNumIterator::new(self.icmp_.clone(), self.files_[level].clone())),
+                Box::new(move |file_value| {
+                    let options = ReadOptions {
+                        verify_checksums,
+                        fill_cache,
+                        snapshot: None,
+                        prefix_same_as_start,
+                    };
+                    get_file_iterator(&table_cache, &options, file_value)
+                })));
+        }
     }
 
     /// 数据搜索
     ///
     /// 一级一级地搜索，因为条目不会跨越级别。如果在较小的级别上发现数据，则后面的级别是不相关的。
+    /// level 0 的文件之间可能重叠, 按照从新到旧的顺序查找。
+    ///
+    /// 与 MemTable::get 相同: 找到 value 时返回 Ok(Some(value)), key 已被删除时返回 NotFound 错误,
+    /// 所有文件中都没有该 key 时返回 Ok(None)。第一个被读取但没有找到 key 的文件记录在 stats 中
+    ///
+    /// Lookup the value for key.  If found, store it in *val and
+    /// return OK.  Else return a non-OK status.  Fills *stats.
+    /// REQUIRES: lock is not held
     ///
     /// # Arguments
     ///
-    /// * `options`:
-    /// * `key`:
-    /// * `value`:
+    /// * `table_cache`: 打开 table 使用的缓存
+    /// * `options`: 读取的配置
+    /// * `key`: 查找键
+    /// * `stats`: 统计信息, 之后传给 update_stats
     ///
-    /// returns: Result<GetStats, Status>
+    /// returns: Result<Option<Slice>, Status>
     ///
     /// # Examples
     ///
+    /// ```ignore
+    /// let mut stats = GetStats::default();
+    /// let value = current.get(&table_cache, &ReadOptions::default(), &LookupKey::new(key, seq), &mut stats);
     /// ```
+    pub fn get(&self, table_cache: &TableCache, options: &ReadOptions, key: &LookupKey,
+               stats: &mut GetStats) -> Result<Option<Slice>> {
+        stats.seek_file = None;
+        stats.seek_file_level = 0;
+
+        let ikey = key.internal_key();
+        let user_key = key.user_key();
+        let ucmp = self.icmp_.user_comparator();
+        let mut last_file_read: Option<(Arc<FileMetaData>, usize)> = None;
+        let mut result = Ok(None);
+
+        self.for_each_overlapping(&user_key, &ikey, |level, f| {
+            if stats.seek_file.is_none() {
+                if let Some((file, file_level)) = last_file_read.take() {
+                    // We have had more than one seek for this read.  Charge the 1st file.
+                    stats.seek_file = Some(file);
+                    stats.seek_file_level = file_level;
+                }
+            }
+            last_file_read = Some((f.clone(), level));
+
+            let mut state = SaverState::NotFound;
+            let s = table_cache.get(options, f.get_number(), f.get_file_size(), &ikey, |k, v| {
+                state = match ParsedInternalKey::parse_internal_key(k) {
+                    None => SaverState::Corrupt,
+                    Some(parsed) => {
+                        if ucmp.compare(parsed.user_key(), &user_key) != Some(Ordering::Equal) {
+                            SaverState::NotFound
+                        } else if parsed.value_type() == ValueType::KTypeValue {
+                            SaverState::Found(Slice::from_buf(v))
+                        } else {
+                            SaverState::Deleted
+                        }
+                    }
+                };
+            });
+            if let Err(e) = s {
+                result = Err(e);
+                return false;
+            }
+            match state {
+                // Keep searching in other files
+                SaverState::NotFound => true,
+                SaverState::Found(value) => {
+                    result = Ok(Some(value));
+                    false
+                }
+                SaverState::Deleted => {
+                    result = Err(LevelError::not_found(user_key.clone(), Slice::default()));
+                    false
+                }
+                SaverState::Corrupt => {
+                    result = Err(LevelError::corruption_string("corrupted key for ",
+                                                               &String::from_utf8_lossy(&user_key)));
+                    false
+                }
+            }
+        });
+        result
+    }
+
+    /// 对与 user_key 重叠的每个文件按照从新到旧的顺序调用 func(level, f), func 返回 false 时停止
     ///
-    /// ```
-    fn get(&self, options: ReadOptions, key: LookupKey, value: Slice) -> Result<GetStats> {
-        todo!()
+    /// Call func(arg, level, f) for every file that overlaps user_key in
+    /// order from newest to oldest.  If an invocation of func returns
+    /// false, makes no more calls.
+    fn for_each_overlapping<F>(&self, user_key: &[u8], internal_key: &[u8], mut func: F)
+        where F: FnMut(usize, &Arc<FileMetaData>) -> bool {
+        let ucmp = self.icmp_.user_comparator();
+
+        // Search level-0 in order from newest to oldest.
+        let mut tmp: Vec<&Arc<FileMetaData>> = self.files_[0].iter()
+            .filter(|f| ucmp.compare(user_key, f.get_smallest().user_key()) != Some(Ordering::Less)
+                && ucmp.compare(user_key, f.get_largest().user_key()) != Some(Ordering::Greater))
+            .collect();
+        if !tmp.is_empty() {
+            tmp.sort_by(|a, b| b.get_number().cmp(&a.get_number()));
+            for f in tmp {
+                if !func(0, f) {
+                    return;
+                }
+            }
+        }
+
+        // Search other levels.
+        for level in 1..Config::K_NUM_LEVELS {
+            let files = &self.files_[level];
+            if files.is_empty() {
+                continue;
+            }
+
+            // Binary search to find earliest index whose largest key >= internal_key.
+            let index = VersionSet::find_file(&self.icmp_, files, internal_key);
+            if index < files.len() {
+                let f = &files[index];
+                if ucmp.compare(user_key, f.get_smallest().user_key()) == Some(Ordering::Less) {
+                    // All of "f" is past any data for user_key
+                } else if !func(level, f) {
+                    return;
+                }
+            }
+        }
     }
 
     /// Adds "stats" into the current state.
@@ -203,17 +397,27 @@ impl Version {
     ///
     /// # Arguments
     ///
-    /// * `stats`:
+    /// * `stats`: Version::get 返回的统计信息
     ///
     /// returns: bool
     ///
     /// # Examples
     ///
+    /// ```ignore
+    /// if current.update_stats(&stats) {
+    ///     db.maybe_schedule_compaction(&mut state);
+    /// }
     /// ```
-    ///
-    /// ```
-    fn update_stats(&mut self, stats: GetStats) -> bool{
-        todo!()
+    pub fn update_stats(&self, stats: &GetStats) -> bool {
+        if let Some(f) = &stats.seek_file {
+            let allowed_seeks = f.decrease_allowed_seeks();
+            let mut file_to_compact = self.file_to_compact_.lock().unwrap();
+            if allowed_seeks <= 0 && file_to_compact.is_none() {
+                *file_to_compact = Some((f.clone(), stats.seek_file_level));
+                return true;
+            }
+        }
+        false
     }
 
     /// 记录在指定内部键处读取的字节样本。
@@ -227,48 +431,161 @@ impl Version {
     ///
     /// # Arguments
     ///
-    /// * `key`:
+    /// * `internal_key`: 编码后的 internal key
     ///
     /// returns: bool
     ///
     /// # Examples
     ///
+    /// ```ignore
+    /// if current.record_read_sample(iter.key().as_ref()) {
+    ///     db.maybe_schedule_compaction(&mut state);
+    /// }
     /// ```
+    pub fn record_read_sample(&self, internal_key: &[u8]) -> bool {
+        let ikey = match ParsedInternalKey::parse_internal_key(internal_key) {
+            Some(ikey) => ikey,
+            None => return false,
+        };
+
+        let mut stats = GetStats::default();
+        let mut matches = 0;
+        self.for_each_overlapping(ikey.user_key(), internal_key, |level, f| {
+            matches += 1;
+            if matches == 1 {
+                // Remember first match.
+                stats.seek_file = Some(f.clone());
+                stats.seek_file_level = level;
+            }
+            // We can stop iterating once we have a second match.
+            matches < 2
+        });
+
+        // Must have at least two matches since we want to merge across
+        // files. But what if we have a single file that contains many
+        // overwrites and deletions?  Should we have another mechanism for
+        // finding such files?
+        if matches >= 2 {
+            // 1MB cost is about 1 seek (see comment in Builder::Apply).
+            return self.update_stats(&stats);
+        }
+        false
+    }
+
+    /// 返回 level 层中与 user key 范围 [begin, end] 重叠的所有文件。
+    /// level 0 的文件之间可能重叠, 加入一个文件后如果范围被扩大, 会以扩大后的范围重新查找
+    ///
+    /// Store in "*inputs" all files in "level" that overlap [begin,end]
+    ///
+    /// # Arguments
+    ///
+    /// * `level`: 层级
+    /// * `begin`: None 表示比所有的 key 都小
+    /// * `end`: None 表示比所有的 key 都大
+    ///
+    /// returns: Vec<Arc<FileMetaData>>
+    ///
+    /// # Examples
     ///
+    /// ```ignore
+    /// let inputs = version.get_overlapping_inputs(1, Some(&begin), None);
     /// ```
-    fn record_read_sample(&self, key: Slice) -> bool {
-        todo!()
+    pub fn get_overlapping_inputs(&self, level: usize, begin: Option<&InternalKey>,
+                                  end: Option<&InternalKey>) -> Vec<Arc<FileMetaData>> {
+        assert!(level < Config::K_NUM_LEVELS);
+        let mut inputs = vec![];
+        let mut user_begin = begin.map(|k| k.user_key());
+        let mut user_end = end.map(|k| k.user_key());
+        let user_cmp = self.icmp_.user_comparator();
+        let files = &self.files_[level];
+
+        let mut i = 0;
+        while i < files.len() {
+            let f = &files[i];
+            i += 1;
+            let file_start = f.get_smallest().user_key();
+            let file_limit = f.get_largest().user_key();
+            if user_begin.is_some_and(|b| user_cmp.compare(file_limit, b) == Some(Ordering::Less)) {
+                // "f" is completely before specified range; skip it
+            } else if user_end.is_some_and(|e| user_cmp.compare(file_start, e) == Some(Ordering::Greater)) {
+                // "f" is completely after specified range; skip it
+            } else {
+                inputs.push(f.clone());
+                if level == 0 {
+                    // Level-0 files may overlap each other.  So check if the newly
+                    // added file has expanded the range.  If so, restart search.
+                    if user_begin.is_some_and(|b| user_cmp.compare(file_start, b) == Some(Ordering::Less)) {
+                        user_begin = Some(file_start);
+                        inputs.clear();
+                        i = 0;
+                    } else if user_end.is_some_and(|e| user_cmp.compare(file_limit, e) == Some(Ordering::Greater)) {
+                        user_end = Some(file_limit);
+                        inputs.clear();
+                        i = 0;
+                    }
+                }
+            }
+        }
+        inputs
     }
 
-    // //
-    // // Reference count management (so Versions do not disappear out from
-    // // under live iterators)
-    // void Ref();
-    // void Unref();
-    //
-    // void GetOverlappingInputs(
-    // int level,
-    // const InternalKey* begin,         // nullptr means before all keys
-    // const InternalKey* end,           // nullptr means after all keys
-    // std::vector<FileMetaData*>* inputs);
-    //
-    // // Returns true iff some file in the specified level overlaps
-    // // some part of [*smallest_user_key,*largest_user_key].
-    // // smallest_user_key==nullptr represents a key smaller than all the DB's keys.
-    // // largest_user_key==nullptr represents a key largest than all the DB's keys.
-    // bool OverlapInLevel(int level,
-    // const Slice* smallest_user_key,
-    // const Slice* largest_user_key);
-    //
-    // // Return the level at which we should place a new memtable compaction
-    // // result that covers the range [smallest_user_key,largest_user_key].
-    // int PickLevelForMemTableOutput(const Slice& smallest_user_key,
-    // const Slice& largest_user_key);
-    //
-    // int NumFiles(int level) const { return files_[level].size(); }
-    //
-    // // Return a human readable string that describes this version's contents.
-    // std::string DebugString() const;
+    /// level 层中是否有文件与 user key 范围 [smallest_user_key, largest_user_key] 重叠
+    ///
+    /// Returns true iff some file in the specified level overlaps
+    /// some part of [*smallest_user_key,*largest_user_key].
+    /// smallest_user_key==nullptr represents a key smaller than all the DB's keys.
+    /// largest_user_key==nullptr represents a key largest than all the DB's keys.
+    pub fn overlap_in_level(&self, level: usize, smallest_user_key: Option<&[u8]>,
+                            largest_user_key: Option<&[u8]>) -> bool {
+        VersionSet::some_file_overlaps_range(&self.icmp_, level > 0, &self.files_[level],
+                                             smallest_user_key, largest_user_key)
+    }
+
+    /// 为 memtable 落盘生成的文件选择层级。
+    ///
+    /// 与 level 0 没有重叠时, 只要与下一层没有重叠, 且与下下层(grandparent)重叠的数据量不超过
+    /// max_grand_parent_overlap_bytes, 就继续向下推, 最多推到 Config::K_MAX_MEM_COMPACT_LEVEL,
+    /// 以减少代价较高的 level 0 => level 1 compaction
+    ///
+    /// Return the level at which we should place a new memtable compaction
+    /// result that covers the range [smallest_user_key,largest_user_key].
+    ///
+    /// # Arguments
+    ///
+    /// * `smallest_user_key`: 新文件中最小的 user key
+    /// * `largest_user_key`: 新文件中最大的 user key
+    ///
+    /// returns: usize
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let level = version.pick_level_for_memtable_output(b"a", b"z");
+    /// ```
+    pub fn pick_level_for_memtable_output(&self, smallest_user_key: &[u8], largest_user_key: &[u8]) -> usize {
+        let mut level = 0;
+        if !self.overlap_in_level(0, Some(smallest_user_key), Some(largest_user_key)) {
+            // Push to next level if there is no overlap in next level,
+            // and the #bytes overlapping in the level after that are limited.
+            let start = InternalKey::new(Slice::from_buf(smallest_user_key), K_MAX_SEQUENCE_NUMBER as u64,
+                                         Config::K_VALUE_TYPE_FOR_SEEK);
+            let limit = InternalKey::new(Slice::from_buf(largest_user_key), 0, ValueType::KTypeDeletion);
+            while level < Config::K_MAX_MEM_COMPACT_LEVEL {
+                if self.overlap_in_level(level + 1, Some(smallest_user_key), Some(largest_user_key)) {
+                    break;
+                }
+                if level + 2 < Config::K_NUM_LEVELS {
+                    // Check that file does not overlap too many grandparent bytes.
+                    let overlaps = self.get_overlapping_inputs(level + 2, Some(&start), Some(&limit));
+                    if total_file_size(&overlaps) > max_grand_parent_overlap_bytes(&self.options_) {
+                        break;
+                    }
+                }
+                level += 1;
+            }
+        }
+        level
+    }
 
     // Iterator* NewConcatenatingIterator(const ReadOptions&, int level) const;
     //
@@ -282,37 +599,480 @@ impl Version {
     // bool (*func)(void*, int, FileMetaData*));
 }
 
-impl Drop for Version {
-    /// version_set.cc 中析构方法 Version::~Version() 的对应实现
-    fn drop(&mut self) {
-        assert_eq!(self.get_refs(), 0);
-
-        // // Remove from linked list
-        // self.prev_.set_next(&self.next_);
-        // self.next_.set_prev(&self.prev_);
-
-        // // Drop references to files
-        // for level in 0..Config::K_NUM_LEVELS {
-        //     for i in 0..self.files_[level].len() {
-        //         let mut meta: FileMetaData = files_[level][i];
-        //         assert!(meta.get_refs() > 0);
-        //
-        //         meta.add_refs(-1);
-        //         if meta.get_refs() <= 0 {
-        //             // todo  C 语义  delete f
-        //             // delete f;
-        //         }
-        //     }
-        // }
+impl VersionSet {
+    /// 创建 VersionSet, 初始时只包含一个空的 Version
+    ///
+    /// # Arguments
+    ///
+    /// * `dbname`: 数据库目录
+    /// * `options`: 配置
+    /// * `table_cache`: 与 DB 共享的 TableCache
+    /// * `icmp`: InternalKeyComparator
+    ///
+    /// returns: VersionSet
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let versions = VersionSet::new("/tmp/db", options, table_cache, icmp);
+    /// ```
+    pub fn new<P: AsRef<Path>>(dbname: P, options: OptionsPtr, table_cache: Arc<TableCache>,
+                               icmp: InternalKeyComparator) -> Self {
+        let current = Arc::new(Version::new(icmp.clone(), options.clone()));
+        Self {
+            dbname_: dbname.as_ref().to_path_buf(),
+            options_: options,
+            table_cache_: table_cache,
+            icmp_: icmp,
+            next_file_number_: 2,
+            manifest_file_number_: 0,
+            last_sequence_: 0,
+            log_number_: 0,
+            prev_log_number_: 0,
+            descriptor_log_: None,
+            descriptor_size_: 0,
+            versions_: vec![Arc::downgrade(&current)],
+            current_: current,
+            compact_pointer_: Default::default()
+        }
     }
-}
 
-// impl VersionSet {
-//     //.
-// }
+    /// 返回当前的 Version, 调用方持有返回值期间该 Version 引用的文件不会被删除
+    pub fn current(&self) -> Arc<Version> {
+        self.current_.clone()
+    }
 
-impl VersionSet {
-    /// 返回文件源数据中最小的索引。 如果文件不存在，则返回文件数量
+    /// 当前 MANIFEST 的文件号
+    pub fn manifest_file_number(&self) -> u64 {
+        self.manifest_file_number_
+    }
+
+    /// 当前预写日志的文件号
+    pub fn log_number(&self) -> u64 {
+        self.log_number_
+    }
+
+    /// 正在被 compact 的 memtable 对应的预写日志文件号, 没有则为 0
+    pub fn prev_log_number(&self) -> u64 {
+        self.prev_log_number_
+    }
+
+    /// 分配一个新的文件号
+    pub fn new_file_number(&mut self) -> u64 {
+        let number = self.next_file_number_;
+        self.next_file_number_ += 1;
+        number
+    }
+
+    /// 归还刚刚通过 new_file_number 分配但是没有使用的文件号
+    ///
+    /// Arrange to reuse "file_number" unless a newer file number has already been allocated.
+    /// REQUIRES: "file_number" was returned by a call to NewFileNumber().
+    pub fn reuse_file_number(&mut self, file_number: u64) {
+        if self.next_file_number_ == file_number + 1 {
+            self.next_file_number_ = file_number;
+        }
+    }
+
+    /// 标记 number 已经被使用, 之后分配的文件号都会大于 number
+    pub fn mark_file_number_used(&mut self, number: u64) {
+        if self.next_file_number_ <= number {
+            self.next_file_number_ = number + 1;
+        }
+    }
+
+    /// Return the last sequence number.
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence_
+    }
+
+    /// Set the last sequence number to s.
+    pub fn set_last_sequence(&mut self, s: u64) {
+        assert!(s >= self.last_sequence_);
+        self.last_sequence_ = s;
+    }
+
+    /// 当前 Version 中 level 层的文件数量
+    pub fn num_level_files(&self, level: usize) -> usize {
+        assert!(level < Config::K_NUM_LEVELS);
+        self.current_.num_files(level)
+    }
+
+    /// 当前 Version 中 level 层的文件总大小
+    pub fn num_level_bytes(&self, level: usize) -> u64 {
+        assert!(level < Config::K_NUM_LEVELS);
+        total_file_size(&self.current_.files_[level])
+    }
+
+    /// 将 edit 应用到当前 Version 上生成新的 Version, 把 edit 持久化到 MANIFEST 后将新的 Version 设为 current。
+    ///
+    /// 还没有打开 MANIFEST 或者当前 MANIFEST 超过 max_file_size 时, 会创建新的 MANIFEST,
+    /// 先写入当前 Version 的完整快照, 再写入 edit, 最后将 CURRENT 指向新的 MANIFEST。
+    /// 写入失败时当前 Version 及 MANIFEST 都保持不变
+    ///
+    /// Apply *edit to the current version to form a new descriptor that
+    /// is both saved to persistent state and installed as the new
+    /// current version.
+    ///
+    /// # Arguments
+    ///
+    /// * `edit`: 本次变更, 会被补充 log number、next file number、last sequence 等信息
+    ///
+    /// returns: Result<(), Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut edit = VersionEdit::new();
+    /// edit.add_file(0, 10, 1024, smallest, largest);
+    /// versions.log_and_apply(&mut edit)?;
+    /// ```
+    pub fn log_and_apply(&mut self, edit: &mut VersionEdit) -> Result<()> {
+        if edit.has_log_number() {
+            assert!(edit.log_number() >= self.log_number_);
+            assert!(edit.log_number() < self.next_file_number_);
+        } else {
+            edit.set_log_number(self.log_number_);
+        }
+
+        if !edit.has_prev_log_number() {
+            edit.set_prev_log_number(self.prev_log_number_);
+        }
+
+        // 需要新的 MANIFEST 时先分配文件号, 保证 edit 中记录的 next file number 大于 MANIFEST 的文件号
+        let old_manifest_file_number = self.manifest_file_number_;
+        let need_new_manifest = self.descriptor_log_.is_none()
+            || self.descriptor_size_ >= self.options_.max_file_size as u64;
+        if need_new_manifest && (self.descriptor_log_.is_some() || self.manifest_file_number_ == 0) {
+            self.manifest_file_number_ = self.new_file_number();
+        }
+
+        edit.set_next_file(self.next_file_number_);
+        edit.set_last_sequence(self.last_sequence_);
+
+        let mut v = Version::new(self.icmp_.clone(), self.options_.clone());
+        {
+            let mut builder = Builder::new(self.icmp_.clone(), self.current_.clone());
+            builder.apply(edit, &mut self.compact_pointer_);
+            builder.save_to(&mut v);
+        }
+        self.finalize(&mut v);
+
+        // Initialize new descriptor log file if necessary by creating
+        // a temporary file that contains a snapshot of the current version.
+        let mut new_manifest: Option<(PathBuf, LogWriter)> = None;
+        let mut descriptor_size = self.descriptor_size_;
+        let mut record = vec![];
+        let mut rs = edit.encode_to(&mut record);
+        if rs.is_ok() && need_new_manifest {
+            let fname = FileName::descriptor_file_name(&self.dbname_, self.manifest_file_number_);
+            rs = self.options_.env.new_writable_file(&fname).and_then(|file| {
+                let mut log = LogWriter::new(Box::new(file));
+                descriptor_size = self.write_snapshot(&mut log)?;
+                new_manifest = Some((fname.clone(), log));
+                Ok(())
+            });
+            if rs.is_err() && new_manifest.is_none() {
+                let _ = self.options_.env.remove_file(&fname);
+            }
+        }
+
+        // Write new record to MANIFEST log
+        if rs.is_ok() {
+            descriptor_size += (record.len() + K_HEADER_SIZE) as u64;
+            let log = match new_manifest.as_mut() {
+                Some((_, log)) => log,
+                None => self.descriptor_log_.as_mut().expect("descriptor log")
+            };
+            rs = log.add_record(Slice::from_vec(record))
+                .and_then(|_| log.sync());
+            if let Err(e) = &rs {
+                log_error!(self.options_.info_log, "MANIFEST write: {}", e.get_msg());
+            }
+        }
+
+        // If we just created a new descriptor file, install it by writing a
+        // new CURRENT file that points to it.
+        if rs.is_ok() && new_manifest.is_some() {
+            rs = FileName::set_current_file(&self.options_.env, &self.dbname_, self.manifest_file_number_);
+        }
+
+        match rs {
+            Ok(_) => {
+                if let Some((fname, log)) = new_manifest {
+                    log_info!(self.options_.info_log, "Switched to new MANIFEST {}", fname.display());
+                    self.descriptor_log_ = Some(log);
+                }
+                self.descriptor_size_ = descriptor_size;
+                // Install the new version
+                self.append_version(v);
+                self.log_number_ = edit.log_number();
+                self.prev_log_number_ = edit.prev_log_number();
+                Ok(())
+            }
+            Err(e) => {
+                if let Some((fname, log)) = new_manifest {
+                    drop(log);
+                    let _ = self.options_.env.remove_file(&fname);
+                }
+                if need_new_manifest {
+                    // 新的 MANIFEST 没有生效, CURRENT 仍然指向原来的 MANIFEST
+                    self.manifest_file_number_ = old_manifest_file_number;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// 从 CURRENT 指向的 MANIFEST 中恢复最后一次持久化的状态
+    ///
+    /// 恢复后不会复用原来的 MANIFEST, 下一次 log_and_apply 会创建新的 MANIFEST
+    ///
+    /// Recover the last saved descriptor from persistent storage.
+    ///
+    /// returns: Result<(), Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// versions.recover()?;
+    /// ```
+    pub fn recover(&mut self) -> Result<()> {
+        // Read "CURRENT" file, which contains a pointer to the current manifest file
+        let env = &self.options_.env;
+        let mut current = env.read_file_to_string(&FileName::current_file_name(&self.dbname_))?;
+        if current.is_empty() || !current.ends_with('\n') {
+            return Err(LevelError::corruption_string("CURRENT file does not end with newline", ""));
+        }
+        current.truncate(current.len() - 1);
+
+        let dscname = self.dbname_.join(&current);
+        let file = match env.new_sequential_file(&dscname) {
+            Ok(file) => file,
+            Err(e) => {
+                if !env.file_exists(&dscname) {
+                    return Err(LevelError::corruption_string("CURRENT points to a non-existent file",
+                                                             &e.get_msg()));
+                }
+                return Err(e);
+            }
+        };
+
+        let mut next_file = None;
+        let mut last_sequence = None;
+        let mut log_number = None;
+        let mut prev_log_number = None;
+        let mut builder = Builder::new(self.icmp_.clone(), self.current_.clone());
+
+        let mut reader = LogReader::new(Box::new(file), true, 0);
+        while let Some(record) = reader.read_next()? {
+            let mut edit = VersionEdit::new();
+            let status = edit.decode_from(&record);
+            if !status.is_ok() {
+                return Err(status);
+            }
+            let user_comparator = self.icmp_.user_comparator().get_name();
+            if edit.has_comparator() && edit.comparator_name() != user_comparator {
+                return Err(LevelError::invalid_argument(
+                    format!("{} does not match existing comparator ", edit.comparator_name()).into(),
+                    user_comparator.into()));
+            }
+
+            builder.apply(&edit, &mut self.compact_pointer_);
+
+            if edit.has_log_number() {
+                log_number = Some(edit.log_number());
+            }
+            if edit.has_prev_log_number() {
+                prev_log_number = Some(edit.prev_log_number());
+            }
+            if edit.has_next_file_number() {
+                next_file = Some(edit.next_file_number());
+            }
+            if edit.has_last_sequence() {
+                last_sequence = Some(edit.last_sequence());
+            }
+        }
+
+        let next_file = next_file.ok_or_else(
+            || LevelError::corruption_string("no meta-nextfile entry in descriptor", ""))?;
+        let log_number = log_number.ok_or_else(
+            || LevelError::corruption_string("no meta-lognumber entry in descriptor", ""))?;
+        let last_sequence = last_sequence.ok_or_else(
+            || LevelError::corruption_string("no last-sequence-number entry in descriptor", ""))?;
+        let prev_log_number = prev_log_number.unwrap_or(0);
+
+        self.mark_file_number_used(prev_log_number);
+        self.mark_file_number_used(log_number);
+
+        let mut v = Version::new(self.icmp_.clone(), self.options_.clone());
+        builder.save_to(&mut v);
+        // Install recovered version
+        self.finalize(&mut v);
+        self.append_version(v);
+        self.manifest_file_number_ = next_file;
+        self.next_file_number_ = next_file + 1;
+        self.last_sequence_ = last_sequence;
+        self.log_number_ = log_number;
+        self.prev_log_number_ = prev_log_number;
+        // 不复用原来的 MANIFEST
+        self.descriptor_log_ = None;
+        self.descriptor_size_ = 0;
+
+        log_info!(self.options_.info_log, "Recovered {}: next file #{}, last sequence {}, log #{}, prev log #{}; {}",
+                  current, next_file, last_sequence, log_number, prev_log_number, self.level_summary());
+        Ok(())
+    }
+
+    /// 将 v 设为 current, 并加入存活的 Version 列表
+    fn append_version(&mut self, v: Version) {
+        let v = Arc::new(v);
+        // 清理已经没有引用的 Version
+        self.versions_.retain(|version| version.strong_count() > 0);
+        self.versions_.push(Arc::downgrade(&v));
+        self.current_ = v;
+    }
+
+    /// 计算 v 中下一次需要 compaction 的层, 以及该层的 compaction score
+    ///
+    /// Precomputed best level for next compaction
+    fn finalize(&self, v: &mut Version) {
+        let mut best_level = -1;
+        let mut best_score = -1.0;
+
+        for level in 0..Config::K_NUM_LEVELS - 1 {
+            let score = if level == 0 {
+                // We treat level-0 specially by bounding the number of files
+                // instead of number of bytes for two reasons:
+                //
+                // (1) With larger write-buffer sizes, it is nice not to do too
+                // many level-0 compactions.
+                //
+                // (2) The files in level-0 are merged on every read and
+                // therefore we wish to avoid too many files when the individual
+                // file size is small (perhaps because of a small write-buffer
+                // setting, or very high compression ratios, or lots of
+                // overwrites/deletions).
+                v.files_[level].len() as f64 / Config::KL0_COMPACTION_TRIGGER as f64
+            } else {
+                // Compute the ratio of current size to size limit.
+                total_file_size(&v.files_[level]) as f64 / max_bytes_for_level(level)
+            };
+
+            if score > best_score {
+                best_level = level as i32;
+                best_score = score;
+            }
+        }
+
+        v.compaction_level_ = best_level;
+        v.compaction_score_ = best_score;
+    }
+
+    /// 将当前 Version 的完整状态作为一条记录写入 MANIFEST, 返回写入的字节数
+    ///
+    /// Save current contents to *log
+    fn write_snapshot(&self, log: &mut LogWriter) -> Result<u64> {
+        // Save metadata
+        let mut edit = VersionEdit::new();
+        edit.set_comparator_name(self.icmp_.user_comparator().get_name().into());
+
+        // Save compaction pointers
+        for (level, pointer) in self.compact_pointer_.iter().enumerate() {
+            if !pointer.empty() {
+                let mut key = InternalKey::default();
+                key.decode_from(pointer);
+                edit.set_compact_pointer(level as u32, key);
+            }
+        }
+
+        // Save files
+        for (level, files) in self.current_.files_.iter().enumerate() {
+            for f in files {
+                edit.add_file(level as u32, f.get_number(), f.get_file_size(),
+                              f.get_smallest().clone(), f.get_largest().clone());
+            }
+        }
+
+        let mut record = vec![];
+        edit.encode_to(&mut record)?;
+        let size = (record.len() + K_HEADER_SIZE) as u64;
+        log.add_record(Slice::from_vec(record))?;
+        Ok(size)
+    }
+
+    /// 将所有存活 Version 中引用的文件号加入 live 中
+    ///
+    /// Add all files listed in any live version to *live.
+    ///
+    /// # Arguments
+    ///
+    /// * `live`: 存活的文件号集合
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut live = BTreeSet::new();
+    /// versions.add_live_files(&mut live);
+    /// ```
+    pub fn add_live_files(&self, live: &mut BTreeSet<u64>) {
+        for version in self.versions_.iter().filter_map(|v| v.upgrade()) {
+            for files in &version.files_ {
+                for f in files {
+                    live.insert(f.get_number());
+                }
+            }
+        }
+    }
+
+    /// 返回 ikey 在 Version v 中的大致偏移量, 即所有层中位于 ikey 之前的数据的字节数
+    ///
+    /// Return the approximate offset in the database of the data for
+    /// "key" as of version "v".
+    ///
+    /// # Arguments
+    ///
+    /// * `v`: Version
+    /// * `ikey`: 内部键
+    ///
+    /// returns: u64
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let offset = versions.approximate_offset_of(&versions.current(), &ikey);
+    /// ```
+    pub fn approximate_offset_of(&self, v: &Version, ikey: &InternalKey) -> u64 {
+        let mut result = 0;
+        for level in 0..Config::K_NUM_LEVELS {
+            for f in &v.files_[level] {
+                if self.icmp_.compare_internal_key(f.get_largest(), ikey) != Ordering::Greater {
+                    // Entire file is before "ikey", so just add the file size
+                    result += f.get_file_size();
+                } else if self.icmp_.compare_internal_key(f.get_smallest(), ikey) == Ordering::Greater {
+                    // Entire file is after "ikey", so ignore
+                    if level > 0 {
+                        // Files other than level 0 are sorted by meta->smallest, so
+                        // no further files in this level will contain data for
+                        // "ikey".
+                        break;
+                    }
+                } else {
+                    // "ikey" falls in the range for this table.  Add the
+                    // approximate offset of "ikey" within the table.
+                    // 打开 table 失败时忽略该文件
+                    if let Ok(table) = self.table_cache_.find_table(f.get_number(), f.get_file_size()) {
+                        result += table.approximate_offset_of(ikey.encode());
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// 返回 largest >= key 的第一个文件的索引, 不存在这样的文件时返回文件数量
     ///
     /// Return the smallest index i such that files[i]->largest >= key.
     /// Return files.size() if there is no such file.
@@ -320,24 +1080,36 @@ impl VersionSet {
     /// REQUIRES: "files" contains a sorted list of non-overlapping files.
     /// # Arguments
     ///
-    /// * `icmp`:
-    /// * `files`:
-    /// * `key`:
+    /// * `icmp`: InternalKeyComparator
+    /// * `files`: 有序且互不重叠的文件
+    /// * `key`: 编码后的内部键
     ///
-    /// returns: u32
+    /// returns: usize
     ///
     /// # Examples
     ///
     /// ```
+    /// use std::sync::Arc;
+    /// use level_db_rust::db::db_format::{InternalKey, InternalKeyComparator, ValueType};
+    /// use level_db_rust::db::file_meta_data::FileMetaData;
+    /// use level_db_rust::db::version_set::VersionSet;
+    /// use level_db_rust::util::slice::Slice;
     ///
+    /// let icmp = InternalKeyComparator::default();
+    /// let files = vec![Arc::new(FileMetaData::new_with_number_file_size_internal_key(1, 100,
+    ///     InternalKey::new(Slice::from("a"), 100, ValueType::KTypeValue),
+    ///     InternalKey::new(Slice::from("c"), 100, ValueType::KTypeValue)))];
+    /// let key = InternalKey::new(Slice::from("b"), 100, ValueType::KTypeValue);
+    /// assert_eq!(0, VersionSet::find_file(&icmp, &files, key.encode()));
     /// ```
-    fn find_file(icmp: InternalKeyComparator, files:&Vec<FileMetaData>, key:&Slice) -> u32 {
-        todo!()
+    pub fn find_file(icmp: &InternalKeyComparator, files: &[Arc<FileMetaData>], key: &[u8]) -> usize {
+        // 二分查找, files[..index] 中文件的 largest 都 < key
+        files.partition_point(|f| icmp.compare_key(f.get_largest().encode(), key) == Ordering::Less)
     }
 
     /// 如果 user key 范围[smallest_user_key, largest_user_key] 与 “files”中的 [smallest.user_key(), largest.user_key()] 重叠，则返回true
-    /// minimal==nullptr表示比DB中的所有键都小的键。
-    /// maximum==nullptr表示比DB中的所有键都大的键。
+    /// smallest_user_key 为 None 表示比DB中的所有键都小的键。
+    /// largest_user_key 为 None 表示比DB中的所有键都大的键。
     ///
     /// Returns true iff some file in "files" overlaps the user key range [smallest_user_key, largest_user_key].
     /// smallest==nullptr represents a key smaller than all keys in the DB.
@@ -348,40 +1120,654 @@ impl VersionSet {
     ///
     /// # Arguments
     ///
-    /// * `icmp`:
-    /// * `disjoint_sorted_files`:
-    /// * `files`:
-    /// * `smallest_user_key`:
-    /// * `largest_user_key`:
+    /// * `icmp`: InternalKeyComparator
+    /// * `disjoint_sorted_files`: files 是否有序且互不重叠, 为 true 时使用二分查找
+    /// * `files`: 文件
+    /// * `smallest_user_key`: 范围的起始 user key
+    /// * `largest_user_key`: 范围的结束 user key
     ///
     /// returns: bool
     ///
     /// # Examples
     ///
     /// ```
+    /// use level_db_rust::db::db_format::InternalKeyComparator;
+    /// use level_db_rust::db::version_set::VersionSet;
     ///
+    /// let icmp = InternalKeyComparator::default();
+    /// assert!(!VersionSet::some_file_overlaps_range(&icmp, true, &[], Some(b"a"), None));
     /// ```
-    fn some_file_overlaps_range(icmp: InternalKeyComparator, disjoint_sorted_files:bool,
-                                files:&Vec<FileMetaData>, smallest_user_key:&Slice,largest_user_key:&Slice) -> bool {
-        todo!()
+    pub fn some_file_overlaps_range(icmp: &InternalKeyComparator, disjoint_sorted_files: bool,
+                                    files: &[Arc<FileMetaData>], smallest_user_key: Option<&[u8]>,
+                                    largest_user_key: Option<&[u8]>) -> bool {
+        let ucmp = icmp.user_comparator();
+        if !disjoint_sorted_files {
+            // Need to check against all files
+            return files.iter().any(|f| {
+                !after_file(ucmp.as_ref(), smallest_user_key, f) && !before_file(ucmp.as_ref(), largest_user_key, f)
+            });
+        }
+
+        // Binary search over file list
+        let mut index = 0;
+        if let Some(smallest_user_key) = smallest_user_key {
+            // Find the earliest possible internal key for smallest_user_key
+            let small_key = InternalKey::new(Slice::from_buf(smallest_user_key), K_MAX_SEQUENCE_NUMBER as u64,
+                                             Config::K_VALUE_TYPE_FOR_SEEK);
+            index = Self::find_file(icmp, files, small_key.encode());
+        }
+
+        if index >= files.len() {
+            // beginning of range is after all files, so no overlap.
+            return false;
+        }
+
+        !before_file(ucmp.as_ref(), largest_user_key, &files[index])
+    }
+
+    /// 当前 Version 是否需要 compaction
+    ///
+    /// Returns true iff some level needs a compaction.
+    pub fn needs_compaction(&self) -> bool {
+        let v = &self.current_;
+        v.compaction_score_ >= 1.0 || v.file_to_compact_.lock().unwrap().is_some()
+    }
+
+    /// 选择下一次自动 compaction 的层级及输入文件, 不需要 compaction 时返回 None。
+    ///
+    /// 优先处理文件过多或者数据量过大的层(size compaction), 其次是 seek 次数过多的文件(seek compaction)
+    ///
+    /// Pick level and inputs for a new compaction.
+    /// Returns nullptr if there is no compaction to be done.
+    /// Otherwise returns a pointer to a heap-allocated object that
+    /// describes the compaction.  Caller should delete the result.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// if let Some(c) = versions.pick_compaction() {
+    ///     // do compaction work
+    /// }
+    /// ```
+    pub fn pick_compaction(&mut self) -> Option<Compaction> {
+        let current = self.current_.clone();
+
+        // We prefer compactions triggered by too much data in a level over
+        // the compactions triggered by seeks.
+        let size_compaction = current.compaction_score_ >= 1.0;
+        let seek_compaction = current.file_to_compact_.lock().unwrap().clone();
+        let mut c;
+        if size_compaction {
+            let level = current.compaction_level_ as usize;
+            assert!(level + 1 < Config::K_NUM_LEVELS);
+            c = Compaction::new(&self.options_, level, current.clone());
+
+            // Pick the first file that comes after compact_pointer_[level]
+            let compact_pointer = &self.compact_pointer_[level];
+            if let Some(f) = current.files_[level].iter().find(|f| {
+                compact_pointer.empty()
+                    || self.icmp_.compare_key(f.get_largest().encode(), compact_pointer) == Ordering::Greater
+            }) {
+                c.inputs_[0].push(f.clone());
+            }
+            if c.inputs_[0].is_empty() {
+                // Wrap-around to the beginning of the key space
+                c.inputs_[0].push(current.files_[level][0].clone());
+            }
+        } else if let Some((f, level)) = seek_compaction {
+            c = Compaction::new(&self.options_, level, current.clone());
+            c.inputs_[0].push(f);
+        } else {
+            return None;
+        }
+
+        // Files in level 0 may overlap each other, so pick up all overlapping ones
+        if c.level() == 0 {
+            let (smallest, largest) = self.get_range(&c.inputs_[0]);
+            // Note that the next call will discard the file we placed in
+            // c->inputs_[0] earlier and replace it with an overlapping set
+            // which will include the picked file.
+            c.inputs_[0] = current.get_overlapping_inputs(0, Some(&smallest), Some(&largest));
+            assert!(!c.inputs_[0].is_empty());
+        }
+
+        self.setup_other_inputs(&mut c);
+        Some(c)
+    }
+
+    /// 返回 level 层中与 user key 范围 [begin, end] 重叠的文件的 compaction, 没有重叠的文件时返回 None。
+    ///
+    /// level > 0 时一次最多选取约 max_file_size 字节的文件, 避免一次 compaction 的数据量过大,
+    /// 调用方需要以返回的最后一个文件的 largest 为起点继续 compaction
+    ///
+    /// Return a compaction object for compacting the range [begin,end] in
+    /// the specified level.  Returns nullptr if there is nothing in that
+    /// level that overlaps the specified range.  Caller should delete
+    /// the result.
+    ///
+    /// # Arguments
+    ///
+    /// * `level`: 层级
+    /// * `begin`: None 表示比所有的 key 都小
+    /// * `end`: None 表示比所有的 key 都大
+    ///
+    /// returns: Option<Compaction>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let c = versions.compact_range(1, Some(&begin), None);
+    /// ```
+    pub fn compact_range(&mut self, level: usize, begin: Option<&InternalKey>,
+                         end: Option<&InternalKey>) -> Option<Compaction> {
+        let current = self.current_.clone();
+        let mut inputs = current.get_overlapping_inputs(level, begin, end);
+        if inputs.is_empty() {
+            return None;
+        }
+
+        // Avoid compacting too much in one shot in case the range is large.
+        // But we cannot do this for level-0 since level-0 files can overlap
+        // and we must not pick one file and drop another older file if the
+        // two files overlap.
+        if level > 0 {
+            let limit = max_file_size_for_level(&self.options_, level);
+            let mut total = 0;
+            if let Some(i) = inputs.iter().position(|f| {
+                total += f.get_file_size();
+                total >= limit
+            }) {
+                inputs.truncate(i + 1);
+            }
+        }
+
+        let mut c = Compaction::new(&self.options_, level, current);
+        c.inputs_[0] = inputs;
+        self.setup_other_inputs(&mut c);
+        Some(c)
+    }
+
+    /// 创建读取 compaction 所有输入文件的迭代器
+    ///
+    /// Create an iterator that reads over the compaction inputs for "*c".
+    /// The caller should delete the iterator when no longer needed.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut input = versions.make_input_iterator(&c);
+    /// input.seek_to_first();
+    /// ```
+    pub fn make_input_iterator(&self, c: &Compaction) -> Box<dyn DataIterator> {
+        let options = ReadOptions {
+            verify_checksums: self.options_.paranoid_checks,
+            fill_cache: false,
+            snapshot: None,
+            prefix_same_as_start: false,
+        };
+
+        // Level-0 files have to be merged together.  For other levels,
+        // we will make a concatenating iterator per level.
+        let mut list = vec![];
+        for which in 0..2 {
+            if c.inputs_[which].is_empty() {
+                continue;
+            }
+            if c.level() + which == 0 {
+                for f in &c.inputs_[which] {
+                    list.push(self.table_cache_.new_iterator(&options, f.get_number(), f.get_file_size()));
+                }
+            } else {
+                // Create concatenating iterator for the files from this level
+                let table_cache = self.table_cache_.clone();
+                let verify_checksums = options.verify_checksums;
+                list.push(new_two_level_iterator(
+                    Box::new(LevelFileNumIterator::new(self.icmp_.clone(), c.inputs_[which].clone())),
+                    Box::new(move |file_value| {
+                        let options = ReadOptions {
+                            verify_checksums,
+                            fill_cache: false,
+                            snapshot: None,
+                            prefix_same_as_start: false,
+                        };
+                        get_file_iterator(&table_cache, &options, file_value)
+                    })));
+            }
+        }
+        new_merging_iterator(Arc::new(self.icmp_.clone()), list)
+    }
+
+    /// 每一层的文件数量, 用于日志输出
+    ///
+    /// Return a human-readable short (single-line) summary of the number
+    /// of files per level.  Uses *scratch as backing store.
+    pub fn level_summary(&self) -> String {
+        let counts: Vec<String> = self.current_.files_.iter().map(|files| files.len().to_string()).collect();
+        format!("files[ {} ]", counts.join(" "))
+    }
+
+    /// 返回 inputs 中所有文件的最小的 smallest 及最大的 largest
+    ///
+    /// Stores the minimal range that covers all entries in inputs in
+    /// *smallest, *largest.
+    /// REQUIRES: inputs is not empty
+    fn get_range(&self, inputs: &[Arc<FileMetaData>]) -> (InternalKey, InternalKey) {
+        assert!(!inputs.is_empty());
+        let mut smallest = inputs[0].get_smallest();
+        let mut largest = inputs[0].get_largest();
+        for f in &inputs[1..] {
+            if self.icmp_.compare_internal_key(f.get_smallest(), smallest) == Ordering::Less {
+                smallest = f.get_smallest();
+            }
+            if self.icmp_.compare_internal_key(f.get_largest(), largest) == Ordering::Greater {
+                largest = f.get_largest();
+            }
+        }
+        (smallest.clone(), largest.clone())
+    }
+
+    /// Stores the minimal range that covers all entries in inputs1 and inputs2
+    /// in *smallest, *largest.
+    /// REQUIRES: inputs is not empty
+    fn get_range2(&self, inputs1: &[Arc<FileMetaData>], inputs2: &[Arc<FileMetaData>]) -> (InternalKey, InternalKey) {
+        let all: Vec<Arc<FileMetaData>> = inputs1.iter().chain(inputs2.iter()).cloned().collect();
+        self.get_range(&all)
+    }
+
+    /// 根据 level 层的输入文件选取 level + 1 层的输入文件, 在不增加 level + 1 层文件的前提下尽量扩大 level 层的输入,
+    /// 并记录与 grandparent 层重叠的文件
+    fn setup_other_inputs(&mut self, c: &mut Compaction) {
+        let current = self.current_.clone();
+        let level = c.level();
+
+        add_boundary_inputs(&self.icmp_, &current.files_[level], &mut c.inputs_[0]);
+        let (smallest, mut largest) = self.get_range(&c.inputs_[0]);
+
+        c.inputs_[1] = current.get_overlapping_inputs(level + 1, Some(&smallest), Some(&largest));
+        add_boundary_inputs(&self.icmp_, &current.files_[level + 1], &mut c.inputs_[1]);
+
+        // Get entire range covered by compaction
+        let (mut all_start, mut all_limit) = self.get_range2(&c.inputs_[0], &c.inputs_[1]);
+
+        // See if we can grow the number of inputs in "level" without
+        // changing the number of "level+1" files we pick up.
+        if !c.inputs_[1].is_empty() {
+            let mut expanded0 = current.get_overlapping_inputs(level, Some(&all_start), Some(&all_limit));
+            add_boundary_inputs(&self.icmp_, &current.files_[level], &mut expanded0);
+            let inputs0_size = total_file_size(&c.inputs_[0]);
+            let inputs1_size = total_file_size(&c.inputs_[1]);
+            let expanded0_size = total_file_size(&expanded0);
+            if expanded0.len() > c.inputs_[0].len()
+                && inputs1_size + expanded0_size < expanded_compaction_byte_size_limit(&self.options_) {
+                let (new_start, new_limit) = self.get_range(&expanded0);
+                let mut expanded1 = current.get_overlapping_inputs(level + 1, Some(&new_start), Some(&new_limit));
+                add_boundary_inputs(&self.icmp_, &current.files_[level + 1], &mut expanded1);
+                if expanded1.len() == c.inputs_[1].len() {
+                    log_info!(self.options_.info_log, "Expanding@{} {}+{} ({}+{} bytes) to {}+{} ({}+{} bytes)",
+                              level, c.inputs_[0].len(), c.inputs_[1].len(), inputs0_size, inputs1_size,
+                              expanded0.len(), expanded1.len(), expanded0_size, inputs1_size);
+                    largest = new_limit;
+                    c.inputs_[0] = expanded0;
+                    c.inputs_[1] = expanded1;
+                    (all_start, all_limit) = self.get_range2(&c.inputs_[0], &c.inputs_[1]);
+                }
+            }
+        }
+
+        // Compute the set of grandparent files that overlap this compaction
+        // (parent == level+1; grandparent == level+2)
+        if level + 2 < Config::K_NUM_LEVELS {
+            c.grandparents_ = current.get_overlapping_inputs(level + 2, Some(&all_start), Some(&all_limit));
+        }
+
+        // Update the place where we will do the next compaction for this level.
+        // We update this immediately instead of waiting for the VersionEdit
+        // to be applied so that if the compaction fails, we will try a different
+        // key range next time.
+        self.compact_pointer_[level] = Slice::from_buf(largest.encode());
+        c.edit_.set_compact_pointer(level as u32, largest);
     }
 }
 
 impl Compaction {
-    // fn create(options: Options, level: u32) -> Self {
-    //     Self {
-    //
-    //     }
-    // }
+    fn new(options: &OptionsPtr, level: usize, input_version: Arc<Version>) -> Self {
+        Self {
+            level_: level,
+            max_output_file_size_: max_file_size_for_level(options, level),
+            input_version_: Some(input_version),
+            edit_: VersionEdit::new(),
+            inputs_: [vec![], vec![]],
+            grandparents_: vec![],
+            grandparent_index_: 0,
+            seen_key_: false,
+            overlapped_bytes_: 0,
+            level_ptrs_: [0; Config::K_NUM_LEVELS],
+        }
+    }
+
+    /// Return the level that is being compacted.  Inputs from "level"
+    /// and "level+1" will be merged to produce a set of "level+1" files.
+    pub fn level(&self) -> usize {
+        self.level_
+    }
+
+    /// Return the object that holds the edits to the descriptor done
+    /// by this compaction.
+    pub fn edit(&mut self) -> &mut VersionEdit {
+        &mut self.edit_
+    }
+
+    /// "which" must be either 0 or 1
+    pub fn num_input_files(&self, which: usize) -> usize {
+        self.inputs_[which].len()
+    }
+
+    /// Return the ith input file at "level()+which" ("which" must be 0 or 1).
+    pub fn input(&self, which: usize, i: usize) -> &Arc<FileMetaData> {
+        &self.inputs_[which][i]
+    }
+
+    /// level() + which 层的所有输入文件
+    pub fn inputs(&self, which: usize) -> &[Arc<FileMetaData>] {
+        &self.inputs_[which]
+    }
+
+    /// Maximum size of files to build during this compaction.
+    pub fn max_output_file_size(&self) -> u64 {
+        self.max_output_file_size_
+    }
+
+    /// 本次 compaction 基于的 Version
+    pub fn input_version(&self) -> &Arc<Version> {
+        self.input_version_.as_ref().expect("inputs have been released")
+    }
+
+    /// 只需要把文件移动到下一层, 不需要合并或者拆分
+    ///
+    /// Is this a trivial compaction that can be implemented by just
+    /// moving a single input file to the next level (no merging or splitting)
+    pub fn is_trivial_move(&self) -> bool {
+        let options = &self.input_version().options_;
+        // Avoid a move if there is lots of overlapping grandparent data.
+        // Otherwise, the move could create a parent file that will require
+        // a very expensive merge later on.
+        self.num_input_files(0) == 1 && self.num_input_files(1) == 0
+            && total_file_size(&self.grandparents_) <= max_grand_parent_overlap_bytes(options)
+    }
+
+    /// 将所有输入文件作为删除操作加入 edit
+    ///
+    /// Add all inputs to this compaction as delete operations to *edit.
+    pub fn add_input_deletions(&mut self) {
+        for which in 0..2 {
+            for f in &self.inputs_[which] {
+                self.edit_.delete_file((self.level_ + which) as u32, f.get_number());
+            }
+        }
+    }
+
+    /// user_key 在 level + 2 及更下面的层中都不存在时返回 true, 此时删除标记可以直接丢弃
+    ///
+    /// Returns true if the information we have available guarantees that
+    /// the compaction is producing data in "level+1" for which no data exists
+    /// in levels greater than "level+1".
+    pub fn is_base_level_for_key(&mut self, user_key: &[u8]) -> bool {
+        // Maybe use binary search to find right entry instead of linear search?
+        let input_version = self.input_version_.as_ref().expect("inputs have been released");
+        let user_cmp = input_version.icmp_.user_comparator();
+        for lvl in self.level_ + 2..Config::K_NUM_LEVELS {
+            let files = &input_version.files_[lvl];
+            while self.level_ptrs_[lvl] < files.len() {
+                let f = &files[self.level_ptrs_[lvl]];
+                if user_cmp.compare(user_key, f.get_largest().user_key()) != Some(Ordering::Greater) {
+                    // We've advanced far enough
+                    if user_cmp.compare(user_key, f.get_smallest().user_key()) != Some(Ordering::Less) {
+                        // Key falls in this file's range, so definitely not base level
+                        return false;
+                    }
+                    break;
+                }
+                self.level_ptrs_[lvl] += 1;
+            }
+        }
+        true
+    }
+
+    /// 当前输出文件与 grandparent 层重叠的数据过多时返回 true, 此时需要切换到新的输出文件
+    ///
+    /// Returns true iff we should stop building the current output
+    /// before processing "internal_key".
+    pub fn should_stop_before(&mut self, internal_key: &[u8]) -> bool {
+        let input_version = self.input_version_.as_ref().expect("inputs have been released");
+        let icmp = &input_version.icmp_;
+        // Scan to find earliest grandparent file that contains key.
+        while self.grandparent_index_ < self.grandparents_.len()
+            && icmp.compare_key(internal_key, self.grandparents_[self.grandparent_index_].get_largest().encode())
+            == Ordering::Greater {
+            if self.seen_key_ {
+                self.overlapped_bytes_ += self.grandparents_[self.grandparent_index_].get_file_size();
+            }
+            self.grandparent_index_ += 1;
+        }
+        self.seen_key_ = true;
+
+        if self.overlapped_bytes_ > max_grand_parent_overlap_bytes(&input_version.options_) {
+            // Too much overlap for current output; start new output
+            self.overlapped_bytes_ = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 释放输入的 Version
+    ///
+    /// Release the input version for the compaction, once the compaction
+    /// is successful.
+    pub fn release_inputs(&mut self) {
+        self.input_version_ = None;
+    }
 }
 
-// todo 等到 Iterator 接口
-// impl Iterator for LevelFileNumIterator {
-//
-// }
+impl LevelFileNumIterator {
+    fn new(icmp: InternalKeyComparator, flist: Vec<Arc<FileMetaData>>) -> Self {
+        // Marks as invalid
+        let index = flist.len();
+        Self {
+            icmp_: icmp,
+            flist_: flist,
+            index_: index,
+            value_buf_: [0; 16],
+        }
+    }
+
+    /// 移动到 index 处, 并更新 value_buf_
+    fn set_index(&mut self, index: usize) {
+        self.index_ = index;
+        if self.index_ < self.flist_.len() {
+            let f = &self.flist_[self.index_];
+            self.value_buf_[..8].copy_from_slice(&f.get_number().to_le_bytes());
+            self.value_buf_[8..].copy_from_slice(&f.get_file_size().to_le_bytes());
+        }
+    }
+}
+
+impl DataIterator for LevelFileNumIterator {
+    fn valid(&self) -> bool {
+        self.index_ < self.flist_.len()
+    }
+
+    fn seek_to_first(&mut self) {
+        self.set_index(0);
+    }
+
+    fn seek_to_last(&mut self) {
+        let index = if self.flist_.is_empty() { 0 } else { self.flist_.len() - 1 };
+        self.set_index(index);
+    }
+
+    fn seek(&mut self, target: &Slice) {
+        let index = VersionSet::find_file(&self.icmp_, &self.flist_, target);
+        self.set_index(index);
+    }
+
+    fn next(&mut self) {
+        assert!(self.valid());
+        self.set_index(self.index_ + 1);
+    }
+
+    fn pre(&mut self) {
+        assert!(self.valid());
+        if self.index_ == 0 {
+            // Marks as invalid
+            self.set_index(self.flist_.len());
+        } else {
+            self.set_index(self.index_ - 1);
+        }
+    }
+
+    fn key(&self) -> UnsafeSlice {
+        assert!(self.valid());
+        let key = self.flist_[self.index_].get_largest().encode();
+        unsafe { UnsafeSlice::from_raw_parts(key.as_ptr(), key.len()) }
+    }
+
+    fn value(&self) -> UnsafeSlice {
+        assert!(self.valid());
+        unsafe { UnsafeSlice::from_raw_parts(self.value_buf_.as_ptr(), self.value_buf_.len()) }
+    }
+
+    fn status(&self) -> Status {
+        Status::default()
+    }
+}
 
 impl Builder {
-    
+    /// 以 base 为基础创建 Builder
+    pub fn new(icmp: InternalKeyComparator, base: Arc<Version>) -> Self {
+        Self {
+            cmp_: BySmallestKey { internal_comparator: icmp },
+            base_: base,
+            levels_: (0..Config::K_NUM_LEVELS).map(|_| LevelState {
+                deleted_files: BTreeSet::new(),
+                added_files: vec![],
+            }).collect(),
+        }
+    }
+
+    /// 将 edit 中的变更记录到 Builder 中
+    ///
+    /// Apply all of the edits in *edit to the current state.
+    ///
+    /// # Arguments
+    ///
+    /// * `edit`: 变更
+    /// * `compact_pointer`: VersionSet 中每一层的 compaction 起始位置, 会被 edit 中的 compact pointer 更新
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut builder = Builder::new(icmp, current);
+    /// builder.apply(&edit, &mut compact_pointer);
+    /// ```
+    pub fn apply(&mut self, edit: &VersionEdit, compact_pointer: &mut [Slice; Config::K_NUM_LEVELS]) {
+        // Update compaction pointers
+        for (level, key) in edit.compact_pointers() {
+            compact_pointer[*level as usize] = Slice::from_buf(key.encode());
+        }
+
+        // Delete files
+        for (level, number) in edit.deleted_files() {
+            self.levels_[*level as usize].deleted_files.insert(*number);
+        }
+
+        // Add new files
+        for (level, file) in edit.new_files() {
+            let f = file.clone();
+            // We arrange to automatically compact this file after
+            // a certain number of seeks.  Let's assume:
+            //   (1) One seek costs 10ms
+            //   (2) Writing or reading 1MB costs 10ms (100MB/s)
+            //   (3) A compaction of 1MB does 25MB of IO:
+            //         1MB read from this level
+            //         10-12MB read from next level (boundaries may be misaligned)
+            //         10-12MB written to next level
+            // This implies that 25 seeks cost the same as the compaction
+            // of 1MB of data.  I.e., one seek costs approximately the
+            // same as the compaction of 40KB of data.  We are a little
+            // conservative and allow approximately one seek for every 16KB
+            // of data before triggering a compaction.
+            f.set_allowed_seeks(((f.get_file_size() / 16384) as i32).max(100));
+
+            let state = &mut self.levels_[*level as usize];
+            state.deleted_files.remove(&f.get_number());
+            // 按 BySmallestKey 的顺序插入, 相同的文件只保留一个
+            let pos = state.added_files.partition_point(|x| self.cmp_.operator(x, &f));
+            if pos == state.added_files.len() || self.cmp_.compare(&state.added_files[pos], &f) != Ordering::Equal {
+                state.added_files.insert(pos, Arc::new(f));
+            }
+        }
+    }
+
+    /// 将 base 与记录的变更合并, 结果保存到 v 中
+    ///
+    /// Save the current state in *v.
+    pub fn save_to(&self, v: &mut Version) {
+        for level in 0..Config::K_NUM_LEVELS {
+            // Merge the set of added files with the set of pre-existing files.
+            // Drop any deleted files.  Store the result in *v.
+            let base_files = &self.base_.files_[level];
+            let added_files = &self.levels_[level].added_files;
+            v.files_[level].reserve(base_files.len() + added_files.len());
+
+            let mut base_iter = 0;
+            for added_file in added_files {
+                // Add all smaller files listed in base_
+                let bpos = base_iter + base_files[base_iter..]
+                    .partition_point(|f| !self.cmp_.operator(added_file, f));
+                while base_iter < bpos {
+                    self.maybe_add_file(v, level, &base_files[base_iter]);
+                    base_iter += 1;
+                }
+                self.maybe_add_file(v, level, added_file);
+            }
+
+            // Add remaining base files
+            for f in &base_files[base_iter..] {
+                self.maybe_add_file(v, level, f);
+            }
+
+            // Make sure there is no overlap in levels > 0
+            if cfg!(debug_assertions) && level > 0 {
+                for pair in v.files_[level].windows(2) {
+                    let prev_end = pair[0].get_largest();
+                    let this_begin = pair[1].get_smallest();
+                    if self.cmp_.internal_comparator.compare_internal_key(prev_end, this_begin) != Ordering::Less {
+                        panic!("overlapping ranges in same level {} vs. {}",
+                               prev_end.debug_string(), this_begin.debug_string());
+                    }
+                }
+            }
+        }
+    }
+
+    fn maybe_add_file(&self, v: &mut Version, level: usize, f: &Arc<FileMetaData>) {
+        if self.levels_[level].deleted_files.contains(&f.get_number()) {
+            // File is deleted: do nothing
+            return;
+        }
+        let files = &mut v.files_[level];
+        if level > 0 {
+            if let Some(last) = files.last() {
+                // Must not overlap
+                assert_eq!(Ordering::Less, self.cmp_.internal_comparator.compare_internal_key(
+                    last.get_largest(), f.get_smallest()));
+            }
+        }
+        files.push(f.clone());
+    }
 }
 
 impl BySmallestKey {
@@ -401,17 +1787,147 @@ impl BySmallestKey {
     ///
     /// ```
     pub fn operator(&self, f1: &FileMetaData, f2: &FileMetaData) -> bool {
+        self.compare(f1, f2) == Ordering::Less
+    }
+
+    /// 先按 smallest key 排序, smallest key 相同时按文件号排序
+    pub fn compare(&self, f1: &FileMetaData, f2: &FileMetaData) -> Ordering {
         // line 607
-        let r: u32 = self.internal_comparator.compare_internal_key(
+        let r = self.internal_comparator.compare_internal_key(
             f1.get_smallest(),
             f2.get_smallest()
         );
 
-        if r != 0 {
-            return r < 0;
+        if r != Ordering::Equal {
+            return r;
         }
 
         // Break ties by file number
-        f1.get_number() < f2.get_number()
+        f1.get_number().cmp(&f2.get_number())
+    }
+}
+
+/// level 层文件总大小的上限
+fn max_bytes_for_level(mut level: usize) -> f64 {
+    // Note: the result for level zero is not really used since we set
+    // the level-0 compaction threshold based on number of files.
+
+    // Result for both level-0 and level-1
+    let mut result = 10. * 1048576.0;
+    while level > 1 {
+        result *= 10.0;
+        level -= 1;
+    }
+    result
+}
+
+fn total_file_size(files: &[Arc<FileMetaData>]) -> u64 {
+    files.iter().map(|f| f.get_file_size()).sum()
+}
+
+/// 生成的文件与 grandparent 层(level + 2)重叠的最大字节数, 超过后停止把 memtable 的输出往下层推
+///
+/// Maximum bytes of overlaps in grandparent (i.e., level+2) before we
+/// stop building a single file in a level->level+1 compaction.
+fn max_grand_parent_overlap_bytes(options: &OptionsPtr) -> u64 {
+    10 * options.max_file_size as u64
+}
+
+/// user_key 是否在文件 f 的范围之后, None 表示比所有的 key 都小
+fn after_file(ucmp: &dyn Comparator, user_key: Option<&[u8]>, f: &FileMetaData) -> bool {
+    // None user_key occurs before all keys and is therefore never after *f
+    user_key.is_some_and(|k| ucmp.compare(k, f.get_largest().user_key()) == Some(Ordering::Greater))
+}
+
+/// user_key 是否在文件 f 的范围之前, None 表示比所有的 key 都大
+fn before_file(ucmp: &dyn Comparator, user_key: Option<&[u8]>, f: &FileMetaData) -> bool {
+    // None user_key occurs after all keys and is therefore never before *f
+    user_key.is_some_and(|k| ucmp.compare(k, f.get_smallest().user_key()) == Some(Ordering::Less))
+}
+
+/// compaction 输出的单个文件的大小上限
+///
+/// We could vary per level to reduce number of files?
+fn max_file_size_for_level(options: &OptionsPtr, _level: usize) -> u64 {
+    options.max_file_size as u64
+}
+
+/// Maximum number of bytes in all compacted files.  We avoid expanding
+/// the lower level file set of a compaction if it would make the
+/// total compaction cover more than this many bytes.
+fn expanded_compaction_byte_size_limit(options: &OptionsPtr) -> u64 {
+    25 * options.max_file_size as u64
+}
+
+/// 由 LevelFileNumIterator 的 value(文件号及文件大小) 生成 table 的迭代器
+fn get_file_iterator(table_cache: &TableCache, options: &ReadOptions, file_value: &[u8]) -> Box<dyn DataIterator> {
+    if file_value.len() != 16 {
+        return new_error_iterator(
+            LevelError::corruption_string("FileReader invoked with unexpected value", ""));
+    }
+    let number = u64::from_le_bytes(file_value[..8].try_into().unwrap());
+    let file_size = u64::from_le_bytes(file_value[8..].try_into().unwrap());
+    table_cache.new_iterator(options, number, file_size)
+}
+
+/// 返回 files 中最大的 largest, files 为空时返回 None
+///
+/// Finds the largest key in a vector of files. Returns true if files is not
+/// empty.
+fn find_largest_key(icmp: &InternalKeyComparator, files: &[Arc<FileMetaData>]) -> Option<InternalKey> {
+    files.iter()
+        .map(|f| f.get_largest())
+        .max_by(|a, b| icmp.compare_internal_key(a, b))
+        .cloned()
+}
+
+/// Finds minimum file b2=(l2, u2) in level file for which l2 > u1 and
+/// user_key(l2) = user_key(u1)
+fn find_smallest_boundary_file(icmp: &InternalKeyComparator, level_files: &[Arc<FileMetaData>],
+                               largest_key: &InternalKey) -> Option<Arc<FileMetaData>> {
+    let user_cmp = icmp.user_comparator();
+    let mut smallest_boundary_file: Option<&Arc<FileMetaData>> = None;
+    for f in level_files {
+        if icmp.compare_internal_key(f.get_smallest(), largest_key) == Ordering::Greater
+            && user_cmp.compare(f.get_smallest().user_key(), largest_key.user_key()) == Some(Ordering::Equal)
+            && smallest_boundary_file.is_none_or(|b| {
+                icmp.compare_internal_key(f.get_smallest(), b.get_smallest()) == Ordering::Less
+            }) {
+            smallest_boundary_file = Some(f);
+        }
+    }
+    smallest_boundary_file.cloned()
+}
+
+/// 把 level_files 中与 compaction_files 的边界 user key 相同的文件加入 compaction_files。
+///
+/// 同一个 user key 的多个版本被拆分到两个文件中时, 如果只 compact 了包含新版本的文件,
+/// 旧版本会留在上层, 之后的读取会读到旧的数据
+///
+/// Extracts the largest file b1 from |compaction_files| and then searches for a
+/// b2 in |level_files| for which user_key(u1) = user_key(l2). If it finds such a
+/// file b2 (known as a boundary file) it adds it to |compaction_files| and then
+/// searches again using this new upper bound.
+///
+/// If there are two blocks, b1=(l1, u1) and b2=(l2, u2) and
+/// user_key(u1) = user_key(l2), and if we compact b1 but not b2 then a
+/// subsequent get operation will yield an incorrect result because it will
+/// return the record from b2 in level i rather than from b1 because it searches
+/// level by level for records matching the supplied user key.
+///
+/// parameters:
+///   in     level_files:      List of files to search for boundary files.
+///   in/out compaction_files: List of files to extend by adding boundary files.
+pub(crate) fn add_boundary_inputs(icmp: &InternalKeyComparator, level_files: &[Arc<FileMetaData>],
+                                  compaction_files: &mut Vec<Arc<FileMetaData>>) {
+    // Quick return if compaction_files is empty.
+    let Some(mut largest_key) = find_largest_key(icmp, compaction_files) else {
+        return;
+    };
+
+    // If a boundary file was found advance largest_key, otherwise we're done.
+    while let Some(smallest_boundary_file) = find_smallest_boundary_file(icmp, level_files, &largest_key) {
+        largest_key = smallest_boundary_file.get_largest().clone();
+        compaction_files.push(smallest_boundary_file);
     }
-}
\ No newline at end of file
+}
@@ -1,9 +1,757 @@
-
+#[cfg(test)]
 mod test {
+    use std::fs;
+    use std::collections::BTreeSet;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::thread;
+    use crate::db::db_format::{InternalKey, InternalKeyComparator, ValueType};
+    use crate::db::file_meta_data::FileMetaData;
+    use crate::db::filename::FileName;
+    use crate::db::table_cache::TableCache;
+    use crate::db::version_edit::VersionEdit;
+    use crate::db::version_set::{add_boundary_inputs, Version, VersionSet};
+    use crate::db::db_format::Config;
+    use crate::util::options::{Options, OptionsPtr};
+    use crate::util::random::Random;
+    use crate::util::slice::Slice;
+    use crate::util::Result;
+
     #[test]
     fn test_() {
 
         println!("get_name: {}", "a");
 
     }
-}
\ No newline at end of file
+
+    fn ikey(user_key: &str, seq: u64) -> InternalKey {
+        InternalKey::new(Slice::from(user_key), seq, ValueType::KTypeValue)
+    }
+
+    fn new_db_dir(name: &str) -> PathBuf {
+        let dbname = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dbname);
+        fs::create_dir_all(&dbname).unwrap();
+        dbname
+    }
+
+    fn new_version_set(dbname: &Path, options: Options) -> VersionSet {
+        let icmp = InternalKeyComparator::new(options.cmp.clone());
+        let options: OptionsPtr = Arc::new(Box::new(options));
+        let table_cache = Arc::new(TableCache::new(dbname, options.clone(), 100));
+        VersionSet::new(dbname, options, table_cache, icmp)
+    }
+
+    /// 每一层的文件号
+    fn level_files(versions: &VersionSet) -> Vec<Vec<u64>> {
+        let current = versions.current();
+        (0..7).map(|level| current.files(level).iter().map(|f| f.get_number()).collect()).collect()
+    }
+
+    #[test]
+    fn test_log_and_apply_and_recover() -> Result<()> {
+        let dbname = new_db_dir("level_db_rust_version_set_recover_test");
+        let mut versions = new_version_set(&dbname, Options::default());
+
+        let mut edit = VersionEdit::new();
+        let log_number = versions.new_file_number();
+        edit.set_log_number(log_number);
+        edit.add_file(0, 10, 100, ikey("a", 1), ikey("c", 2));
+        edit.add_file(1, 11, 200, ikey("m", 3), ikey("p", 4));
+        edit.add_file(1, 12, 300, ikey("d", 5), ikey("f", 6));
+        versions.log_and_apply(&mut edit)?;
+        versions.set_last_sequence(6);
+
+        let mut edit = VersionEdit::new();
+        edit.delete_file(0, 10);
+        edit.add_file(2, 13, 400, ikey("a", 1), ikey("c", 2));
+        edit.set_compact_pointer(1, ikey("f", 6));
+        versions.log_and_apply(&mut edit)?;
+
+        // level 1 中的文件按照 smallest key 排序
+        assert_eq!(vec![vec![], vec![12, 11], vec![13], vec![], vec![], vec![], vec![]], level_files(&versions));
+        assert_eq!(300 + 200, versions.num_level_bytes(1));
+        assert_eq!(log_number, versions.log_number());
+
+        // CURRENT 指向新创建的 MANIFEST
+        let manifest_number = versions.manifest_file_number();
+        let current = fs::read_to_string(FileName::current_file_name(&dbname))?;
+        assert_eq!(format!("MANIFEST-{:06}\n", manifest_number), current);
+
+        let mut recovered = new_version_set(&dbname, Options::default());
+        recovered.recover()?;
+        assert_eq!(level_files(&versions), level_files(&recovered));
+        assert_eq!(6, recovered.last_sequence());
+        assert_eq!(log_number, recovered.log_number());
+        let current = recovered.current();
+        let f = &current.files(2)[0];
+        assert_eq!(400, f.get_file_size());
+        assert_eq!(&ikey("a", 1), f.get_smallest());
+        assert_eq!(&ikey("c", 2), f.get_largest());
+        // 恢复后分配的文件号不会与已经使用的文件号冲突
+        assert!(recovered.new_file_number() > manifest_number);
+
+        // 恢复后第一次 log_and_apply 会创建新的 MANIFEST, 其中包含完整的快照
+        let mut edit = VersionEdit::new();
+        edit.add_file(3, 14, 500, ikey("x", 7), ikey("z", 8));
+        recovered.log_and_apply(&mut edit)?;
+        assert_ne!(manifest_number, recovered.manifest_file_number());
+
+        let mut again = new_version_set(&dbname, Options::default());
+        again.recover()?;
+        assert_eq!(vec![vec![], vec![12, 11], vec![13], vec![14], vec![], vec![], vec![]], level_files(&again));
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest_rollover() -> Result<()> {
+        let dbname = new_db_dir("level_db_rust_version_set_rollover_test");
+        let mut options = Options::default();
+        // 每次写入后 MANIFEST 都超过大小限制
+        options.max_file_size = 1;
+        let mut versions = new_version_set(&dbname, options);
+
+        let mut manifest_numbers = vec![];
+        for i in 0..3 {
+            let mut edit = VersionEdit::new();
+            let number = versions.new_file_number();
+            edit.add_file(0, number, 100, ikey(&format!("k{}", i), 1), ikey(&format!("k{}", i), 1));
+            versions.log_and_apply(&mut edit)?;
+            manifest_numbers.push(versions.manifest_file_number());
+        }
+        manifest_numbers.dedup();
+        assert_eq!(3, manifest_numbers.len());
+
+        // 最新的 MANIFEST 中的快照包含之前所有的文件
+        let mut recovered = new_version_set(&dbname, Options::default());
+        recovered.recover()?;
+        assert_eq!(3, recovered.num_level_files(0));
+        assert_eq!(level_files(&versions), level_files(&recovered));
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_recover_errors() -> Result<()> {
+        let dbname = new_db_dir("level_db_rust_version_set_recover_errors_test");
+
+        // 没有 CURRENT 文件
+        let mut versions = new_version_set(&dbname, Options::default());
+        assert!(versions.recover().is_err());
+
+        fs::write(FileName::current_file_name(&dbname), "MANIFEST-000009")?;
+        let status = versions.recover().unwrap_err();
+        assert!(status.is_corruption());
+
+        fs::write(FileName::current_file_name(&dbname), "MANIFEST-000009\n")?;
+        let status = versions.recover().unwrap_err();
+        assert!(status.is_corruption());
+        assert!(status.get_msg().starts_with("CURRENT points to a non-existent file"));
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_old_version_alive_while_held() -> Result<()> {
+        let dbname = new_db_dir("level_db_rust_version_set_live_test");
+        let mut versions = new_version_set(&dbname, Options::default());
+
+        let mut edit = VersionEdit::new();
+        edit.add_file(1, 10, 100, ikey("a", 1), ikey("c", 2));
+        edit.add_file(1, 11, 100, ikey("d", 3), ikey("f", 4));
+        versions.log_and_apply(&mut edit)?;
+
+        // 模拟一个正在进行的读操作持有旧的 Version
+        let old = versions.current();
+
+        let mut edit = VersionEdit::new();
+        edit.delete_file(1, 10);
+        edit.add_file(1, 12, 100, ikey("x", 5), ikey("z", 6));
+        versions.log_and_apply(&mut edit)?;
+
+        // 未被修改的文件在新旧 Version 之间共享
+        let current = versions.current();
+        assert!(Arc::ptr_eq(&old.files(1)[1], &current.files(1)[0]));
+
+        let mut live = BTreeSet::new();
+        versions.add_live_files(&mut live);
+        assert_eq!(BTreeSet::from([10, 11, 12]), live);
+
+        // 旧的 Version 释放后, 只被它引用的文件不再存活
+        drop(old);
+        let mut live = BTreeSet::new();
+        versions.add_live_files(&mut live);
+        assert_eq!(BTreeSet::from([11, 12]), live);
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() -> Result<()> {
+        assert_send_sync::<Version>();
+        assert_send_sync::<FileMetaData>();
+        assert_send_sync::<VersionSet>();
+
+        let dbname = new_db_dir("level_db_rust_version_set_send_test");
+        let mut versions = new_version_set(&dbname, Options::default());
+        let mut edit = VersionEdit::new();
+        edit.add_file(2, 10, 100, ikey("a", 1), ikey("c", 2));
+        versions.log_and_apply(&mut edit)?;
+
+        // 后台线程持有 Version 时, 前台可以继续安装新的 Version
+        let version = versions.current();
+        let handle = thread::spawn(move || version.files(2).iter().map(|f| f.get_number()).collect::<Vec<u64>>());
+        let mut edit = VersionEdit::new();
+        edit.delete_file(2, 10);
+        versions.log_and_apply(&mut edit)?;
+        assert_eq!(vec![10], handle.join().unwrap());
+        assert_eq!(0, versions.num_level_files(2));
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    /// 对应 C++ version_set_test.cc 中的 FindFileTest
+    struct FindFileTest {
+        disjoint_sorted_files: bool,
+        files: Vec<Arc<FileMetaData>>,
+        icmp: InternalKeyComparator,
+    }
+
+    impl FindFileTest {
+        fn new() -> Self {
+            Self { disjoint_sorted_files: true, files: vec![], icmp: InternalKeyComparator::default() }
+        }
+
+        fn add(&mut self, smallest: &str, largest: &str) {
+            self.add_with_seq(smallest, largest, 100, 100);
+        }
+
+        fn add_with_seq(&mut self, smallest: &str, largest: &str, smallest_seq: u64, largest_seq: u64) {
+            let number = self.files.len() as u64 + 1;
+            self.files.push(Arc::new(FileMetaData::new_with_number_file_size_internal_key(
+                number, 0, ikey(smallest, smallest_seq), ikey(largest, largest_seq))));
+        }
+
+        fn find(&self, key: &str) -> usize {
+            VersionSet::find_file(&self.icmp, &self.files, ikey(key, 100).encode())
+        }
+
+        fn overlaps(&self, smallest: Option<&str>, largest: Option<&str>) -> bool {
+            VersionSet::some_file_overlaps_range(&self.icmp, self.disjoint_sorted_files, &self.files,
+                                                 smallest.map(|k| k.as_bytes()), largest.map(|k| k.as_bytes()))
+        }
+    }
+
+    #[test]
+    fn test_find_file_empty() {
+        let t = FindFileTest::new();
+        assert_eq!(0, t.find("foo"));
+        assert!(!t.overlaps(Some("a"), Some("z")));
+        assert!(!t.overlaps(None, Some("z")));
+        assert!(!t.overlaps(Some("a"), None));
+        assert!(!t.overlaps(None, None));
+    }
+
+    #[test]
+    fn test_find_file_single() {
+        let mut t = FindFileTest::new();
+        t.add("p", "q");
+        assert_eq!(0, t.find("a"));
+        assert_eq!(0, t.find("p"));
+        assert_eq!(0, t.find("p1"));
+        assert_eq!(0, t.find("q"));
+        assert_eq!(1, t.find("q1"));
+        assert_eq!(1, t.find("z"));
+
+        assert!(!t.overlaps(Some("a"), Some("b")));
+        assert!(!t.overlaps(Some("z1"), Some("z2")));
+        assert!(t.overlaps(Some("a"), Some("p")));
+        assert!(t.overlaps(Some("a"), Some("q")));
+        assert!(t.overlaps(Some("a"), Some("z")));
+        assert!(t.overlaps(Some("p"), Some("p1")));
+        assert!(t.overlaps(Some("p"), Some("q")));
+        assert!(t.overlaps(Some("p"), Some("z")));
+        assert!(t.overlaps(Some("p1"), Some("p2")));
+        assert!(t.overlaps(Some("p1"), Some("z")));
+        assert!(t.overlaps(Some("q"), Some("q")));
+        assert!(t.overlaps(Some("q"), Some("q1")));
+
+        assert!(!t.overlaps(None, Some("j")));
+        assert!(!t.overlaps(Some("r"), None));
+        assert!(t.overlaps(None, Some("p")));
+        assert!(t.overlaps(None, Some("p1")));
+        assert!(t.overlaps(Some("q"), None));
+        assert!(t.overlaps(None, None));
+    }
+
+    #[test]
+    fn test_find_file_multiple() {
+        let mut t = FindFileTest::new();
+        t.add("150", "200");
+        t.add("200", "250");
+        t.add("300", "350");
+        t.add("400", "450");
+        assert_eq!(0, t.find("100"));
+        assert_eq!(0, t.find("150"));
+        assert_eq!(0, t.find("151"));
+        assert_eq!(0, t.find("199"));
+        assert_eq!(0, t.find("200"));
+        assert_eq!(1, t.find("201"));
+        assert_eq!(1, t.find("249"));
+        assert_eq!(1, t.find("250"));
+        assert_eq!(2, t.find("251"));
+        assert_eq!(2, t.find("299"));
+        assert_eq!(2, t.find("300"));
+        assert_eq!(2, t.find("349"));
+        assert_eq!(2, t.find("350"));
+        assert_eq!(3, t.find("351"));
+        assert_eq!(3, t.find("400"));
+        assert_eq!(3, t.find("450"));
+        assert_eq!(4, t.find("451"));
+
+        assert!(!t.overlaps(Some("100"), Some("149")));
+        assert!(!t.overlaps(Some("251"), Some("299")));
+        assert!(!t.overlaps(Some("451"), Some("500")));
+        assert!(!t.overlaps(Some("351"), Some("399")));
+
+        assert!(t.overlaps(Some("100"), Some("150")));
+        assert!(t.overlaps(Some("100"), Some("200")));
+        assert!(t.overlaps(Some("100"), Some("300")));
+        assert!(t.overlaps(Some("100"), Some("400")));
+        assert!(t.overlaps(Some("100"), Some("500")));
+        assert!(t.overlaps(Some("375"), Some("400")));
+        assert!(t.overlaps(Some("450"), Some("450")));
+        assert!(t.overlaps(Some("450"), Some("500")));
+    }
+
+    #[test]
+    fn test_find_file_multiple_null_boundaries() {
+        let mut t = FindFileTest::new();
+        t.add("150", "200");
+        t.add("200", "250");
+        t.add("300", "350");
+        t.add("400", "450");
+        assert!(!t.overlaps(None, Some("149")));
+        assert!(!t.overlaps(Some("451"), None));
+        assert!(t.overlaps(None, None));
+        assert!(t.overlaps(None, Some("150")));
+        assert!(t.overlaps(None, Some("199")));
+        assert!(t.overlaps(None, Some("200")));
+        assert!(t.overlaps(None, Some("201")));
+        assert!(t.overlaps(None, Some("400")));
+        assert!(t.overlaps(None, Some("800")));
+        assert!(t.overlaps(Some("100"), None));
+        assert!(t.overlaps(Some("200"), None));
+        assert!(t.overlaps(Some("449"), None));
+        assert!(t.overlaps(Some("450"), None));
+    }
+
+    #[test]
+    fn test_find_file_overlap_sequence_checks() {
+        let mut t = FindFileTest::new();
+        t.add_with_seq("200", "200", 5000, 3000);
+        assert!(!t.overlaps(Some("199"), Some("199")));
+        assert!(!t.overlaps(Some("201"), Some("300")));
+        assert!(t.overlaps(Some("200"), Some("200")));
+        assert!(t.overlaps(Some("190"), Some("200")));
+        assert!(t.overlaps(Some("200"), Some("210")));
+    }
+
+    #[test]
+    fn test_find_file_overlapping_files() {
+        let mut t = FindFileTest::new();
+        t.disjoint_sorted_files = false;
+        t.add("150", "600");
+        t.add("400", "500");
+        assert!(!t.overlaps(Some("100"), Some("149")));
+        assert!(!t.overlaps(Some("601"), Some("700")));
+        assert!(t.overlaps(Some("100"), Some("150")));
+        assert!(t.overlaps(Some("100"), Some("200")));
+        assert!(t.overlaps(Some("100"), Some("300")));
+        assert!(t.overlaps(Some("100"), Some("400")));
+        assert!(t.overlaps(Some("100"), Some("500")));
+        assert!(t.overlaps(Some("375"), Some("400")));
+        assert!(t.overlaps(Some("450"), Some("450")));
+        assert!(t.overlaps(Some("450"), Some("500")));
+        assert!(t.overlaps(Some("450"), Some("700")));
+        assert!(t.overlaps(Some("600"), Some("700")));
+    }
+
+    /// 随机生成的文件, 只记录 user key 对应的数字, 用于和暴力计算的结果比较
+    #[derive(Clone, Copy, Debug)]
+    struct RandomFile {
+        number: u64,
+        size: u64,
+        smallest: u32,
+        largest: u32,
+    }
+
+    fn key_of(k: u32) -> String {
+        format!("{:06}", k)
+    }
+
+    /// 随机生成 n 个文件, disjoint 为 true 时生成有序且互不重叠的文件
+    fn random_files(rnd: &mut Random, next_number: &mut u64, n: usize, disjoint: bool) -> Vec<RandomFile> {
+        let mut files = vec![];
+        let mut bound = 0;
+        for _ in 0..n {
+            let (smallest, largest) = if disjoint {
+                let smallest = bound + rnd.uniform(20);
+                let largest = smallest + rnd.uniform(20);
+                // 相邻文件的 user key 不能相同
+                bound = largest + 1;
+                (smallest, largest)
+            } else {
+                let smallest = rnd.uniform(400);
+                (smallest, smallest + rnd.uniform(60))
+            };
+            *next_number += 1;
+            files.push(RandomFile { number: *next_number, size: 100 + rnd.uniform(500) as u64, smallest, largest });
+        }
+        files
+    }
+
+    fn to_file_meta(files: &[RandomFile]) -> Vec<Arc<FileMetaData>> {
+        files.iter().map(|f| Arc::new(FileMetaData::new_with_number_file_size_internal_key(
+            f.number, f.size, ikey(&key_of(f.smallest), 100), ikey(&key_of(f.largest), 100)))).collect()
+    }
+
+    fn brute_overlaps(files: &[RandomFile], smallest: Option<u32>, largest: Option<u32>) -> Vec<RandomFile> {
+        files.iter().filter(|f| smallest.is_none_or(|s| f.largest >= s) && largest.is_none_or(|l| f.smallest <= l))
+            .copied().collect()
+    }
+
+    fn random_bound(rnd: &mut Random, max: u32) -> Option<u32> {
+        if rnd.one_in(8) { None } else { Some(rnd.uniform(max)) }
+    }
+
+    #[test]
+    fn test_find_file_random() {
+        let icmp = InternalKeyComparator::default();
+        let mut rnd = Random::new(301);
+        let mut next_number = 0;
+        for _ in 0..200 {
+            let n = rnd.uniform(12) as usize;
+            let disjoint = rnd.next_bool();
+            let files = random_files(&mut rnd, &mut next_number, n, disjoint);
+            let metas = to_file_meta(&files);
+
+            if disjoint {
+                for _ in 0..20 {
+                    let k = rnd.uniform(450);
+                    let expected = files.iter().position(|f| f.largest >= k).unwrap_or(files.len());
+                    assert_eq!(expected, VersionSet::find_file(&icmp, &metas, ikey(&key_of(k), 100).encode()),
+                               "files: {:?}, key: {}", files, k);
+                }
+            }
+
+            for _ in 0..20 {
+                let smallest = random_bound(&mut rnd, 450);
+                let largest = random_bound(&mut rnd, 450);
+                let expected = !brute_overlaps(&files, smallest, largest).is_empty();
+                let smallest_key = smallest.map(key_of);
+                let largest_key = largest.map(key_of);
+                let overlaps = VersionSet::some_file_overlaps_range(
+                    &icmp, disjoint, &metas,
+                    smallest_key.as_ref().map(|k| k.as_bytes()), largest_key.as_ref().map(|k| k.as_bytes()));
+                assert_eq!(expected, overlaps, "files: {:?}, range: {:?} - {:?}", files, smallest, largest);
+            }
+        }
+    }
+
+    /// level 0 的暴力计算: 不断用重叠文件扩大范围, 直到不再变化
+    fn brute_level0_inputs(files: &[RandomFile], mut smallest: Option<u32>, mut largest: Option<u32>) -> Vec<RandomFile> {
+        loop {
+            let inputs = brute_overlaps(files, smallest, largest);
+            let new_smallest = smallest.map(|s| inputs.iter().map(|f| f.smallest).fold(s, u32::min));
+            let new_largest = largest.map(|l| inputs.iter().map(|f| f.largest).fold(l, u32::max));
+            if new_smallest == smallest && new_largest == largest {
+                return inputs;
+            }
+            smallest = new_smallest;
+            largest = new_largest;
+        }
+    }
+
+    fn numbers(files: &[RandomFile]) -> BTreeSet<u64> {
+        files.iter().map(|f| f.number).collect()
+    }
+
+    #[test]
+    fn test_overlapping_inputs_random() -> Result<()> {
+        let dbname = new_db_dir("level_db_rust_version_set_overlapping_inputs_test");
+        let max_file_size = 200;
+        let max_grand_parent_overlap_bytes = 10 * max_file_size as u64;
+
+        let mut rnd = Random::new(301);
+        for _ in 0..30 {
+            let mut options = Options::default();
+            options.max_file_size = max_file_size;
+            let mut versions = new_version_set(&dbname, options);
+            let mut next_number = 100;
+            let mut layout: Vec<Vec<RandomFile>> = vec![];
+            let mut edit = VersionEdit::new();
+            for level in 0..Config::K_NUM_LEVELS {
+                let n = rnd.uniform(6) as usize;
+                let files = random_files(&mut rnd, &mut next_number, n, level > 0);
+                for f in &files {
+                    edit.add_file(level as u32, f.number, f.size,
+                                  ikey(&key_of(f.smallest), 100), ikey(&key_of(f.largest), 100));
+                }
+                layout.push(files);
+            }
+            versions.log_and_apply(&mut edit)?;
+            let current = versions.current();
+
+            for _ in 0..20 {
+                let level = rnd.uniform(Config::K_NUM_LEVELS as u32) as usize;
+                let begin = random_bound(&mut rnd, 450);
+                let end = random_bound(&mut rnd, 450);
+                let expected = if level == 0 {
+                    brute_level0_inputs(&layout[0], begin, end)
+                } else {
+                    brute_overlaps(&layout[level], begin, end)
+                };
+                let begin_key = begin.map(|k| ikey(&key_of(k), 100));
+                let end_key = end.map(|k| ikey(&key_of(k), 100));
+                let inputs = current.get_overlapping_inputs(level, begin_key.as_ref(), end_key.as_ref());
+                let actual: BTreeSet<u64> = inputs.iter().map(|f| f.get_number()).collect();
+                assert_eq!(inputs.len(), actual.len());
+                assert_eq!(numbers(&expected), actual, "level {}: {:?}, range: {:?} - {:?}",
+                           level, layout[level], begin, end);
+
+                let smallest_key = begin.map(key_of);
+                let largest_key = end.map(key_of);
+                assert_eq!(!brute_overlaps(&layout[level], begin, end).is_empty(),
+                           current.overlap_in_level(level, smallest_key.as_ref().map(|k| k.as_bytes()),
+                                                    largest_key.as_ref().map(|k| k.as_bytes())));
+            }
+
+            for _ in 0..20 {
+                let smallest = rnd.uniform(450);
+                let largest = smallest + rnd.uniform(30);
+                let mut expected = 0;
+                if brute_overlaps(&layout[0], Some(smallest), Some(largest)).is_empty() {
+                    while expected < Config::K_MAX_MEM_COMPACT_LEVEL {
+                        if !brute_overlaps(&layout[expected + 1], Some(smallest), Some(largest)).is_empty() {
+                            break;
+                        }
+                        let grandparent_bytes: u64 = brute_overlaps(&layout[expected + 2], Some(smallest), Some(largest))
+                            .iter().map(|f| f.size).sum();
+                        if grandparent_bytes > max_grand_parent_overlap_bytes {
+                            break;
+                        }
+                        expected += 1;
+                    }
+                }
+                let level = current.pick_level_for_memtable_output(key_of(smallest).as_bytes(), key_of(largest).as_bytes());
+                assert_eq!(expected, level, "layout: {:?}, range: {} - {}", layout, smallest, largest);
+                assert!(level <= Config::K_MAX_MEM_COMPACT_LEVEL);
+                // 落盘的文件不会与 0..=level 层中的文件重叠
+                if level > 0 {
+                    for l in 0..=level {
+                        assert!(!current.overlap_in_level(l, Some(key_of(smallest).as_bytes()),
+                                                          Some(key_of(largest).as_bytes())));
+                    }
+                }
+            }
+        }
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_pick_level_for_memtable_output() -> Result<()> {
+        let dbname = new_db_dir("level_db_rust_version_set_pick_level_test");
+        let mut options = Options::default();
+        options.max_file_size = 100;
+        let mut versions = new_version_set(&dbname, options);
+
+        // 空的 db 直接推到 K_MAX_MEM_COMPACT_LEVEL
+        assert_eq!(Config::K_MAX_MEM_COMPACT_LEVEL, versions.current().pick_level_for_memtable_output(b"a", b"z"));
+
+        let mut edit = VersionEdit::new();
+        edit.add_file(0, 10, 100, ikey("a", 1), ikey("c", 2));
+        edit.add_file(1, 11, 100, ikey("m", 3), ikey("p", 4));
+        // grandparent 重叠超过 10 * max_file_size
+        edit.add_file(2, 12, 2000, ikey("x", 5), ikey("y", 6));
+        edit.add_file(2, 13, 100, ikey("g", 7), ikey("h", 8));
+        versions.log_and_apply(&mut edit)?;
+        let current = versions.current();
+
+        // 与 level 0 重叠
+        assert_eq!(0, current.pick_level_for_memtable_output(b"b", b"d"));
+        // 与 level 1 重叠
+        assert_eq!(0, current.pick_level_for_memtable_output(b"n", b"o"));
+        // 与 level 2 重叠
+        assert_eq!(1, current.pick_level_for_memtable_output(b"g", b"g"));
+        // 与 grandparent(level 2) 的重叠过多
+        assert_eq!(0, current.pick_level_for_memtable_output(b"q", b"z"));
+        // 没有任何重叠
+        assert_eq!(2, current.pick_level_for_memtable_output(b"d", b"e"));
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_range_chunks() -> Result<()> {
+        let dbname = new_db_dir("level_db_rust_version_set_compact_range_test");
+        let mut options = Options::default();
+        options.max_file_size = 150;
+        let mut versions = new_version_set(&dbname, options);
+
+        let mut edit = VersionEdit::new();
+        edit.add_file(1, 10, 100, ikey("a", 1), ikey("c", 2));
+        edit.add_file(1, 11, 100, ikey("d", 3), ikey("f", 4));
+        edit.add_file(1, 12, 100, ikey("g", 5), ikey("i", 6));
+        edit.add_file(2, 20, 100, ikey("b", 7), ikey("e", 8));
+        edit.add_file(2, 21, 100, ikey("h", 9), ikey("j", 10));
+        edit.add_file(3, 30, 100, ikey("a", 11), ikey("b", 12));
+        edit.add_file(3, 31, 100, ikey("x", 13), ikey("y", 14));
+        versions.log_and_apply(&mut edit)?;
+
+        let numbers = |files: &[Arc<FileMetaData>]| files.iter().map(|f| f.get_number()).collect::<Vec<_>>();
+
+        // 一次最多选取约 max_file_size 字节的文件
+        let mut c = versions.compact_range(1, None, None).unwrap();
+        assert_eq!(1, c.level());
+        assert_eq!(vec![10, 11], numbers(c.inputs(0)));
+        assert_eq!(vec![20], numbers(c.inputs(1)));
+        assert!(!c.is_trivial_move());
+        assert_eq!(&vec![(1, ikey("f", 4))].iter().map(|(l, k)| (*l, k.encode().to_vec())).collect::<Vec<_>>(),
+                   &c.edit().compact_pointers().iter().map(|(l, k)| (*l, k.encode().to_vec())).collect::<Vec<_>>());
+
+        // 在不增加 level 2 文件的情况下扩大 level 1 的输入
+        let begin = ikey("f", 4);
+        let c = versions.compact_range(1, Some(&begin), None).unwrap();
+        assert_eq!(vec![10, 11, 12], numbers(c.inputs(0)));
+        assert_eq!(vec![20, 21], numbers(c.inputs(1)));
+
+        let c = versions.compact_range(2, Some(&ikey("a", 100)), Some(&ikey("e", 0))).unwrap();
+        assert_eq!(vec![20], numbers(c.inputs(0)));
+        assert_eq!(vec![30], numbers(c.inputs(1)));
+        assert!(!c.is_trivial_move());
+
+        // 与下一层没有重叠时可以直接移动到下一层
+        let c = versions.compact_range(2, Some(&ikey("h", 100)), Some(&ikey("j", 0))).unwrap();
+        assert_eq!(vec![21], numbers(c.inputs(0)));
+        assert!(c.inputs(1).is_empty());
+        assert!(c.is_trivial_move());
+
+        assert!(versions.compact_range(1, Some(&ikey("k", 100)), Some(&ikey("w", 0))).is_none());
+        assert!(versions.compact_range(4, None, None).is_none());
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_pick_compaction() -> Result<()> {
+        let dbname = new_db_dir("level_db_rust_version_set_pick_compaction_test");
+        let mut versions = new_version_set(&dbname, Options::default());
+        assert!(!versions.needs_compaction());
+        assert!(versions.pick_compaction().is_none());
+
+        let mut edit = VersionEdit::new();
+        edit.add_file(0, 10, 100, ikey("a", 1), ikey("b", 2));
+        edit.add_file(0, 11, 100, ikey("c", 3), ikey("d", 4));
+        edit.add_file(0, 12, 100, ikey("b", 5), ikey("c", 6));
+        edit.add_file(0, 13, 100, ikey("x", 7), ikey("y", 8));
+        edit.add_file(1, 20, 100, ikey("d", 9), ikey("e", 10));
+        versions.log_and_apply(&mut edit)?;
+        assert!(versions.needs_compaction());
+
+        let numbers = |files: &[Arc<FileMetaData>]| {
+            let mut numbers = files.iter().map(|f| f.get_number()).collect::<Vec<_>>();
+            numbers.sort();
+            numbers
+        };
+
+        // level 0 的文件之间可能重叠, 选取所有与第一个文件相互重叠的文件
+        let c = versions.pick_compaction().unwrap();
+        assert_eq!(0, c.level());
+        assert_eq!(vec![10, 11, 12], numbers(c.inputs(0)));
+        assert_eq!(vec![20], numbers(c.inputs(1)));
+
+        // 下一次从 compact pointer 之后开始
+        let c = versions.pick_compaction().unwrap();
+        assert_eq!(vec![13], numbers(c.inputs(0)));
+        assert!(c.inputs(1).is_empty());
+
+        // 回到 key 空间的开头
+        let c = versions.pick_compaction().unwrap();
+        assert_eq!(vec![10, 11, 12], numbers(c.inputs(0)));
+
+        fs::remove_dir_all(&dbname)?;
+        Ok(())
+    }
+
+    fn boundary_file(number: u64, smallest: InternalKey, largest: InternalKey) -> Arc<FileMetaData> {
+        Arc::new(FileMetaData::new_with_number_file_size_internal_key(number, 0, smallest, largest))
+    }
+
+    fn boundary_numbers(files: &[Arc<FileMetaData>]) -> Vec<u64> {
+        files.iter().map(|f| f.get_number()).collect()
+    }
+
+    #[test]
+    fn test_add_boundary_inputs() {
+        let icmp = InternalKeyComparator::new(Options::default().cmp);
+
+        // 空的文件列表
+        let mut compaction_files = vec![];
+        add_boundary_inputs(&icmp, &[], &mut compaction_files);
+        assert!(compaction_files.is_empty());
+
+        let f1 = boundary_file(1, ikey("100", 2), ikey("100", 1));
+        let mut compaction_files = vec![f1.clone()];
+        add_boundary_inputs(&icmp, &[], &mut compaction_files);
+        assert_eq!(vec![1], boundary_numbers(&compaction_files));
+
+        let mut compaction_files = vec![];
+        add_boundary_inputs(&icmp, &[f1.clone()], &mut compaction_files);
+        assert!(compaction_files.is_empty());
+
+        // 没有边界文件
+        let f2 = boundary_file(2, ikey("200", 2), ikey("200", 1));
+        let f3 = boundary_file(3, ikey("300", 2), ikey("300", 1));
+        let mut compaction_files = vec![f2.clone(), f3.clone()];
+        add_boundary_inputs(&icmp, &[f3.clone(), f2.clone(), f1.clone()], &mut compaction_files);
+        assert_eq!(vec![2, 3], boundary_numbers(&compaction_files));
+
+        // 一个边界文件
+        let f1 = boundary_file(1, ikey("100", 3), ikey("100", 2));
+        let f2 = boundary_file(2, ikey("100", 1), ikey("200", 3));
+        let f3 = boundary_file(3, ikey("300", 2), ikey("300", 1));
+        let mut compaction_files = vec![f1.clone()];
+        add_boundary_inputs(&icmp, &[f3.clone(), f2.clone(), f1.clone()], &mut compaction_files);
+        assert_eq!(vec![1, 2], boundary_numbers(&compaction_files));
+
+        // 两个边界文件
+        let f1 = boundary_file(1, ikey("100", 6), ikey("100", 5));
+        let f2 = boundary_file(2, ikey("100", 2), ikey("300", 1));
+        let f3 = boundary_file(3, ikey("100", 4), ikey("100", 3));
+        let mut compaction_files = vec![f1.clone()];
+        add_boundary_inputs(&icmp, &[f2.clone(), f3.clone(), f1.clone()], &mut compaction_files);
+        assert_eq!(vec![1, 3, 2], boundary_numbers(&compaction_files));
+
+        // compaction_files 中的文件与 level_files 中的文件 key 范围相同, 但不是同一个文件
+        let f1 = boundary_file(1, ikey("100", 6), ikey("100", 5));
+        let f2 = boundary_file(2, ikey("100", 6), ikey("100", 5));
+        let f3 = boundary_file(3, ikey("100", 2), ikey("300", 1));
+        let f4 = boundary_file(4, ikey("100", 4), ikey("100", 3));
+        let mut compaction_files = vec![f1.clone()];
+        add_boundary_inputs(&icmp, &[f2.clone(), f3.clone(), f4.clone()], &mut compaction_files);
+        assert_eq!(vec![1, 4, 3], boundary_numbers(&compaction_files));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::db::db_format::ValueType;
+use crate::db::mem_table::MemTable;
+use crate::util::coding::{Decoder, Encoder};
+use crate::util::Result;
+use crate::util::slice::Slice;
+use crate::util::status::LevelError;
+
+/// WriteBatch header has an 8-byte sequence number followed by a 4-byte count.
+const K_HEADER: usize = 12;
+
+/// 批量写入, 其中的所有修改会被原子地写入 DB
+///
+/// WriteBatch::rep_ :=
+///    sequence: fixed64
+///    count: fixed32
+///    data: record[count]
+/// record :=
+///    kTypeValue varstring varstring         |
+///    kTypeDeletion varstring
+/// varstring :=
+///    len: varint32
+///    data: uint8[len]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteBatch {
+    rep: Vec<u8>,
+}
+
+/// 遍历 WriteBatch 中的记录
+pub trait WriteBatchHandler {
+    fn put(&mut self, key: &[u8], value: &[u8]);
+
+    fn delete(&mut self, key: &[u8]);
+}
+
+/// 将 WriteBatch 中的记录依次写入 memtable, 序列号从 WriteBatch 的 sequence 开始递增
+struct MemTableInserter<'a> {
+    sequence: u64,
+    mem: &'a mut MemTable,
+    status: Result<()>,
+}
+
+impl Default for WriteBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Self {
+            rep: vec![0; K_HEADER],
+        }
+    }
+
+    /// Store the mapping "key->value" in the database.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::db::write_batch::WriteBatch;
+    ///
+    /// let mut batch = WriteBatch::new();
+    /// batch.put(b"key", b"value");
+    /// assert_eq!(1, batch.count());
+    /// ```
+    pub fn put(&mut self, key: &[u8], value: &[u8]) {
+        self.set_count(self.count() + 1);
+        self.rep.push(ValueType::KTypeValue.get_value() as u8);
+        self.put_length_prefixed(key);
+        self.put_length_prefixed(value);
+    }
+
+    /// If the database contains a mapping for "key", erase it.  Else do nothing.
+    pub fn delete(&mut self, key: &[u8]) {
+        self.set_count(self.count() + 1);
+        self.rep.push(ValueType::KTypeDeletion.get_value() as u8);
+        self.put_length_prefixed(key);
+    }
+
+    /// Clear all updates buffered in this batch.
+    pub fn clear(&mut self) {
+        self.rep.clear();
+        self.rep.resize(K_HEADER, 0);
+    }
+
+    /// The size of the database changes caused by this batch.
+    ///
+    /// This number is tied to implementation details, and may change across
+    /// releases. It is intended for LevelDB usage metrics.
+    pub fn approximate_size(&self) -> usize {
+        self.rep.len()
+    }
+
+    /// Copies the operations in "source" to this batch.
+    ///
+    /// This runs in O(source size) time. However, the constant factor is better
+    /// than calling Iterate() over the source batch with a Handler that replicates
+    /// the operations into this batch.
+    pub fn append(&mut self, source: &WriteBatch) {
+        assert!(source.rep.len() >= K_HEADER);
+        self.set_count(self.count() + source.count());
+        self.rep.extend_from_slice(&source.rep[K_HEADER..]);
+    }
+
+    /// 按写入的顺序遍历所有记录
+    ///
+    /// # Arguments
+    ///
+    /// * `handler`: 处理每一条记录
+    ///
+    /// returns: Result<(), Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// batch.iterate(&mut handler)?;
+    /// ```
+    pub fn iterate(&self, handler: &mut dyn WriteBatchHandler) -> Result<()> {
+        if self.rep.len() < K_HEADER {
+            return Err(LevelError::corruption_string("malformed WriteBatch (too small)", ""));
+        }
+
+        let mut decoder = Decoder::with_buf(&self.rep[K_HEADER..]);
+        let mut found = 0;
+        while decoder.can_get() {
+            found += 1;
+            let tag = decoder.get_buf(1)?[0];
+            decoder.skip(1)?;
+            match ValueType::try_from(tag as i32) {
+                Ok(ValueType::KTypeValue) => {
+                    let key = decoder.get_length_prefixed_slice()
+                        .map_err(|_| LevelError::corruption_string("bad WriteBatch Put", ""))?;
+                    let value = decoder.get_length_prefixed_slice()
+                        .map_err(|_| LevelError::corruption_string("bad WriteBatch Put", ""))?;
+                    handler.put(&key, &value);
+                }
+                Ok(ValueType::KTypeDeletion) => {
+                    let key = decoder.get_length_prefixed_slice()
+                        .map_err(|_| LevelError::corruption_string("bad WriteBatch Delete", ""))?;
+                    handler.delete(&key);
+                }
+                Err(_) => return Err(LevelError::corruption_string("unknown WriteBatch tag", "")),
+            }
+        }
+
+        if found != self.count() {
+            Err(LevelError::corruption_string("WriteBatch has wrong count", ""))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Return the number of entries in the batch.
+    pub fn count(&self) -> u32 {
+        u32::from_le_bytes(self.rep[8..K_HEADER].try_into().unwrap())
+    }
+
+    /// Set the count for the number of entries in the batch.
+    pub fn set_count(&mut self, n: u32) {
+        self.rep[8..K_HEADER].copy_from_slice(&n.to_le_bytes());
+    }
+
+    /// Return the sequence number for the start of this batch.
+    pub fn sequence(&self) -> u64 {
+        u64::from_le_bytes(self.rep[..8].try_into().unwrap())
+    }
+
+    /// Store the specified number as the sequence number for the start of
+    /// this batch.
+    pub fn set_sequence(&mut self, seq: u64) {
+        self.rep[..8].copy_from_slice(&seq.to_le_bytes());
+    }
+
+    /// 编码后的内容, 即写入预写日志的数据
+    pub fn contents(&self) -> &[u8] {
+        &self.rep
+    }
+
+    /// 以预写日志中读取的数据重建 WriteBatch, contents 至少包含 12 字节的 header
+    pub fn set_contents(&mut self, contents: &[u8]) {
+        assert!(contents.len() >= K_HEADER);
+        self.rep.clear();
+        self.rep.extend_from_slice(contents);
+    }
+
+    /// 将所有记录写入 memtable
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// batch.set_sequence(versions.last_sequence() + 1);
+    /// batch.insert_into(&mut mem)?;
+    /// ```
+    pub fn insert_into(&self, mem: &mut MemTable) -> Result<()> {
+        let mut inserter = MemTableInserter {
+            sequence: self.sequence(),
+            mem,
+            status: Ok(()),
+        };
+        self.iterate(&mut inserter)?;
+        inserter.status
+    }
+
+    fn put_length_prefixed(&mut self, data: &[u8]) {
+        let mut encoder = Encoder::with_vec_append(&mut self.rep);
+        unsafe { encoder.uncheck_put_length_prefixed_slice(&Slice::from_buf(data)); }
+    }
+}
+
+impl MemTableInserter<'_> {
+    fn add(&mut self, value_type: ValueType, key: &[u8], value: &[u8]) {
+        if self.status.is_ok() {
+            self.status = self.mem.add(self.sequence as usize, value_type, &key, &value);
+        }
+        self.sequence += 1;
+    }
+}
+
+impl WriteBatchHandler for MemTableInserter<'_> {
+    fn put(&mut self, key: &[u8], value: &[u8]) {
+        self.add(ValueType::KTypeValue, key, value);
+    }
+
+    fn delete(&mut self, key: &[u8]) {
+        self.add(ValueType::KTypeDeletion, key, &[]);
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use crate::db::db_format::{ParsedInternalKey, ValueType};
+    use crate::db::write_batch::{WriteBatch, WriteBatchHandler};
+    use crate::db::mem_table::MemTable;
+    use crate::util::comparator::BytewiseComparatorImpl;
+
+    /// 将 WriteBatch 写入 memtable 后按 internal key 的顺序输出所有记录
+    fn print_contents(b: &WriteBatch) -> String {
+        let mut mem = MemTable::create(Arc::new(BytewiseComparatorImpl::default()));
+        let status = b.insert_into(&mut mem);
+        let mut state = String::new();
+        let mut count = 0;
+        let mut iter = mem.new_iterator();
+        iter.seek_to_first();
+        while iter.valid() {
+            let key = iter.key();
+            let ikey = ParsedInternalKey::parse_internal_key(key.as_ref()).unwrap();
+            let user_key = String::from_utf8(ikey.user_key().to_vec()).unwrap();
+            match ikey.value_type() {
+                ValueType::KTypeValue => {
+                    state.push_str(&format!("Put({}, {})", user_key, iter.value().as_str()));
+                }
+                ValueType::KTypeDeletion => {
+                    state.push_str(&format!("Delete({})", user_key));
+                }
+            }
+            state.push_str(&format!("@{}", ikey.sequence()));
+            count += 1;
+            iter.next();
+        }
+        if status.is_err() {
+            state.push_str("ParseError()");
+        } else if count != b.count() {
+            state.push_str("CountMismatch()");
+        }
+        state
+    }
+
+    #[test]
+    fn test_empty() {
+        let batch = WriteBatch::new();
+        assert_eq!("", print_contents(&batch));
+        assert_eq!(0, batch.count());
+    }
+
+    #[test]
+    fn test_multiple() {
+        let mut batch = WriteBatch::new();
+        batch.put(b"foo", b"bar");
+        batch.delete(b"box");
+        batch.put(b"baz", b"boo");
+        batch.set_sequence(100);
+        assert_eq!(100, batch.sequence());
+        assert_eq!(3, batch.count());
+        assert_eq!("Put(baz, boo)@102Delete(box)@101Put(foo, bar)@100", print_contents(&batch));
+    }
+
+    #[test]
+    fn test_corruption() {
+        let mut batch = WriteBatch::new();
+        batch.put(b"foo", b"bar");
+        batch.delete(b"box");
+        batch.set_sequence(200);
+        let contents = batch.contents().to_vec();
+        batch.set_contents(&contents[..contents.len() - 1]);
+        assert_eq!("Put(foo, bar)@200ParseError()", print_contents(&batch));
+    }
+
+    #[test]
+    fn test_append() {
+        let mut b1 = WriteBatch::new();
+        let mut b2 = WriteBatch::new();
+        b1.set_sequence(200);
+        b2.set_sequence(300);
+        b1.append(&b2);
+        assert_eq!("", print_contents(&b1));
+        b2.put(b"a", b"va");
+        b1.append(&b2);
+        assert_eq!("Put(a, va)@200", print_contents(&b1));
+        b2.clear();
+        b2.put(b"b", b"vb");
+        b1.append(&b2);
+        assert_eq!("Put(a, va)@200Put(b, vb)@201", print_contents(&b1));
+        b2.delete(b"foo");
+        b1.append(&b2);
+        assert_eq!("Put(a, va)@200Put(b, vb)@202Put(b, vb)@201Delete(foo)@203", print_contents(&b1));
+    }
+
+    #[test]
+    fn test_approximate_size() {
+        let mut batch = WriteBatch::new();
+        let empty_size = batch.approximate_size();
+
+        batch.put(b"foo", b"bar");
+        let one_key_size = batch.approximate_size();
+        assert!(empty_size < one_key_size);
+
+        batch.put(b"baz", b"boo");
+        let two_keys_size = batch.approximate_size();
+        assert!(one_key_size < two_keys_size);
+
+        batch.delete(b"box");
+        let post_delete_size = batch.approximate_size();
+        assert!(two_keys_size < post_delete_size);
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        ops: Vec<String>,
+    }
+
+    impl WriteBatchHandler for Recorder {
+        fn put(&mut self, key: &[u8], value: &[u8]) {
+            self.ops.push(format!("Put({}, {})", String::from_utf8_lossy(key), String::from_utf8_lossy(value)));
+        }
+
+        fn delete(&mut self, key: &[u8]) {
+            self.ops.push(format!("Delete({})", String::from_utf8_lossy(key)));
+        }
+    }
+
+    #[test]
+    fn test_iterate() {
+        let mut batch = WriteBatch::new();
+        batch.put(b"foo", b"bar");
+        batch.delete(b"foo");
+        batch.put(b"", b"");
+
+        // 按写入的顺序遍历
+        let mut recorder = Recorder::default();
+        batch.iterate(&mut recorder).unwrap();
+        assert_eq!(vec!["Put(foo, bar)", "Delete(foo)", "Put(, )"], recorder.ops);
+
+        // count 与实际的记录数不一致
+        batch.set_count(2);
+        let status = batch.iterate(&mut Recorder::default()).err().unwrap();
+        assert!(status.is_corruption());
+
+        // 未知的记录类型
+        let mut contents = WriteBatch::new().contents().to_vec();
+        contents.push(9);
+        batch.set_contents(&contents);
+        batch.set_count(1);
+        assert!(batch.iterate(&mut Recorder::default()).err().unwrap().is_corruption());
+    }
+}
@@ -3,9 +3,9 @@
 extern crate core;
 
 pub mod db;
-mod table;
+pub mod table;
 pub mod util;
-mod traits;
+pub mod traits;
 
 #[global_allocator]
 static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
@@ -1,37 +1,337 @@
-use std::rc::Rc;
+use std::cmp::Ordering;
+use std::sync::Arc;
+use crate::table::format::BlockContent;
 use crate::traits::comparator_trait::Comparator;
 use crate::traits::DataIterator;
+use crate::util::coding::Decoder;
+use crate::util::slice::Slice;
+use crate::util::status::{LevelError, Status};
+use crate::util::unsafe_slice::UnsafeSlice;
 
-use crate::util::Result;
-
-pub struct Block {}
+/// 由 BlockBuilder 生成的 block, 格式见 BlockBuilder
+pub struct Block {
+    data: Arc<Slice>,
+    size: usize,
+    // Offset in data_ of restart array
+    restart_offset: usize,
+}
 
 impl Block {
+    /// 使用读取到的 block 内容创建 Block, 内容格式不正确时 size 为 0, 生成的迭代器会返回错误
+    ///
+    /// # Arguments
+    ///
+    /// * `contents`: block 的内容
+    ///
+    /// returns: Block
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let block = Block::new(read_block(file, &options, &ReadOptions::default(), footer.checksum(), &handle)?);
+    /// ```
+    pub fn new(contents: BlockContent) -> Self {
+        let data = contents.data;
+        let mut size = data.size();
+        let mut restart_offset = 0;
+        if size < 4 {
+            // Error marker
+            size = 0;
+        } else {
+            let max_restarts_allowed = (size - 4) / 4;
+            let num_restarts = Self::decode_num_restarts(&data);
+            if num_restarts > max_restarts_allowed {
+                // The size is too small for NumRestarts()
+                size = 0;
+            } else {
+                restart_offset = size - (1 + num_restarts) * 4;
+            }
+        }
+        Self {
+            data: Arc::new(data),
+            size,
+            restart_offset,
+        }
+    }
+
     /// 获取block的大小
     ///
     /// # Examples
     ///
     /// ```
-    /// let block = Block {};
     /// let size = block.size();
     /// ```
-    pub fn size(&self) {
-        todo!()
+    pub fn size(&self) -> usize {
+        self.size
     }
+
     /// 生成迭代器
     ///
     /// # Arguments
     ///
-    /// * `_comparator`: 比较器
+    /// * `comparator`: 比较器, 需要与生成 block 时 key 的顺序一致
     ///
-    /// returns: Result<Box<dyn DataIterator, Global>, Status>
+    /// returns: Box<dyn DataIterator, Global>
     ///
     /// # Examples
     ///
+    /// ```ignore
+    /// let mut iter = block.new_iterator(options.cmp.clone());
+    /// iter.seek_to_first();
     /// ```
-    ///
-    /// ```
-    pub fn new_iterator(&self, _comparator: Rc<impl Comparator>) -> Result<Box<dyn DataIterator>> {
-        todo!()
+    pub fn new_iterator(&self, comparator: Arc<dyn Comparator>) -> Box<dyn DataIterator> {
+        if self.size < 4 {
+            return Box::new(BlockIter::new_error(
+                comparator, LevelError::corruption_string("bad block contents", "")));
+        }
+        let num_restarts = self.num_restarts();
+        Box::new(BlockIter::new(comparator, self.data.clone(), self.restart_offset, num_restarts))
+    }
+
+    fn num_restarts(&self) -> usize {
+        Self::decode_num_restarts(&self.data)
+    }
+
+    fn decode_num_restarts(data: &[u8]) -> usize {
+        assert!(data.len() >= 4);
+        let mut decoder = Decoder::with_buf(&data[data.len() - 4..]);
+        decoder.get_fixed32().unwrap_or_default() as usize
+    }
+}
+
+/// Block 的迭代器
+struct BlockIter {
+    comparator: Arc<dyn Comparator>,
+    // underlying block contents
+    data: Arc<Slice>,
+    // Offset of restart array (list of fixed32)
+    restarts: usize,
+    // Number of uint32_t entries in restart array
+    num_restarts: usize,
+
+    // current_ is offset in data_ of current entry.  >= restarts_ if !Valid
+    current: usize,
+    // Index of restart block in which current_ falls
+    restart_index: usize,
+    key: Vec<u8>,
+    // value 在 data 中的偏移量及长度
+    value_offset: usize,
+    value_len: usize,
+    status: Status,
+}
+
+impl BlockIter {
+    fn new(comparator: Arc<dyn Comparator>, data: Arc<Slice>, restarts: usize, num_restarts: usize) -> Self {
+        Self {
+            comparator,
+            data,
+            restarts,
+            num_restarts,
+            current: restarts,
+            restart_index: num_restarts,
+            key: vec![],
+            value_offset: 0,
+            value_len: 0,
+            status: Status::default(),
+        }
+    }
+
+    /// 不包含任何数据, 状态为 status 的迭代器
+    fn new_error(comparator: Arc<dyn Comparator>, status: Status) -> Self {
+        let mut iter = Self::new(comparator, Arc::new(Slice::default()), 0, 0);
+        iter.status = status;
+        iter
+    }
+
+    #[inline]
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        self.comparator.compare(a, b).unwrap_or(Ordering::Equal)
+    }
+
+    /// Return the offset in data_ just past the end of the current entry.
+    #[inline]
+    fn next_entry_offset(&self) -> usize {
+        self.value_offset + self.value_len
+    }
+
+    fn get_restart_point(&self, index: usize) -> usize {
+        assert!(index < self.num_restarts);
+        let offset = self.restarts + index * 4;
+        let mut decoder = Decoder::with_buf(&self.data[offset..offset + 4]);
+        decoder.get_fixed32().unwrap_or_default() as usize
+    }
+
+    fn seek_to_restart_point(&mut self, index: usize) {
+        self.key.clear();
+        self.restart_index = index;
+        // current_ will be fixed by ParseNextKey();
+
+        // ParseNextKey() starts at the end of value_, so set value_ accordingly
+        self.value_offset = self.get_restart_point(index);
+        self.value_len = 0;
+    }
+
+    fn corruption_error(&mut self) {
+        self.current = self.restarts;
+        self.restart_index = self.num_restarts;
+        self.status = LevelError::corruption_string("bad entry in block", "");
+        self.key.clear();
+        self.value_offset = 0;
+        self.value_len = 0;
+    }
+
+    fn parse_next_key(&mut self) -> bool {
+        self.current = self.next_entry_offset();
+        if self.current >= self.restarts {
+            // No more entries to return.  Mark as invalid.
+            self.current = self.restarts;
+            self.restart_index = self.num_restarts;
+            return false;
+        }
+
+        // Decode next entry
+        match decode_entry(&self.data[..self.restarts], self.current) {
+            Some((shared, non_shared, value_len, key_offset)) if self.key.len() >= shared => {
+                self.key.truncate(shared);
+                self.key.extend_from_slice(&self.data[key_offset..key_offset + non_shared]);
+                self.value_offset = key_offset + non_shared;
+                self.value_len = value_len;
+                while self.restart_index + 1 < self.num_restarts
+                    && self.get_restart_point(self.restart_index + 1) < self.current {
+                    self.restart_index += 1;
+                }
+                true
+            }
+            _ => {
+                self.corruption_error();
+                false
+            }
+        }
+    }
+}
+
+/// 解码 offset 处的 entry, 返回 (shared, non_shared, value_length, key_delta 的偏移量)。
+/// entry 不完整时返回 None
+///
+/// Helper routine: decode the next block entry starting at "p",
+/// storing the number of shared key bytes, non_shared key bytes,
+/// and the length of the value in "*shared", "*non_shared", and
+/// "*value_length", respectively.  Will not dereference past "limit".
+fn decode_entry(data: &[u8], offset: usize) -> Option<(usize, usize, usize, usize)> {
+    let mut decoder = Decoder::with_buf(&data[offset..]);
+    let shared = decoder.get_varint32().ok()? as usize;
+    let non_shared = decoder.get_varint32().ok()? as usize;
+    let value_length = decoder.get_varint32().ok()? as usize;
+    let key_offset = decoder.offset();
+    if key_offset > decoder.limit() || decoder.limit() - key_offset < non_shared + value_length {
+        return None;
+    }
+    Some((shared, non_shared, value_length, offset + key_offset))
+}
+
+impl DataIterator for BlockIter {
+    fn valid(&self) -> bool {
+        self.current < self.restarts
+    }
+
+    fn seek_to_first(&mut self) {
+        if self.num_restarts == 0 {
+            return;
+        }
+        self.seek_to_restart_point(0);
+        self.parse_next_key();
+    }
+
+    fn seek_to_last(&mut self) {
+        if self.num_restarts == 0 {
+            return;
+        }
+        self.seek_to_restart_point(self.num_restarts - 1);
+        while self.parse_next_key() && self.next_entry_offset() < self.restarts {
+            // Keep skipping
+        }
+    }
+
+    fn seek(&mut self, target: &Slice) {
+        if self.num_restarts == 0 {
+            return;
+        }
+        // Binary search in restart array to find the last restart point
+        // with a key < target
+        let mut left = 0;
+        let mut right = self.num_restarts - 1;
+        while left < right {
+            let mid = (left + right).div_ceil(2);
+            let region_offset = self.get_restart_point(mid);
+            match decode_entry(&self.data[..self.restarts], region_offset) {
+                Some((0, non_shared, _, key_offset)) => {
+                    let mid_key = &self.data[key_offset..key_offset + non_shared];
+                    if self.compare(mid_key, target) == Ordering::Less {
+                        // Key at "mid" is smaller than "target".  Therefore all
+                        // blocks before "mid" are uninteresting.
+                        left = mid;
+                    } else {
+                        // Key at "mid" is >= "target".  Therefore all blocks at or
+                        // after "mid" are uninteresting.
+                        right = mid - 1;
+                    }
+                }
+                _ => {
+                    self.corruption_error();
+                    return;
+                }
+            }
+        }
+
+        // Linear search (within restart block) for first key >= target
+        self.seek_to_restart_point(left);
+        loop {
+            if !self.parse_next_key() {
+                return;
+            }
+            if self.compare(&self.key, target) != Ordering::Less {
+                return;
+            }
+        }
+    }
+
+    fn next(&mut self) {
+        assert!(self.valid());
+        self.parse_next_key();
+    }
+
+    fn pre(&mut self) {
+        assert!(self.valid());
+
+        // Scan backwards to a restart point before current_
+        let original = self.current;
+        while self.get_restart_point(self.restart_index) >= original {
+            if self.restart_index == 0 {
+                // No more entries
+                self.current = self.restarts;
+                self.restart_index = self.num_restarts;
+                return;
+            }
+            self.restart_index -= 1;
+        }
+
+        self.seek_to_restart_point(self.restart_index);
+        // Loop until end of current entry hits the start of original entry
+        while self.parse_next_key() && self.next_entry_offset() < original {}
+    }
+
+    fn key(&self) -> UnsafeSlice {
+        assert!(self.valid());
+        unsafe { UnsafeSlice::from_raw_parts(self.key.as_ptr(), self.key.len()) }
+    }
+
+    fn value(&self) -> UnsafeSlice {
+        assert!(self.valid());
+        let value = &self.data[self.value_offset..self.value_offset + self.value_len];
+        unsafe { UnsafeSlice::from_raw_parts(value.as_ptr(), value.len()) }
+    }
+
+    fn status(&self) -> Status {
+        self.status.clone()
     }
-}
\ No newline at end of file
+}
@@ -1,49 +1,80 @@
-use std::fs::File;
-use std::sync::Arc;
-use crate::util::options::{Options, OptionsPtr};
-use crate::util::slice::Slice;
-
-use crate::util::Result;
-use crate::util::status::Status;
-
-// 智能指针 Rc<T>, 引用计数器，用来记录一个值是否被使用，如果计数为零可清除。
-// 适用于堆中数据需要被程序多部分使用，但编译时不能确定谁最后完成。
-
-// Arc 是一种能够使得数据在线程间安全共享的智能指针.
-// Arc会追踪这个指针的所有拷贝，当最后一份拷贝离开作用域时，它就会安全释放内存。
-
-// 智能指针 Box<T>。 box 允许你将一个值放在堆上而不是栈上。留在栈上的则是指向堆数据的指针。
-
-/// BlockBuilder 的 `Arc<BlockBuilder>` 别名
-pub type BlockBuilderPtr = Arc<BlockBuilder>;
+use crate::util::coding::Encoder;
 
 /// 生成块
+///
+/// BlockBuilder generates blocks where keys are prefix-compressed:
+///
+/// When we store a key, we drop the prefix shared with the previous
+/// string.  This helps reduce the space requirement significantly.
+/// Furthermore, once every K keys, we do not apply the prefix
+/// compression and store the entire key.  We call this a "restart
+/// point".  The tail end of the block stores the offsets of all of the
+/// restart points, and can be used to do a binary search when looking
+/// for a particular key.  Values are stored as-is (without compression)
+/// immediately following the corresponding key.
+///
+/// An entry for a particular key-value pair has the form:
+///     shared_bytes: varint32
+///     unshared_bytes: varint32
+///     value_length: varint32
+///     key_delta: char[unshared_bytes]
+///     value: char[value_length]
+/// shared_bytes == 0 for restart points.
+///
+/// The trailer of the block has the form:
+///     restarts: uint32[num_restarts]
+///     num_restarts: uint32
+/// restarts[i] contains the offset within the block of the ith restart point.
 pub struct BlockBuilder {
-    // 在 BlockBuilder 初始化时，指定的配置项
-    options: OptionsPtr,
+    // 每隔多少个 key 设置一个重启点
+    block_restart_interval: usize,
 
     // 目标缓冲区，也就是按照输出格式处理好的内存区域
-    buffer: Slice,
-
-    // SSTable 生成后的文件
-    file: Arc<File>,
-
-    offset: u64,
-    status: Status,
-
-    // 生成 SSTable 中的数据区域
-    data_block: BlockBuilderPtr,
-    // 生成 SSTable 中的数据索引区域
-    index_block: BlockBuilderPtr,
+    buffer: Vec<u8>,
+    // Restart points
+    restarts: Vec<u32>,
+    // Number of entries emitted since restart
+    counter: usize,
+    // Has Finish() been called?
+    finished: bool,
+    last_key: Vec<u8>,
 }
 
 impl BlockBuilder {
-    pub fn new(options: OptionsPtr) -> Self {
-        todo!()
+    /// 创建 BlockBuilder
+    ///
+    /// # Arguments
+    ///
+    /// * `block_restart_interval`: 每隔多少个 key 设置一个重启点, data block 使用
+    ///   Options::block_restart_interval, index block 固定为 1
+    ///
+    /// returns: BlockBuilder
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::table::block_builder::BlockBuilder;
+    ///
+    /// let mut block_builder = BlockBuilder::new(16);
+    /// ```
+    pub fn new(block_restart_interval: usize) -> Self {
+        assert!(block_restart_interval >= 1);
+        Self {
+            block_restart_interval,
+            buffer: vec![],
+            // First restart point is at offset 0
+            restarts: vec![0],
+            counter: 0,
+            finished: false,
+            last_key: vec![],
+        }
     }
 
     /// 向datablock增加entry
     ///
+    /// REQUIRES: Finish() has not been called since the last call to Reset().
+    /// REQUIRES: key is larger than any previously added key
+    ///
     /// # Arguments
     ///
     /// * `key`: 键
@@ -54,10 +85,43 @@ impl BlockBuilder {
     /// # Examples
     ///
     /// ```
+    /// use level_db_rust::table::block_builder::BlockBuilder;
     ///
+    /// let mut block_builder = BlockBuilder::new(16);
+    /// block_builder.add(b"key", b"value");
     /// ```
-    pub fn add(&mut self, _key: &Slice, _value: &Slice) {
-        todo!()
+    pub fn add(&mut self, key: &[u8], value: &[u8]) {
+        assert!(!self.finished);
+        assert!(self.counter <= self.block_restart_interval);
+        let mut shared = 0;
+        if self.counter < self.block_restart_interval {
+            // See how much sharing to do with previous string
+            shared = self.last_key.iter().zip(key.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+        } else {
+            // Restart compression
+            self.restarts.push(self.buffer.len() as u32);
+            self.counter = 0;
+        }
+        let non_shared = key.len() - shared;
+
+        // Add "<shared><non_shared><value_size>" to buffer_
+        let mut encoder = Encoder::with_vec_append(&mut self.buffer);
+        // 写入 Vec 时会自动扩容, 不会失败
+        encoder.put_varint32(shared as u32).expect("put shared");
+        encoder.put_varint32(non_shared as u32).expect("put non_shared");
+        encoder.put_varint32(value.len() as u32).expect("put value size");
+
+        // Add string delta to buffer_ followed by value
+        self.buffer.extend_from_slice(&key[shared..]);
+        self.buffer.extend_from_slice(value);
+
+        // Update state
+        self.last_key.truncate(shared);
+        self.last_key.extend_from_slice(&key[shared..]);
+        debug_assert_eq!(self.last_key.as_slice(), key);
+        self.counter += 1;
     }
 
     /// 重置builder
@@ -68,19 +132,31 @@ impl BlockBuilder {
     /// block_builder.reset();
     /// ```
     pub fn reset(&mut self) {
-        todo!()
+        self.buffer.clear();
+        self.restarts.clear();
+        // First restart point is at offset 0
+        self.restarts.push(0);
+        self.counter = 0;
+        self.finished = false;
+        self.last_key.clear();
     }
 
-    /// 追加Restart points
-    ///
+    /// 追加Restart points, 返回 block 的内容。返回的内容在 reset 之前一直有效
     ///
     /// # Examples
     ///
     /// ```
     /// let block = block_builder.finish();
     /// ```
-    pub fn finish(&mut self) -> Result<Slice> {
-        todo!()
+    pub fn finish(&mut self) -> &[u8] {
+        // Append restart array
+        let mut encoder = Encoder::with_vec_append(&mut self.buffer);
+        for restart in &self.restarts {
+            encoder.put_fixed32(*restart).expect("put restart");
+        }
+        encoder.put_fixed32(self.restarts.len() as u32).expect("put num_restarts");
+        self.finished = true;
+        &self.buffer
     }
 
     /// 判断builder是否为空
@@ -91,7 +167,7 @@ impl BlockBuilder {
     /// let is_empty = block_builder.empty();
     /// ```
     pub fn empty(&self) -> bool {
-        todo!()
+        self.buffer.is_empty()
     }
 
     /// 估算当前的block大小, 超过一定大小后，写入文件
@@ -102,7 +178,7 @@ impl BlockBuilder {
     /// let estimate_size = block_builder.current_size_estimate();
     /// ```
     pub fn current_size_estimate(&self) -> usize {
-        todo!()
+        // Raw data buffer + Restart array + Restart array length
+        self.buffer.len() + self.restarts.len() * 4 + 4
     }
-
-}
\ No newline at end of file
+}
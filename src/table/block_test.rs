@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use crate::table::block::Block;
+    use crate::table::block_builder::BlockBuilder;
+    use crate::table::format::BlockContent;
+    use crate::traits::comparator_trait::Comparator;
+    use crate::traits::DataIterator;
+    use crate::util::comparator::BytewiseComparatorImpl;
+    use crate::util::slice::Slice;
+
+    fn cmp() -> Arc<dyn Comparator> {
+        Arc::new(BytewiseComparatorImpl::default())
+    }
+
+    fn new_block(data: &[u8]) -> Block {
+        Block::new(BlockContent {
+            data: Slice::from_buf(data),
+            cachable: false,
+            heap_allocated: false,
+        })
+    }
+
+    fn build_block(entries: &[(String, String)], block_restart_interval: usize) -> Block {
+        let mut builder = BlockBuilder::new(block_restart_interval);
+        for (k, v) in entries {
+            builder.add(k.as_bytes(), v.as_bytes());
+        }
+        let estimate = builder.current_size_estimate();
+        let contents = builder.finish().to_vec();
+        assert_eq!(estimate, contents.len());
+        new_block(&contents)
+    }
+
+    fn entries(n: usize) -> Vec<(String, String)> {
+        (0..n).map(|i| (format!("key{:05}", i * 2), format!("value{}", i))).collect()
+    }
+
+    fn entry_of(iter: &dyn DataIterator) -> (String, String) {
+        (iter.key().as_str().to_string(), iter.value().as_str().to_string())
+    }
+
+    #[test]
+    fn test_block_builder() {
+        let mut builder = BlockBuilder::new(16);
+        assert!(builder.empty());
+        // 只有一个重启点
+        assert_eq!(8, builder.current_size_estimate());
+        builder.add(b"apple", b"1");
+        builder.add(b"apply", b"2");
+        assert!(!builder.empty());
+
+        // 第二个 key 与第一个 key 共享 "appl"
+        let contents = builder.finish().to_vec();
+        assert_eq!(vec![0, 5, 1, b'a', b'p', b'p', b'l', b'e', b'1',
+                        4, 1, 1, b'y', b'2',
+                        0, 0, 0, 0,
+                        1, 0, 0, 0], contents);
+
+        builder.reset();
+        assert!(builder.empty());
+        assert_eq!(vec![0, 0, 0, 0, 1, 0, 0, 0], builder.finish().to_vec());
+    }
+
+    #[test]
+    fn test_block_iterate() {
+        for block_restart_interval in [1, 2, 16] {
+            for n in [0, 1, 2, 15, 16, 17, 100] {
+                let entries = entries(n);
+                let block = build_block(&entries, block_restart_interval);
+                let mut iter = block.new_iterator(cmp());
+                assert!(iter.status().is_ok());
+
+                // 正向遍历
+                iter.seek_to_first();
+                for entry in &entries {
+                    assert!(iter.valid());
+                    assert_eq!(*entry, entry_of(iter.as_ref()));
+                    iter.next();
+                }
+                assert!(!iter.valid());
+
+                // 反向遍历
+                iter.seek_to_last();
+                for entry in entries.iter().rev() {
+                    assert!(iter.valid());
+                    assert_eq!(*entry, entry_of(iter.as_ref()));
+                    iter.pre();
+                }
+                assert!(!iter.valid());
+
+                // seek 到第一个 >= target 的 key
+                for i in 0..n * 2 + 1 {
+                    iter.seek(&Slice::from(format!("key{:05}", i)));
+                    match entries.get(i.div_ceil(2)) {
+                        Some(entry) => {
+                            assert!(iter.valid());
+                            assert_eq!(*entry, entry_of(iter.as_ref()));
+                        }
+                        None => assert!(!iter.valid()),
+                    }
+                }
+                assert!(iter.status().is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_block_corruption() {
+        // 长度不足以保存重启点的数量
+        let block = new_block(&[1, 2]);
+        assert_eq!(0, block.size());
+        let mut iter = block.new_iterator(cmp());
+        iter.seek_to_first();
+        assert!(!iter.valid());
+        assert!(iter.status().is_corruption());
+
+        // 重启点的数量超过 block 的大小
+        let block = new_block(&[0, 0, 0, 0, 9, 0, 0, 0]);
+        assert!(block.new_iterator(cmp()).status().is_corruption());
+
+        // entry 的长度超过了 block 的数据区域
+        let block = new_block(&[0, 100, 1, b'a', 0, 0, 0, 0, 1, 0, 0, 0]);
+        let mut iter = block.new_iterator(cmp());
+        iter.seek_to_first();
+        assert!(!iter.valid());
+        assert!(iter.status().is_corruption());
+    }
+}
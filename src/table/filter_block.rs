@@ -1,8 +1,6 @@
-use std::io::Write;
 use std::sync::Arc;
-use crate::debug;
 use crate::traits::filter_policy_trait::{FilterPolicy, FilterPolicyPtr};
-use crate::util::coding::Encoder;
+use crate::util::coding::{Decoder, Encoder};
 use crate::util::slice::Slice;
 
 use crate::util::Result;
@@ -14,6 +12,12 @@ const FILTER_BASE_LG: usize = 11;
 // Generate new filter every 2KB of data
 const FILTER_BASE: usize = 1 << FILTER_BASE_LG;
 
+/// meta index block 中 filter block 的 key 前缀, 完整的 key 为 "filter." + FilterPolicy::name()
+pub const FILTER_BLOCK_KEY_PREFIX: &str = "filter.";
+
+/// meta index block 中整个 table 的 filter 的 key 前缀, 完整的 key 为 "fullfilter." + FilterPolicy::name()
+pub const FULL_FILTER_BLOCK_KEY_PREFIX: &str = "fullfilter.";
+
 ///
 /// meta block 构建器
 /// FilterBlock，实质上就是SST文件里面的 meta block
@@ -69,7 +73,7 @@ pub trait FilterBlock {
     /// ```
     ///
     /// ```
-    fn add_key(&mut self, key: &Slice);
+    fn add_key(&mut self, key: &[u8]);
 
     /// 构造filterBlock
     ///
@@ -91,8 +95,6 @@ pub trait FilterBlock {
 
     fn get_result(&self) -> Vec<u8>;
 
-    fn get_tmp_keys(&self) -> Vec<Slice>;
-
     fn get_tmp_filter_offsets(&self) -> Vec<u32>;
 }
 
@@ -113,17 +115,15 @@ pub struct FilterBlockBuilder {
     // 比如 BloomFilter 经过各种key计算之后，可能会得到一个 filter_str。这个 filter_str 就是放到result里面。
     result: Vec<u8>,
 
-    // policy_->CreateFilter() argument
-    tmp_keys: Vec<Slice>,
     // 里面的每个元素就是用来记录每个filter内容的offset
     filter_offsets: Vec<u32>,
 }
 
 pub struct FilterBlockReader {
     policy: FilterPolicyPtr,
-    // Pointer to filter data (at block-start)
-    data: Vec<u32>,
-    // Pointer to beginning of offset array (at block-end)
+    // filter data (at block-start), 不包含末尾的 offset 数组
+    data: Vec<u8>,
+    // 解码后的 offset 数组, 最后一个元素为 offset 数组的起始位置, 也就是最后一个 filter 的结束位置
     offset: Vec<u32>,
     // Number of entries in offset array
     num: usize,
@@ -131,6 +131,25 @@ pub struct FilterBlockReader {
     base_lg: usize,
 }
 
+/// 整个 table 只生成一个 filter 的构建器 (Options::full_filter 为 true 时使用)。
+///
+/// 与 FilterBlockBuilder 按 data block 的偏移量每 2KB 生成一个 filter 不同, 所有的 key 生成一个 filter,
+/// filter block 的内容就是 FilterPolicy::create_filter 的结果, 没有 offset 数组。
+/// 查询时不需要 data block 的偏移量, 因此可以在读取 index block 之前判断 key 是否在 table 中。
+pub struct FullFilterBlockBuilder {
+    policy: FilterPolicyPtr,
+    // 所有的 key 连续存放, start 记录每个 key 在 keys 中的 offset
+    keys: Vec<u8>,
+    start: Vec<usize>,
+    result: Vec<u8>,
+}
+
+/// 读取 FullFilterBlockBuilder 生成的 filter block
+pub struct FullFilterBlockReader {
+    policy: FilterPolicyPtr,
+    data: Vec<u8>,
+}
+
 impl FilterBlock for FilterBlockBuilder {
     fn new_with_policy(policy: FilterPolicyPtr) -> Self {
         FilterBlock::new_with_policy_capacity(policy, 64)
@@ -140,7 +159,6 @@ impl FilterBlock for FilterBlockBuilder {
         let keys: Vec<u8> = Vec::with_capacity(capacity);
         let start: Vec<usize> = Vec::with_capacity(capacity);
         let result: Vec<u8> = Vec::with_capacity(capacity);
-        let tmp_keys: Vec<Slice> = vec![];
         let filter_offsets: Vec<u32> = vec![];
 
         Self {
@@ -148,7 +166,6 @@ impl FilterBlock for FilterBlockBuilder {
             keys,
             start,
             result,
-            tmp_keys,
             filter_offsets,
         }
     }
@@ -157,23 +174,22 @@ impl FilterBlock for FilterBlockBuilder {
         // 计算出需要创建的filter的总数目. filters_number ==> filter_index
         let filters_number = block_offset / (FILTER_BASE as u64);
 
-        let len = self.filter_offsets.len() as u64;
-        assert!(filters_number >= len);
+        assert!(filters_number >= self.filter_offsets.len() as u64);
 
         // 当已经生成的filter的数目小于需要生成的filter的总数时，那么就继续创建filter。
-        while filters_number > len {
+        while filters_number > self.filter_offsets.len() as u64 {
             self.generate_new_filter();
         }
     }
 
     fn add_key_from_str(&mut self, key: &str) {
-        self.add_key(&Slice::from(key))
+        self.add_key(key.as_bytes())
     }
 
-    fn add_key(&mut self, key: &Slice) {
+    fn add_key(&mut self, key: &[u8]) {
         // start_记录key在keys的offset，因此可以还原出key
         self.start.push(self.keys.len());
-        self.keys.write(key.as_str().as_bytes()).expect("add_key error!");
+        self.keys.extend_from_slice(key);
     }
 
     fn finish(&mut self) -> Result<Slice> {
@@ -186,8 +202,7 @@ impl FilterBlock for FilterBlockBuilder {
 
         // 当前需要写入的位置。result 中可能存在数据，因此为 offset ==> self.result.len()  的位置
         let dst: &mut Vec<u8> = &mut self.result;
-        // let mut dst_append = self.result.as_mut_slice();
-        let mut encoder = Encoder::with_vec(dst);
+        let mut encoder = Encoder::with_vec_append(dst);
         for i in 0..self.filter_offsets.len() {
             encoder.put_fixed32(self.filter_offsets[i])?;
         }
@@ -215,10 +230,6 @@ impl FilterBlock for FilterBlockBuilder {
         self.result.to_vec()
     }
 
-    fn get_tmp_keys(&self) -> Vec<Slice> {
-        self.tmp_keys.to_vec()
-    }
-
     fn get_tmp_filter_offsets(&self) -> Vec<u32> {
         self.filter_offsets.to_vec()
     }
@@ -243,87 +254,136 @@ impl FilterBlockBuilder {
         // start_里面记录下offset.
         // starts最后一个元素是keys_的总大小，此时starts元素个数=num_keys + 1. 这样 [starts[i], starts[i+1]) 就可以还原所有的key了
         self.start.push(self.keys.len());
-        // 需要多少个key
-        // 如果 new_len 大于 len ，则 Vec 由差异扩展，每个额外的插槽都用 value 填充。
-        // 如果 new_len 小于 len ，则 Vec 将被截断。
-        self.tmp_keys.resize(num_keys, Slice::default());
-
-        // 依次拿到每个key
-        for i in 0..num_keys {
-            // 拿到key的长度
-            let length = self.start[i + 1] - self.start[i];
-            // 这里拿到每个key的数据
-            let base = &self.keys[self.start[i]..(self.start[i] + length)];
-
-            // 生成相应的key，并且放到tmp_keys里面
-            let mut tmp_key = Vec::with_capacity(length);
-            tmp_key.write(&base);
-            self.tmp_keys[i] = Slice::from_vec(tmp_key);
-        }
+
+        // 依次拿到每个key, 这里只是引用 keys 中的数据, 不会复制 key
+        // policy_->CreateFilter() argument
+        let tmp_keys: Vec<&[u8]> = (0..num_keys)
+            .map(|i| &self.keys[self.start[i]..self.start[i + 1]])
+            .collect();
 
         // Generate filter for current set of keys and append to result_.
         // 记录下offset
         self.filter_offsets.push(self.result.len() as u32);
 
-        // 利用tmp_keys生成输出，并且放到result里面。
-        let mut keys: Vec<&Slice> = Vec::new();
-        for tmp_key in &self.tmp_keys {
-            keys.push(&tmp_key);
-        }
-        // let create_filter:Slice = self.policy.create_filter_with_len(num_keys, keys);
-        let create_filter: Slice = self.policy.create_filter(keys);
-        debug!("create_filter:{:?}.", create_filter);
-
-        self.result.write(create_filter.as_ref());
+        // 利用tmp_keys生成输出，直接追加到result里面。
+        self.policy.create_filter(&tmp_keys, &mut self.result);
 
         // 清空keys/start变量
-        self.tmp_keys.clear();
         self.keys.clear();
         self.start.clear();
     }
 }
 
 impl FilterBlockReader {
-    pub fn new_with_policy(policy: FilterPolicyPtr, contents: &Slice) -> Self {
-        let data = Vec::new();
-        let offset = Vec::new();
-
-        let contents_len = contents.len();
-
-        // 1 byte for base_lg_ and 4 for start of offset array
-        if contents_len < 5 {
-            return Self {
-                policy,
-                data,
-                offset,
-                num: 0,
-                base_lg: 0,
-            };
+    /// 解析 filter block, contents 为 FilterBlockBuilder::finish 的结果。
+    /// contents 格式错误时不会报错, 此时 key_may_match 对所有的 key 都返回 true
+    ///
+    /// # Arguments
+    ///
+    /// * `policy`: 与生成 filter block 时相同的 FilterPolicy
+    /// * `contents`: filter block 的内容
+    ///
+    /// returns: FilterBlockReader
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let reader = FilterBlockReader::new_with_policy(policy.clone(), &filter_block_builder.finish()?);
+    /// assert!(reader.key_may_match(100, b"foo"));
+    /// ```
+    pub fn new_with_policy(policy: FilterPolicyPtr, contents: &[u8]) -> Self {
+        let mut reader = Self {
+            policy,
+            data: Vec::new(),
+            offset: Vec::new(),
+            num: 0,
+            base_lg: 0,
         };
 
-        // let buf = contents.as_ref()[contents_len-5..];
+        let buf = contents;
+        let n = buf.len();
+        // 1 byte for base_lg_ and 4 for start of offset array
+        if n < 5 {
+            return reader;
+        }
 
-        // let base_lg_ = contentsVe[contents_len-1];
+        let last_word = Decoder::with_buf(&buf[n - 5..n - 1]).get_fixed32().unwrap_or(u32::MAX) as usize;
+        if last_word > n - 5 {
+            return reader;
+        }
 
-        // let last_word: u32 = Coding::decode_fixed32(buf));
-        Self {
-            policy,
-            data,
-            offset,
-            num: 0,
-            base_lg: 0,
+        let num = (n - 5 - last_word) / 4;
+        let mut decoder = Decoder::with_buf(&buf[last_word..n - 1]);
+        let mut offset = Vec::with_capacity(num + 1);
+        for _ in 0..=num {
+            match decoder.get_fixed32() {
+                Ok(v) => offset.push(v),
+                Err(_) => return reader,
+            }
         }
+
+        reader.base_lg = buf[n - 1] as usize;
+        reader.data = buf[..last_word].to_vec();
+        reader.offset = offset;
+        reader.num = num;
+        reader
     }
 
-    pub fn key_may_match(&self, block_offset: u64, key: &Slice) -> bool {
-        todo!()
+    /// 判断 key 是否可能存在于 block_offset 对应的 data block 中。
+    ///
+    /// 如果 key 存在，一定返回 true。 如果 key 不存在，可能返回 true 也可能返回 false。
+    ///
+    /// # Arguments
+    ///
+    /// * `block_offset`: data block 在 sstable 中的偏移量
+    /// * `key`: 判断的 key, 与 FilterBlockBuilder::add_key 的 key 格式相同
+    ///
+    /// returns: bool
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// assert!(!reader.key_may_match(0, b"missing"));
+    /// ```
+    pub fn key_may_match(&self, block_offset: u64, key: &[u8]) -> bool {
+        self.filter_may_match(block_offset, |filter| self.policy.key_may_match(key, filter))
+    }
+
+    /// 判断 key 的前缀是否可能存在于 block_offset 对应的 data block 中,
+    /// 需要生成 filter 时加入了前缀 (参见 FilterPolicy::prefix_may_match)
+    ///
+    /// # Arguments
+    ///
+    /// * `block_offset`: data block 在 sstable 中的偏移量
+    /// * `key`: 提取前缀的 key
+    ///
+    /// returns: bool
+    pub fn prefix_may_match(&self, block_offset: u64, key: &[u8]) -> bool {
+        self.filter_may_match(block_offset, |filter| self.policy.prefix_may_match(key, filter))
+    }
+
+    /// 找到 block_offset 对应的 filter, 由 may_match 判断
+    fn filter_may_match<F: Fn(&[u8]) -> bool>(&self, block_offset: u64, may_match: F) -> bool {
+        let index = (block_offset >> self.base_lg) as usize;
+        if index < self.num {
+            let start = self.offset[index] as usize;
+            let limit = self.offset[index + 1] as usize;
+            if start < limit && limit <= self.data.len() {
+                return may_match(&self.data[start..limit]);
+            } else if start == limit {
+                // Empty filters do not match any keys
+                return false;
+            }
+        }
+        // Errors are treated as potential matches
+        true
     }
 
     pub fn get_policy(&self) -> FilterPolicyPtr {
         self.policy.clone()
     }
 
-    pub fn get_data(&self) -> Vec<u32> {
+    pub fn get_data(&self) -> Vec<u8> {
         self.data.to_vec()
     }
 
@@ -338,4 +398,69 @@ impl FilterBlockReader {
     pub fn get_base_lg(&self) -> usize {
         self.base_lg
     }
-}
\ No newline at end of file
+}
+impl FullFilterBlockBuilder {
+    pub fn new_with_policy(policy: FilterPolicyPtr) -> Self {
+        Self {
+            policy,
+            keys: vec![],
+            start: vec![],
+            result: vec![],
+        }
+    }
+
+    /// 添加 key 到 builder, 与 FilterBlock::add_key 相同
+    pub fn add_key(&mut self, key: &[u8]) {
+        self.start.push(self.keys.len());
+        self.keys.extend_from_slice(key);
+    }
+
+    /// 使用所有的 key 生成 filter, 返回 filter block 的内容
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut builder = FullFilterBlockBuilder::new_with_policy(policy.clone());
+    /// builder.add_key(b"foo");
+    /// let reader = FullFilterBlockReader::new_with_policy(policy, builder.finish());
+    /// assert!(reader.key_may_match(b"foo"));
+    /// ```
+    pub fn finish(&mut self) -> &[u8] {
+        self.start.push(self.keys.len());
+        let keys: Vec<&[u8]> = self.start.windows(2)
+            .map(|w| &self.keys[w[0]..w[1]])
+            .collect();
+        self.result.clear();
+        self.policy.create_filter(&keys, &mut self.result);
+
+        self.keys.clear();
+        self.start.clear();
+        &self.result
+    }
+
+    pub fn get_policy(&self) -> FilterPolicyPtr {
+        self.policy.clone()
+    }
+}
+
+impl FullFilterBlockReader {
+    /// contents 为 FullFilterBlockBuilder::finish 的结果
+    pub fn new_with_policy(policy: FilterPolicyPtr, contents: &[u8]) -> Self {
+        Self {
+            policy,
+            data: contents.to_vec(),
+        }
+    }
+
+    /// 判断 key 是否可能存在于 table 中。
+    ///
+    /// 如果 key 存在，一定返回 true。 如果 key 不存在，可能返回 true 也可能返回 false。
+    pub fn key_may_match(&self, key: &[u8]) -> bool {
+        self.policy.key_may_match(key, &self.data)
+    }
+
+    /// 判断 key 的前缀是否可能存在于 table 中, 参见 FilterPolicy::prefix_may_match
+    pub fn prefix_may_match(&self, key: &[u8]) -> bool {
+        self.policy.prefix_may_match(key, &self.data)
+    }
+}
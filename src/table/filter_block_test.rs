@@ -4,7 +4,7 @@ mod test {
     use std::sync::Arc;
     use crate::debug;
     use crate::table::filter_block;
-    use crate::table::filter_block::{FilterBlock, FilterBlockBuilder, FilterBlockReader};
+    use crate::table::filter_block::{FilterBlock, FilterBlockBuilder, FilterBlockReader, FullFilterBlockBuilder, FullFilterBlockReader};
     use crate::table::filter_block_test_filter_policy::TestHashFilter;
     use crate::traits::filter_policy_trait::FilterPolicy;
     use crate::util::slice::Slice;
@@ -25,7 +25,6 @@ mod test {
         assert_eq!(filter_block.get_keys().len(), 0);
         assert_eq!(filter_block.get_result().len(), 0);
         assert_eq!(filter_block.get_start().len(), 0);
-        assert_eq!(filter_block.get_tmp_keys().len(), 0);
         assert_eq!(filter_block.get_tmp_filter_offsets().len(), 0);
     }
 
@@ -67,14 +66,123 @@ mod test {
         let reader = FilterBlockReader::new_with_policy(
             policy.clone(), &sliceRs.unwrap());
 
-        // todo  key_may_match  not impl
-        // assert!(reader.key_may_match(100, &Slice::from("foo")));
-        // assert!(reader.key_may_match(100, &Slice::from("bar")));
-        // assert!(reader.key_may_match(100, &Slice::from("box")));
-        // assert!(reader.key_may_match(100, &Slice::from("hello")));
-        // assert!(reader.key_may_match(100, &Slice::from("foo")));
-        // assert!(!reader.key_may_match(100, &Slice::from("missing")));
-        // assert!(!reader.key_may_match(100, &Slice::from("other")));
+        assert!(reader.key_may_match(100, &Slice::from("foo")));
+        assert!(reader.key_may_match(100, &Slice::from("bar")));
+        assert!(reader.key_may_match(100, &Slice::from("box")));
+        assert!(reader.key_may_match(100, &Slice::from("hello")));
+        assert!(reader.key_may_match(100, &Slice::from("foo")));
+        assert!(!reader.key_may_match(100, &Slice::from("missing")));
+        assert!(!reader.key_may_match(100, &Slice::from("other")));
+    }
+
+    #[test]
+    fn test_filter_block_empty_builder() -> Result<()> {
+        let policy: Arc<Box<dyn FilterPolicy>> = Arc::new(Box::new(TestHashFilter::new()));
+        let mut filter_block_builder = FilterBlockBuilder::new_with_policy(policy.clone());
+        let block = filter_block_builder.finish()?;
+        assert_eq!(&[0, 0, 0, 0, 11], block.as_ref());
+
+        let reader = FilterBlockReader::new_with_policy(policy, &block);
+        assert_eq!(0, reader.get_num());
+        assert_eq!(11, reader.get_base_lg());
+        // 没有对应的 filter 时视为可能存在
+        assert!(reader.key_may_match(0, &Slice::from("foo")));
+        assert!(reader.key_may_match(100000, &Slice::from("foo")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_block_multi_chunk() -> Result<()> {
+        let policy: Arc<Box<dyn FilterPolicy>> = Arc::new(Box::new(TestHashFilter::new()));
+        let mut filter_block_builder = FilterBlockBuilder::new_with_policy(policy.clone());
+
+        // First filter
+        filter_block_builder.start_block(0);
+        filter_block_builder.add_key_from_str("foo");
+        filter_block_builder.start_block(2000);
+        filter_block_builder.add_key_from_str("bar");
+
+        // Second filter
+        filter_block_builder.start_block(3100);
+        filter_block_builder.add_key_from_str("box");
+
+        // Third filter is empty
+
+        // Last filter
+        filter_block_builder.start_block(9000);
+        filter_block_builder.add_key_from_str("box");
+        filter_block_builder.add_key_from_str("hello");
+
+        let block = filter_block_builder.finish()?;
+        let reader = FilterBlockReader::new_with_policy(policy, &block);
+        assert_eq!(5, reader.get_num());
+
+        // Check first filter
+        assert!(reader.key_may_match(0, &Slice::from("foo")));
+        assert!(reader.key_may_match(2000, &Slice::from("bar")));
+        assert!(!reader.key_may_match(0, &Slice::from("box")));
+        assert!(!reader.key_may_match(0, &Slice::from("hello")));
+
+        // Check second filter
+        assert!(reader.key_may_match(3100, &Slice::from("box")));
+        assert!(!reader.key_may_match(3100, &Slice::from("foo")));
+        assert!(!reader.key_may_match(3100, &Slice::from("bar")));
+        assert!(!reader.key_may_match(3100, &Slice::from("hello")));
+
+        // Check third filter (empty)
+        assert!(!reader.key_may_match(4100, &Slice::from("foo")));
+        assert!(!reader.key_may_match(4100, &Slice::from("bar")));
+        assert!(!reader.key_may_match(4100, &Slice::from("box")));
+        assert!(!reader.key_may_match(4100, &Slice::from("hello")));
+
+        // Check last filter
+        assert!(reader.key_may_match(9000, &Slice::from("box")));
+        assert!(reader.key_may_match(9000, &Slice::from("hello")));
+        assert!(!reader.key_may_match(9000, &Slice::from("foo")));
+        assert!(!reader.key_may_match(9000, &Slice::from("bar")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_filter_block() {
+        let policy: Arc<Box<dyn FilterPolicy>> = Arc::new(Box::new(TestHashFilter::new()));
+        let mut builder = FullFilterBlockBuilder::new_with_policy(policy.clone());
+        for key in ["foo", "bar", "box", "box", "hello"] {
+            builder.add_key(key.as_bytes());
+        }
+        let contents = builder.finish().to_vec();
+
+        // 所有的 key 生成一个 filter, 没有 offset 数组
+        let mut expected = vec![];
+        policy.create_filter(&[b"foo", b"bar", b"box", b"box", b"hello"], &mut expected);
+        assert_eq!(expected, contents);
+
+        let reader = FullFilterBlockReader::new_with_policy(policy.clone(), &contents);
+        assert!(reader.key_may_match(b"foo"));
+        assert!(reader.key_may_match(b"bar"));
+        assert!(reader.key_may_match(b"box"));
+        assert!(reader.key_may_match(b"hello"));
+        assert!(!reader.key_may_match(b"missing"));
+        assert!(!reader.key_may_match(b"other"));
+
+        // 没有 key 时生成空的 filter
+        let mut builder = FullFilterBlockBuilder::new_with_policy(policy.clone());
+        let reader = FullFilterBlockReader::new_with_policy(policy, builder.finish());
+        assert!(!reader.key_may_match(b"foo"));
+    }
+
+    #[test]
+    fn test_filter_block_binary_key() -> Result<()> {
+        let policy: Arc<Box<dyn FilterPolicy>> = Arc::new(Box::new(TestHashFilter::new()));
+        let mut filter_block_builder = FilterBlockBuilder::new_with_policy(policy.clone());
+        filter_block_builder.start_block(0);
+        filter_block_builder.add_key(&Slice::from_buf(&[0xff, 0x00, 0x80]));
+
+        let block = filter_block_builder.finish()?;
+        let reader = FilterBlockReader::new_with_policy(policy, &block);
+        assert!(reader.key_may_match(0, &Slice::from_buf(&[0xff, 0x00, 0x80])));
+        assert!(!reader.key_may_match(0, &Slice::from_buf(&[0xff, 0x00])));
+        Ok(())
     }
 
     // #[test]
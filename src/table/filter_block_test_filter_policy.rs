@@ -23,38 +23,22 @@ impl FilterPolicy for TestHashFilter {
         String::from("TestHashFilter")
     }
 
-    fn create_filter(&self, keys: Vec<&Slice>) -> Slice {
+    fn create_filter(&self, keys: &[&[u8]], dst: &mut Vec<u8>) {
         // 每个 key 都会 hash_code 转为 u32， 所以 * 4
-        let mut len: usize = keys.len() * 4;
+        dst.reserve(keys.len() * 4);
+        let mut encoder = Encoder::with_vec_append(dst);
+        for key in keys {
+            let h = Hash::hash_code(key, 1); // seed 固定为 1
 
-        self.create_filter_with_len(len, keys)
-    }
-
-    fn create_filter_with_len(&self, capacity: usize, keys: Vec<&Slice>) -> Slice {
-        // Actually capacity
-        let mut len: usize = capacity;
-
-        let need_capacity = keys.len() * 4;
-        // 指定大小和 need_capacity 取最大值
-        len = max(len, need_capacity);
-
-        let mut dst_chars = vec![0; len];
-        let mut encoder = Encoder::with_vec(&mut dst_chars);
-        // for [0, len)
-        for i in 0..keys.len() {
-            let h = Hash::hash_code(keys[i].as_ref(), 1); // seed 固定为 1
-
-            encoder.put_fixed32(h).expect("Encoder:with_vec.put_fixed32 error");
+            encoder.put_fixed32(h).expect("Encoder:with_vec_append.put_fixed32 error");
         }
-        debug!("debug: dst_chars:{:?}", dst_chars);
-
-        Slice::from_vec(dst_chars)
+        debug!("debug: dst:{:?}", dst);
     }
 
-    fn key_may_match(&self, key: &Slice, bloom_filter: &Slice) -> bool {
-        let h = Hash::hash_code(key.to_vec().as_ref(), 1);
+    fn key_may_match(&self, key: &[u8], filter: &[u8]) -> bool {
+        let h = Hash::hash_code(key, 1);
 
-        let mut decoder = Decoder::with_buf(bloom_filter);
+        let mut decoder = Decoder::with_buf(filter);
         loop {
             if !decoder.can_get() {
                 return false;
@@ -77,13 +61,13 @@ fn test_create_filter() {
     let s2 = Slice::try_from("world").unwrap();
     let s3 = Slice::try_from("hello world").unwrap();
 
-    let mut keys: Vec<&Slice> = Vec::new();
+    let mut keys: Vec<&[u8]> = Vec::new();
     keys.push(&s1);
     keys.push(&s2);
     keys.push(&s3);
 
-    let bloom_filter: Slice = policy.create_filter(keys);
-    debug!("bloom_filter:{:?}", bloom_filter);
+    let mut bloom_filter: Vec<u8> = vec![];
+    policy.create_filter(&keys, &mut bloom_filter);
 
     // 验证通过
     let mut key_may_match = policy.key_may_match(
@@ -123,9 +107,9 @@ fn test_create_filter() {
     assert!(!key_not_match);
 }
 
-/// 指定超长长度。可以超过放置的值
+/// 追加到已有较长数据的 dst 中
 #[test]
-fn test_create_filter_with_long_len() {
+fn test_create_filter_append_long_prefix() {
     let policy = TestHashFilter::new();
 
     // 如下三个值， 存放在 BloomFilter 中
@@ -133,12 +117,16 @@ fn test_create_filter_with_long_len() {
     let s2 = Slice::try_from("world").unwrap();
     let s3 = Slice::try_from("hello world").unwrap();
 
-    let mut keys: Vec<&Slice> = Vec::new();
+    let mut keys: Vec<&[u8]> = Vec::new();
     keys.push(&s1);
     keys.push(&s2);
     keys.push(&s3);
 
-    let bloom_filter: Slice = policy.create_filter_with_len(500, keys);
+    // dst 中已有 500 字节的数据, filter 追加在其后
+    let mut dst: Vec<u8> = vec![0xff; 500];
+    policy.create_filter(&keys, &mut dst);
+    assert_eq!(vec![0xff; 500], dst[..500].to_vec());
+    let bloom_filter = &dst[500..];
 
     // 验证通过
     let mut key_may_match = policy.key_may_match(
@@ -178,9 +166,9 @@ fn test_create_filter_with_long_len() {
     assert!(!key_not_match);
 }
 
-/// 指定端长度。放不开放置的值。 此时需要扩容
+/// 追加到已有较短数据的 dst 中
 #[test]
-fn test_create_filter_with_short_len() {
+fn test_create_filter_append_short_prefix() {
     let policy = TestHashFilter::new();
 
     // 如下三个值， 存放在 BloomFilter 中
@@ -188,12 +176,16 @@ fn test_create_filter_with_short_len() {
     let s2 = Slice::try_from("world").unwrap();
     let s3 = Slice::try_from("hello world").unwrap();
 
-    let mut keys: Vec<&Slice> = Vec::new();
+    let mut keys: Vec<&[u8]> = Vec::new();
     keys.push(&s1);
     keys.push(&s2);
     keys.push(&s3);
 
-    let bloom_filter: Slice = policy.create_filter_with_len(5, keys);
+    // dst 中已有 5 字节的数据, filter 追加在其后
+    let mut dst: Vec<u8> = vec![0xff; 5];
+    policy.create_filter(&keys, &mut dst);
+    assert_eq!(vec![0xff; 5], dst[..5].to_vec());
+    let bloom_filter = &dst[5..];
 
     // 验证通过
     let mut key_may_match = policy.key_may_match(
@@ -1,6 +1,13 @@
+use crate::util::coding::{Decoder, Encoder};
+use crate::util::crc::CRC;
+use crate::util::env::RandomAccessFile;
+use crate::util::compression::{LZ4, Zstd};
+use crate::util::options::{ChecksumType, CompressionType, Options, ReadOptions};
 use crate::util::slice::Slice;
 use crate::util::Result;
-use crate::util::status::Status;
+use crate::util::snappy::Snappy;
+use crate::util::status::LevelError;
+use xxhash_rust::xxh64::Xxh64;
 
 /// Maximum encoding length of a BlockHandle
 pub const k_max_encoded_length: u32 = 10 + 10;
@@ -16,6 +23,9 @@ pub const k_table_magic_number: u64 = 0xdb4775248b80fb57;
 /// 1-byte type + 32-bit crc
 pub const k_block_trailer_size: usize = 5;
 
+/// BlockHandle is a pointer to the extent of a file that stores a data
+/// block or a meta block.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct BlockHandle {
     // 偏移量， 编码为可变长度的64位整列，最多占用10个字节
     offset: u64,
@@ -23,7 +33,7 @@ pub struct BlockHandle {
     size: u64
 }
 
-trait ToBlockHandle {
+pub trait ToBlockHandle {
     ///
     /// The offset of the block in the file.
     ///
@@ -49,33 +59,47 @@ trait ToBlockHandle {
     fn set_size(&mut self, size: u64);
 
     ///
-    /// 将 Block 对象编码成 Slice
+    /// 将 BlockHandle 编码后追加到 dst 中
     ///
     /// # Arguments
     ///
-    /// returns: Slice
+    /// * `dst`: 编码的输出
+    ///
+    /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use level_db_rust::table::format::{BlockHandle, ToBlockHandle};
     ///
+    /// let handle = BlockHandle::new(1000, 200);
+    /// let mut dst = vec![];
+    /// handle.encode_to(&mut dst);
     /// ```
-    fn encode_to(&self) -> Result<Slice>;
+    fn encode_to(&self, dst: &mut Vec<u8>);
 
     ///
-    /// 将 Slice 对象解码后与BlockHandle set field
+    /// 从 input 中解码出 BlockHandle 的各个字段, input 的偏移量会移动到 BlockHandle 之后
     ///
     /// # Arguments
-    /// * `input`:
+    /// * `input`: 解码器
     ///
     /// returns: Result
     ///
     /// # Examples
     ///
     /// ```
-    ///
+    /// use level_db_rust::table::format::{BlockHandle, ToBlockHandle};
+    /// use level_db_rust::util::coding::Decoder;
+    ///
+    /// let mut data = vec![];
+    /// BlockHandle::new(1000, 200).encode_to(&mut data);
+    /// let mut decoder = Decoder::with_buf(&data);
+    /// let mut handle = BlockHandle::default();
+    /// handle.decode_from(&mut decoder).unwrap();
+    /// assert_eq!(200, handle.size());
     /// ```
-    fn decode_from(&mut self, input: Slice) -> Result<()>;
+    fn decode_from(&mut self, input: &mut Decoder) -> Result<()>;
 }
 
 /// Footer 的大小为 48 字节，最后8个字节为 magic number， 通过魔术对比，可以判断一个文件是否为 SST 文件。
@@ -86,12 +110,18 @@ trait ToBlockHandle {
 ///     因此如果前两部分不足40字节，则需要padding结构补充，这也构成了第三部分。
 ///  PS: 可变长度编码 变长的64位整型。
 ///
+/// padding 的最后一个字节 (magic number 之前) 记录 block 的校验算法 (ChecksumType), 两个 BlockHandle 最多使用前 39 个字节。
+/// 文件中的偏移量及大小都小于 2^63, 每个 varint 最多 9 字节, 两个 BlockHandle 最多 36 字节。
+/// LevelDB 生成的 table 中该字节为 0, 即 ChecksumType::Crc32c
+///
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
 pub struct Footer {
     meta_index_handle: BlockHandle,
-    index_handle: BlockHandle
+    index_handle: BlockHandle,
+    checksum: ChecksumType,
 }
 
-trait ToFoot {
+pub trait ToFoot {
     // The block handle for the metaindex block of the table
     fn meta_index_handle(&self) -> BlockHandle;
 
@@ -101,34 +131,63 @@ trait ToFoot {
 
     fn set_index_handle(&mut self, h: BlockHandle);
 
+    /// table 中所有 block 使用的校验算法
+    fn checksum(&self) -> ChecksumType;
+
+    fn set_checksum(&mut self, checksum: ChecksumType);
+
+    ///
+    /// 将 Footer 编码后追加到 dst 中, 固定占用 k_encoded_length 个字节
     ///
-    /// 将 Foot 对象编码成 Slice
+    /// 两个 BlockHandle 的编码超过 39 个字节 (会覆盖校验算法的字节) 时 panic
     ///
     /// # Arguments
     ///
-    /// returns: Slice
+    /// * `dst`: 编码的输出
+    ///
+    /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use level_db_rust::table::format::{BlockHandle, Footer, k_encoded_length, ToFoot};
     ///
+    /// let footer = Footer::new(BlockHandle::new(1000, 200), BlockHandle::new(1205, 300));
+    /// let mut dst = vec![];
+    /// footer.encode_to(&mut dst);
+    /// assert_eq!(k_encoded_length as usize, dst.len());
     /// ```
-    fn encode_to(&self) -> Result<Slice>;
+    fn encode_to(&self, dst: &mut Vec<u8>);
 
     ///
-    /// 将 Slice 对象解码后与 BlockHandle 比较，是否可以成功
+    /// 从 input 中解码 Footer, 并校验 magic number
     ///
     /// # Arguments
-    /// * `input`:
+    /// * `input`: Footer 的编码, 长度至少为 k_encoded_length
     ///
     /// returns: Result
     ///
     /// # Examples
     ///
     /// ```
+    /// use level_db_rust::table::format::{BlockHandle, Footer, ToFoot};
     ///
+    /// let mut data = vec![];
+    /// Footer::new(BlockHandle::new(1000, 200), BlockHandle::new(1205, 300)).encode_to(&mut data);
+    /// let mut footer = Footer::default();
+    /// footer.decode_from(&data).unwrap();
+    /// assert_eq!(BlockHandle::new(1205, 300), footer.index_handle());
     /// ```
-    fn decode_from(&mut self, input: Slice) -> Result<()>;
+    fn decode_from(&mut self, input: &[u8]) -> Result<()>;
+}
+
+impl BlockHandle {
+    pub fn new(offset: u64, size: u64) -> Self {
+        Self {
+            offset,
+            size,
+        }
+    }
 }
 
 impl ToBlockHandle for BlockHandle {
@@ -148,21 +207,22 @@ impl ToBlockHandle for BlockHandle {
         self.size = size;
     }
 
-    fn encode_to(&self) -> Result<Slice> {
-        todo!()
-
-        // // Sanity check that all fields have been set
-        // assert!(self.offset_ != 0);
-        // assert!(self.size_ != 0);
-        //
-        // let mut buf: [u8; 4] = [0, 0, 0, 0];
-        // coding::Coding::put_varint64(&mut buf, 0, &self.offset_);
-        //
-        // Slice::default()
+    fn encode_to(&self, dst: &mut Vec<u8>) {
+        let mut encoder = Encoder::with_vec_append(dst);
+        // 写入 Vec 时会自动扩容, 不会失败
+        encoder.put_varint64(self.offset).expect("put offset");
+        encoder.put_varint64(self.size).expect("put size");
     }
 
-    fn decode_from(&mut self, input: Slice) -> Result<()> {
-        todo!()
+    fn decode_from(&mut self, input: &mut Decoder) -> Result<()> {
+        match (input.get_varint64(), input.get_varint64()) {
+            (Ok(offset), Ok(size)) => {
+                self.offset = offset;
+                self.size = size;
+                Ok(())
+            }
+            _ => Err(LevelError::corruption_string("bad block handle", ""))
+        }
     }
 }
 
@@ -176,58 +236,179 @@ impl Default for BlockHandle {
     }
 }
 
+impl Footer {
+    pub fn new(meta_index_handle: BlockHandle, index_handle: BlockHandle) -> Self {
+        Self {
+            meta_index_handle,
+            index_handle,
+            checksum: ChecksumType::Crc32c,
+        }
+    }
+}
+
 impl ToFoot for Footer {
     /// The block handle for the metaindex block of the table
     fn meta_index_handle(&self) -> BlockHandle {
-        todo!()
+        self.meta_index_handle
     }
 
     fn set_metaindex_handle(&mut self, h: BlockHandle) {
-        todo!()
+        self.meta_index_handle = h;
     }
 
     fn index_handle(&self) -> BlockHandle {
-        todo!()
+        self.index_handle
     }
 
     fn set_index_handle(&mut self, h: BlockHandle) {
-        todo!()
+        self.index_handle = h;
     }
 
-    fn encode_to(&self) -> Result<Slice> {
-        todo!()
+    fn checksum(&self) -> ChecksumType {
+        self.checksum
     }
 
-    fn decode_from(&mut self, input: Slice) -> Result<()> {
-        todo!()
+    fn set_checksum(&mut self, checksum: ChecksumType) {
+        self.checksum = checksum;
+    }
+
+    fn encode_to(&self, dst: &mut Vec<u8>) {
+        let original_size = dst.len();
+        self.meta_index_handle.encode_to(dst);
+        self.index_handle.encode_to(dst);
+        // Padding, 最后一个字节保存校验算法
+        let checksum_offset = original_size + 2 * k_max_encoded_length as usize - 1;
+        assert!(dst.len() <= checksum_offset, "block handles overlap the checksum type in footer");
+        dst.resize(checksum_offset, 0);
+        dst.push(self.checksum as u8);
+        let mut encoder = Encoder::with_vec_append(dst);
+        encoder.put_fixed32((k_table_magic_number & 0xffffffff) as u32).expect("put magic");
+        encoder.put_fixed32((k_table_magic_number >> 32) as u32).expect("put magic");
+        debug_assert_eq!(dst.len(), original_size + k_encoded_length as usize);
+    }
+
+    fn decode_from(&mut self, input: &[u8]) -> Result<()> {
+        if input.len() < k_encoded_length as usize {
+            return Err(LevelError::corruption_string("not an sstable", "footer too short"));
+        }
+        let magic_offset = k_encoded_length as usize - 8;
+        let mut decoder = Decoder::with_buf(&input[magic_offset..k_encoded_length as usize]);
+        let magic_lo = decoder.get_fixed32()? as u64;
+        let magic_hi = decoder.get_fixed32()? as u64;
+        let magic = (magic_hi << 32) | magic_lo;
+        if magic != k_table_magic_number {
+            return Err(LevelError::corruption_string("not an sstable", "bad magic number"));
+        }
+
+        self.checksum = ChecksumType::try_from(input[magic_offset - 1])?;
+        let mut decoder = Decoder::with_buf(&input[..magic_offset - 1]);
+        self.meta_index_handle.decode_from(&mut decoder)?;
+        self.index_handle.decode_from(&mut decoder)
     }
 }
 
 /// ############################# BlockContent
 pub struct BlockContent {
     // Actual contents of data
-    data: Slice,
+    pub data: Slice,
 
     // True if data can be cached
-    cachable: bool,
+    pub cachable: bool,
 
     // True if caller should delete[] data.data()
-    heap_allocated:bool,
+    pub heap_allocated: bool,
 }
 
-trait ToBlockContent {
-    /// Read the block identified by "handle" from "file".  On failure
-    /// return non-OK.  On success fill *result and return OK.
-    fn read_block(&self,
-                  // todo RandomAccessFile, ReadOptions 未提供
-                  // file: RandomAccessFile, options: ReadOptions,
-                  handle: BlockHandle
-    ) -> Result<BlockContent>;
-
+/// 计算 block trailer 中保存的校验值, 校验的内容为 block 的数据及 1 字节的压缩类型
+///
+/// # Arguments
+///
+/// * `checksum`: 校验算法
+/// * `contents`: block 的数据 (压缩后)
+/// * `compression_type`: trailer 中的压缩类型
+///
+/// returns: u32
+///
+/// # Examples
+///
+/// ```
+/// use level_db_rust::table::format::block_checksum;
+/// use level_db_rust::util::options::ChecksumType;
+///
+/// let value = block_checksum(ChecksumType::Crc32c, b"block contents", 0);
+/// ```
+pub fn block_checksum(checksum: ChecksumType, contents: &[u8], compression_type: u8) -> u32 {
+    match checksum {
+        ChecksumType::Crc32c => CRC::mask(CRC::extend(CRC::value(contents), &[compression_type])),
+        ChecksumType::XxHash64 => {
+            let mut hasher = Xxh64::new(0);
+            hasher.update(contents);
+            hasher.update(&[compression_type]);
+            hasher.digest() as u32
+        }
+    }
 }
 
-impl ToBlockContent for BlockContent {
-    fn read_block(&self, handle: BlockHandle) -> Result<BlockContent> {
-        todo!()
+/// Read the block identified by "handle" from "file".  On failure
+/// return non-OK.  On success fill *result and return OK.
+///
+/// 读取 block 并按 trailer 中的压缩类型解压, verify_checksums 为 true 时使用 checksum 校验 block,
+/// 校验失败时返回的 Corruption 中包含 block 的偏移量。
+/// 不支持的压缩类型返回 NotSupported
+///
+/// # Arguments
+///
+/// * `file`: SSTable 文件
+/// * `table_options`: table 的配置, 使用其中的 zstd_dictionary
+/// * `options`: 读取的配置
+/// * `checksum`: table 的校验算法, 即 Footer::checksum
+/// * `handle`: block 在文件中的位置
+///
+/// returns: Result<BlockContent, Status>
+///
+/// # Examples
+///
+/// ```ignore
+/// let contents = read_block(file.as_ref(), &options, &ReadOptions::default(), footer.checksum(), &footer.index_handle())?;
+/// ```
+pub fn read_block(file: &dyn RandomAccessFile, table_options: &Options, options: &ReadOptions,
+                  checksum: ChecksumType, handle: &BlockHandle) -> Result<BlockContent> {
+    // Read the block contents as well as the type/crc footer.
+    // See table_builder.cc for the code that built this structure.
+    let n = handle.size() as usize;
+    let contents = file.read(handle.offset(), n + k_block_trailer_size)?;
+    if contents.size() != n + k_block_trailer_size {
+        return Err(LevelError::corruption_string("truncated block read", ""));
+    }
+
+    // Check the checksum of the type and the block contents
+    if options.verify_checksums {
+        let mut decoder = Decoder::with_buf(&contents[n + 1..]);
+        let expected = decoder.get_fixed32()?;
+        let actual = block_checksum(checksum, &contents[..n], contents[n]);
+        if actual != expected {
+            return Err(LevelError::corruption_string("block checksum mismatch",
+                                                     &format!("block offset {}", handle.offset())));
+        }
     }
+
+    let data = match CompressionType::try_from(contents[n])? {
+        CompressionType::NoCompression => {
+            let mut data: Vec<u8> = contents.into();
+            data.truncate(n);
+            data
+        }
+        CompressionType::SnappyCompression => Snappy::decompress(&contents[..n])
+            .map_err(|_| LevelError::corruption_string("corrupted compressed block contents", ""))?,
+        CompressionType::LZ4Compression => LZ4::uncompress(&contents[..n])
+            .map_err(|_| LevelError::corruption_string("corrupted compressed block contents", ""))?,
+        CompressionType::ZstdCompression => Zstd::uncompress(&contents[..n], table_options.zstd_dictionary.as_deref())
+            .map_err(|_| LevelError::corruption_string("corrupted compressed block contents", ""))?,
+    };
+    Ok(BlockContent {
+        data: Slice::from_vec(data),
+        cachable: true,
+        heap_allocated: true,
+    })
 }
+
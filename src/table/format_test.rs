@@ -1,7 +1,106 @@
-
+use crate::table::format::{block_checksum, BlockHandle, Footer, k_encoded_length, k_max_encoded_length, ToBlockHandle, ToFoot};
+use crate::util::coding::Decoder;
+use crate::util::options::ChecksumType;
 
 // ####################  BlockHandle test
 #[test]
 fn test_block_handle() {
-    todo!()
-}
\ No newline at end of file
+    for (offset, size) in [(0, 0), (1, 2), (127, 128), (1 << 32, 300000), (u64::MAX, u64::MAX)] {
+        let handle = BlockHandle::new(offset, size);
+        let mut encoding = vec![];
+        handle.encode_to(&mut encoding);
+        assert!(encoding.len() <= k_max_encoded_length as usize);
+
+        // 解码后偏移量移动到 BlockHandle 之后
+        encoding.push(0xff);
+        let mut decoder = Decoder::with_buf(&encoding);
+        let mut decoded = BlockHandle::default();
+        decoded.decode_from(&mut decoder).unwrap();
+        assert_eq!(handle, decoded);
+        assert_eq!(offset, decoded.offset());
+        assert_eq!(size, decoded.size());
+        assert_eq!(encoding.len() - 1, decoder.offset());
+    }
+
+    let mut decoder = Decoder::with_buf(&[]);
+    let status = BlockHandle::default().decode_from(&mut decoder).unwrap_err();
+    assert!(status.is_corruption());
+}
+
+#[test]
+fn test_footer() {
+    let footer = Footer::new(BlockHandle::new(1000, 200), BlockHandle::new(1205, 1 << 20));
+    // Footer 之前已有的数据不会被覆盖
+    let mut encoding = vec![7_u8; 3];
+    footer.encode_to(&mut encoding);
+    assert_eq!(3 + k_encoded_length as usize, encoding.len());
+    assert_eq!(vec![7_u8; 3], encoding[..3]);
+
+    let mut decoded = Footer::default();
+    decoded.decode_from(&encoding[3..]).unwrap();
+    assert_eq!(footer, decoded);
+    assert_eq!(BlockHandle::new(1000, 200), decoded.meta_index_handle());
+    assert_eq!(BlockHandle::new(1205, 1 << 20), decoded.index_handle());
+
+    // 长度不足
+    let status = Footer::default().decode_from(&encoding[4..]).unwrap_err();
+    assert!(status.is_corruption());
+
+    // magic number 不正确
+    let last = encoding.len() - 1;
+    encoding[last] ^= 1;
+    let status = Footer::default().decode_from(&encoding[3..]).unwrap_err();
+    assert!(status.is_corruption());
+    assert!(status.get_msg().contains("bad magic number"), "{}", status.get_msg());
+}
+
+#[test]
+fn test_footer_checksum() {
+    let mut footer = Footer::new(BlockHandle::new(1000, 200), BlockHandle::new(1205, 300));
+    assert_eq!(ChecksumType::Crc32c, footer.checksum());
+    footer.set_checksum(ChecksumType::XxHash64);
+
+    let mut encoding = vec![];
+    footer.encode_to(&mut encoding);
+    let mut decoded = Footer::default();
+    decoded.decode_from(&encoding).unwrap();
+    assert_eq!(ChecksumType::XxHash64, decoded.checksum());
+
+    // 未知的校验算法
+    let magic_offset = encoding.len() - 8;
+    encoding[magic_offset - 1] = 9;
+    let status = Footer::default().decode_from(&encoding).unwrap_err();
+    assert!(status.is_not_supported_error(), "{}", status.get_msg());
+}
+
+#[test]
+fn test_footer_max_handles() {
+    // 文件中偏移量及大小的最大值, 校验算法的字节不能被 BlockHandle 覆盖
+    let max = i64::MAX as u64;
+    let mut footer = Footer::new(BlockHandle::new(max, max), BlockHandle::new(max, max));
+    footer.set_checksum(ChecksumType::XxHash64);
+
+    let mut encoding = vec![];
+    footer.encode_to(&mut encoding);
+    assert_eq!(k_encoded_length as usize, encoding.len());
+    let mut decoded = Footer::default();
+    decoded.decode_from(&encoding).unwrap();
+    assert_eq!(footer, decoded);
+}
+
+#[test]
+#[should_panic(expected = "block handles overlap the checksum type in footer")]
+fn test_footer_handles_too_large() {
+    let footer = Footer::new(BlockHandle::new(u64::MAX, u64::MAX), BlockHandle::new(u64::MAX, u64::MAX));
+    footer.encode_to(&mut vec![]);
+}
+
+#[test]
+fn test_block_checksum() {
+    let crc = block_checksum(ChecksumType::Crc32c, b"block contents", 0);
+    let xxhash = block_checksum(ChecksumType::XxHash64, b"block contents", 0);
+    assert_ne!(crc, xxhash);
+    // 压缩类型也参与校验
+    assert_ne!(xxhash, block_checksum(ChecksumType::XxHash64, b"block contents", 1));
+    assert_eq!(xxhash, block_checksum(ChecksumType::XxHash64, b"block contents", 0));
+}
@@ -0,0 +1,78 @@
+use crate::traits::DataIterator;
+use crate::util::slice::Slice;
+use crate::util::status::Status;
+use crate::util::unsafe_slice::UnsafeSlice;
+
+/// 不包含任何数据的迭代器, 可以携带一个错误状态
+struct EmptyIterator {
+    status: Status,
+}
+
+impl DataIterator for EmptyIterator {
+    fn valid(&self) -> bool {
+        false
+    }
+
+    fn seek_to_first(&mut self) {}
+
+    fn seek_to_last(&mut self) {}
+
+    fn seek(&mut self, _target: &Slice) {}
+
+    fn next(&mut self) {
+        panic!("next on empty iterator");
+    }
+
+    fn pre(&mut self) {
+        panic!("pre on empty iterator");
+    }
+
+    fn key(&self) -> UnsafeSlice {
+        panic!("key on empty iterator");
+    }
+
+    fn value(&self) -> UnsafeSlice {
+        panic!("value on empty iterator");
+    }
+
+    fn status(&self) -> Status {
+        self.status.clone()
+    }
+}
+
+/// Return an empty iterator (yields nothing).
+///
+/// # Examples
+///
+/// ```
+/// use level_db_rust::table::iterator::new_empty_iterator;
+/// use level_db_rust::traits::DataIterator;
+///
+/// let iter = new_empty_iterator();
+/// assert!(!iter.valid());
+/// ```
+pub fn new_empty_iterator() -> Box<dyn DataIterator> {
+    Box::new(EmptyIterator { status: Status::default() })
+}
+
+/// Return an empty iterator with the specified status.
+///
+/// # Arguments
+///
+/// * `status`: 迭代器的状态
+///
+/// returns: Box<dyn DataIterator, Global>
+///
+/// # Examples
+///
+/// ```
+/// use level_db_rust::table::iterator::new_error_iterator;
+/// use level_db_rust::traits::DataIterator;
+/// use level_db_rust::util::status::LevelError;
+///
+/// let iter = new_error_iterator(LevelError::corruption_string("bad block handle", ""));
+/// assert!(iter.status().is_corruption());
+/// ```
+pub fn new_error_iterator(status: Status) -> Box<dyn DataIterator> {
+    Box::new(EmptyIterator { status })
+}
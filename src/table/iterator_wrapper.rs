@@ -1,3 +1,118 @@
+use crate::traits::DataIterator;
+use crate::util::slice::Slice;
+use crate::util::status::Status;
+use crate::util::unsafe_slice::UnsafeSlice;
+
+/// 包装一个 DataIterator, 缓存 valid() 及 key() 的结果
+///
+/// A internal wrapper class with an interface similar to Iterator that
+/// caches the valid() and key() results for an underlying iterator.
+/// This can help avoid virtual function calls and also gives better
+/// cache locality.
 pub struct IteratorWrapper {
+    iter: Option<Box<dyn DataIterator>>,
+    valid: bool,
+    // 迭代器有效时为当前的 key
+    key: Option<UnsafeSlice>,
+}
+
+impl IteratorWrapper {
+    /// 包装迭代器, iter 为 None 时迭代器无效
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut wrapper = IteratorWrapper::new(Some(block.new_iterator(cmp)));
+    /// wrapper.seek_to_first();
+    /// ```
+    pub fn new(iter: Option<Box<dyn DataIterator>>) -> Self {
+        let mut wrapper = Self {
+            iter: None,
+            valid: false,
+            key: None,
+        };
+        wrapper.set(iter);
+        wrapper
+    }
+
+    /// 被包装的迭代器
+    pub fn iter(&self) -> Option<&dyn DataIterator> {
+        self.iter.as_deref()
+    }
+
+    /// 替换被包装的迭代器, 原来的迭代器会被释放
+    ///
+    /// Takes ownership of "iter" and will delete it when destroyed, or
+    /// when Set() is invoked again.
+    pub fn set(&mut self, iter: Option<Box<dyn DataIterator>>) {
+        self.iter = iter;
+        if self.iter.is_none() {
+            self.valid = false;
+            self.key = None;
+        } else {
+            self.update();
+        }
+    }
+
+    #[inline]
+    pub fn valid(&self) -> bool {
+        self.valid
+    }
+
+    #[inline]
+    pub fn key(&self) -> UnsafeSlice {
+        assert!(self.valid());
+        self.key.expect("valid iterator has a key")
+    }
+
+    #[inline]
+    pub fn value(&self) -> UnsafeSlice {
+        assert!(self.valid());
+        self.inner().value()
+    }
+
+    /// Methods below require iter() != nullptr
+    pub fn status(&self) -> Status {
+        self.inner().status()
+    }
+
+    pub fn next(&mut self) {
+        self.inner_mut().next();
+        self.update();
+    }
+
+    pub fn pre(&mut self) {
+        self.inner_mut().pre();
+        self.update();
+    }
+
+    pub fn seek(&mut self, k: &Slice) {
+        self.inner_mut().seek(k);
+        self.update();
+    }
+
+    pub fn seek_to_first(&mut self) {
+        self.inner_mut().seek_to_first();
+        self.update();
+    }
+
+    pub fn seek_to_last(&mut self) {
+        self.inner_mut().seek_to_last();
+        self.update();
+    }
+
+    #[inline]
+    fn inner(&self) -> &dyn DataIterator {
+        self.iter.as_deref().expect("iterator is not set")
+    }
+
+    #[inline]
+    fn inner_mut(&mut self) -> &mut dyn DataIterator {
+        self.iter.as_deref_mut().expect("iterator is not set")
+    }
 
-}
\ No newline at end of file
+    fn update(&mut self) {
+        self.valid = self.inner().valid();
+        self.key = if self.valid { Some(self.inner().key()) } else { None };
+    }
+}
@@ -0,0 +1,204 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+use crate::table::iterator::new_empty_iterator;
+use crate::table::iterator_wrapper::IteratorWrapper;
+use crate::traits::comparator_trait::Comparator;
+use crate::traits::DataIterator;
+use crate::util::slice::Slice;
+use crate::util::status::Status;
+use crate::util::unsafe_slice::UnsafeSlice;
+
+/// 迭代的方向
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Direction {
+    Forward,
+    Reverse,
+}
+
+/// 归并多个有序的迭代器
+struct MergingIterator {
+    // We might want to use a heap in case there are lots of children.
+    // For now we use a simple array since we expect a very small number
+    // of children in leveldb.
+    comparator: Arc<dyn Comparator>,
+    children: Vec<IteratorWrapper>,
+    // 当前位置所在的 children 下标
+    current: Option<usize>,
+    direction: Direction,
+}
+
+/// 创建归并迭代器
+///
+/// Return an iterator that provided the union of the data in
+/// children[0,n-1].  Takes ownership of the child iterators and
+/// will delete them when the result iterator is deleted.
+///
+/// The result does no duplicate suppression.  I.e., if a particular
+/// key is present in K child iterators, it will be yielded K times.
+///
+/// # Arguments
+///
+/// * `comparator`: 所有 children 的 key 的顺序都由 comparator 决定
+/// * `children`: 子迭代器
+///
+/// returns: Box<dyn DataIterator, Global>
+///
+/// # Examples
+///
+/// ```ignore
+/// let iter = new_merging_iterator(Arc::new(icmp.clone()), vec![mem.new_iterator(), imm.new_iterator()]);
+/// ```
+pub fn new_merging_iterator(comparator: Arc<dyn Comparator>, mut children: Vec<Box<dyn DataIterator>>) -> Box<dyn DataIterator> {
+    match children.len() {
+        0 => new_empty_iterator(),
+        1 => children.pop().unwrap(),
+        _ => Box::new(MergingIterator {
+            comparator,
+            children: children.into_iter().map(|child| IteratorWrapper::new(Some(child))).collect(),
+            current: None,
+            direction: Direction::Forward,
+        })
+    }
+}
+
+impl MergingIterator {
+    #[inline]
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        self.comparator.compare(a, b).unwrap_or(Ordering::Equal)
+    }
+
+    /// key 相同时选择下标较小的 child
+    fn find_smallest(&mut self) {
+        let mut smallest: Option<usize> = None;
+        for (i, child) in self.children.iter().enumerate() {
+            if child.valid() {
+                match smallest {
+                    Some(s) if self.compare(child.key().as_ref(), self.children[s].key().as_ref()) != Ordering::Less => {}
+                    _ => smallest = Some(i),
+                }
+            }
+        }
+        self.current = smallest;
+    }
+
+    /// key 相同时选择下标较大的 child
+    fn find_largest(&mut self) {
+        let mut largest: Option<usize> = None;
+        for (i, child) in self.children.iter().enumerate().rev() {
+            if child.valid() {
+                match largest {
+                    Some(l) if self.compare(child.key().as_ref(), self.children[l].key().as_ref()) != Ordering::Greater => {}
+                    _ => largest = Some(i),
+                }
+            }
+        }
+        self.current = largest;
+    }
+
+    #[inline]
+    fn current(&self) -> &IteratorWrapper {
+        &self.children[self.current.expect("merging iterator is not valid")]
+    }
+}
+
+impl DataIterator for MergingIterator {
+    fn valid(&self) -> bool {
+        self.current.is_some()
+    }
+
+    fn seek_to_first(&mut self) {
+        for child in self.children.iter_mut() {
+            child.seek_to_first();
+        }
+        self.find_smallest();
+        self.direction = Direction::Forward;
+    }
+
+    fn seek_to_last(&mut self) {
+        for child in self.children.iter_mut() {
+            child.seek_to_last();
+        }
+        self.find_largest();
+        self.direction = Direction::Reverse;
+    }
+
+    fn seek(&mut self, target: &Slice) {
+        for child in self.children.iter_mut() {
+            child.seek(target);
+        }
+        self.find_smallest();
+        self.direction = Direction::Forward;
+    }
+
+    fn next(&mut self) {
+        assert!(self.valid());
+        let current = self.current.unwrap();
+
+        // Ensure that all children are positioned after key().
+        // If we are moving in the forward direction, it is already
+        // true for all of the non-current_ children since current_ is
+        // the smallest child and key() == current_->key().  Otherwise,
+        // we explicitly position the non-current_ children.
+        if self.direction != Direction::Forward {
+            let key = Slice::from_buf(self.key().as_ref());
+            for i in 0..self.children.len() {
+                if i != current {
+                    self.children[i].seek(&key);
+                    if self.children[i].valid()
+                        && self.compare(key.as_ref(), self.children[i].key().as_ref()) == Ordering::Equal {
+                        self.children[i].next();
+                    }
+                }
+            }
+            self.direction = Direction::Forward;
+        }
+
+        self.children[current].next();
+        self.find_smallest();
+    }
+
+    fn pre(&mut self) {
+        assert!(self.valid());
+        let current = self.current.unwrap();
+
+        // Ensure that all children are positioned before key().
+        // If we are moving in the reverse direction, it is already
+        // true for all of the non-current_ children since current_ is
+        // the largest child and key() == current_->key().  Otherwise,
+        // we explicitly position the non-current_ children.
+        if self.direction != Direction::Reverse {
+            let key = Slice::from_buf(self.key().as_ref());
+            for i in 0..self.children.len() {
+                if i != current {
+                    self.children[i].seek(&key);
+                    if self.children[i].valid() {
+                        // Child is at first entry >= key().  Step back one to be < key()
+                        self.children[i].pre();
+                    } else {
+                        // Child has no entries >= key().  Position at last entry.
+                        self.children[i].seek_to_last();
+                    }
+                }
+            }
+            self.direction = Direction::Reverse;
+        }
+
+        self.children[current].pre();
+        self.find_largest();
+    }
+
+    fn key(&self) -> UnsafeSlice {
+        self.current().key()
+    }
+
+    fn value(&self) -> UnsafeSlice {
+        self.current().value()
+    }
+
+    fn status(&self) -> Status {
+        self.children.iter()
+            .map(|child| child.status())
+            .find(|status| !status.is_ok())
+            .unwrap_or_default()
+    }
+}
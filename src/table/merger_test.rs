@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use crate::table::block::Block;
+    use crate::table::block_builder::BlockBuilder;
+    use crate::table::format::BlockContent;
+    use crate::table::iterator::new_error_iterator;
+    use crate::table::merger::new_merging_iterator;
+    use crate::traits::comparator_trait::Comparator;
+    use crate::traits::DataIterator;
+    use crate::util::comparator::BytewiseComparatorImpl;
+    use crate::util::slice::Slice;
+    use crate::util::status::LevelError;
+
+    fn cmp() -> Arc<dyn Comparator> {
+        Arc::new(BytewiseComparatorImpl::default())
+    }
+
+    fn block_iter(keys: &[&str]) -> Box<dyn DataIterator> {
+        let mut builder = BlockBuilder::new(2);
+        for k in keys {
+            builder.add(k.as_bytes(), format!("v_{}", k).as_bytes());
+        }
+        let block = Block::new(BlockContent {
+            data: Slice::from_buf(builder.finish()),
+            cachable: false,
+            heap_allocated: false,
+        });
+        block.new_iterator(cmp())
+    }
+
+    fn new_iter(children: &[&[&str]]) -> Box<dyn DataIterator> {
+        new_merging_iterator(cmp(), children.iter().map(|keys| block_iter(keys)).collect())
+    }
+
+    fn key_of(iter: &dyn DataIterator) -> String {
+        assert_eq!(format!("v_{}", iter.key().as_str()), iter.value().as_str());
+        iter.key().as_str().to_string()
+    }
+
+    fn forward(iter: &mut dyn DataIterator) -> Vec<String> {
+        let mut keys = vec![];
+        iter.seek_to_first();
+        while iter.valid() {
+            keys.push(key_of(iter));
+            iter.next();
+        }
+        keys
+    }
+
+    fn backward(iter: &mut dyn DataIterator) -> Vec<String> {
+        let mut keys = vec![];
+        iter.seek_to_last();
+        while iter.valid() {
+            keys.push(key_of(iter));
+            iter.pre();
+        }
+        keys
+    }
+
+    #[test]
+    fn test_merging_iterator() {
+        let mut iter = new_iter(&[&["a", "d", "g"], &["b", "e"], &[], &["c", "f", "h", "i"]]);
+        let expected: Vec<String> = "abcdefghi".chars().map(|c| c.to_string()).collect();
+        assert_eq!(expected, forward(iter.as_mut()));
+        assert_eq!(expected.iter().rev().cloned().collect::<Vec<_>>(), backward(iter.as_mut()));
+        assert!(iter.status().is_ok());
+
+        iter.seek(&Slice::from("dd"));
+        assert_eq!("e", key_of(iter.as_ref()));
+        iter.seek(&Slice::from("z"));
+        assert!(!iter.valid());
+    }
+
+    #[test]
+    fn test_merging_iterator_change_direction() {
+        let mut iter = new_iter(&[&["a", "d", "g"], &["b", "e"], &["c", "f"]]);
+
+        iter.seek(&Slice::from("d"));
+        assert_eq!("d", key_of(iter.as_ref()));
+        iter.pre();
+        assert_eq!("c", key_of(iter.as_ref()));
+        iter.pre();
+        assert_eq!("b", key_of(iter.as_ref()));
+        iter.next();
+        assert_eq!("c", key_of(iter.as_ref()));
+        iter.next();
+        assert_eq!("d", key_of(iter.as_ref()));
+        iter.next();
+        assert_eq!("e", key_of(iter.as_ref()));
+
+        iter.seek_to_first();
+        iter.pre();
+        assert!(!iter.valid());
+        iter.seek_to_last();
+        assert_eq!("g", key_of(iter.as_ref()));
+        iter.next();
+        assert!(!iter.valid());
+    }
+
+    #[test]
+    fn test_merging_iterator_empty() {
+        let mut iter = new_merging_iterator(cmp(), vec![]);
+        assert!(forward(iter.as_mut()).is_empty());
+        assert!(backward(iter.as_mut()).is_empty());
+
+        let mut iter = new_iter(&[&[], &[]]);
+        assert!(forward(iter.as_mut()).is_empty());
+        iter.seek(&Slice::from("a"));
+        assert!(!iter.valid());
+
+        // 只有一个子迭代器时与子迭代器相同
+        let mut iter = new_iter(&[&["a", "b"]]);
+        assert_eq!(vec!["a", "b"], forward(iter.as_mut()));
+    }
+
+    #[test]
+    fn test_merging_iterator_error() {
+        let children = vec![
+            block_iter(&["a"]),
+            new_error_iterator(LevelError::corruption_string("bad block", "")),
+        ];
+        let mut iter = new_merging_iterator(cmp(), children);
+        iter.seek_to_first();
+        assert_eq!("a", key_of(iter.as_ref()));
+        assert!(iter.status().is_corruption());
+    }
+}
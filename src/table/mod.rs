@@ -1,4 +1,5 @@
 pub mod block;
+mod block_test;
 pub mod block_builder;
 pub mod filter_block;
 mod filter_block_test;
@@ -7,8 +8,15 @@ pub mod format;
 mod format_test;
 pub mod ss_table;
 mod ss_table_test;
+pub mod iterator;
 pub mod iterator_wrapper;
+pub mod merger;
+mod merger_test;
+pub mod two_level_iterator;
+pub mod prefix_iterator;
 pub mod table_builder;
 mod table_builder_test;
 pub mod table;
-mod table_test;
\ No newline at end of file
+mod table_test;
+pub mod table_properties;
+mod table_properties_test;
\ No newline at end of file
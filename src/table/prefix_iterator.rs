@@ -0,0 +1,113 @@
+use crate::traits::DataIterator;
+use crate::traits::slice_transform_trait::SliceTransformPtr;
+use crate::util::slice::Slice;
+use crate::util::status::Status;
+use crate::util::unsafe_slice::UnsafeSlice;
+
+/// 判断 seek 的目标的前缀是否可能存在, 返回 false 时不会 seek 内部的迭代器
+pub type PrefixMayMatch = Box<dyn Fn(&[u8]) -> bool>;
+
+/// 按前缀迭代的迭代器 (ReadOptions::prefix_same_as_start)。
+///
+/// seek(target) 之后只返回与 target 前缀相同的 key, key 的前缀不同时变为无效;
+/// seek_to_first/seek_to_last 之后与内部的迭代器相同, 按完整的顺序迭代
+struct PrefixIterator {
+    iter: Box<dyn DataIterator>,
+    prefix_extractor: SliceTransformPtr,
+    prefix_may_match: PrefixMayMatch,
+    // 上一次 seek 的目标的前缀, 目标没有前缀或者 seek_to_first/seek_to_last 之后为 None
+    prefix: Option<Vec<u8>>,
+    // filter 判断前缀不存在, 没有 seek 内部的迭代器
+    filtered: bool,
+}
+
+/// 创建按前缀迭代的迭代器
+///
+/// # Arguments
+///
+/// * `iter`: 内部的迭代器
+/// * `prefix_extractor`: 前缀提取器, 与 iter 中 key 的格式相同
+/// * `prefix_may_match`: seek 时判断目标的前缀是否可能存在, 例如通过 table 的 filter 判断
+///
+/// returns: Box<dyn DataIterator, Global>
+///
+/// # Examples
+///
+/// ```ignore
+/// let iter = new_prefix_iterator(iter, prefix_extractor, Box::new(move |target| table.prefix_may_match(target)));
+/// ```
+pub fn new_prefix_iterator(iter: Box<dyn DataIterator>, prefix_extractor: SliceTransformPtr,
+                           prefix_may_match: PrefixMayMatch) -> Box<dyn DataIterator> {
+    Box::new(PrefixIterator {
+        iter,
+        prefix_extractor,
+        prefix_may_match,
+        prefix: None,
+        filtered: false,
+    })
+}
+
+impl DataIterator for PrefixIterator {
+    fn valid(&self) -> bool {
+        if self.filtered || !self.iter.valid() {
+            return false;
+        }
+        match &self.prefix {
+            None => true,
+            Some(prefix) => {
+                let key = self.iter.key();
+                self.prefix_extractor.in_domain(key.as_ref())
+                    && self.prefix_extractor.transform(key.as_ref()) == prefix.as_slice()
+            }
+        }
+    }
+
+    fn seek_to_first(&mut self) {
+        self.prefix = None;
+        self.filtered = false;
+        self.iter.seek_to_first();
+    }
+
+    fn seek_to_last(&mut self) {
+        self.prefix = None;
+        self.filtered = false;
+        self.iter.seek_to_last();
+    }
+
+    fn seek(&mut self, target: &Slice) {
+        self.filtered = false;
+        self.prefix = None;
+        if self.prefix_extractor.in_domain(target) {
+            self.prefix = Some(self.prefix_extractor.transform(target).to_vec());
+            if !(self.prefix_may_match)(target) {
+                self.filtered = true;
+                return;
+            }
+        }
+        self.iter.seek(target);
+    }
+
+    fn next(&mut self) {
+        assert!(self.valid());
+        self.iter.next();
+    }
+
+    fn pre(&mut self) {
+        assert!(self.valid());
+        self.iter.pre();
+    }
+
+    fn key(&self) -> UnsafeSlice {
+        assert!(self.valid());
+        self.iter.key()
+    }
+
+    fn value(&self) -> UnsafeSlice {
+        assert!(self.valid());
+        self.iter.value()
+    }
+
+    fn status(&self) -> Status {
+        self.iter.status()
+    }
+}
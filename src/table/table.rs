@@ -1,25 +1,466 @@
-use std::fs::File;
 use std::sync::Arc;
-use crate::util::options::Options;
+use crate::table::block::Block;
+use crate::table::filter_block::{FILTER_BLOCK_KEY_PREFIX, FilterBlockReader, FULL_FILTER_BLOCK_KEY_PREFIX, FullFilterBlockReader};
+use crate::table::format::{BlockContent, BlockHandle, Footer, k_encoded_length, read_block, ToBlockHandle, ToFoot};
+use crate::table::iterator::new_error_iterator;
+use crate::table::prefix_iterator::new_prefix_iterator;
+use crate::table::table_properties::{PROPERTIES_BLOCK_KEY, TableProperties};
+use crate::table::two_level_iterator::new_two_level_iterator;
+use crate::traits::DataIterator;
+use crate::util::coding::Decoder;
+use crate::util::comparator::BytewiseComparatorImpl;
+use crate::util::env::RandomAccessFile;
+use crate::util::options::{ChecksumType, OptionsPtr, ReadOptions};
+use crate::util::slice::Slice;
 use crate::util::Result;
-use crate::util::status::Status;
+use crate::util::status::{LevelError, Status};
 
+/// A Table is a sorted map from strings to strings.  Tables are
+/// immutable and persistent.  A Table may be safely accessed from
+/// multiple threads without external synchronization.
 pub struct Table {
     rep: Rep
 }
 
 struct Rep {
-    options: Box<Options>,
-    status: Status,
-    file: Arc<File>,
+    options: OptionsPtr,
+    file: Box<dyn RandomAccessFile>,
+    // 文件号, 只用于错误信息
+    file_number: Option<u64>,
+    // block cache 中本 table 的 key 前缀, 没有 block cache 时为 0
+    cache_id: u64,
+    // Footer 中记录的 block 校验算法
+    checksum: ChecksumType,
+    metaindex_handle: BlockHandle,
+    index_block: Block,
+    // options.filter_policy 对应的 filter block, 不存在时为 None
+    filter: Option<FilterBlockReader>,
+    // 整个 table 的 filter, 由 options.full_filter 为 true 时生成的 table 读取
+    full_filter: Option<FullFilterBlockReader>,
+    // properties block 中的属性, 旧版本生成的 table 没有 properties block
+    properties: Option<TableProperties>,
 }
 
 impl Table {
-    pub fn new() -> Self{
-        todo!()
+    /// 打开 SSTable, 读取 Footer 及 index block
+    ///
+    /// Attempt to open the table that is stored in bytes [0..file_size)
+    /// of "file", and read the metadata entries necessary to allow
+    /// retrieving data from the table.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 配置, 其中的 cmp 需要与生成 table 时使用的比较器一致
+    /// * `file`: SSTable 文件
+    /// * `file_size`: 文件大小
+    ///
+    /// returns: Result<Table, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let file = options.env.new_random_access_file(&fname)?;
+    /// let table = Table::open(options.clone(), Box::new(file), file_size)?;
+    /// ```
+    pub fn open(options: OptionsPtr, file: Box<dyn RandomAccessFile>, file_size: u64) -> Result<Table> {
+        Self::open_internal(options, None, file, file_size)
     }
 
-    pub fn open(&self, options:&Options, randomAccessFile:&File, file_size: u64, table:&Table) -> Result<bool>{
-        todo!()
+    /// 与 open 相同, 读取 block 出错 (例如校验失败) 时返回的 Corruption 中包含文件号
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let table = Table::open_with_file_number(options.clone(), file_number, Box::new(file), file_size)?;
+    /// ```
+    pub fn open_with_file_number(options: OptionsPtr, file_number: u64, file: Box<dyn RandomAccessFile>,
+                                 file_size: u64) -> Result<Table> {
+        Self::open_internal(options, Some(file_number), file, file_size)
     }
-}
\ No newline at end of file
+
+    fn open_internal(options: OptionsPtr, file_number: Option<u64>, file: Box<dyn RandomAccessFile>,
+                     file_size: u64) -> Result<Table> {
+        if file_size < k_encoded_length as u64 {
+            return Err(LevelError::corruption_string("file is too short to be an sstable", ""));
+        }
+
+        let footer_input = file.read(file_size - k_encoded_length as u64, k_encoded_length as usize)?;
+        let mut footer = Footer::default();
+        footer.decode_from(&footer_input)?;
+
+        // Read the index block
+        let opt = ReadOptions {
+            verify_checksums: options.paranoid_checks,
+            ..ReadOptions::default()
+        };
+        let index_block_contents = read_block(file.as_ref(), &options, &opt, footer.checksum(), &footer.index_handle())
+            .map_err(|e| with_file_number(file_number, e))?;
+
+        // We've successfully read the footer and the index block: we're
+        // ready to serve requests.
+        let cache_id = options.block_cache.as_ref().map_or(0, |cache| cache.new_id());
+        let mut table = Table {
+            rep: Rep {
+                options,
+                file,
+                file_number,
+                cache_id,
+                checksum: footer.checksum(),
+                metaindex_handle: footer.meta_index_handle(),
+                index_block: Block::new(index_block_contents),
+                filter: None,
+                full_filter: None,
+                properties: None,
+            }
+        };
+        table.read_meta(&footer);
+        Ok(table)
+    }
+
+    /// 读取 meta index block, 找到 properties block 及 options.filter_policy 对应的 filter block。
+    /// 优先使用整个 table 的 filter ("fullfilter."), 不存在时使用按 2KB 划分的 filter ("filter."),
+    /// 因此与 options.full_filter 无关, 两种 table 都可以读取。
+    /// meta 信息不是必须的, 读取失败时不影响 table 的使用, 只是没有 filter 及 properties
+    fn read_meta(&mut self, footer: &Footer) {
+        // TODO(sanjay): Skip this if footer.metaindex_handle() size indicates
+        // it is an empty block.
+        let opt = ReadOptions {
+            verify_checksums: self.rep.options.paranoid_checks,
+            ..ReadOptions::default()
+        };
+        let contents = match self.read_block(&opt, &footer.meta_index_handle()) {
+            Ok(contents) => contents,
+            // Do not propagate errors since meta info is not needed for operation
+            Err(_) => return,
+        };
+        let meta = Block::new(contents);
+
+        // meta index block 中的 key 按字节序排列
+        let mut iter = meta.new_iterator(Arc::new(BytewiseComparatorImpl::default()));
+        iter.seek(&Slice::from(PROPERTIES_BLOCK_KEY));
+        if iter.valid() && iter.key().as_ref() == PROPERTIES_BLOCK_KEY.as_bytes() {
+            self.rep.properties = self.read_properties(iter.value().as_ref());
+        }
+
+        let policy = match &self.rep.options.filter_policy {
+            Some(policy) => policy.clone(),
+            None => return,
+        };
+        for prefix in [FULL_FILTER_BLOCK_KEY_PREFIX, FILTER_BLOCK_KEY_PREFIX] {
+            let key = format!("{}{}", prefix, policy.name());
+            iter.seek(&Slice::from(key.as_str()));
+            if !iter.valid() || iter.key().as_ref() != key.as_bytes() {
+                continue;
+            }
+            let filter_handle_value = iter.value();
+            if let Some(contents) = self.read_filter(filter_handle_value.as_ref()) {
+                if prefix == FULL_FILTER_BLOCK_KEY_PREFIX {
+                    self.rep.full_filter = Some(FullFilterBlockReader::new_with_policy(policy, &contents));
+                } else {
+                    self.rep.filter = Some(FilterBlockReader::new_with_policy(policy, &contents));
+                }
+                return;
+            }
+        }
+    }
+
+    /// 读取 properties block, 失败时返回 None
+    fn read_properties(&self, properties_handle_value: &[u8]) -> Option<TableProperties> {
+        let mut properties_handle = BlockHandle::default();
+        if properties_handle.decode_from(&mut Decoder::with_buf(properties_handle_value)).is_err() {
+            return None;
+        }
+        let opt = ReadOptions {
+            verify_checksums: self.rep.options.paranoid_checks,
+            ..ReadOptions::default()
+        };
+        let contents = self.read_block(&opt, &properties_handle).ok()?;
+        TableProperties::decode_from(&Block::new(contents)).ok()
+    }
+
+    /// 读取 filter block 的内容, 失败时返回 None
+    fn read_filter(&self, filter_handle_value: &[u8]) -> Option<Vec<u8>> {
+        let mut filter_handle = BlockHandle::default();
+        if filter_handle.decode_from(&mut Decoder::with_buf(filter_handle_value)).is_err() {
+            return None;
+        }
+
+        // We might want to unify with ReadBlock() if we start
+        // requiring checksum verification in Table::Open.
+        let opt = ReadOptions {
+            verify_checksums: self.rep.options.paranoid_checks,
+            ..ReadOptions::default()
+        };
+        self.read_block(&opt, &filter_handle)
+            .ok()
+            .map(|block| block.data.to_vec())
+    }
+
+    /// table 的属性, 没有 properties block (旧版本生成的 table) 或者读取失败时返回 None
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let num_entries = table.properties().map(|p| p.num_entries).unwrap_or(0);
+    /// ```
+    pub fn properties(&self) -> Option<&TableProperties> {
+        self.rep.properties.as_ref()
+    }
+
+    /// 创建 table 的迭代器, 迭代器持有 table, 使用期间 table 不会被释放。
+    /// options.prefix_same_as_start 为 true 且配置了 prefix_extractor 时, seek 之后只迭代与目标前缀相同的 key,
+    /// filter 判断前缀不存在时不会读取 data block
+    ///
+    /// Returns a new iterator over the table contents.
+    /// The result of NewIterator() is initially invalid (caller must
+    /// call one of the Seek methods on the iterator before using it).
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 读取的配置, 使用其中的 verify_checksums 及 fill_cache
+    ///
+    /// returns: Box<dyn DataIterator, Global>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut iter = table.new_iterator(&ReadOptions::default());
+    /// iter.seek_to_first();
+    /// ```
+    pub fn new_iterator(self: &Arc<Self>, options: &ReadOptions) -> Box<dyn DataIterator> {
+        let table = self.clone();
+        let verify_checksums = options.verify_checksums;
+        let fill_cache = options.fill_cache;
+        let iter = new_two_level_iterator(
+            self.rep.index_block.new_iterator(self.rep.options.cmp.clone()),
+            Box::new(move |index_value| {
+                let options = ReadOptions {
+                    verify_checksums,
+                    fill_cache,
+                    snapshot: None,
+                    prefix_same_as_start: false,
+                };
+                table.block_reader(&options, index_value)
+            }));
+
+        match (options.prefix_same_as_start, &self.rep.options.prefix_extractor) {
+            (true, Some(prefix_extractor)) => {
+                let table = self.clone();
+                new_prefix_iterator(iter, prefix_extractor.clone(),
+                                    Box::new(move |target| table.prefix_may_match(target)))
+            }
+            _ => iter,
+        }
+    }
+
+    /// 判断 seek(key) 之后是否可能找到与 key 前缀相同的记录。
+    ///
+    /// 有整个 table 的 filter 时只检查该 filter, 不读取 index block;
+    /// 否则检查 key 在 index block 中对应的 data block 的 filter。
+    /// 只有生成 filter 时加入了前缀 (参见 FilterPolicy::prefix_may_match) 才可能返回 false
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: seek 的目标, 与 table 中的 key 格式相同
+    ///
+    /// returns: bool
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// if !table.prefix_may_match(target) {
+    ///     // table 中没有前缀相同的 key
+    /// }
+    /// ```
+    pub fn prefix_may_match(&self, key: &[u8]) -> bool {
+        if let Some(full_filter) = &self.rep.full_filter {
+            return full_filter.prefix_may_match(key);
+        }
+        let filter = match &self.rep.filter {
+            Some(filter) => filter,
+            None => return true,
+        };
+
+        // 前缀相同的 key 是连续的, seek 之后的第一条记录所在的 data block 中没有该前缀时,
+        // 之后也不会有前缀相同的记录
+        let mut index_iter = self.rep.index_block.new_iterator(self.rep.options.cmp.clone());
+        index_iter.seek(&Slice::from_buf(key));
+        if !index_iter.valid() {
+            return true;
+        }
+        let mut handle = BlockHandle::default();
+        match handle.decode_from(&mut Decoder::with_buf(index_iter.value().as_ref())) {
+            Ok(_) => filter.prefix_may_match(handle.offset(), key),
+            Err(_) => true,
+        }
+    }
+
+    /// 查找 key, 找到 key 之后的第一条记录时调用 handle_result(key, value)。
+    /// 如果 filter 判断 key 不在对应的 data block 中, 则不会读取 data block, 也不会调用 handle_result;
+    /// 有整个 table 的 filter 时, 在查找 index block 之前就判断 key 是否在 table 中
+    ///
+    /// Calls (*handle_result)(arg, ...) with the entry found after a call
+    /// to Seek(key).  May not make such a call if filter policy says
+    /// that key is not present.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 读取的配置
+    /// * `key`: 与 table 中的 key 格式相同, DB 中为编码后的 internal key
+    /// * `handle_result`: 回调函数, 参数为找到的 key 及 value
+    ///
+    /// returns: Result<(), Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// table.internal_get(&ReadOptions::default(), lookup_key.internal_key(), |k, v| {
+    ///     // 比较 user key, 保存 value
+    /// })?;
+    /// ```
+    pub fn internal_get<F>(&self, options: &ReadOptions, key: &[u8], mut handle_result: F) -> Result<()>
+        where F: FnMut(&[u8], &[u8]) {
+        if let Some(full_filter) = &self.rep.full_filter {
+            if !full_filter.key_may_match(key) {
+                // Not found
+                return Ok(());
+            }
+        }
+
+        let mut index_iter = self.rep.index_block.new_iterator(self.rep.options.cmp.clone());
+        index_iter.seek(&Slice::from_buf(key));
+        if index_iter.valid() {
+            let handle_value = index_iter.value();
+            let mut handle = BlockHandle::default();
+            let decoded = handle.decode_from(&mut Decoder::with_buf(handle_value.as_ref())).is_ok();
+            if let (true, Some(filter)) = (decoded, &self.rep.filter) {
+                if !filter.key_may_match(handle.offset(), key) {
+                    // Not found
+                    return Ok(());
+                }
+            }
+
+            let mut block_iter = self.block_reader(options, handle_value.as_ref());
+            block_iter.seek(&Slice::from_buf(key));
+            if block_iter.valid() {
+                handle_result(block_iter.key().as_ref(), block_iter.value().as_ref());
+            }
+            let status = block_iter.status();
+            if !status.is_ok() {
+                return Err(status);
+            }
+        }
+        let status = index_iter.status();
+        if status.is_ok() {
+            Ok(())
+        } else {
+            Err(status)
+        }
+    }
+
+    /// 读取 table 中的 block, 使用 Footer 中记录的校验算法
+    fn read_block(&self, options: &ReadOptions, handle: &BlockHandle) -> Result<BlockContent> {
+        read_block(self.rep.file.as_ref(), &self.rep.options, options, self.rep.checksum, handle)
+            .map_err(|e| with_file_number(self.rep.file_number, e))
+    }
+
+    /// 将 index block 中的 value 解码为 BlockHandle, 读取对应的 data block 并返回其迭代器。
+    /// 配置了 block cache 时先从 cache 中查找, fill_cache 为 true 时将读取的 block 放入 cache
+    ///
+    /// Convert an index iterator value (i.e., an encoded BlockHandle)
+    /// into an iterator over the contents of the corresponding block.
+    fn block_reader(&self, options: &ReadOptions, index_value: &[u8]) -> Box<dyn DataIterator> {
+        let mut handle = BlockHandle::default();
+        let mut decoder = Decoder::with_buf(index_value);
+        // We intentionally allow extra stuff in index_value so that we
+        // can add more features in the future.
+        if let Err(e) = handle.decode_from(&mut decoder) {
+            return new_error_iterator(e);
+        }
+
+        let block_cache = match &self.rep.options.block_cache {
+            Some(block_cache) => block_cache,
+            None => return match self.read_block(options, &handle) {
+                Ok(contents) => Block::new(contents).new_iterator(self.rep.options.cmp.clone()),
+                Err(e) => new_error_iterator(e),
+            },
+        };
+
+        // cache 的 key 为 cache_id 及 block 在文件中的偏移
+        let mut cache_key = [0u8; 16];
+        cache_key[..8].copy_from_slice(&self.rep.cache_id.to_le_bytes());
+        cache_key[8..].copy_from_slice(&handle.offset().to_le_bytes());
+        let cache_key = Slice::from_buf(&cache_key);
+        if let Ok(Some(block)) = block_cache.lookup(&cache_key) {
+            return block.new_iterator(self.rep.options.cmp.clone());
+        }
+        match self.read_block(options, &handle) {
+            Ok(contents) => {
+                let cachable = contents.cachable && options.fill_cache;
+                let block = Block::new(contents);
+                let iter = block.new_iterator(self.rep.options.cmp.clone());
+                if cachable {
+                    let charge = block.size();
+                    // 插入失败时只是没有缓存
+                    let _ = block_cache.insert(&cache_key, block, charge);
+                }
+                iter
+            }
+            Err(e) => new_error_iterator(e),
+        }
+    }
+
+    /// 返回 key 对应的数据在文件中的大致偏移量, key 超过所有数据时返回接近文件大小的值
+    ///
+    /// Given a key, return an approximate byte offset in the file where
+    /// the data for that key begins (or would begin if the key were
+    /// present in the file).  The returned value is in terms of file
+    /// bytes, and so includes effects like compression of the underlying data.
+    /// E.g., the approximate offset of the last key in the table will
+    /// be close to the file length.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: 与 table 中的 key 格式相同, DB 中为编码后的 internal key
+    ///
+    /// returns: u64
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let offset = table.approximate_offset_of(b"k05");
+    /// ```
+    pub fn approximate_offset_of(&self, key: &[u8]) -> u64 {
+        let mut index_iter = self.rep.index_block.new_iterator(self.rep.options.cmp.clone());
+        index_iter.seek(&Slice::from_buf(key));
+        if index_iter.valid() {
+            let mut handle = BlockHandle::default();
+            let value = index_iter.value();
+            let mut decoder = Decoder::with_buf(value.as_ref());
+            if handle.decode_from(&mut decoder).is_ok() {
+                return handle.offset();
+            }
+            // Strange: we can't decode the block handle in the index block.
+            // We'll just return the offset of the metaindex block, which is
+            // close to the whole file size for this case.
+        }
+        // key is past the last key in the file.  If table properties are
+        // available, the end of the data blocks is exact; otherwise
+        // approximate the offset by returning the offset of the metaindex
+        // block (which is right near the end of the file).
+        match &self.rep.properties {
+            Some(properties) => properties.data_size,
+            None => self.rep.metaindex_handle.offset(),
+        }
+    }
+}
+
+/// 在 Corruption 的错误信息前加上文件号, 便于定位损坏的文件
+fn with_file_number(file_number: Option<u64>, status: Status) -> Status {
+    match file_number {
+        Some(number) if status.is_corruption() => {
+            LevelError::corruption_string(&format!("file {}", number), &status.get_msg())
+        }
+        _ => status,
+    }
+}
@@ -1,32 +1,41 @@
-use std::borrow::Borrow;
-use std::fs::File;
-use std::sync::Arc;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use crate::db::db_format::{ParsedInternalKey, ValueType};
+use crate::db::log_writer::WritableFile;
 use crate::table::block_builder::BlockBuilder;
-use crate::table::filter_block::{FilterBlock, FilterBlockBuilder};
-use crate::table::format::BlockHandle;
-use crate::traits::filter_policy_trait::FilterPolicy;
-use crate::util::options::{CompressionType, OptionsPtr, Options};
-use crate::util::slice::Slice;
+use crate::table::filter_block::{FILTER_BLOCK_KEY_PREFIX, FilterBlock, FilterBlockBuilder, FULL_FILTER_BLOCK_KEY_PREFIX, FullFilterBlockBuilder};
+use crate::table::format::{block_checksum, BlockHandle, Footer, k_block_trailer_size, ToBlockHandle, ToFoot};
+use crate::table::table_properties::{PROPERTIES_BLOCK_KEY, TableProperties};
+use crate::traits::table_properties_collector_trait::{TablePropertiesCollector, UserCollectedProperties};
+use crate::util::coding::Encoder;
+use crate::util::options::{CompressionType, OptionsPtr};
+use crate::util::Result;
+use crate::util::compression::{LZ4, Zstd};
+use crate::util::snappy::Snappy;
 use crate::util::status::Status;
-use crate::util::unsafe_slice::UnsafeSlice;
 
 /// 在一个 SSTable 中，文件末尾的 Footer 是定长的，
 /// 其他数据都被划分成一个个变长的 block：
 /// index block(@see format.BlockHandle、Footer#index_handle)、
 /// meta_index block(@see format.BlockHandle、Footer#meta_index_handle)、
-/// meta blocks(@see table.FilterBlock)、
+/// meta blocks(@see table.FilterBlock、table_properties.TableProperties)、
 /// data blocks。
+///
+/// TableBuilder provides the interface used to build a Table
+/// (an immutable and sorted map from keys to values).
+///
+/// key 的顺序由 options.cmp 决定, DB 内部使用时为 InternalKeyComparator
 pub struct TableBuilder {
     rep: Box<Rep>
 }
 
 /// TableBuilder Rep 结构体， 内部使用
-struct Rep<> {
+struct Rep {
     options: OptionsPtr,
-    index_block_options: OptionsPtr,
 
     // SSTable 生成后的文件
-    file: Arc<File>,
+    file: Box<dyn WritableFile>,
 
     offset: u64,
     status: Status,
@@ -35,104 +44,437 @@ struct Rep<> {
     data_block: BlockBuilder,
     // 生成 SSTable 中的数据索引区域
     index_block: BlockBuilder,
+    // 生成 SSTable 中的 filter block, options.filter_policy 为 None 或 options.full_filter 为 true 时不生成
+    filter_block: Option<FilterBlockBuilder>,
+    // 生成整个 SSTable 的 filter, options.full_filter 为 true 时代替 filter_block
+    full_filter_block: Option<FullFilterBlockBuilder>,
 
-    last_key: Slice,
+    last_key: Vec<u8>,
     num_entries: u64,
     // Either Finish() or Abandon() has been called.
     closed: bool,
 
-    // 生成 SSTable 中的元数据区域
-    filter_block: Option<FilterBlockBuilder>,
     // 判断是否需要生成 SSTable中的数据索引， SSTable中每次生成一个完整的块之后，需要将该值置为 true， 说明需要为该块添加索引
+    //
+    // We do not emit the index entry for a block until we have seen the
+    // first key for the next data block.  This allows us to use shorter
+    // keys in the index block.
+    //
+    // Invariant: r->pending_index_entry is true only if data_block is empty.
     pending_index_entry: bool,
     // Handle to add to index block
     // pending_handle 记录需要生成数据索引的数据块在 SSTable 中的偏移量和大小
     // 也就是说， pending_handle 主要用于表示当前块的offset及size。
     pending_handle: BlockHandle,
 
-    compressed_output: Slice,
+    // block 的压缩类型, 默认为 options.compression
+    compression: CompressionType,
+
+    // 写入 properties block 的属性, 在 add 及 finish 时累计
+    properties: TableProperties,
+    // options.cmp 为 InternalKeyComparator 时 key 为 internal key, 统计删除标记及 sequence
+    internal_keys: bool,
+    // 由 options.table_properties_collectors 创建的收集器
+    collectors: Vec<Box<dyn TablePropertiesCollector>>,
 }
 
 impl TableBuilder {
-    pub fn new_with_writable_file(options: OptionsPtr, writable_file: Arc<File>) -> Self {
-        let rep = Rep::new(options, writable_file);
-
-        // Self {
-        //     rep
-        // }
+    /// Create a builder that will store the contents of the table it is
+    /// building in *file.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 配置, 使用其中的 cmp、block_size、block_restart_interval 及压缩相关的配置
+    /// * `writable_file`: 写入的文件
+    ///
+    /// returns: TableBuilder
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let file = options.env.new_writable_file(&fname)?;
+    /// let mut builder = TableBuilder::new_with_writable_file(options.clone(), Box::new(file));
+    /// ```
+    pub fn new_with_writable_file(options: OptionsPtr, writable_file: Box<dyn WritableFile>) -> Self {
+        Self {
+            rep: Box::new(Rep::new(options, writable_file))
+        }
+    }
 
-        todo!()
+    /// 修改之后写入的 block 使用的压缩类型, 用于按 table 所在的层选择压缩类型
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// builder.set_compression(options.compression_for_level(level));
+    /// ```
+    pub fn set_compression(&mut self, compression: CompressionType) {
+        self.rep.compression = compression;
     }
 
     /// 写入 entry
-    pub fn add(&self, key: &UnsafeSlice, value: &UnsafeSlice) {
-        todo!()
-    }
+    ///
+    /// Add key,value to the table being constructed.
+    /// REQUIRES: key is after any previously added key according to comparator.
+    /// REQUIRES: Finish(), Abandon() have not been called
+    pub fn add(&mut self, key: &[u8], value: &[u8]) {
+        let r = &mut self.rep;
+        assert!(!r.closed);
+        if !r.status.is_ok() {
+            return;
+        }
+        if r.num_entries > 0 {
+            assert_eq!(Some(Ordering::Greater), r.options.cmp.compare(key, &r.last_key));
+        }
 
-    /// flush到文件
-    pub fn flush(&self) {
-        todo!()
-    }
+        if r.pending_index_entry {
+            assert!(r.data_block.empty());
+            // todo Comparator::find_shortest_separator 目前只支持 String, 无法处理二进制的 internal key,
+            //  因此直接使用上一个 block 的最后一个 key 作为索引, 只是索引稍大, 不影响正确性
+            let mut handle_encoding = vec![];
+            r.pending_handle.encode_to(&mut handle_encoding);
+            r.index_block.add(&r.last_key, &handle_encoding);
+            r.pending_index_entry = false;
+        }
 
-    /// block->Finish、压缩
-    pub fn write_block(&self, block: &BlockBuilder, handler: &BlockHandle) {
-        todo!()
+        if let Some(filter_block) = r.filter_block.as_mut() {
+            filter_block.add_key(key);
+        }
+        if let Some(full_filter_block) = r.full_filter_block.as_mut() {
+            full_filter_block.add_key(key);
+        }
+        r.collect_properties(key, value);
+
+        r.last_key.clear();
+        r.last_key.extend_from_slice(key);
+        r.num_entries += 1;
+        r.data_block.add(key, value);
+
+        let estimated_block_size = r.data_block.current_size_estimate();
+        if estimated_block_size >= r.options.block_size {
+            self.flush();
+        }
     }
 
-    /// datablock写入文件，添加压缩方式、crc。
-    pub fn write_raw_block(&self, block_contents: &UnsafeSlice, compression_type: CompressionType, handler: &BlockHandle) {
-        todo!()
+    /// flush到文件
+    ///
+    /// Advanced operation: flush any buffered key/value pairs to file.
+    /// Can be used to ensure that two adjacent entries never live in
+    /// the same data block.  Most clients should not need to use this method.
+    /// REQUIRES: Finish(), Abandon() have not been called
+    pub fn flush(&mut self) {
+        let r = &mut self.rep;
+        assert!(!r.closed);
+        if !r.status.is_ok() {
+            return;
+        }
+        if r.data_block.empty() {
+            return;
+        }
+        assert!(!r.pending_index_entry);
+        r.pending_handle = r.write_data_block();
+        if r.status.is_ok() {
+            r.pending_index_entry = true;
+            if let Err(e) = r.file.flush() {
+                r.status = e.into();
+            }
+        }
+        if let Some(filter_block) = r.filter_block.as_mut() {
+            filter_block.start_block(r.offset);
+        }
     }
 
+    /// Return non-ok iff some error has been detected.
     pub fn status(&self) -> Status {
-        todo!()
+        self.rep.status.clone()
     }
 
     /// 剩余datablock写入文件，并生成管理区。
-    pub fn finish(&self) -> Status {
-        todo!()
+    ///
+    /// Finish building the table.  Stops using the file passed to the
+    /// constructor after this function returns.
+    /// REQUIRES: Finish(), Abandon() have not been called
+    pub fn finish(&mut self) -> Result<()> {
+        self.flush();
+        let r = &mut self.rep;
+        assert!(!r.closed);
+        r.closed = true;
+
+        r.properties.data_size = r.offset;
+
+        // Write filter block
+        let mut filter_block_handle = None;
+        if r.status.is_ok() {
+            if let Some(filter_block) = r.filter_block.as_mut() {
+                // filter block 不压缩
+                match filter_block.finish() {
+                    Ok(contents) => {
+                        let key = format!("{}{}", FILTER_BLOCK_KEY_PREFIX, filter_block.get_policy().name());
+                        let handle = r.write_raw_block(contents.as_ref(), CompressionType::NoCompression);
+                        r.properties.filter_size = handle.size() + k_block_trailer_size as u64;
+                        filter_block_handle = Some((key, handle));
+                    }
+                    Err(e) => r.status = e,
+                }
+            }
+            if let Some(full_filter_block) = r.full_filter_block.as_mut() {
+                let key = format!("{}{}", FULL_FILTER_BLOCK_KEY_PREFIX, full_filter_block.get_policy().name());
+                let contents = full_filter_block.finish().to_vec();
+                let handle = r.write_raw_block(&contents, CompressionType::NoCompression);
+                r.properties.filter_size = handle.size() + k_block_trailer_size as u64;
+                filter_block_handle = Some((key, handle));
+            }
+        }
+
+        // index block 在 properties block 之后写入, 先生成其内容以得到 index_size
+        if r.pending_index_entry {
+            let mut handle_encoding = vec![];
+            r.pending_handle.encode_to(&mut handle_encoding);
+            r.index_block.add(&r.last_key, &handle_encoding);
+            r.pending_index_entry = false;
+        }
+        let mut index_block = std::mem::replace(&mut r.index_block, BlockBuilder::new(1));
+        let (index_contents, index_compression) = {
+            let (contents, compression) = r.compress_block(index_block.finish());
+            (contents.into_owned(), compression)
+        };
+
+        // Write properties block
+        let mut properties_block_handle = None;
+        if r.status.is_ok() {
+            r.properties.index_size = (index_contents.len() + k_block_trailer_size) as u64;
+            let mut properties_block = BlockBuilder::new(r.options.block_restart_interval as usize);
+            for (name, value) in r.finish_properties().encode() {
+                properties_block.add(name.as_bytes(), &value);
+            }
+            // properties block 不压缩
+            let handle = r.write_raw_block(properties_block.finish(), CompressionType::NoCompression);
+            properties_block_handle = Some(handle);
+        }
+
+        // Write metaindex block
+        let mut metaindex_block_handle = BlockHandle::default();
+        if r.status.is_ok() {
+            // meta index block 中的 key 按字节序排列
+            let mut meta_blocks = BTreeMap::new();
+            if let Some((key, handle)) = filter_block_handle {
+                // Add mapping from "filter.Name" (or "fullfilter.Name") to location of filter data
+                meta_blocks.insert(key, handle);
+            }
+            if let Some(handle) = properties_block_handle {
+                meta_blocks.insert(PROPERTIES_BLOCK_KEY.to_string(), handle);
+            }
+            let mut meta_index_block = BlockBuilder::new(r.options.block_restart_interval as usize);
+            for (key, handle) in meta_blocks {
+                let mut handle_encoding = vec![];
+                handle.encode_to(&mut handle_encoding);
+                meta_index_block.add(key.as_bytes(), &handle_encoding);
+            }
+            metaindex_block_handle = r.write_block(&mut meta_index_block);
+        }
+
+        // Write index block
+        let mut index_block_handle = BlockHandle::default();
+        if r.status.is_ok() {
+            index_block_handle = r.write_raw_block(&index_contents, index_compression);
+        }
+
+        // Write footer
+        if r.status.is_ok() {
+            let mut footer = Footer::new(metaindex_block_handle, index_block_handle);
+            footer.set_checksum(r.options.checksum);
+            let mut footer_encoding = vec![];
+            footer.encode_to(&mut footer_encoding);
+            match r.file.write_all(&footer_encoding) {
+                Ok(_) => r.offset += footer_encoding.len() as u64,
+                Err(e) => r.status = e.into(),
+            }
+        }
+
+        if r.status.is_ok() {
+            Ok(())
+        } else {
+            Err(r.status.clone())
+        }
+    }
+
+    /// 将文件中已写入的数据同步到磁盘
+    pub fn sync(&mut self) -> Result<()> {
+        self.rep.file.sync()?;
+        Ok(())
     }
 
-    pub fn abandon(&self) {
-        todo!()
+    /// Indicate that the contents of this builder should be abandoned.  Stops
+    /// using the file passed to the constructor after this function returns.
+    /// If the caller is not going to call Finish(), it must call Abandon()
+    /// before destroying this builder.
+    /// REQUIRES: Finish(), Abandon() have not been called
+    pub fn abandon(&mut self) {
+        assert!(!self.rep.closed);
+        self.rep.closed = true;
     }
 
+    /// Number of calls to Add() so far.
     pub fn get_num_entries(&self) -> u64 {
-        todo!()
+        self.rep.num_entries
     }
 
+    /// Size of the file generated so far.  If invoked after a successful
+    /// Finish() call, returns the size of the final generated file.
     pub fn get_file_size(&self) -> u64 {
-        todo!()
+        self.rep.offset
+    }
+
+    /// 目前为止统计的属性, finish 成功之后为写入 properties block 的属性
+    pub fn get_properties(&self) -> &TableProperties {
+        &self.rep.properties
     }
 }
 
 impl Rep {
-    pub fn new(opt: OptionsPtr, writableFile: Arc<File>) -> Self {
-        let mut filter_block: Option<FilterBlockBuilder>;
-        if opt.filter_policy.is_none() {
-            filter_block = None;
-        }else {
-            filter_block = Some(FilterBlockBuilder::new_with_policy(opt.filter_policy.clone().unwrap()));
-        }
-        // TODo if let sytax
-        // let filter_block = opt.filter_policy.map(|e|FilterBlockBuilder::new_with_policy(e.clone().unwrap()));
-
+    fn new(options: OptionsPtr, file: Box<dyn WritableFile>) -> Self {
+        let block_restart_interval = options.block_restart_interval as usize;
+        let (filter_block, full_filter_block) = match options.filter_policy.clone() {
+            Some(policy) if options.full_filter => (None, Some(FullFilterBlockBuilder::new_with_policy(policy))),
+            Some(policy) => {
+                let mut filter_block = FilterBlockBuilder::new_with_policy(policy);
+                filter_block.start_block(0);
+                (Some(filter_block), None)
+            }
+            None => (None, None),
+        };
+        let properties = TableProperties {
+            comparator_name: options.cmp.get_name(),
+            filter_policy_name: options.filter_policy.as_ref().map(|policy| policy.name()).unwrap_or_default(),
+            ..TableProperties::default()
+        };
+        let internal_keys = options.cmp.get_name() == "leveldb.InternalKeyComparator";
+        let collectors = options.table_properties_collectors.iter().map(|factory| factory()).collect();
         Self {
-            options: opt.clone(),
-            index_block_options: opt.clone(),
-            file: writableFile,
+            compression: options.compression,
+            options,
+            file,
             offset: 0,
             // default  Status::OK
             status: Status::default(),
-            data_block: BlockBuilder::new(opt.clone()),
-            index_block: BlockBuilder::new(opt.clone()),
-            last_key: Slice::default(),
+            data_block: BlockBuilder::new(block_restart_interval),
+            // index block 中的每个 key 都是重启点, 便于二分查找
+            index_block: BlockBuilder::new(1),
+            filter_block,
+            full_filter_block,
+            last_key: vec![],
             num_entries: 0,
             closed: false,
-            filter_block,
             pending_index_entry: false,
             pending_handle: BlockHandle::default(),
-            compressed_output: Slice::default(),
+            properties,
+            internal_keys,
+            collectors,
+        }
+    }
+
+    /// 统计 entry 的属性, 并交给 collectors 收集
+    fn collect_properties(&mut self, key: &[u8], value: &[u8]) {
+        let (user_key, value_type, sequence) = match self.internal_keys {
+            true => match ParsedInternalKey::parse_internal_key(key) {
+                Some(parsed) => (ParsedInternalKey::extract_user_key(key), parsed.value_type(), parsed.sequence()),
+                None => (key, ValueType::KTypeValue, 0),
+            },
+            false => (key, ValueType::KTypeValue, 0),
+        };
+
+        let properties = &mut self.properties;
+        if self.internal_keys {
+            if properties.num_entries == 0 {
+                properties.smallest_seqno = sequence;
+                properties.largest_seqno = sequence;
+            } else {
+                properties.smallest_seqno = properties.smallest_seqno.min(sequence);
+                properties.largest_seqno = properties.largest_seqno.max(sequence);
+            }
+        }
+        properties.num_entries += 1;
+        if value_type == ValueType::KTypeDeletion {
+            properties.num_deletions += 1;
+        }
+        properties.raw_key_size += key.len() as u64;
+        properties.raw_value_size += value.len() as u64;
+
+        for collector in self.collectors.iter_mut() {
+            collector.add(user_key, value, value_type, sequence);
         }
     }
-}
\ No newline at end of file
+
+    /// 结束统计, 返回写入 properties block 的属性
+    fn finish_properties(&mut self) -> &TableProperties {
+        let mut user_collected = UserCollectedProperties::new();
+        for collector in self.collectors.iter_mut() {
+            collector.finish(&mut user_collected);
+        }
+        self.properties.user_collected_properties = user_collected;
+        self.properties.compression = format!("{:?}", self.compression);
+        &self.properties
+    }
+
+    fn write_data_block(&mut self) -> BlockHandle {
+        let mut data_block = std::mem::replace(&mut self.data_block, BlockBuilder::new(1));
+        let handle = self.write_block(&mut data_block);
+        self.data_block = data_block;
+        self.properties.num_data_blocks += 1;
+        handle
+    }
+
+    /// block->Finish、压缩
+    ///
+    /// File format contains a sequence of blocks where each block has:
+    ///    block_data: uint8[n]
+    ///    type: uint8
+    ///    crc: uint32
+    fn write_block(&mut self, block: &mut BlockBuilder) -> BlockHandle {
+        assert!(self.status.is_ok());
+        let (contents, compression) = self.compress_block(block.finish());
+        let handle = self.write_raw_block(&contents, compression);
+        block.reset();
+        handle
+    }
+
+    /// 按 compression 压缩 block, 返回写入文件的内容及实际使用的压缩类型
+    fn compress_block<'a>(&self, raw: &'a [u8]) -> (Cow<'a, [u8]>, CompressionType) {
+        let compression = self.compression;
+        let compressed = match compression {
+            CompressionType::NoCompression => None,
+            CompressionType::SnappyCompression => Some(Snappy::compress(raw)),
+            CompressionType::LZ4Compression => Some(LZ4::compress(raw)),
+            CompressionType::ZstdCompression => {
+                Zstd::compress(raw, self.options.zstd_level, self.options.zstd_dictionary.as_deref()).ok()
+            }
+        };
+        match compressed {
+            Some(compressed) if compressed.len() < raw.len() - (raw.len() / 8) => {
+                (Cow::Owned(compressed), compression)
+            }
+            // Compression failed, or compressed less than 12.5%, so just
+            // store uncompressed form
+            _ => (Cow::Borrowed(raw), CompressionType::NoCompression),
+        }
+    }
+
+    /// datablock写入文件，添加压缩方式、crc。
+    fn write_raw_block(&mut self, block_contents: &[u8], compression_type: CompressionType) -> BlockHandle {
+        let handle = BlockHandle::new(self.offset, block_contents.len() as u64);
+        if let Err(e) = self.file.write_all(block_contents) {
+            self.status = e.into();
+            return handle;
+        }
+
+        let mut trailer = Vec::with_capacity(k_block_trailer_size);
+        trailer.push(compression_type as u8);
+        let checksum = block_checksum(self.options.checksum, block_contents, compression_type as u8);
+        Encoder::with_vec_append(&mut trailer).put_fixed32(checksum).expect("put checksum");
+        match self.file.write_all(&trailer) {
+            Ok(_) => self.offset += (block_contents.len() + k_block_trailer_size) as u64,
+            Err(e) => self.status = e.into(),
+        }
+        handle
+    }
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+use crate::db::db_format::escape_string;
+use crate::table::block::Block;
+use crate::traits::table_properties_collector_trait::UserCollectedProperties;
+use crate::util::coding::{Decoder, Encoder};
+use crate::util::comparator::BytewiseComparatorImpl;
+use crate::util::Result;
+use crate::util::status::LevelError;
+
+/// meta index block 中 properties block 的 key
+pub const PROPERTIES_BLOCK_KEY: &str = "leveldb.properties";
+
+const NUM_ENTRIES: &str = "leveldb.num.entries";
+const NUM_DELETIONS: &str = "leveldb.num.deletions";
+const NUM_DATA_BLOCKS: &str = "leveldb.num.data.blocks";
+const RAW_KEY_SIZE: &str = "leveldb.raw.key.size";
+const RAW_VALUE_SIZE: &str = "leveldb.raw.value.size";
+const DATA_SIZE: &str = "leveldb.data.size";
+const INDEX_SIZE: &str = "leveldb.index.size";
+const FILTER_SIZE: &str = "leveldb.filter.size";
+const SMALLEST_SEQNO: &str = "leveldb.smallest.seqno";
+const LARGEST_SEQNO: &str = "leveldb.largest.seqno";
+const COMPRESSION: &str = "leveldb.compression";
+const COMPARATOR: &str = "leveldb.comparator";
+const FILTER_POLICY: &str = "leveldb.filter.policy";
+
+/// table 的属性, 由 TableBuilder 写入 properties block。
+///
+/// properties block 与 data block 的格式相同, key 为属性名, 按字节序排列;
+/// 数值类型的属性以 varint64 编码, 字符串类型的属性直接保存。
+/// 各个 block 的大小都包括 block 的 trailer
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableProperties {
+    /// entry 的数量
+    pub num_entries: u64,
+    /// 删除标记 (KTypeDeletion) 的数量, table 中的 key 不是 internal key 时为 0
+    pub num_deletions: u64,
+    /// data block 的数量
+    pub num_data_blocks: u64,
+    /// 所有 key 的长度之和 (未压缩)
+    pub raw_key_size: u64,
+    /// 所有 value 的长度之和 (未压缩)
+    pub raw_value_size: u64,
+    /// data block 在文件中占用的字节数
+    pub data_size: u64,
+    /// index block 在文件中占用的字节数
+    pub index_size: u64,
+    /// filter block 在文件中占用的字节数, 没有 filter 时为 0
+    pub filter_size: u64,
+    /// 最小的 sequence, table 中的 key 不是 internal key 或者 table 为空时为 0
+    pub smallest_seqno: u64,
+    /// 最大的 sequence, table 中的 key 不是 internal key 或者 table 为空时为 0
+    pub largest_seqno: u64,
+    /// 压缩类型的名字
+    pub compression: String,
+    /// 比较器的名字
+    pub comparator_name: String,
+    /// filter policy 的名字, 没有 filter 时为空
+    pub filter_policy_name: String,
+    /// TablePropertiesCollector 收集的属性, 以及不能识别的属性
+    pub user_collected_properties: UserCollectedProperties,
+}
+
+impl TableProperties {
+    /// 将所有属性编码为 属性名 -> 属性值, 按属性名排序, 可以直接写入 BlockBuilder
+    pub(crate) fn encode(&self) -> UserCollectedProperties {
+        let mut properties = self.user_collected_properties.clone();
+        let mut put_u64 = |name: &str, value: u64| {
+            let mut buf = vec![];
+            Encoder::with_vec(&mut buf).put_varint64(value).expect("put varint64");
+            properties.insert(name.to_string(), buf);
+        };
+        put_u64(NUM_ENTRIES, self.num_entries);
+        put_u64(NUM_DELETIONS, self.num_deletions);
+        put_u64(NUM_DATA_BLOCKS, self.num_data_blocks);
+        put_u64(RAW_KEY_SIZE, self.raw_key_size);
+        put_u64(RAW_VALUE_SIZE, self.raw_value_size);
+        put_u64(DATA_SIZE, self.data_size);
+        put_u64(INDEX_SIZE, self.index_size);
+        put_u64(FILTER_SIZE, self.filter_size);
+        put_u64(SMALLEST_SEQNO, self.smallest_seqno);
+        put_u64(LARGEST_SEQNO, self.largest_seqno);
+        properties.insert(COMPRESSION.to_string(), self.compression.as_bytes().to_vec());
+        properties.insert(COMPARATOR.to_string(), self.comparator_name.as_bytes().to_vec());
+        properties.insert(FILTER_POLICY.to_string(), self.filter_policy_name.as_bytes().to_vec());
+        properties
+    }
+
+    /// 从 properties block 中解码属性, 不能识别的属性放入 user_collected_properties
+    ///
+    /// # Arguments
+    ///
+    /// * `block`: properties block
+    ///
+    /// returns: Result<TableProperties, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let properties = TableProperties::decode_from(&Block::new(contents))?;
+    /// ```
+    pub fn decode_from(block: &Block) -> Result<TableProperties> {
+        let mut properties = TableProperties::default();
+        let mut iter = block.new_iterator(Arc::new(BytewiseComparatorImpl::default()));
+        iter.seek_to_first();
+        while iter.valid() {
+            let key = iter.key();
+            let value = iter.value();
+            let name = String::from_utf8_lossy(key.as_ref()).to_string();
+            let get_u64 = || -> Result<u64> {
+                Decoder::with_buf(value.as_ref()).get_varint64()
+                    .map_err(|_| LevelError::corruption_string("bad table property", &name))
+            };
+            let get_string = || String::from_utf8_lossy(value.as_ref()).to_string();
+            match name.as_str() {
+                NUM_ENTRIES => properties.num_entries = get_u64()?,
+                NUM_DELETIONS => properties.num_deletions = get_u64()?,
+                NUM_DATA_BLOCKS => properties.num_data_blocks = get_u64()?,
+                RAW_KEY_SIZE => properties.raw_key_size = get_u64()?,
+                RAW_VALUE_SIZE => properties.raw_value_size = get_u64()?,
+                DATA_SIZE => properties.data_size = get_u64()?,
+                INDEX_SIZE => properties.index_size = get_u64()?,
+                FILTER_SIZE => properties.filter_size = get_u64()?,
+                SMALLEST_SEQNO => properties.smallest_seqno = get_u64()?,
+                LARGEST_SEQNO => properties.largest_seqno = get_u64()?,
+                COMPRESSION => properties.compression = get_string(),
+                COMPARATOR => properties.comparator_name = get_string(),
+                FILTER_POLICY => properties.filter_policy_name = get_string(),
+                _ => {
+                    properties.user_collected_properties.insert(name, value.as_ref().to_vec());
+                }
+            }
+            iter.next();
+        }
+        let status = iter.status();
+        if status.is_ok() { Ok(properties) } else { Err(status) }
+    }
+
+    /// 将 other 的属性累加到 self, 用于统计多个 table 的属性。
+    ///
+    /// 只累加数值类型的属性, sequence 取两者的范围; 字符串类型的属性及用户收集的属性不合并
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut aggregated = TableProperties::default();
+    /// aggregated.add(table.properties().unwrap());
+    /// ```
+    pub fn add(&mut self, other: &TableProperties) {
+        if other.num_entries > 0 {
+            if self.num_entries == 0 {
+                self.smallest_seqno = other.smallest_seqno;
+                self.largest_seqno = other.largest_seqno;
+            } else {
+                self.smallest_seqno = self.smallest_seqno.min(other.smallest_seqno);
+                self.largest_seqno = self.largest_seqno.max(other.largest_seqno);
+            }
+        }
+        self.num_entries += other.num_entries;
+        self.num_deletions += other.num_deletions;
+        self.num_data_blocks += other.num_data_blocks;
+        self.raw_key_size += other.raw_key_size;
+        self.raw_value_size += other.raw_value_size;
+        self.data_size += other.data_size;
+        self.index_size += other.index_size;
+        self.filter_size += other.filter_size;
+    }
+
+    /// 输出可读的属性, 每行一个 "属性名: 属性值", 为空的字符串属性不输出, 用户收集的属性转义后输出
+    pub fn debug_string(&self) -> String {
+        let mut r = String::new();
+        for (name, value) in [
+            (NUM_ENTRIES, self.num_entries),
+            (NUM_DELETIONS, self.num_deletions),
+            (NUM_DATA_BLOCKS, self.num_data_blocks),
+            (RAW_KEY_SIZE, self.raw_key_size),
+            (RAW_VALUE_SIZE, self.raw_value_size),
+            (DATA_SIZE, self.data_size),
+            (INDEX_SIZE, self.index_size),
+            (FILTER_SIZE, self.filter_size),
+            (SMALLEST_SEQNO, self.smallest_seqno),
+            (LARGEST_SEQNO, self.largest_seqno),
+        ] {
+            r.push_str(&format!("{}: {}\n", name, value));
+        }
+        for (name, value) in [
+            (COMPRESSION, &self.compression),
+            (COMPARATOR, &self.comparator_name),
+            (FILTER_POLICY, &self.filter_policy_name),
+        ] {
+            if !value.is_empty() {
+                r.push_str(&format!("{}: {}\n", name, value));
+            }
+        }
+        for (name, value) in &self.user_collected_properties {
+            r.push_str(&format!("{}: {}\n", name, escape_string(value)));
+        }
+        r
+    }
+}
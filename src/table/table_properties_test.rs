@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod test {
+    use crate::table::block::Block;
+    use crate::table::block_builder::BlockBuilder;
+    use crate::table::format::BlockContent;
+    use crate::table::table_properties::TableProperties;
+    use crate::util::slice::Slice;
+    use crate::util::Result;
+
+    fn properties(num_entries: u64, smallest_seqno: u64, largest_seqno: u64) -> TableProperties {
+        let mut properties = TableProperties {
+            num_entries,
+            num_deletions: 1,
+            num_data_blocks: 2,
+            raw_key_size: 300,
+            raw_value_size: 4000,
+            data_size: 5000,
+            index_size: 60,
+            filter_size: 70,
+            smallest_seqno,
+            largest_seqno,
+            compression: "SnappyCompression".to_string(),
+            comparator_name: "leveldb.InternalKeyComparator".to_string(),
+            filter_policy_name: "leveldb.BuiltinBloomFilter".to_string(),
+            ..TableProperties::default()
+        };
+        properties.user_collected_properties.insert("my.tag".to_string(), b"\x01tag".to_vec());
+        properties
+    }
+
+    fn new_block(data: &[u8]) -> Block {
+        Block::new(BlockContent {
+            data: Slice::from_buf(data),
+            cachable: false,
+            heap_allocated: false,
+        })
+    }
+
+    #[test]
+    fn test_encode_decode() -> Result<()> {
+        let expected = properties(10, 5, 200);
+        let mut builder = BlockBuilder::new(16);
+        for (name, value) in expected.encode() {
+            builder.add(name.as_bytes(), &value);
+        }
+        let block = new_block(builder.finish());
+        assert_eq!(expected, TableProperties::decode_from(&block)?);
+
+        // 数值属性不是合法的 varint64
+        let mut builder = BlockBuilder::new(16);
+        builder.add(b"leveldb.num.entries", &[]);
+        let block = new_block(builder.finish());
+        let status = TableProperties::decode_from(&block).unwrap_err();
+        assert!(status.is_corruption());
+        assert!(status.get_msg().contains("leveldb.num.entries"), "{}", status.get_msg());
+        Ok(())
+    }
+
+    #[test]
+    fn test_add() {
+        let mut aggregated = TableProperties::default();
+        aggregated.add(&properties(10, 5, 200));
+        // 空的 table 不影响 sequence 的范围
+        aggregated.add(&TableProperties::default());
+        aggregated.add(&properties(20, 1, 100));
+
+        assert_eq!(30, aggregated.num_entries);
+        assert_eq!(2, aggregated.num_deletions);
+        assert_eq!(4, aggregated.num_data_blocks);
+        assert_eq!(600, aggregated.raw_key_size);
+        assert_eq!(8000, aggregated.raw_value_size);
+        assert_eq!(10000, aggregated.data_size);
+        assert_eq!(120, aggregated.index_size);
+        assert_eq!(140, aggregated.filter_size);
+        assert_eq!(1, aggregated.smallest_seqno);
+        assert_eq!(200, aggregated.largest_seqno);
+        // 字符串属性及用户收集的属性不合并
+        assert!(aggregated.compression.is_empty());
+        assert!(aggregated.user_collected_properties.is_empty());
+    }
+
+    #[test]
+    fn test_debug_string() {
+        let debug_string = properties(10, 5, 200).debug_string();
+        assert!(debug_string.starts_with("leveldb.num.entries: 10\nleveldb.num.deletions: 1\n"), "{}", debug_string);
+        assert!(debug_string.contains("leveldb.compression: SnappyCompression\n"), "{}", debug_string);
+        assert!(debug_string.ends_with("my.tag: \\x01tag\n"), "{}", debug_string);
+
+        let debug_string = TableProperties::default().debug_string();
+        assert!(!debug_string.contains("leveldb.compression"), "{}", debug_string);
+    }
+}
@@ -0,0 +1,688 @@
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::db::db_format::{InternalKey, InternalKeyComparator, ParsedInternalKey, ValueType};
+    use crate::table::table::Table;
+    use crate::table::table_builder::TableBuilder;
+    use crate::util::cache::ShardLRUCache;
+    use crate::traits::DataIterator;
+    use crate::traits::filter_policy_trait::FilterPolicyPtr;
+    use crate::util::filter_policy_bloom::BloomFilterPolicy;
+    use crate::util::filter_policy_internal::InternalFilterPolicy;
+    use crate::util::filter_policy_prefix::PrefixFilterPolicy;
+    use crate::util::slice_transform::{DelimitedPrefixTransform, InternalSliceTransform};
+    use crate::util::options::{ChecksumType, CompressionType, Options, OptionsPtr, ReadOptions};
+    use crate::util::slice::Slice;
+    use crate::util::Result;
+
+    fn new_table_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("level_db_rust_table_test");
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    fn build_table(fname: &Path, options: OptionsPtr, entries: &[(&str, String)]) -> Result<u64> {
+        let file = options.env.new_writable_file(fname)?;
+        let mut builder = TableBuilder::new_with_writable_file(options, Box::new(file));
+        for (k, v) in entries {
+            builder.add(k.as_bytes(), v.as_bytes());
+        }
+        assert_eq!(entries.len() as u64, builder.get_num_entries());
+        builder.finish()?;
+        builder.sync()?;
+        Ok(builder.get_file_size())
+    }
+
+    fn between(val: u64, low: u64, high: u64) -> bool {
+        let result = val >= low && val <= high;
+        if !result {
+            println!("Value {} is not in range [{}, {}]", val, low, high);
+        }
+        result
+    }
+
+    #[test]
+    fn test_approximate_offset_of_plain() -> Result<()> {
+        let fname = new_table_file("approximate_offset_of_plain.ldb");
+        let mut options = Options::default();
+        options.block_size = 1024;
+        options.compression = CompressionType::NoCompression;
+        let options: OptionsPtr = Arc::new(Box::new(options));
+
+        let entries = [
+            ("k01", "hello".to_string()),
+            ("k02", "hello2".to_string()),
+            ("k03", "x".repeat(10000)),
+            ("k04", "x".repeat(200000)),
+            ("k05", "x".repeat(300000)),
+            ("k06", "hello3".to_string()),
+            ("k07", "x".repeat(100000)),
+        ];
+        let file_size = build_table(&fname, options.clone(), &entries)?;
+        assert_eq!(fs::metadata(&fname)?.len(), file_size);
+
+        let file = options.env.new_random_access_file(&fname)?;
+        let table = Table::open(options.clone(), Box::new(file), file_size)?;
+        assert!(between(table.approximate_offset_of(b"abc"), 0, 0));
+        assert!(between(table.approximate_offset_of(b"k01"), 0, 0));
+        assert!(between(table.approximate_offset_of(b"k01a"), 0, 0));
+        assert!(between(table.approximate_offset_of(b"k02"), 0, 0));
+        assert!(between(table.approximate_offset_of(b"k03"), 0, 0));
+        assert!(between(table.approximate_offset_of(b"k04"), 10000, 11000));
+        assert!(between(table.approximate_offset_of(b"k04a"), 210000, 211000));
+        assert!(between(table.approximate_offset_of(b"k05"), 210000, 211000));
+        assert!(between(table.approximate_offset_of(b"k06"), 510000, 511000));
+        assert!(between(table.approximate_offset_of(b"k07"), 510000, 511000));
+        assert!(between(table.approximate_offset_of(b"xyz"), 610000, 612000));
+
+        fs::remove_file(&fname)?;
+        Ok(())
+    }
+
+    /// 长度为 len 的字符串, 压缩后的大小约为 len * compressed_fraction
+    fn compressible_string(rnd: &mut StdRng, compressed_fraction: f64, len: usize) -> String {
+        let raw_len = ((len as f64 * compressed_fraction) as usize).max(1);
+        let raw: String = (0..raw_len).map(|_| rnd.gen_range(' '..='~')).collect();
+        raw.repeat(len / raw_len + 1)[..len].to_string()
+    }
+
+    #[test]
+    fn test_approximate_offset_of_compressed() -> Result<()> {
+        let fname = new_table_file("approximate_offset_of_compressed.ldb");
+        let mut options = Options::default();
+        options.block_size = 1024;
+        options.compression = CompressionType::SnappyCompression;
+        let options: OptionsPtr = Arc::new(Box::new(options));
+
+        let mut rnd = StdRng::seed_from_u64(301);
+        let entries = [
+            ("k01", "hello".to_string()),
+            ("k02", compressible_string(&mut rnd, 0.25, 10000)),
+            ("k03", "hello3".to_string()),
+            ("k04", compressible_string(&mut rnd, 0.25, 10000)),
+        ];
+        let file_size = build_table(&fname, options.clone(), &entries)?;
+
+        let file = options.env.new_random_access_file(&fname)?;
+        let table = Table::open(options.clone(), Box::new(file), file_size)?;
+
+        // Expected upper and lower bounds of space used by compressible strings.
+        let slop = 1000; // Compressor effectiveness varies.
+        let expected = 2500; // 10000 * compression ratio (0.25)
+        let min_z = expected - slop;
+        let max_z = expected + slop;
+
+        assert!(between(table.approximate_offset_of(b"abc"), 0, slop));
+        assert!(between(table.approximate_offset_of(b"k01"), 0, slop));
+        assert!(between(table.approximate_offset_of(b"k02"), 0, slop));
+        // Have now emitted a large compressible string, so adjust expected offset.
+        assert!(between(table.approximate_offset_of(b"k03"), min_z, max_z));
+        assert!(between(table.approximate_offset_of(b"k04"), min_z, max_z));
+        // Have now emitted two large compressible strings, so adjust expected offset.
+        assert!(between(table.approximate_offset_of(b"xyz"), 2 * min_z, 2 * max_z));
+
+        fs::remove_file(&fname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_errors() -> Result<()> {
+        let options: OptionsPtr = Arc::new(Box::new(Options::default()));
+
+        // 文件太短
+        let status = Table::open(options.clone(), Box::new(vec![0_u8; 10]), 10).err().unwrap();
+        assert!(status.is_corruption());
+
+        // 不是 SSTable
+        let status = Table::open(options.clone(), Box::new(vec![0_u8; 100]), 100).err().unwrap();
+        assert!(status.is_corruption());
+
+        // 文件被截断, 读取 index block 失败
+        let fname = new_table_file("open_errors.ldb");
+        let file_size = build_table(&fname, options.clone(), &[("a", "1".to_string()), ("b", "2".to_string())])?;
+        let contents = fs::read(&fname)?;
+        let truncated = contents[contents.len() - 48..].to_vec();
+        let status = Table::open(options.clone(), Box::new(truncated), 48).err().unwrap();
+        assert!(status.is_corruption());
+
+        // 完整的文件可以正常打开
+        let table = Table::open(options.clone(), Box::new(contents), file_size)?;
+        assert_eq!(0, table.approximate_offset_of(b"a"));
+
+        fs::remove_file(&fname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_table() -> Result<()> {
+        let fname = new_table_file("empty.ldb");
+        let options: OptionsPtr = Arc::new(Box::new(Options::default()));
+        let file_size = build_table(&fname, options.clone(), &[])?;
+
+        let file = options.env.new_random_access_file(&fname)?;
+        let table = Table::open(options.clone(), Box::new(file), file_size)?;
+        assert_eq!(0, table.approximate_offset_of(b"a"));
+        assert_eq!(0, table.properties().unwrap().num_entries);
+
+        fs::remove_file(&fname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_properties() -> Result<()> {
+        let fname = new_table_file("properties.ldb");
+        let mut options = Options::default();
+        options.block_size = 256;
+        options.compression = CompressionType::NoCompression;
+        options.filter_policy = Some(Arc::new(Box::new(BloomFilterPolicy::new())));
+        let options: OptionsPtr = Arc::new(Box::new(options));
+
+        let keys: Vec<String> = (0..100).map(|i| format!("key{:05}", i)).collect();
+        let file = options.env.new_writable_file(&fname)?;
+        let mut builder = TableBuilder::new_with_writable_file(options.clone(), Box::new(file));
+        for k in &keys {
+            builder.add(k.as_bytes(), format!("value_{}", k).as_bytes());
+        }
+        builder.finish()?;
+        let file_size = builder.get_file_size();
+
+        let file = options.env.new_random_access_file(&fname)?;
+        let table = Table::open(options.clone(), Box::new(file), file_size)?;
+        let properties = table.properties().unwrap();
+        assert_eq!(builder.get_properties(), properties);
+        assert_eq!(100, properties.num_entries);
+        assert_eq!(0, properties.num_deletions);
+        assert_eq!(100 * 8, properties.raw_key_size);
+        assert_eq!(100 * 14, properties.raw_value_size);
+        assert!(properties.num_data_blocks > 1);
+        // 不是 internal key, 没有 sequence
+        assert_eq!(0, properties.smallest_seqno);
+        assert_eq!(0, properties.largest_seqno);
+        assert!(properties.filter_size > 0);
+        assert!(properties.index_size > 0);
+        assert_eq!("NoCompression", properties.compression);
+        assert_eq!("leveldb.BytewiseComparator", properties.comparator_name);
+        assert_eq!("leveldb.BuiltinBloomFilter", properties.filter_policy_name);
+        assert!(properties.user_collected_properties.is_empty());
+
+        // data block 之后是 filter block
+        assert_eq!(properties.data_size, table.approximate_offset_of(b"zzz"));
+        assert!(properties.data_size + properties.filter_size + properties.index_size < file_size);
+
+        fs::remove_file(&fname)?;
+        Ok(())
+    }
+
+    /// 生成 table 并遍历, 返回文件大小
+    fn check_iterate(name: &str, compression: CompressionType) -> Result<u64> {
+        let fname = new_table_file(name);
+        let mut options = Options::default();
+        options.block_size = 256;
+        options.compression = compression;
+        let options: OptionsPtr = Arc::new(Box::new(options));
+
+        let keys: Vec<String> = (0..500).map(|i| format!("key{:05}", i * 2)).collect();
+        let entries: Vec<(&str, String)> = keys.iter().map(|k| (k.as_str(), format!("value_{}", k))).collect();
+        let file_size = build_table(&fname, options.clone(), &entries)?;
+
+        let file = options.env.new_random_access_file(&fname)?;
+        let table = Arc::new(Table::open(options.clone(), Box::new(file), file_size)?);
+        let mut iter = table.new_iterator(&ReadOptions::default());
+        let entry_of = |iter: &dyn DataIterator| (iter.key().as_str().to_string(), iter.value().as_str().to_string());
+
+        // 跨越多个 data block 正向及反向遍历
+        let mut actual = vec![];
+        iter.seek_to_first();
+        while iter.valid() {
+            actual.push(entry_of(iter.as_ref()));
+            iter.next();
+        }
+        let expected: Vec<(String, String)> = entries.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+        assert_eq!(expected, actual);
+
+        actual.clear();
+        iter.seek_to_last();
+        while iter.valid() {
+            actual.push(entry_of(iter.as_ref()));
+            iter.pre();
+        }
+        actual.reverse();
+        assert_eq!(expected, actual);
+
+        iter.seek(&Slice::from("key00101"));
+        assert_eq!(("key00102".to_string(), "value_key00102".to_string()), entry_of(iter.as_ref()));
+        iter.pre();
+        assert_eq!("key00100", iter.key().as_str());
+        iter.seek(&Slice::from("key99999"));
+        assert!(!iter.valid());
+        assert!(iter.status().is_ok());
+
+        fs::remove_file(&fname)?;
+        Ok(file_size)
+    }
+
+    #[test]
+    fn test_table_iterate() -> Result<()> {
+        let plain_size = check_iterate("iterate.ldb", CompressionType::NoCompression)?;
+        for compression in [CompressionType::SnappyCompression, CompressionType::LZ4Compression,
+            CompressionType::ZstdCompression] {
+            let compressed_size = check_iterate(&format!("iterate_{:?}.ldb", compression), compression)?;
+            assert!(compressed_size < plain_size, "{:?} {} {}", compression, compressed_size, plain_size);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_zstd_options() -> Result<()> {
+        let fname = new_table_file("zstd_options.ldb");
+        let mut options = Options::default();
+        options.compression = CompressionType::ZstdCompression;
+        options.zstd_level = Some(19);
+        options.zstd_dictionary = Some("key00000value_key00000".repeat(10).into_bytes());
+        let options: OptionsPtr = Arc::new(Box::new(options));
+
+        let keys: Vec<String> = (0..100).map(|i| format!("key{:05}", i)).collect();
+        let entries: Vec<(&str, String)> = keys.iter().map(|k| (k.as_str(), format!("value_{}", k))).collect();
+        let file_size = build_table(&fname, options.clone(), &entries)?;
+
+        let read_all = |options: OptionsPtr| -> Result<Vec<String>> {
+            let file = options.env.new_random_access_file(&fname)?;
+            let table = Arc::new(Table::open(options, Box::new(file), file_size)?);
+            let mut iter = table.new_iterator(&ReadOptions::default());
+            let mut values = vec![];
+            iter.seek_to_first();
+            while iter.valid() {
+                values.push(iter.value().as_str().to_string());
+                iter.next();
+            }
+            let status = iter.status();
+            if status.is_ok() { Ok(values) } else { Err(status) }
+        };
+        let expected: Vec<String> = entries.iter().map(|(_, v)| v.clone()).collect();
+        assert_eq!(expected, read_all(options.clone())?);
+
+        // 没有字典时无法解压
+        let mut no_dictionary = Options::default();
+        no_dictionary.compression = CompressionType::ZstdCompression;
+        assert!(read_all(Arc::new(Box::new(no_dictionary))).is_err());
+
+        fs::remove_file(&fname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_compression_type() -> Result<()> {
+        let fname = new_table_file("unknown_compression_type.ldb");
+        let mut options = Options::default();
+        options.compression = CompressionType::NoCompression;
+        let options: OptionsPtr = Arc::new(Box::new(options));
+        let file_size = build_table(&fname, options.clone(), &[("k", "v".to_string())])?;
+
+        // 修改 data block trailer 中的压缩类型, 不校验 crc 时读取返回 NotSupported
+        let mut contents = fs::read(&fname)?;
+        let block_len = 1 + 1 + 1 + 1 + 1 + 4 + 4;
+        assert_eq!(CompressionType::NoCompression as u8, contents[block_len]);
+        contents[block_len] = 9;
+
+        let table = Arc::new(Table::open(options.clone(), Box::new(contents), file_size)?);
+        let mut iter = table.new_iterator(&ReadOptions::default());
+        iter.seek_to_first();
+        assert!(!iter.valid());
+        assert!(iter.status().is_not_supported_error(), "{}", iter.status().get_msg());
+
+        fs::remove_file(&fname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_incompressible_block() -> Result<()> {
+        let fname = new_table_file("incompressible.ldb");
+        let mut options = Options::default();
+        options.compression = CompressionType::SnappyCompression;
+        let options: OptionsPtr = Arc::new(Box::new(options));
+
+        // 压缩率不足 12.5% 的 block 以不压缩的格式保存
+        let mut rnd = StdRng::seed_from_u64(301);
+        let value: String = (0..2000).map(|_| rnd.gen_range(' '..='~')).collect();
+        let file_size = build_table(&fname, options.clone(), &[("k", value.clone())])?;
+        let contents = fs::read(&fname)?;
+        let block_len = 1 + 1 + 1 + 2 + value.len() + 4 + 4;
+        assert_eq!(CompressionType::NoCompression as u8, contents[block_len]);
+
+        let table = Arc::new(Table::open(options.clone(), Box::new(contents), file_size)?);
+        let mut iter = table.new_iterator(&ReadOptions::default());
+        iter.seek_to_first();
+        assert_eq!("k", iter.key().as_str());
+        assert_eq!(value, iter.value().as_str());
+
+        fs::remove_file(&fname)?;
+        Ok(())
+    }
+
+    /// 以 verify_checksums 读取 table 中全部的 value
+    fn read_verified(table: Table) -> Result<Vec<String>> {
+        let table = Arc::new(table);
+        let mut read_options = ReadOptions::default();
+        read_options.verify_checksums = true;
+        let mut iter = table.new_iterator(&read_options);
+        let mut values = vec![];
+        iter.seek_to_first();
+        while iter.valid() {
+            values.push(iter.value().as_str().to_string());
+            iter.next();
+        }
+        let status = iter.status();
+        if status.is_ok() { Ok(values) } else { Err(status) }
+    }
+
+    #[test]
+    fn test_checksum_type() -> Result<()> {
+        let entries = [("a", "1".to_string()), ("b", "2".to_string())];
+        let expected = vec!["1".to_string(), "2".to_string()];
+        for checksum in [ChecksumType::Crc32c, ChecksumType::XxHash64] {
+            let fname = new_table_file(&format!("checksum_{:?}.ldb", checksum));
+            let mut options = Options::default();
+            options.compression = CompressionType::NoCompression;
+            options.checksum = checksum;
+            let options: OptionsPtr = Arc::new(Box::new(options));
+            let file_size = build_table(&fname, options.clone(), &entries)?;
+            let mut contents = fs::read(&fname)?;
+
+            // 读取时使用 footer 中记录的算法, 与 reader 的 options 无关
+            let default_options: OptionsPtr = Arc::new(Box::new(Options::default()));
+            let table = Table::open(default_options.clone(), Box::new(contents.clone()), file_size)?;
+            assert_eq!(expected, read_verified(table)?);
+
+            // 修改 data block 中的 value, 校验失败时返回文件号及 block 的偏移
+            let block_len = 1 + 1 + 1 + 1 + 1 + 4 + 4;
+            contents[block_len - 9] ^= 1;
+            let table = Table::open_with_file_number(default_options.clone(), 123, Box::new(contents.clone()), file_size)?;
+            let status = read_verified(table).unwrap_err();
+            assert!(status.is_corruption());
+            assert!(status.get_msg().contains("file 123"), "{}", status.get_msg());
+            assert!(status.get_msg().contains("block checksum mismatch"), "{}", status.get_msg());
+            assert!(status.get_msg().contains("block offset 0"), "{}", status.get_msg());
+
+            // 不校验时可以读取
+            let table = Arc::new(Table::open(default_options, Box::new(contents), file_size)?);
+            let mut iter = table.new_iterator(&ReadOptions::default());
+            iter.seek_to_first();
+            assert!(iter.valid());
+
+            fs::remove_file(&fname)?;
+        }
+        Ok(())
+    }
+
+    /// DB 内部使用的 options: 以 InternalKeyComparator 作为比较器, filter 使用 user key
+    fn internal_options(filter_policy: Option<FilterPolicyPtr>) -> OptionsPtr {
+        let mut options = Options::default();
+        options.block_size = 256;
+        options.cmp = Arc::new(InternalKeyComparator::new(options.cmp.clone()));
+        options.filter_policy = InternalFilterPolicy::wrap(filter_policy);
+        Arc::new(Box::new(options))
+    }
+
+    /// 调用 internal_get, 返回找到的 user key
+    fn internal_get(table: &Table, user_key: &str, seq: u64) -> Result<Option<String>> {
+        let key = InternalKey::new(Slice::from(user_key), seq, ValueType::KTypeValue);
+        let mut found = None;
+        table.internal_get(&ReadOptions::default(), key.encode(), |k, _| {
+            found = Some(String::from_utf8(ParsedInternalKey::extract_user_key(k).to_vec()).unwrap());
+        })?;
+        Ok(found)
+    }
+
+    #[test]
+    fn test_internal_get_with_filter() -> Result<()> {
+        let fname = new_table_file("internal_get_with_filter.ldb");
+        let policy: FilterPolicyPtr = Arc::new(Box::new(BloomFilterPolicy::new()));
+        let options = internal_options(Some(policy.clone()));
+
+        let file = options.env.new_writable_file(&fname)?;
+        let mut builder = TableBuilder::new_with_writable_file(options.clone(), Box::new(file));
+        // 4 字节的 key 只有最后一个字节不同时 bloom hash 的低位相同, 假阳性率很高, 因此使用 5 字节的 key
+        for i in 0..100 {
+            let key = InternalKey::new(Slice::from(format!("k{:04}", i * 2)), i + 1, ValueType::KTypeValue);
+            builder.add(key.encode(), "v".repeat(50).as_bytes());
+        }
+        builder.finish()?;
+        let file_size = builder.get_file_size();
+        drop(builder);
+
+        // meta index block 中记录了以用户 FilterPolicy 命名的 filter block
+        let contents = fs::read(&fname)?;
+        let name = format!("filter.{}", policy.name());
+        assert!(contents.windows(name.len()).any(|w| w == name.as_bytes()));
+
+        let table = Table::open(options.clone(), Box::new(contents), file_size)?;
+        // filter 中保存的是 user key, 任意 sequence 都可以命中
+        for i in 0..100 {
+            let user_key = format!("k{:04}", i * 2);
+            assert_eq!(Some(user_key.clone()), internal_get(&table, &user_key, 1000)?);
+            assert_eq!(Some(user_key.clone()), internal_get(&table, &user_key, i + 1)?);
+        }
+
+        // 不存在的 key 被 filter 排除, 不会读取 data block
+        let mut filtered = 0;
+        for i in 0..99 {
+            if internal_get(&table, &format!("k{:04}", i * 2 + 1), 1000)?.is_none() {
+                filtered += 1;
+            }
+        }
+        assert!(filtered >= 95, "filtered {}", filtered);
+
+        // 没有 filter 时返回 seek 到的下一条记录
+        let file = options.env.new_random_access_file(&fname)?;
+        let table = Table::open(internal_options(None), Box::new(file), file_size)?;
+        assert_eq!(Some("k0002".to_string()), internal_get(&table, "k0001", 1000)?);
+        assert_eq!(None, internal_get(&table, "k9999", 1000)?);
+
+        fs::remove_file(&fname)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_internal_get_with_full_filter() -> Result<()> {
+        let fname = new_table_file("internal_get_with_full_filter.ldb");
+        let policy: FilterPolicyPtr = Arc::new(Box::new(BloomFilterPolicy::new()));
+        let mut options = Options::default();
+        options.block_size = 256;
+        options.cmp = Arc::new(InternalKeyComparator::new(options.cmp.clone()));
+        options.filter_policy = InternalFilterPolicy::wrap(Some(policy.clone()));
+        options.full_filter = true;
+        let options: OptionsPtr = Arc::new(Box::new(options));
+
+        let file = options.env.new_writable_file(&fname)?;
+        let mut builder = TableBuilder::new_with_writable_file(options.clone(), Box::new(file));
+        for i in 0..100 {
+            let key = InternalKey::new(Slice::from(format!("k{:03}", i * 2)), i + 1, ValueType::KTypeValue);
+            builder.add(key.encode(), "v".repeat(50).as_bytes());
+        }
+        builder.finish()?;
+        let file_size = builder.get_file_size();
+        drop(builder);
+
+        // meta index block 中只有整个 table 的 filter
+        let contents = fs::read(&fname)?;
+        let name = format!("fullfilter.{}", policy.name());
+        assert_eq!(1, contents.windows(name.len()).filter(|w| *w == name.as_bytes()).count());
+        assert_eq!(1, contents.windows(name.len() - 4).filter(|w| *w == &name.as_bytes()[4..]).count());
+
+        // 读取时与 options.full_filter 无关
+        for read_options in [options.clone(), internal_options(Some(policy.clone()))] {
+            let table = Table::open(read_options, Box::new(contents.clone()), file_size)?;
+            for i in 0..100 {
+                let user_key = format!("k{:03}", i * 2);
+                assert_eq!(Some(user_key.clone()), internal_get(&table, &user_key, 1000)?);
+            }
+
+            // 不存在的 key 在查找 index block 之前被 filter 排除
+            let mut filtered = 0;
+            for i in 0..99 {
+                if internal_get(&table, &format!("k{:03}", i * 2 + 1), 1000)?.is_none() {
+                    filtered += 1;
+                }
+            }
+            assert!(filtered >= 95, "filtered {}", filtered);
+        }
+
+        // 没有 filter 时返回 seek 到的下一条记录
+        let table = Table::open(internal_options(None), Box::new(contents), file_size)?;
+        assert_eq!(Some("k002".to_string()), internal_get(&table, "k001", 1000)?);
+
+        fs::remove_file(&fname)?;
+        Ok(())
+    }
+
+    /// 与 DB::new 相同的方式配置前缀提取器
+    fn prefix_options(full_filter: bool) -> OptionsPtr {
+        let mut options = Options::default();
+        options.block_size = 256;
+        options.cmp = Arc::new(InternalKeyComparator::new(options.cmp.clone()));
+        options.prefix_extractor = Some(Arc::new(Box::new(DelimitedPrefixTransform::new(b'/', 1))));
+        options.filter_policy = InternalFilterPolicy::wrap(PrefixFilterPolicy::wrap(
+            Some(Arc::new(Box::new(BloomFilterPolicy::new()))), options.prefix_extractor.clone()));
+        options.prefix_extractor = InternalSliceTransform::wrap(options.prefix_extractor.take());
+        options.full_filter = full_filter;
+        Arc::new(Box::new(options))
+    }
+
+    /// 从 seek(user_key) 开始迭代, 返回所有的 user key
+    fn seek_user_keys(table: &Arc<Table>, prefix_same_as_start: bool, user_key: &str) -> Vec<String> {
+        let options = ReadOptions { prefix_same_as_start, ..ReadOptions::default() };
+        let mut iter = table.new_iterator(&options);
+        let target = InternalKey::new(Slice::from(user_key), 1000, ValueType::KTypeValue);
+        iter.seek(&Slice::from_buf(target.encode()));
+        let mut keys = vec![];
+        while iter.valid() && keys.len() < 100 {
+            keys.push(String::from_utf8(ParsedInternalKey::extract_user_key(iter.key().as_ref()).to_vec()).unwrap());
+            iter.next();
+        }
+        keys
+    }
+
+    #[test]
+    fn test_prefix_seek() -> Result<()> {
+        for full_filter in [false, true] {
+            let fname = new_table_file(&format!("prefix_seek_{}.ldb", full_filter));
+            let options = prefix_options(full_filter);
+
+            // 只有偶数的 tenant, 每个 tenant 5 条记录
+            let file = options.env.new_writable_file(&fname)?;
+            let mut builder = TableBuilder::new_with_writable_file(options.clone(), Box::new(file));
+            for tenant in (0..100).step_by(2) {
+                for entity in 0..5 {
+                    let key = InternalKey::new(Slice::from(format!("t{:02}/e{}", tenant, entity)), 1, ValueType::KTypeValue);
+                    builder.add(key.encode(), "v".repeat(20).as_bytes());
+                }
+            }
+            builder.finish()?;
+            let file_size = builder.get_file_size();
+            drop(builder);
+            let table = Arc::new(Table::open(options.clone(), Box::new(fs::read(&fname)?), file_size)?);
+
+            // 只返回前缀相同的 key
+            assert_eq!(vec!["t10/e0", "t10/e1", "t10/e2", "t10/e3", "t10/e4"], seek_user_keys(&table, true, "t10/"));
+            assert_eq!(vec!["t10/e3", "t10/e4"], seek_user_keys(&table, true, "t10/e3"));
+            // 不按前缀迭代时继续返回之后的 key
+            assert_eq!("t12/e0", seek_user_keys(&table, false, "t10/e0")[5]);
+            assert_eq!("t12/e0", seek_user_keys(&table, false, "t10/e9")[0]);
+            // seek_to_first 按完整的顺序迭代
+            let mut iter = table.new_iterator(&ReadOptions { prefix_same_as_start: true, ..ReadOptions::default() });
+            iter.seek_to_first();
+            let mut count = 0;
+            while iter.valid() {
+                count += 1;
+                iter.next();
+            }
+            assert_eq!(250, count);
+
+            // 不存在的 tenant
+            let mut filtered = 0;
+            for tenant in (1..99).step_by(2) {
+                let prefix = format!("t{:02}/", tenant);
+                assert!(seek_user_keys(&table, true, &prefix).is_empty());
+                assert_eq!(format!("t{:02}/e0", tenant + 1), seek_user_keys(&table, false, &prefix)[0]);
+
+                let target = InternalKey::new(Slice::from(prefix), 1000, ValueType::KTypeValue);
+                if !table.prefix_may_match(target.encode()) {
+                    filtered += 1;
+                }
+            }
+            assert!(filtered >= 45, "full_filter {} filtered {}", full_filter, filtered);
+
+            // 存在的 tenant 一定可能匹配
+            for tenant in (0..100).step_by(2) {
+                let target = InternalKey::new(Slice::from(format!("t{:02}/", tenant)), 1000, ValueType::KTypeValue);
+                assert!(table.prefix_may_match(target.encode()));
+            }
+
+            fs::remove_file(&fname)?;
+        }
+        Ok(())
+    }
+
+    /// 以 read_options 读取 table 中全部的 value
+    fn read_values(table: &Arc<Table>, read_options: &ReadOptions) -> Result<Vec<String>> {
+        let mut iter = table.new_iterator(read_options);
+        let mut values = vec![];
+        iter.seek_to_first();
+        while iter.valid() {
+            values.push(iter.value().as_str().to_string());
+            iter.next();
+        }
+        let status = iter.status();
+        if status.is_ok() { Ok(values) } else { Err(status) }
+    }
+
+    #[test]
+    fn test_block_cache() -> Result<()> {
+        let fname = new_table_file("block_cache.ldb");
+        let cache = ShardLRUCache::new_arc_with_capacity(1 << 20, 0);
+        let mut options = Options::default();
+        options.compression = CompressionType::NoCompression;
+        options.block_size = 256;
+        options.block_cache = Some(cache.clone());
+        let options: OptionsPtr = Arc::new(Box::new(options));
+        let entries: Vec<_> = (0..100).map(|i| (format!("k{:03}", i), format!("v{:03}", i).repeat(10))).collect();
+        let entries: Vec<_> = entries.iter().map(|(k, v)| (k.as_str(), v.clone())).collect();
+        let expected: Vec<_> = entries.iter().map(|(_, v)| v.clone()).collect();
+        let file_size = build_table(&fname, options.clone(), &entries)?;
+        let table = Arc::new(Table::open(options.clone(), Box::new(options.env.new_random_access_file(&fname)?), file_size)?);
+
+        let mut read_options = ReadOptions::default();
+        read_options.verify_checksums = true;
+        read_options.fill_cache = false;
+        assert_eq!(expected, read_values(&table, &read_options)?);
+        assert_eq!(0, cache.total_charge()?);
+
+        read_options.fill_cache = true;
+        assert_eq!(expected, read_values(&table, &read_options)?);
+        let charge = cache.total_charge()?;
+        assert!(charge > 256 * 10, "{}", charge);
+
+        // 文件被破坏后 data block 仍然从 cache 中读取
+        fs::write(&fname, vec![0u8; file_size as usize])?;
+        assert_eq!(expected, read_values(&table, &read_options)?);
+        assert_eq!(charge, cache.total_charge()?);
+
+        // 共享同一个 cache 的 table 使用不同的 cache_id, block 的偏移相同时也不会读到其他 table 的数据
+        let other_fname = new_table_file("block_cache_other.ldb");
+        let other_entries: Vec<_> = entries.iter().map(|(k, v)| (*k, v.replace('v', "w"))).collect();
+        let other_expected: Vec<_> = other_entries.iter().map(|(_, v)| v.clone()).collect();
+        let other_size = build_table(&other_fname, options.clone(), &other_entries)?;
+        assert_eq!(file_size, other_size);
+        let other = Arc::new(Table::open(options.clone(), Box::new(options.env.new_random_access_file(&other_fname)?), other_size)?);
+        assert_eq!(other_expected, read_values(&other, &read_options)?);
+        assert_eq!(2 * charge, cache.total_charge()?);
+
+        fs::remove_file(&fname)?;
+        fs::remove_file(&other_fname)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+use crate::table::iterator_wrapper::IteratorWrapper;
+use crate::traits::DataIterator;
+use crate::util::slice::Slice;
+use crate::util::status::Status;
+use crate::util::unsafe_slice::UnsafeSlice;
+
+/// 根据 index 迭代器的 value(例如编码后的 BlockHandle) 生成 data 迭代器
+pub type BlockFunction = Box<dyn Fn(&[u8]) -> Box<dyn DataIterator>>;
+
+/// 两级迭代器: 第一级是 index 迭代器, 其中每个 value 都对应一个第二级的 data 迭代器,
+/// 依次遍历所有 data 迭代器即可得到完整的数据
+struct TwoLevelIterator {
+    block_function: BlockFunction,
+    status: Status,
+    index_iter: IteratorWrapper,
+    // May be nullptr
+    data_iter: IteratorWrapper,
+    // If data_iter_ is non-null, then "data_block_handle_" holds the
+    // "index_value" passed to block_function_ to create the data_iter_.
+    data_block_handle: Vec<u8>,
+}
+
+/// 创建两级迭代器
+///
+/// Return a new two level iterator.  A two-level iterator contains an
+/// index iterator whose values point to a sequence of blocks where
+/// each block is itself a sequence of key,value pairs.  The returned
+/// two-level iterator yields the concatenation of all key/value pairs
+/// in the sequence of blocks.  Takes ownership of "index_iter" and
+/// will delete it when no longer needed.
+///
+/// Uses a supplied function to convert an index_iter value into
+/// an iterator over the contents of the corresponding block.
+///
+/// # Arguments
+///
+/// * `index_iter`: index 迭代器
+/// * `block_function`: 由 index 迭代器的 value 生成 data 迭代器
+///
+/// returns: Box<dyn DataIterator, Global>
+///
+/// # Examples
+///
+/// ```ignore
+/// let iter = new_two_level_iterator(index_block.new_iterator(cmp), Box::new(move |index_value| {
+///     Table::block_reader(&table, &options, index_value)
+/// }));
+/// ```
+pub fn new_two_level_iterator(index_iter: Box<dyn DataIterator>, block_function: BlockFunction) -> Box<dyn DataIterator> {
+    Box::new(TwoLevelIterator {
+        block_function,
+        status: Status::default(),
+        index_iter: IteratorWrapper::new(Some(index_iter)),
+        data_iter: IteratorWrapper::new(None),
+        data_block_handle: vec![],
+    })
+}
+
+impl TwoLevelIterator {
+    fn save_error(&mut self, s: Status) {
+        if self.status.is_ok() && !s.is_ok() {
+            self.status = s;
+        }
+    }
+
+    fn skip_empty_data_blocks_forward(&mut self) {
+        while self.data_iter.iter().is_none() || !self.data_iter.valid() {
+            // Move to next block
+            if !self.index_iter.valid() {
+                self.set_data_iterator(None);
+                return;
+            }
+            self.index_iter.next();
+            self.init_data_block();
+            if self.data_iter.iter().is_some() {
+                self.data_iter.seek_to_first();
+            }
+        }
+    }
+
+    fn skip_empty_data_blocks_backward(&mut self) {
+        while self.data_iter.iter().is_none() || !self.data_iter.valid() {
+            // Move to next block
+            if !self.index_iter.valid() {
+                self.set_data_iterator(None);
+                return;
+            }
+            self.index_iter.pre();
+            self.init_data_block();
+            if self.data_iter.iter().is_some() {
+                self.data_iter.seek_to_last();
+            }
+        }
+    }
+
+    fn set_data_iterator(&mut self, data_iter: Option<Box<dyn DataIterator>>) {
+        if self.data_iter.iter().is_some() {
+            let status = self.data_iter.status();
+            self.save_error(status);
+        }
+        self.data_iter.set(data_iter);
+    }
+
+    fn init_data_block(&mut self) {
+        if !self.index_iter.valid() {
+            self.set_data_iterator(None);
+        } else {
+            let handle = self.index_iter.value();
+            if self.data_iter.iter().is_some() && handle.as_ref() == self.data_block_handle.as_slice() {
+                // data_iter_ is already constructed with this iterator, so
+                // no need to change anything
+            } else {
+                let iter = (self.block_function)(handle.as_ref());
+                self.data_block_handle.clear();
+                self.data_block_handle.extend_from_slice(handle.as_ref());
+                self.set_data_iterator(Some(iter));
+            }
+        }
+    }
+}
+
+impl DataIterator for TwoLevelIterator {
+    fn valid(&self) -> bool {
+        self.data_iter.valid()
+    }
+
+    fn seek_to_first(&mut self) {
+        self.index_iter.seek_to_first();
+        self.init_data_block();
+        if self.data_iter.iter().is_some() {
+            self.data_iter.seek_to_first();
+        }
+        self.skip_empty_data_blocks_forward();
+    }
+
+    fn seek_to_last(&mut self) {
+        self.index_iter.seek_to_last();
+        self.init_data_block();
+        if self.data_iter.iter().is_some() {
+            self.data_iter.seek_to_last();
+        }
+        self.skip_empty_data_blocks_backward();
+    }
+
+    fn seek(&mut self, target: &Slice) {
+        self.index_iter.seek(target);
+        self.init_data_block();
+        if self.data_iter.iter().is_some() {
+            self.data_iter.seek(target);
+        }
+        self.skip_empty_data_blocks_forward();
+    }
+
+    fn next(&mut self) {
+        assert!(self.valid());
+        self.data_iter.next();
+        self.skip_empty_data_blocks_forward();
+    }
+
+    fn pre(&mut self) {
+        assert!(self.valid());
+        self.data_iter.pre();
+        self.skip_empty_data_blocks_backward();
+    }
+
+    fn key(&self) -> UnsafeSlice {
+        assert!(self.valid());
+        self.data_iter.key()
+    }
+
+    fn value(&self) -> UnsafeSlice {
+        assert!(self.valid());
+        self.data_iter.value()
+    }
+
+    fn status(&self) -> Status {
+        // It'd be nice if status() returned a const Status& instead of a Status
+        let index_status = self.index_iter.status();
+        if !index_status.is_ok() {
+            return index_status;
+        }
+        if self.data_iter.iter().is_some() {
+            let data_status = self.data_iter.status();
+            if !data_status.is_ok() {
+                return data_status;
+            }
+        }
+        self.status.clone()
+    }
+}
@@ -1,8 +1,8 @@
 use std::cmp::Ordering;
 use crate::util::slice::Slice;
 
-/// 比较器
-pub trait Comparator {
+/// 比较器, 会在后台 compaction 线程中使用, 因此需要 Send + Sync
+pub trait Comparator: Send + Sync {
 
     ///  Slice 的大小比较, 按字典逐字节序进行比较
     ///
@@ -1,12 +1,11 @@
 use std::sync::Arc;
-use crate::util::slice::Slice;
 
 
 /// FilterPolicy 的 `Arc<Box<dyn FilterPolicy>>` 别名
 pub type FilterPolicyPtr = Arc<Box<dyn FilterPolicy>>;
 
 /// 用于key过滤，可以快速的排除不存在的key
-pub trait FilterPolicy {
+pub trait FilterPolicy: Send + Sync {
 
     ///
     /// filter的名字
@@ -17,37 +16,34 @@ pub trait FilterPolicy {
     ///
     fn name(&self) -> String;
 
-    fn create_filter(&self, keys: Vec<&Slice>) -> Slice;
-
     ///
-    /// 根据 key 列表创建一个BloomFilter
+    /// 根据 key 列表创建一个 filter, 并追加到 dst 的末尾, 不会修改 dst 中已有的数据
     ///
-    /// 有n个整数set，以及一个m位的bit数组，以及k个哈希函数。m[i]表示访问第i个bit位。
+    /// keys[0,n-1] contains a list of keys (potentially with duplicates)
+    /// that are ordered according to the user supplied comparator.
+    /// Append a filter that summarizes keys[0,n-1] to *dst.
     ///
     /// # Arguments
     ///
-    /// * `capacity`: key的个数
     /// * `keys`: key列表
+    /// * `dst`: filter 写入的位置
     ///
-    /// returns: bloom filter Slice
+    /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    ///     use level_db_rust::traits::filter_policy_trait::FilterPolicy;
     ///     use level_db_rust::util::filter_policy_bloom::BloomFilterPolicy;
-    ///     use level_db_rust::util::slice::Slice;
     ///
-    ///     let mut keys : Vec<&Slice>  = Vec::new();
-    ///     keys.push(&Slice::try_from(String::from("hello")).unwrap());
-    ///     keys.push(&Slice::try_from(String::from("world")).unwrap());
+    ///     let keys: Vec<&[u8]> = vec![b"hello", b"world"];
     ///
     ///     let policy = BloomFilterPolicy::new();
-    ///     let bloom_filter: Slice = policy.create_filter(keys);
+    ///     let mut dst = vec![];
+    ///     policy.create_filter(&keys, &mut dst);
+    ///     assert!(policy.key_may_match(b"hello", &dst));
     /// ```
-    fn create_filter_with_len(&self, capacity: usize, keys: Vec<&Slice>) -> Slice;
-
-    // fn create_filter_u8(&self, keys: Vec<u8>) -> Slice;
-    // fn create_filter_u8_with_len(&self, capacity: usize, keys: Vec<u8>) -> Slice;
+    fn create_filter(&self, keys: &[&[u8]], dst: &mut Vec<u8>);
 
     /// 判断一个 key 是否可能存在。
     ///
@@ -57,14 +53,34 @@ pub trait FilterPolicy {
     /// # Arguments
     ///
     /// * `key`:  判断的key 值
-    /// * `bloom_filter`:
+    /// * `filter`: create_filter 追加到 dst 中的 filter 内容
     ///
     /// returns: bool
     ///
     /// # Examples
     ///
     /// ```
+    ///     use level_db_rust::traits::filter_policy_trait::FilterPolicy;
+    ///     use level_db_rust::util::filter_policy_bloom::BloomFilterPolicy;
     ///
+    ///     let policy = BloomFilterPolicy::new();
+    ///     let mut dst = vec![];
+    ///     policy.create_filter(&[b"hello"], &mut dst);
+    ///     assert!(!policy.key_may_match(b"world", &dst));
     /// ```
-    fn key_may_match(&self, key: &Slice, bloom_filter: &Slice) -> bool;
-}
\ No newline at end of file
+    fn key_may_match(&self, key: &[u8], filter: &[u8]) -> bool;
+
+    /// 判断 key 的前缀是否可能存在, 只有生成 filter 时加入了前缀的 FilterPolicy (例如 PrefixFilterPolicy) 才能判断。
+    ///
+    /// 如果存在前缀相同的 key，一定返回 true。默认实现总是返回 true
+    ///
+    /// # Arguments
+    ///
+    /// * `key`:  提取前缀的 key, 例如 seek 的目标
+    /// * `filter`: create_filter 追加到 dst 中的 filter 内容
+    ///
+    /// returns: bool
+    fn prefix_may_match(&self, _key: &[u8], _filter: &[u8]) -> bool {
+        true
+    }
+}
@@ -0,0 +1,63 @@
+use std::fmt;
+use std::fmt::Arguments;
+use std::sync::Arc;
+
+/// Logger 的 `Arc<dyn Logger>` 别名
+pub type LoggerPtr = Arc<dyn Logger>;
+
+/// 运行日志的级别, 按严重程度递增排列
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum InfoLogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for InfoLogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InfoLogLevel::Debug => "DEBUG",
+            InfoLogLevel::Info => "INFO",
+            InfoLogLevel::Warn => "WARN",
+            InfoLogLevel::Error => "ERROR",
+        })
+    }
+}
+
+/// 运行日志, 记录数据库内部的进度及错误信息 (恢复、memtable 落盘、compaction、删除文件、写入停顿等)。
+///
+/// 默认写入数据库目录下的 LOG 文件, 也可以通过 Options::info_log 指定自定义的实现,
+/// 例如转发到 tracing。会被后台 compaction 线程调用, 因此需要 Send + Sync
+///
+/// An interface for writing log messages.
+pub trait Logger: Send + Sync {
+
+    /// 写入一条日志, 由实现决定是否丢弃低于某一级别的日志
+    ///
+    /// # Arguments
+    ///
+    /// * `level`: 日志级别
+    /// * `args`: 日志内容, 不包含换行符
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// struct TracingLogger;
+    ///
+    /// impl Logger for TracingLogger {
+    ///     fn log(&self, level: InfoLogLevel, args: Arguments) {
+    ///         match level {
+    ///             InfoLogLevel::Debug => tracing::debug!("{}", args),
+    ///             InfoLogLevel::Info => tracing::info!("{}", args),
+    ///             InfoLogLevel::Warn => tracing::warn!("{}", args),
+    ///             InfoLogLevel::Error => tracing::error!("{}", args),
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    fn log(&self, level: InfoLogLevel, args: Arguments);
+}
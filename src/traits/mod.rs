@@ -2,6 +2,9 @@
 pub mod iterator;
 pub mod comparator_trait;
 pub mod filter_policy_trait;
+pub mod slice_transform_trait;
+pub mod table_properties_collector_trait;
+pub mod logger_trait;
 
 
 pub use iterator::DataIterator;
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+/// SliceTransform 的 `Arc<Box<dyn SliceTransform>>` 别名
+pub type SliceTransformPtr = Arc<Box<dyn SliceTransform>>;
+
+/// 从 key 中提取前缀, 用于在 filter 中保存前缀以及按前缀迭代。
+///
+/// 前缀必须是 key 的前缀 (key 的开头部分), 因此按字节序排列时, 前缀相同的 key 是连续的。
+pub trait SliceTransform: Send + Sync {
+
+    ///
+    /// 前缀提取器的名字。提取的规则改变时, 名字也必须改变,
+    /// 否则使用旧规则生成的 filter 会被当作新规则的 filter 使用
+    ///
+    fn name(&self) -> String;
+
+    /// 提取 key 的前缀
+    ///
+    /// REQUIRES: in_domain(key) 为 true
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: 提取前缀的 key
+    ///
+    /// returns: &[u8]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///     use level_db_rust::traits::slice_transform_trait::SliceTransform;
+    ///     use level_db_rust::util::slice_transform::FixedPrefixTransform;
+    ///
+    ///     let transform = FixedPrefixTransform::new(3);
+    ///     assert_eq!(b"abc", transform.transform(b"abcdef"));
+    /// ```
+    fn transform<'a>(&self, key: &'a [u8]) -> &'a [u8];
+
+    /// 判断 key 是否可以提取前缀, 不能提取前缀的 key 不会加入 filter, 也不会按前缀迭代
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: 判断的 key
+    ///
+    /// returns: bool
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///     use level_db_rust::traits::slice_transform_trait::SliceTransform;
+    ///     use level_db_rust::util::slice_transform::FixedPrefixTransform;
+    ///
+    ///     let transform = FixedPrefixTransform::new(3);
+    ///     assert!(!transform.in_domain(b"ab"));
+    /// ```
+    fn in_domain(&self, key: &[u8]) -> bool;
+}
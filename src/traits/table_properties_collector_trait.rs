@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use crate::db::db_format::ValueType;
+
+/// 用户收集的属性, 属性名 -> 属性值
+pub type UserCollectedProperties = BTreeMap<String, Vec<u8>>;
+
+/// 创建 TablePropertiesCollector 的工厂, 每生成一个 table 调用一次
+pub type TablePropertiesCollectorFactory = Arc<dyn Fn() -> Box<dyn TablePropertiesCollector> + Send + Sync>;
+
+/// 在生成 table 时收集自定义的属性, 结果与内置的属性一起保存在 table 的 properties block 中,
+/// 可以通过 Table::properties 读取。
+///
+/// 属性名不能以 "leveldb." 开头, 这些名字保留给内置的属性
+pub trait TablePropertiesCollector {
+
+    ///
+    /// 收集器的名字, 用于调试
+    ///
+    fn name(&self) -> String;
+
+    /// 每写入一条 entry 调用一次, entry 按 key 的顺序写入
+    ///
+    /// # Arguments
+    ///
+    /// * `user_key`: user key, table 中的 key 不是 internal key 时为 table 中的 key
+    /// * `value`: value
+    /// * `value_type`: entry 的类型, table 中的 key 不是 internal key 时为 KTypeValue
+    /// * `sequence`: entry 的 sequence, table 中的 key 不是 internal key 时为 0
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// collector.add(b"key", b"value", ValueType::KTypeValue, 100);
+    /// ```
+    fn add(&mut self, user_key: &[u8], value: &[u8], value_type: ValueType, sequence: u64);
+
+    /// table 生成结束时调用, 将收集的属性加入 properties
+    ///
+    /// # Arguments
+    ///
+    /// * `properties`: 收集的属性
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut properties = UserCollectedProperties::new();
+    /// collector.finish(&mut properties);
+    /// ```
+    fn finish(&mut self, properties: &mut UserCollectedProperties);
+}
@@ -4,7 +4,7 @@ use std::marker::PhantomData;
 use std::ops::Deref;
 use std::ptr::NonNull;
 use std::sync::{Arc, RwLock};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 use custom_proc_macro::{arr, non_null_new_uncheck};
 
@@ -422,8 +422,8 @@ impl<T> LRUCache<T> {
         // 使用量加上写入的value的长度或者数据大小
         self.usage += charge;
 
-        // 使用量已经达到容量, 那么删除最少使用的
-        if self.usage >= self.capacity {
+        // 使用量超过容量, 那么删除最少使用的
+        if self.usage > self.capacity {
             if let Some(tail) = self.tail_of_lru {
                 let tail_ref = unsafe { tail.as_ref() };
                 // 先删除lru链表尾
@@ -600,6 +600,8 @@ pub struct ShardLRUCache<T> {
     default_length: usize,
     // 当前所有shard中lru cache的最大容量, 超过这个容量将会淘汰数据
     capacity: usize,
+    // new_id 最近一次分配的 id
+    last_id: AtomicU64,
 }
 
 #[inline]
@@ -631,6 +633,7 @@ impl<T> ShardLRUCache<T> {
             shard: arr!([cache_element_default; 32]),
             default_length: DEFAULT_SHARD_LENGTH,
             capacity: DEFAULT_CACHE_CAPACITY,
+            last_id: AtomicU64::new(0),
         }
     }
 
@@ -663,6 +666,7 @@ impl<T> ShardLRUCache<T> {
             shard: arr!([cache_element; 32]),
             default_length,
             capacity,
+            last_id: AtomicU64::new(0),
         }
     }
 
@@ -702,6 +706,7 @@ impl<T> ShardLRUCache<T> {
             shard: arr!([cache_element; 32]),
             default_length: default_length_per_shard,
             capacity,
+            last_id: AtomicU64::new(0),
         })
     }
 
@@ -761,11 +766,11 @@ impl<T> ShardLRUCache<T> {
     /// use level_db_rust::util::cache::ShardLRUCache;
     /// use level_db_rust::util::slice::Slice;
     ///
-    /// let mut cache = ShardLRUCache::new_with_capacity(40_0000, 1000);
+    /// let cache = ShardLRUCache::new_with_capacity(40_0000, 1000);
     /// let key: Slice = "key".into();
     /// cache.erase(&key)?;
     /// ```
-    pub fn erase(&mut self, key: &Slice) -> Result<()> {
+    pub fn erase(&self, key: &Slice) -> Result<()> {
         let hash = hash_slice(key);
         // 删除缓存
         self.shard[shard(hash)].write()?.erase(key, hash)?;
@@ -790,6 +795,25 @@ impl<T> ShardLRUCache<T> {
         Ok(())
     }
 
+    /// 分配一个新的数字 id, 共享同一个 cache 的多个使用者可以用它作为 key 的前缀来划分 key 空间
+    ///
+    /// Return a new numeric id.  May be used by multiple clients who are
+    /// sharing the same cache to partition the key space.  Typically the
+    /// client will allocate a new id at startup and prepend the id to
+    /// its cache keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::cache::ShardLRUCache;
+    ///
+    /// let cache: ShardLRUCache<u32> = ShardLRUCache::new_with_capacity(40_0000, 1000);
+    /// assert_ne!(cache.new_id(), cache.new_id());
+    /// ```
+    pub fn new_id(&self) -> u64 {
+        self.last_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
     /// 获取当前缓存的总数据量
     pub fn total_charge(&self) -> Result<usize> {
         let mut total_charge = 0;
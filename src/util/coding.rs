@@ -429,12 +429,8 @@ unsafe fn uncheck_write_buf(data: &mut MutEncodeData, offset: usize, buf: &[u8])
 /// ```
 unsafe fn uncheck_read_buf(data: &EncodeData, offset: usize, len: usize) -> Slice {
     let ptr: *const u8 = get_ptr!(data).add(offset);
-    // 分配一块内存长度为buf的长度
-    let dst: *mut u8 = alloc(Layout::from_size_align_unchecked(len, 4));
-    // 将数据拷贝到这块内存上
-    intrinsics::copy_nonoverlapping(ptr, dst, len);
-    // 使用slice包装内存
-    Slice::from_raw_parts(dst, len)
+    // 将数据拷贝到新的 slice 中, 由 Vec 负责分配内存, 保证释放时的内存布局一致
+    Slice::from_buf(std::slice::from_raw_parts(ptr, len))
 }
 
 /// 编码的数据 只读的
@@ -622,6 +618,30 @@ impl<'a> Encoder<'a> {
             data: MutVector(vec),
         }
     }
+
+    /// 以vec做为容器生成encoder, 从vec的末尾开始追加编码, 不会覆盖vec中已有的数据
+    ///
+    /// # Arguments
+    ///
+    /// * `vec`: vec
+    ///
+    /// returns: Encoder
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///  use level_db_rust::util::coding::Encoder;
+    ///  let mut vec = vec![1, 2];
+    ///  let mut encoder = Encoder::with_vec_append(&mut vec);
+    ///  encoder.put_fixed32(3).unwrap();
+    ///  // [1, 2, 3, 0, 0, 0]
+    /// ```
+    pub fn with_vec_append(vec: &'a mut Vec<u8>) -> Self {
+        Self {
+            offset: vec.len(),
+            data: MutVector(vec),
+        }
+    }
     /// 以切片做为容器生成encoder
     /// 编码时当容量不足时可能会造成内存溢出
     /// 需要提前规划好需要使用的容量, 并保证调用编码方式时不会溢出
@@ -1925,4 +1945,14 @@ fn test_swap_bytes() {
     // 小端存储的0x01,0x02,0x03,0x04解出来的数据要等于0x04030201_u32
     println!("value: {:?}, decode: {:?}", value, decode);
     assert_eq!(value, decode);
-}
\ No newline at end of file
+}
+#[test]
+fn test_encoder_with_vec_append() -> Result<()> {
+    let mut vec = vec![1, 2];
+    let mut encoder = Encoder::with_vec_append(&mut vec);
+    encoder.put_fixed32(3)?;
+    encoder.put_varint32(300)?;
+    assert_eq!(8, encoder.offset());
+    assert_eq!(vec![1, 2, 3, 0, 0, 0, 172, 2], vec);
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::io::Read;
+use crate::util::coding::{Decoder, Encoder};
+use crate::util::Result;
+use crate::util::status::{LevelError, Status};
+
+/// Zstd 未指定压缩级别时使用的级别, 与 zstd 命令行的默认值相同
+pub const DEFAULT_ZSTD_LEVEL: i32 = zstd::DEFAULT_COMPRESSION_LEVEL;
+
+/// LZ4 的压缩率不会超过 255, 记录的原始长度超过压缩数据长度的 255 倍时说明数据已损坏
+const LZ4_MAX_COMPRESSION_RATIO: usize = 255;
+
+/// LZ4 block 压缩
+///
+/// LZ4 压缩后的数据以 varint32 编码的原始长度开头, 之后是压缩数据,
+/// 解压时可以直接分配所需的空间 (与 RocksDB format_version 2 的格式相同)
+pub struct LZ4 {}
+
+/// Zstd block 压缩, 支持指定压缩级别及字典
+///
+/// 压缩后的数据是完整的 zstd frame, 没有额外的长度前缀, 与 LevelDB 的 kZstdCompression 格式相同
+pub struct Zstd {}
+
+impl LZ4 {
+    /// 压缩数据
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::compression::LZ4;
+    /// let compressed = LZ4::compress(b"hello hello hello hello");
+    /// assert_eq!(b"hello hello hello hello".to_vec(), LZ4::uncompress(&compressed).unwrap());
+    /// ```
+    pub fn compress(raw: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(5 + lz4_flex::block::get_maximum_output_size(raw.len()));
+        put_raw_length(&mut out, raw.len());
+        out.extend_from_slice(&lz4_flex::block::compress(raw));
+        out
+    }
+
+    /// 解压数据, 数据损坏时返回 Corruption
+    pub fn uncompress(data: &[u8]) -> Result<Vec<u8>> {
+        let (raw_len, offset) = get_raw_length(data)?;
+        // 原始长度来自文件, 分配空间之前先检查是否可信
+        let compressed_len = data.len() - offset;
+        if raw_len > compressed_len.saturating_mul(LZ4_MAX_COMPRESSION_RATIO) {
+            return Err(corruption("lz4", format!("raw length {} too large for {} compressed bytes", raw_len, compressed_len)));
+        }
+        let raw = lz4_flex::block::decompress(&data[offset..], raw_len)
+            .map_err(|e| corruption("lz4", e.to_string()))?;
+        check_raw_length(raw, raw_len, "lz4")
+    }
+}
+
+impl Zstd {
+    /// 压缩数据
+    ///
+    /// # Arguments
+    ///
+    /// * `raw`: 原始数据
+    /// * `level`: 压缩级别, 为 None 时使用 DEFAULT_ZSTD_LEVEL
+    /// * `dictionary`: 字典, 解压时需要使用相同的字典
+    ///
+    /// returns: Result<Vec<u8>, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::compression::Zstd;
+    /// let compressed = Zstd::compress(b"hello hello hello hello", Some(19), None).unwrap();
+    /// assert_eq!(b"hello hello hello hello".to_vec(), Zstd::uncompress(&compressed, None).unwrap());
+    /// ```
+    pub fn compress(raw: &[u8], level: Option<i32>, dictionary: Option<&[u8]>) -> Result<Vec<u8>> {
+        let level = level.unwrap_or(DEFAULT_ZSTD_LEVEL);
+        let mut compressor = match dictionary {
+            Some(dictionary) => zstd::bulk::Compressor::with_dictionary(level, dictionary)?,
+            None => zstd::bulk::Compressor::new(level)?,
+        };
+        Ok(compressor.compress(raw)?)
+    }
+
+    /// 解压数据, 数据损坏或者字典与压缩时不同时返回 Corruption
+    ///
+    /// 与 LevelDB 相同, 要求 frame 头中记录了原始长度, 解压得到的数据不会超过该长度
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: 压缩数据
+    /// * `dictionary`: 压缩时使用的字典
+    ///
+    /// returns: Result<Vec<u8>, Status>
+    pub fn uncompress(data: &[u8], dictionary: Option<&[u8]>) -> Result<Vec<u8>> {
+        let raw_len = match zstd::zstd_safe::get_frame_content_size(data) {
+            Ok(Some(raw_len)) if raw_len <= u32::MAX as u64 => raw_len as usize,
+            _ => return Err(corruption("zstd", "unknown frame content size".to_string())),
+        };
+        // 记录的原始长度不可信, 不预先分配空间, 按流的方式解压并限制读取的长度
+        let decoder = zstd::stream::read::Decoder::with_dictionary(data, dictionary.unwrap_or_default())?;
+        let mut raw = vec![];
+        // 多读一个字节, 用于发现超过原始长度的数据
+        decoder.take(raw_len as u64 + 1).read_to_end(&mut raw).map_err(|e| corruption("zstd", e.to_string()))?;
+        check_raw_length(raw, raw_len, "zstd")
+    }
+}
+
+fn corruption(codec: &str, msg: String) -> Status {
+    LevelError::corruption_string(&format!("corrupted {} data", codec), &msg)
+}
+
+fn put_raw_length(out: &mut Vec<u8>, raw_len: usize) {
+    Encoder::with_vec_append(out).put_varint32(raw_len as u32).expect("put varint32");
+}
+
+/// 读取开头的原始长度, 返回原始长度及压缩数据的起始位置
+fn get_raw_length(data: &[u8]) -> Result<(usize, usize)> {
+    let mut decoder = Decoder::with_buf(data);
+    let raw_len = decoder.get_varint32()?;
+    Ok((raw_len as usize, decoder.offset()))
+}
+
+fn check_raw_length(raw: Vec<u8>, raw_len: usize, codec: &str) -> Result<Vec<u8>> {
+    if raw.len() != raw_len {
+        return Err(corruption(codec, format!("expect {} bytes, got {}", raw_len, raw.len())));
+    }
+    Ok(raw)
+}
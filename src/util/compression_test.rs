@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+    use crate::util::compression::{LZ4, Zstd};
+    use crate::util::options::{CompressionType, Options};
+
+    fn inputs() -> Vec<Vec<u8>> {
+        let mut rnd = StdRng::seed_from_u64(301);
+        let mut random = vec![0_u8; 10000];
+        rnd.fill_bytes(&mut random);
+        vec![
+            vec![],
+            b"a".to_vec(),
+            "leveldb compression ".repeat(500).into_bytes(),
+            random,
+        ]
+    }
+
+    #[test]
+    fn test_lz4() {
+        for input in inputs() {
+            let compressed = LZ4::compress(&input);
+            assert_eq!(input, LZ4::uncompress(&compressed).unwrap());
+        }
+        let data = "leveldb compression ".repeat(500).into_bytes();
+        let compressed = LZ4::compress(&data);
+        assert!(compressed.len() < data.len() / 10, "{}", compressed.len());
+
+        // 数据损坏
+        assert!(LZ4::uncompress(&compressed[..compressed.len() / 2]).err().unwrap().is_corruption());
+        let mut bad = compressed.clone();
+        // 原始长度不一致
+        bad[0] ^= 1;
+        assert!(LZ4::uncompress(&bad).err().unwrap().is_corruption());
+
+        // 原始长度超过压缩率的上限, 不会按该长度分配空间
+        let mut bad = vec![0xff, 0xff, 0xff, 0xff, 0x0f];
+        bad.extend_from_slice(&compressed[2..]);
+        let status = LZ4::uncompress(&bad).err().unwrap();
+        assert!(status.is_corruption());
+        assert!(status.get_msg().contains("too large"), "{}", status.get_msg());
+    }
+
+    #[test]
+    fn test_zstd() {
+        for input in inputs() {
+            for level in [None, Some(1), Some(19)] {
+                let compressed = Zstd::compress(&input, level, None).unwrap();
+                assert_eq!(input, Zstd::uncompress(&compressed, None).unwrap());
+            }
+        }
+        let data = "leveldb compression ".repeat(500).into_bytes();
+        let compressed = Zstd::compress(&data, None, None).unwrap();
+        assert!(compressed.len() < data.len() / 10, "{}", compressed.len());
+
+        // 没有长度前缀, 以 zstd frame 的 magic number 开头
+        assert_eq!([0x28, 0xb5, 0x2f, 0xfd], compressed[..4]);
+        assert!(Zstd::uncompress(&compressed[..compressed.len() / 2], None).err().unwrap().is_corruption());
+        let mut bad = compressed.clone();
+        bad[0] ^= 1;
+        assert!(Zstd::uncompress(&bad, None).err().unwrap().is_corruption());
+
+        // frame 头中没有记录原始长度
+        let mut encoder = zstd::stream::write::Encoder::new(vec![], 1).unwrap();
+        encoder.include_contentsize(false).unwrap();
+        std::io::Write::write_all(&mut encoder, &data).unwrap();
+        let without_size = encoder.finish().unwrap();
+        assert!(Zstd::uncompress(&without_size, None).err().unwrap().is_corruption());
+    }
+
+    #[test]
+    fn test_zstd_dictionary() {
+        let dictionary = "user_id=00000000 name=leveldb city=hangzhou ".repeat(20).into_bytes();
+        let data = b"user_id=00012345 name=rust city=hangzhou".to_vec();
+
+        let with_dictionary = Zstd::compress(&data, None, Some(&dictionary)).unwrap();
+        let without_dictionary = Zstd::compress(&data, None, None).unwrap();
+        assert!(with_dictionary.len() < without_dictionary.len(),
+                "{} {}", with_dictionary.len(), without_dictionary.len());
+        assert_eq!(data, Zstd::uncompress(&with_dictionary, Some(&dictionary)).unwrap());
+
+        // 解压时必须使用相同的字典, 未经训练的字典没有 dictID, 使用错误的字典时可能得到错误的数据
+        let other = "something completely different ".repeat(20).into_bytes();
+        assert!(Zstd::uncompress(&with_dictionary, Some(&other)).map_or(true, |raw| raw != data));
+        assert!(Zstd::uncompress(&with_dictionary, None).map_or(true, |raw| raw != data));
+    }
+
+    #[test]
+    fn test_compression_type() {
+        for compression in [CompressionType::NoCompression, CompressionType::SnappyCompression,
+            CompressionType::LZ4Compression, CompressionType::ZstdCompression] {
+            assert_eq!(compression, CompressionType::try_from(compression as u8).unwrap());
+        }
+        for value in [3_u8, 5, 6, 7, 8, 0xff] {
+            let status = CompressionType::try_from(value).err().unwrap();
+            assert!(status.is_not_supported_error(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_compression_for_level() {
+        let mut options = Options::default();
+        options.compression = CompressionType::LZ4Compression;
+        assert_eq!(CompressionType::LZ4Compression, options.compression_for_level(0));
+        assert_eq!(CompressionType::LZ4Compression, options.compression_for_level(6));
+
+        options.compression_per_level = vec![
+            CompressionType::NoCompression,
+            CompressionType::NoCompression,
+            CompressionType::LZ4Compression,
+            CompressionType::LZ4Compression,
+            CompressionType::LZ4Compression,
+            CompressionType::ZstdCompression,
+        ];
+        assert_eq!(CompressionType::NoCompression, options.compression_for_level(0));
+        assert_eq!(CompressionType::NoCompression, options.compression_for_level(1));
+        assert_eq!(CompressionType::LZ4Compression, options.compression_for_level(2));
+        assert_eq!(CompressionType::LZ4Compression, options.compression_for_level(4));
+        // 超出长度的层使用最后一个
+        assert_eq!(CompressionType::ZstdCompression, options.compression_for_level(5));
+        assert_eq!(CompressionType::ZstdCompression, options.compression_for_level(6));
+    }
+}
@@ -370,6 +370,35 @@ impl CRC {
     ///let crc = CRC::extend(init_crc, &[1_u8,2,3,4,5]);
     /// ```
     pub fn extend(init_crc: u32, data: &[u8]) -> u32 {
+        // 运行时检测 CPU 是否支持 crc32c 指令, 检测结果由标准库缓存
+        #[cfg(target_arch = "x86_64")]
+        if std::arch::is_x86_feature_detected!("sse4.2") {
+            return unsafe { extend_sse42(init_crc, data) };
+        }
+        #[cfg(target_arch = "aarch64")]
+        if std::arch::is_aarch64_feature_detected!("crc") {
+            return unsafe { extend_arm_crc(init_crc, data) };
+        }
+        CRC::extend_portable(init_crc, data)
+    }
+
+    /// 查表实现的 CRC, 不支持 crc32c 指令时由 extend 使用, 结果与 extend 相同
+    ///
+    /// # Arguments
+    ///
+    /// * `init_crc`: 初始的CRC码
+    /// * `data`: 数据
+    ///
+    /// returns: u32 新的CRC码
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///use level_db_rust::util::crc::CRC;
+    ///let crc = CRC::extend_portable(0, &[1_u8,2,3,4,5]);
+    ///assert_eq!(CRC::value(&[1_u8,2,3,4,5]), crc);
+    /// ```
+    pub fn extend_portable(init_crc: u32, data: &[u8]) -> u32 {
         // s: start_offset, n: data length, l: result crc
         let mut s = 0_usize;
         let n = data.len();
@@ -384,7 +413,7 @@ impl CRC {
         }
 
         if n - s >= 16 {
-            let mut decoder = Decoder::with_buf(data);
+            let mut decoder = Decoder::with_buf(&data[s..]);
             let mut crc0 = unsafe { decoder.uncheck_get_fixed32() } ^ l;
             let mut crc1 = unsafe { decoder.uncheck_get_fixed32() };
             let mut crc2 = unsafe { decoder.uncheck_get_fixed32() };
@@ -397,8 +426,8 @@ impl CRC {
             }
             while (n - s) >= 4 {
                 step4!(crc0, data, decoder, s, 0);
-                // swap variables
-                (crc1, crc2, crc3) = (crc0, crc1, crc2);
+                // rotate variables
+                (crc0, crc1, crc2, crc3) = (crc1, crc2, crc3, crc0);
                 s += 4;
                 // println!("step4, c0: {:x}, c1: {:x}, c2: {:x}, c3: {:x}, s: {}", crc0, crc1, crc2, crc3, s);
             }
@@ -471,15 +500,57 @@ impl CRC {
     /// ```
     #[inline]
     pub fn unmask(masked_crc: u32) -> u32 {
-        let rot = masked_crc - K_MASK_DELTA;
+        let rot = masked_crc.wrapping_sub(K_MASK_DELTA);
         (rot >> 17) | (rot << 15)
     }
 }
 
+/// 使用 SSE4.2 的 crc32 指令计算, 每次处理 8 个字节
+///
+/// # Safety
+///
+/// 调用前需要确认 CPU 支持 SSE4.2
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2")]
+unsafe fn extend_sse42(init_crc: u32, data: &[u8]) -> u32 {
+    use std::arch::x86_64::{_mm_crc32_u64, _mm_crc32_u8};
+
+    let mut l = (init_crc ^ K_CRC32_XOR) as u64;
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        l = _mm_crc32_u64(l, u64::from_le_bytes(chunk.try_into().unwrap()));
+    }
+    let mut l = l as u32;
+    for b in chunks.remainder() {
+        l = _mm_crc32_u8(l, *b);
+    }
+    l ^ K_CRC32_XOR
+}
+
+/// 使用 ARMv8 的 crc32c 指令计算, 每次处理 8 个字节
+///
+/// # Safety
+///
+/// 调用前需要确认 CPU 支持 crc 扩展
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "crc")]
+unsafe fn extend_arm_crc(init_crc: u32, data: &[u8]) -> u32 {
+    use std::arch::aarch64::{__crc32cb, __crc32cd};
+
+    let mut l = init_crc ^ K_CRC32_XOR;
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        l = __crc32cd(l, u64::from_le_bytes(chunk.try_into().unwrap()));
+    }
+    for b in chunks.remainder() {
+        l = __crc32cb(l, *b);
+    }
+    l ^ K_CRC32_XOR
+}
+
 /// 指针对齐到 4byte 需要的偏移量
 fn ptr_align_by4_offset(ptr: *const u8) -> usize {
     let addr = ptr as usize;
     // eg: addr = 10, output = 2
-    // (addr+3) & !(addr-3)
-    addr % 4
+    (4 - addr % 4) % 4
 }
\ No newline at end of file
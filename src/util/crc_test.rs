@@ -66,4 +66,22 @@ fn test_mask2() {
     let crc1 = a2.as_crc().to_mask();
     println!("crc0: {}, crc1: {}", crc0, crc1);
     assert_eq!(crc0, crc1);
-}
\ No newline at end of file
+}
+
+/// extend 在支持 crc32c 指令时使用硬件实现, 结果必须与查表实现一致
+#[test]
+fn test_extend_matches_portable() {
+    let data: Vec<u8> = (0..1024_u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+    for len in [0, 1, 3, 7, 8, 9, 15, 16, 17, 31, 64, 100, 1000] {
+        // 不同的起始位置, 覆盖未对齐的数据
+        for start in 0..8 {
+            let buf = &data[start..start + len];
+            assert_eq!(CRC::extend_portable(0, buf), CRC::value(buf), "len {} start {}", len, start);
+            assert_eq!(CRC::extend_portable(0x12345678, buf), CRC::extend(0x12345678, buf), "len {} start {}", len, start);
+        }
+    }
+
+    // mask/unmask 与实现无关
+    let crc = CRC::value(&data);
+    assert_eq!(CRC::mask(CRC::extend_portable(0, &data)), CRC::mask(crc));
+}
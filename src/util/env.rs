@@ -1,11 +1,232 @@
-use std::fs::File;
+use std::fs;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::traits::logger_trait::{InfoLogLevel, LoggerPtr};
+use crate::util::logger::FileLogger;
 use crate::util::Result;
 use crate::util::slice::Slice;
+use crate::util::status::LevelError;
 
+/// 支持随机读取的文件, 用于读取 SSTable。会被多个线程同时读取, 因此需要 Send + Sync
+pub trait RandomAccessFile: Send + Sync {
+    /// 从 offset 处读取最多 n 个字节, 读到文件末尾时返回的数据会少于 n 个字节
+    ///
+    /// # Arguments
+    ///
+    /// * `offset`: 读取的起始位置
+    /// * `n`: 读取的字节数
+    ///
+    /// returns: Result<Slice, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let data = file.read(0, 1024)?;
+    /// ```
+    fn read(&self, offset: u64, n: usize) -> Result<Slice>;
+}
+
+impl RandomAccessFile for File {
+    fn read(&self, offset: u64, n: usize) -> Result<Slice> {
+        let mut buf = vec![0_u8; n];
+        let mut read = 0;
+        while read < n {
+            let len = self.read_at(&mut buf[read..], offset + read as u64)?;
+            if len == 0 {
+                break;
+            }
+            read += len;
+        }
+        buf.truncate(read);
+        Ok(Slice::from_vec(buf))
+    }
+}
+
+/// 内存中的文件, 用于测试
+impl RandomAccessFile for Vec<u8> {
+    fn read(&self, offset: u64, n: usize) -> Result<Slice> {
+        let start = (offset as usize).min(self.len());
+        let end = (start + n).min(self.len());
+        Ok(Slice::from_buf(&self[start..end]))
+    }
+}
+
+/// 文件系统等运行环境的抽象, 目前直接基于 std::fs 实现
 pub struct  Env {}
 
+/// Env::lock_file 获得的文件锁, 通过 Env::unlock_file 释放, drop 时也会随文件关闭自动释放
+#[derive(Debug)]
+pub struct FileLock {
+    file: File,
+    fname: PathBuf,
+}
+
+impl FileLock {
+    /// 被锁定的文件
+    pub fn file_name(&self) -> &Path {
+        &self.fname
+    }
+}
+
 impl Env {
-    pub fn new_writable_file(&self, fname: &Slice) -> Result<File> {
-        todo!()
+    /// 创建一个新的可写文件, 如果文件已存在则清空原有内容
+    ///
+    /// # Arguments
+    ///
+    /// * `fname`: 文件路径
+    ///
+    /// returns: Result<File, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let file = env.new_writable_file(Path::new("/tmp/db/000001.log"))?;
+    /// ```
+    pub fn new_writable_file(&self, fname: &Path) -> Result<File> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(fname)?;
+        Ok(file)
+    }
+
+    /// 创建写入文件 fname 的 Logger, 如果文件已存在则清空原有内容, 低于 level 的日志会被丢弃
+    ///
+    /// # Arguments
+    ///
+    /// * `fname`: 日志文件路径
+    /// * `level`: 写入的最低日志级别
+    ///
+    /// returns: Result<LoggerPtr, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let logger = env.new_logger(&FileName::info_log_file_name(dbname), InfoLogLevel::Info)?;
+    /// ```
+    pub fn new_logger(&self, fname: &Path, level: InfoLogLevel) -> Result<LoggerPtr> {
+        let file = self.new_writable_file(fname)?;
+        Ok(Arc::new(FileLogger::new(file, level)))
+    }
+
+    /// 打开一个只读的文件用于顺序读取
+    pub fn new_sequential_file(&self, fname: &Path) -> Result<File> {
+        let file = File::open(fname)?;
+        Ok(file)
+    }
+
+    /// 打开一个只读的文件用于随机读取
+    pub fn new_random_access_file(&self, fname: &Path) -> Result<File> {
+        let file = File::open(fname)?;
+        Ok(file)
+    }
+
+    /// 获取文件大小
+    pub fn get_file_size(&self, fname: &Path) -> Result<u64> {
+        Ok(fs::metadata(fname)?.len())
+    }
+
+    /// 读取文件的全部内容
+    pub fn read_file_to_string(&self, fname: &Path) -> Result<String> {
+        let contents = fs::read_to_string(fname)?;
+        Ok(contents)
+    }
+
+    /// 判断文件是否存在
+    pub fn file_exists(&self, fname: &Path) -> bool {
+        fname.exists()
+    }
+
+    /// 删除文件
+    pub fn remove_file(&self, fname: &Path) -> Result<()> {
+        fs::remove_file(fname)?;
+        Ok(())
+    }
+
+    /// 获取目录下所有文件的文件名(不含目录部分)
+    ///
+    /// # Arguments
+    ///
+    /// * `dir`: 目录
+    ///
+    /// returns: Result<Vec<String>, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let children = env.get_children(Path::new("/tmp/db"))?;
+    /// ```
+    pub fn get_children(&self, dir: &Path) -> Result<Vec<String>> {
+        let mut result = vec![];
+        for entry in fs::read_dir(dir)? {
+            result.push(entry?.file_name().to_string_lossy().into_owned());
+        }
+        Ok(result)
+    }
+
+    /// 创建目录, 目录已存在时返回错误
+    pub fn create_dir(&self, dir: &Path) -> Result<()> {
+        fs::create_dir(dir)?;
+        Ok(())
+    }
+
+    /// 删除空目录, 目录中还有文件时返回错误
+    pub fn remove_dir(&self, dir: &Path) -> Result<()> {
+        fs::remove_dir(dir)?;
+        Ok(())
+    }
+
+    /// 锁定文件, 用于防止多个进程同时打开同一个数据库。文件不存在时会被创建
+    ///
+    /// Lock the specified file.  Used to prevent concurrent access to
+    /// the same db by multiple processes.  On failure, returns an error.
+    ///
+    /// If somebody else already holds the lock, finishes immediately
+    /// with a failure.  I.e., this call does not wait for existing locks
+    /// to go away.
+    ///
+    /// # Arguments
+    ///
+    /// * `fname`: 锁文件路径
+    ///
+    /// returns: Result<FileLock, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let lock = env.lock_file(&FileName::lock_file_name(dbname))?;
+    /// // ...
+    /// env.unlock_file(lock)?;
+    /// ```
+    pub fn lock_file(&self, fname: &Path) -> Result<FileLock> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(fname)?;
+        match file.try_lock() {
+            Ok(_) => Ok(FileLock { file, fname: fname.to_path_buf() }),
+            Err(TryLockError::WouldBlock) => {
+                Err(LevelError::io_error(Slice::from(format!("lock {}", fname.display())),
+                                         Slice::from("already held by process")))
+            }
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+
+    /// 释放 lock_file 获得的锁
+    pub fn unlock_file(&self, lock: FileLock) -> Result<()> {
+        lock.file.unlock()?;
+        Ok(())
+    }
+
+    /// 将文件 src 重命名为 target, 如果 target 已存在则原子地替换
+    pub fn rename_file(&self, src: &Path, target: &Path) -> Result<()> {
+        fs::rename(src, target)?;
+        Ok(())
     }
-}
\ No newline at end of file
+}
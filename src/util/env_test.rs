@@ -0,0 +1,30 @@
+mod test {
+    use std::fs;
+    use crate::util::env::Env;
+    use crate::util::Result;
+
+    #[test]
+    fn test_lock_file() -> Result<()> {
+        let dir = std::env::temp_dir().join("level_db_rust_env_lock_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        let env = Env {};
+        let fname = dir.join("LOCK");
+
+        let lock = env.lock_file(&fname)?;
+        assert_eq!(fname.as_path(), lock.file_name());
+        // 锁被持有时立即返回错误
+        let status = env.lock_file(&fname).err().unwrap();
+        assert!(status.is_io_error());
+        assert!(status.get_msg().contains("already held by process"), "{}", status.get_msg());
+
+        env.unlock_file(lock)?;
+        let lock = env.lock_file(&fname)?;
+        // drop 时随文件关闭释放锁
+        drop(lock);
+        env.unlock_file(env.lock_file(&fname)?)?;
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+use crate::traits::filter_policy_trait::FilterPolicy;
+use crate::util::coding::{Decoder, Encoder};
+use crate::util::hash::Hash;
+use crate::util::r#const::HASH_DEFAULT_SEED;
+
+/// 每个 key 映射到 4 个位置
+const ARITY: usize = 4;
+
+/// segment 的最大长度, 位置的计算中每个 segment 内的偏移量使用 hash 值的 18 bit
+const MAX_SEGMENT_LENGTH_LOG2: u8 = 18;
+
+/// seed(4) + segment_count(4) + segment_length_log2(1) + fingerprint_bits(1)
+const TRAILER_SIZE: usize = 10;
+
+/// 构造失败时更换 seed 重试的次数, 每失败 8 次将数组扩大 10%
+const MAX_ATTEMPTS: u32 = 64;
+
+// #########################  BinaryFuseFilterPolicy
+/// 4-wise binary fuse filter (Graf & Lemire, "Binary Fuse Filters: Fast and Smaller Than Xor Filters")。
+///
+/// 每个 key 映射到相邻 4 个 segment 中的各一个位置, 构造时求解使得这 4 个位置上的 fingerprint 异或等于
+/// key 的 fingerprint, 查询时只需要读取 4 个 fingerprint。fingerprint 为 r bit 时误判率约为 2^-r,
+/// 每个 key 占用约 1.075 * r bit (key 较少时比例更高, 20 万个 key 时约为 1.1 * r bit)。
+/// 默认的 7 bit fingerprint 误判率低于 BloomFilterPolicy::new(), 空间节省 22% 以上。
+///
+/// 数组的额外开销随 key 的数量减少而增大, key 只有几十个时 filter 比 BloomFilterPolicy 更大,
+/// 因此适合 key 较多的 filter, 例如覆盖整个 table 的 filter。
+///
+/// Filter 的格式:
+///
+/// ```text
+///     [fingerprints: array_length * r bit, 按字节对齐]
+///     [seed: fixed32] [segment_count: fixed32] [segment_length_log2: u8] [r: u8]
+/// ```
+///
+/// 名字与 BloomFilterPolicy 不同, 使用 BloomFilterPolicy 生成的旧 table 仍然需要 BloomFilterPolicy 来读取 filter。
+pub struct BinaryFuseFilterPolicy {
+    // 每个 fingerprint 的 bit 数, 1 ~ 16
+    fingerprint_bits: usize,
+}
+
+/// filter 中数组的布局
+struct Layout {
+    segment_length_log2: u8,
+    segment_count: u32,
+}
+
+impl BinaryFuseFilterPolicy {
+    ///
+    /// 7 bit 的 fingerprint, 误判率约为 0.8%, 与 BloomFilterPolicy::new() 相当
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::filter_policy_binary_fuse::BinaryFuseFilterPolicy;
+    ///
+    /// let policy = BinaryFuseFilterPolicy::new();
+    /// ```
+    pub fn new() -> Self {
+        BinaryFuseFilterPolicy::new_with_fingerprint_bits(7)
+    }
+
+    ///
+    /// 指定 fingerprint 的 bit 数, 误判率约为 2^-fingerprint_bits
+    ///
+    /// # Arguments
+    ///
+    /// * `fingerprint_bits`: fingerprint 的 bit 数, 超出 1 ~ 16 时取最接近的值
+    ///
+    /// returns: BinaryFuseFilterPolicy
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::filter_policy_binary_fuse::BinaryFuseFilterPolicy;
+    ///
+    /// // 误判率约为 0.4%
+    /// let policy = BinaryFuseFilterPolicy::new_with_fingerprint_bits(8);
+    /// ```
+    pub fn new_with_fingerprint_bits(fingerprint_bits: usize) -> Self {
+        Self {
+            fingerprint_bits: fingerprint_bits.clamp(1, 16)
+        }
+    }
+
+    pub fn fingerprint_bits(&self) -> usize {
+        self.fingerprint_bits
+    }
+
+    /// 为 hash 值 (已去重) 构造 fingerprint 数组, 失败时返回 None
+    fn build(&self, hashes: &[u32], seed: u32, layout: &Layout) -> Option<Vec<u16>> {
+        let array_length = layout.array_length();
+        // 每个位置上的 key 的个数 (高 6 bit) 及这些 key 在 positions 中序号的异或 (低 2 bit)
+        let mut t2count = vec![0_u8; array_length];
+        // 每个位置上的 key 的 hash 值的异或, 只剩一个 key 时即为该 key 的 hash 值
+        let mut t2hash = vec![0_u64; array_length];
+        for h in hashes {
+            let hash = mix(*h, seed);
+            for (i, p) in layout.positions(hash).into_iter().enumerate() {
+                t2count[p] = t2count[p].wrapping_add(4) ^ i as u8;
+                t2hash[p] ^= hash;
+                if t2count[p] < 4 {
+                    // 同一个位置上的 key 太多
+                    return None;
+                }
+            }
+        }
+
+        // 不断取出只有一个 key 的位置, 将该 key 从其他位置上移除
+        let mut queue: Vec<usize> = (0..array_length).filter(|p| t2count[*p] >> 2 == 1).collect();
+        let mut stack: Vec<(u64, usize)> = Vec::with_capacity(hashes.len());
+        while let Some(index) = queue.pop() {
+            if t2count[index] >> 2 != 1 {
+                continue;
+            }
+            let hash = t2hash[index];
+            let found = (t2count[index] & 3) as usize;
+            stack.push((hash, found));
+            for (i, p) in layout.positions(hash).into_iter().enumerate() {
+                t2count[p] = (t2count[p] - 4) ^ i as u8;
+                t2hash[p] ^= hash;
+                if i != found && t2count[p] >> 2 == 1 {
+                    queue.push(p);
+                }
+            }
+        }
+        if stack.len() != hashes.len() {
+            return None;
+        }
+
+        // 按移除的相反顺序为每个 key 的 found 位置赋值, 该位置之后不会再被修改
+        let mut fingerprints = vec![0_u16; array_length];
+        for (hash, found) in stack.into_iter().rev() {
+            let positions = layout.positions(hash);
+            let mut f = self.fingerprint(hash);
+            for (i, p) in positions.iter().enumerate() {
+                if i != found {
+                    f ^= fingerprints[*p];
+                }
+            }
+            fingerprints[positions[found]] = f;
+        }
+        Some(fingerprints)
+    }
+
+    #[inline]
+    fn fingerprint(&self, hash: u64) -> u16 {
+        ((hash ^ (hash >> 32)) & ((1 << self.fingerprint_bits) - 1)) as u16
+    }
+}
+
+impl Default for BinaryFuseFilterPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Layout {
+    /// 根据 key 的个数计算数组的布局, size_scale 用于构造失败时扩大数组
+    fn new(num_keys: usize, size_scale: f64) -> Self {
+        let n = num_keys.max(2) as f64;
+        let segment_length_log2 = ((n.ln() / 2.91_f64.ln() - 0.5).floor().max(0.0) as u8)
+            .min(MAX_SEGMENT_LENGTH_LOG2);
+        let size_factor = (0.77 + 0.305 * 600000_f64.ln() / n.ln()).max(1.075) * size_scale;
+        let capacity = (n * size_factor).round() as u64;
+        let segment_count = (capacity.div_ceil(1 << segment_length_log2) as i64 - (ARITY as i64 - 1)).max(1);
+        Self {
+            segment_length_log2,
+            segment_count: segment_count as u32,
+        }
+    }
+
+    fn array_length(&self) -> usize {
+        (self.segment_count as usize + ARITY - 1) << self.segment_length_log2
+    }
+
+    /// key 在数组中的 4 个位置, 分别位于相邻的 4 个 segment 中
+    #[inline]
+    fn positions(&self, hash: u64) -> [usize; ARITY] {
+        let segment_length = 1_u64 << self.segment_length_log2;
+        let mask = segment_length - 1;
+        let segment_count_length = self.segment_count as u64 * segment_length;
+        let h0 = ((hash as u128 * segment_count_length as u128) >> 64) as u64;
+        let h1 = (h0 + segment_length) ^ ((hash >> 18) & mask);
+        let h2 = (h0 + 2 * segment_length) ^ (hash & mask);
+        let h3 = (h0 + 3 * segment_length) ^ ((hash >> 36) & mask);
+        [h0 as usize, h1 as usize, h2 as usize, h3 as usize]
+    }
+}
+
+/// 由 key 的 hash 值及 seed 生成 64 bit 的 hash 值 (murmur3 fmix64)
+#[inline]
+fn mix(h: u32, seed: u32) -> u64 {
+    let mut x = h as u64 ^ (seed as u64).wrapping_mul(0x9e3779b97f4a7c15);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51afd7ed558ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
+    x ^ (x >> 33)
+}
+
+/// 将 fingerprint 按 bits 位紧密排列后追加到 dst
+fn pack(fingerprints: &[u16], bits: usize, dst: &mut Vec<u8>) {
+    dst.reserve((fingerprints.len() * bits).div_ceil(8));
+    let mut acc: u64 = 0;
+    let mut acc_bits = 0;
+    for f in fingerprints {
+        acc |= (*f as u64) << acc_bits;
+        acc_bits += bits;
+        while acc_bits >= 8 {
+            dst.push(acc as u8);
+            acc >>= 8;
+            acc_bits -= 8;
+        }
+    }
+    if acc_bits > 0 {
+        dst.push(acc as u8);
+    }
+}
+
+/// 读取第 index 个 fingerprint
+#[inline]
+fn unpack(data: &[u8], bits: usize, index: usize) -> u16 {
+    let bit = index * bits;
+    let byte = bit / 8;
+    let mut v: u32 = 0;
+    // bits 最大为 16, 加上字节内的偏移最多跨 3 个字节
+    for (j, b) in data[byte..data.len().min(byte + 3)].iter().enumerate() {
+        v |= (*b as u32) << (8 * j);
+    }
+    ((v >> (bit % 8)) & ((1 << bits) - 1)) as u16
+}
+
+impl FilterPolicy for BinaryFuseFilterPolicy {
+
+    fn name(&self) -> String {
+        String::from("leveldb.BinaryFuseFilter")
+    }
+
+    fn create_filter(&self, keys: &[&[u8]], dst: &mut Vec<u8>) {
+        // keys 中可能有重复的 key, 重复的 key 会导致构造失败, 因此先对 hash 值去重
+        let mut hashes: Vec<u32> = keys.iter().map(|key| Hash::hash_code(key, HASH_DEFAULT_SEED)).collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+
+        let mut size_scale = 1.0;
+        let mut result = None;
+        if !hashes.is_empty() {
+            for seed in 0..MAX_ATTEMPTS {
+                if seed > 0 && seed % 8 == 0 {
+                    size_scale *= 1.1;
+                }
+                let layout = Layout::new(hashes.len(), size_scale);
+                if let Some(fingerprints) = self.build(&hashes, seed, &layout) {
+                    result = Some((seed, layout, fingerprints));
+                    break;
+                }
+            }
+        }
+
+        let (seed, segment_count, segment_length_log2, bits) = match result {
+            Some((seed, layout, fingerprints)) => {
+                pack(&fingerprints, self.fingerprint_bits, dst);
+                (seed, layout.segment_count, layout.segment_length_log2, self.fingerprint_bits as u8)
+            }
+            // 没有 key, segment_count 为 0, 不匹配任何 key
+            None if hashes.is_empty() => (0, 0, 0, self.fingerprint_bits as u8),
+            // 多次构造都失败 (几乎不会发生), fingerprint_bits 为 0, 匹配所有的 key
+            None => (0, 0, 0, 0),
+        };
+        let mut encoder = Encoder::with_vec_append(dst);
+        encoder.put_fixed32(seed).expect("put seed");
+        encoder.put_fixed32(segment_count).expect("put segment_count");
+        dst.push(segment_length_log2);
+        dst.push(bits);
+    }
+
+    fn key_may_match(&self, key: &[u8], filter: &[u8]) -> bool {
+        let len = filter.len();
+        if len < TRAILER_SIZE {
+            // 格式错误, 视为可能存在
+            return true;
+        }
+        let bits = filter[len - 1] as usize;
+        let segment_length_log2 = filter[len - 2];
+        if bits == 0 || bits > 16 || segment_length_log2 > MAX_SEGMENT_LENGTH_LOG2 {
+            return true;
+        }
+        let mut decoder = Decoder::with_buf(&filter[len - TRAILER_SIZE..len - 2]);
+        let (seed, segment_count) = match (decoder.get_fixed32(), decoder.get_fixed32()) {
+            (Ok(seed), Ok(segment_count)) => (seed, segment_count),
+            _ => return true,
+        };
+        if segment_count == 0 {
+            // Empty filters do not match any keys
+            return false;
+        }
+
+        let layout = Layout { segment_length_log2, segment_count };
+        let data = &filter[..len - TRAILER_SIZE];
+        if data.len() != (layout.array_length() * bits).div_ceil(8) {
+            return true;
+        }
+
+        let hash = mix(Hash::hash_code(key, HASH_DEFAULT_SEED), seed);
+        let mut f = ((hash ^ (hash >> 32)) & ((1 << bits) - 1)) as u16;
+        for p in layout.positions(hash) {
+            f ^= unpack(data, bits, p);
+        }
+        f == 0
+    }
+}
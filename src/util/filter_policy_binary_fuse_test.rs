@@ -0,0 +1,118 @@
+#![cfg(test)]
+
+use crate::traits::filter_policy_trait::FilterPolicy;
+use crate::util::filter_policy_binary_fuse::BinaryFuseFilterPolicy;
+use crate::util::filter_policy_bloom::BloomFilterPolicy;
+
+fn key(i: u32) -> [u8; 4] {
+    i.to_le_bytes()
+}
+
+/// 使用 keys 创建 filter, 返回 filter 及 10000 个不存在的 key 的误判率
+fn create_and_check(policy: &dyn FilterPolicy, length: u32) -> (Vec<u8>, f64) {
+    let keys: Vec<[u8; 4]> = (0..length).map(key).collect();
+    let key_refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+    let mut filter: Vec<u8> = vec![];
+    policy.create_filter(&key_refs, &mut filter);
+
+    // All added keys must match
+    for k in &key_refs {
+        assert!(policy.key_may_match(k, &filter), "length {}", length);
+    }
+
+    let false_positives = (0..10000).filter(|i| policy.key_may_match(&key(i + 1000000000), &filter)).count();
+    (filter, false_positives as f64 / 10000.0)
+}
+
+// ####################  BinaryFuseFilterPolicy test
+#[test]
+fn test_new() {
+    let policy = BinaryFuseFilterPolicy::new();
+    assert_eq!(7, policy.fingerprint_bits());
+    assert_eq!(8, BinaryFuseFilterPolicy::new_with_fingerprint_bits(8).fingerprint_bits());
+    assert_eq!(1, BinaryFuseFilterPolicy::new_with_fingerprint_bits(0).fingerprint_bits());
+    assert_eq!(16, BinaryFuseFilterPolicy::new_with_fingerprint_bits(32).fingerprint_bits());
+
+    assert_eq!("leveldb.BinaryFuseFilter", policy.name());
+    assert_ne!(BloomFilterPolicy::new().name(), policy.name());
+}
+
+#[test]
+fn test_empty_filter() {
+    let policy = BinaryFuseFilterPolicy::new();
+    let mut filter: Vec<u8> = vec![];
+    policy.create_filter(&[], &mut filter);
+    assert_eq!(10, filter.len());
+    assert!(!policy.key_may_match(b"hello", &filter));
+    assert!(!policy.key_may_match(b"world", &filter));
+}
+
+#[test]
+fn test_create_filter() {
+    let policy = BinaryFuseFilterPolicy::new();
+
+    // filter 追加在 dst 已有的数据之后
+    let mut dst: Vec<u8> = vec![0xff; 3];
+    policy.create_filter(&[b"hello", b"world", b"hello world"], &mut dst);
+    assert_eq!(vec![0xff; 3], dst[..3].to_vec());
+
+    let filter = &dst[3..];
+    assert!(policy.key_may_match(b"hello", filter));
+    assert!(policy.key_may_match(b"world", filter));
+    assert!(policy.key_may_match(b"hello world", filter));
+    assert!(!policy.key_may_match(b"x", filter));
+    assert!(!policy.key_may_match(b"foo", filter));
+
+    // 格式错误的 filter 视为可能存在
+    assert!(policy.key_may_match(b"x", &filter[1..]));
+    assert!(policy.key_may_match(b"x", b""));
+}
+
+/// 重复的 key 不影响构造
+#[test]
+fn test_duplicate_keys() {
+    let policy = BinaryFuseFilterPolicy::new();
+    let keys: Vec<[u8; 4]> = (0..1000).map(|i| key(i % 10)).collect();
+    let key_refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+    let mut filter: Vec<u8> = vec![];
+    policy.create_filter(&key_refs, &mut filter);
+
+    let mut unique: Vec<u8> = vec![];
+    policy.create_filter(&key_refs[..10], &mut unique);
+    assert_eq!(unique, filter);
+    for i in 0..10 {
+        assert!(policy.key_may_match(&key(i), &filter));
+    }
+}
+
+/// 不同 key 数量下的误判率, fingerprint 为 7 bit 时约为 1/128
+#[test]
+fn test_false_positive_rate() {
+    let policy = BinaryFuseFilterPolicy::new();
+
+    let mut length = 1;
+    while length <= 10000 {
+        let (_, rate) = create_and_check(&policy, length);
+        assert!(rate <= 0.015, "length {} rate {}", length, rate);
+
+        length = if length < 10 { length + 1 } else if length < 100 { length + 10 } else if length < 1000 { length + 100 } else { length + 1000 };
+    }
+
+    // fingerprint 为 12 bit 时误判率低于 0.1%
+    let (_, rate) = create_and_check(&BinaryFuseFilterPolicy::new_with_fingerprint_bits(12), 10000);
+    assert!(rate <= 0.001, "rate {}", rate);
+}
+
+/// key 较多时, 误判率不高于 BloomFilterPolicy, 每个 key 占用的空间少 22% 以上
+#[test]
+fn test_bits_per_key() {
+    for length in [200000, 1000000] {
+        let (fuse_filter, fuse_rate) = create_and_check(&BinaryFuseFilterPolicy::new(), length);
+        let (bloom_filter, bloom_rate) = create_and_check(&BloomFilterPolicy::new(), length);
+
+        let fuse_bits = fuse_filter.len() as f64 * 8.0 / length as f64;
+        let bloom_bits = bloom_filter.len() as f64 * 8.0 / length as f64;
+        assert!(fuse_bits <= bloom_bits * 0.78, "length {} fuse {} bloom {}", length, fuse_bits, bloom_bits);
+        assert!(fuse_rate <= bloom_rate, "length {} fuse rate {} bloom rate {}", length, fuse_rate, bloom_rate);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::traits::filter_policy_trait::FilterPolicy;
+use crate::util::filter_policy::FromPolicy;
+use crate::util::hash::Hash;
+use crate::util::r#const::HASH_DEFAULT_SEED;
+
+/// 每个 block 的字节数, 与 cache line 的大小相同
+const CACHE_LINE_SIZE: usize = 64;
+
+/// 每个 block 有 512 bit, 取 hash 值的高 9 bit 作为 block 内的位置
+const BIT_INDEX_SHIFT: u32 = 32 - 9;
+
+// #########################  BlockedBloomFilterPolicy
+/// 将每个 key 的所有探测位置限制在同一个 64 字节 block 内的布隆过滤器。
+///
+/// BloomFilterPolicy 的 k 个探测位置分布在整个位数组中, 一次查询最多会访问 k 个 cache line;
+/// BlockedBloomFilterPolicy 先用 hash 值选出一个 64 字节的 block, 再在 block 内设置/检查 k 个 bit,
+/// 一次查询只访问一个 block (filter 在 filter block 中的起始位置不一定按 64 字节对齐, 最多跨两个 cache line)。
+/// 代价是相同 bits_per_key 下误判率略高于 BloomFilterPolicy。
+///
+/// Filter 的格式:
+///
+/// ```text
+///     [block 0: 64 bytes] ... [block n-1: 64 bytes] [k: 1 byte]
+/// ```
+///
+/// 名字与 BloomFilterPolicy 不同, 两者生成的 filter 不能互相读取,
+/// 使用 BloomFilterPolicy 生成的旧 table 仍然需要 BloomFilterPolicy 来读取 filter。
+pub struct BlockedBloomFilterPolicy {
+    // 每个key需要多少bit来存储表示
+    bits_per_key: usize,
+
+    // 每个 key 在 block 内探测的 bit 数
+    k: usize
+}
+
+impl BlockedBloomFilterPolicy {
+    ///
+    /// 每个 key 约使用 10 bit, 误判率约为 1.2%
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::filter_policy_blocked_bloom::BlockedBloomFilterPolicy;
+    ///
+    /// let policy = BlockedBloomFilterPolicy::new();
+    /// ```
+    pub fn new() -> Self {
+        BlockedBloomFilterPolicy::new_with_bits_per_key(10)
+    }
+
+    ///
+    /// 指定每个 key 使用的 bit 数
+    ///
+    /// # Arguments
+    ///
+    /// * `bits_per_key`:   m位的bit数组 / n个整数set 的值
+    ///
+    /// returns: BlockedBloomFilterPolicy
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::filter_policy_blocked_bloom::BlockedBloomFilterPolicy;
+    ///
+    /// let policy = BlockedBloomFilterPolicy::new_with_bits_per_key(16);
+    /// ```
+    pub fn new_with_bits_per_key(bits_per_key: usize) -> Self {
+        Self {
+            bits_per_key,
+            k: Self::choose_num_probes(bits_per_key * 1000)
+        }
+    }
+
+    /// 所有探测位置都在同一个 block 内时, 最优的 k 比 ln2 * bits_per_key 小。
+    /// 取值与 RocksDB FastLocalBloom 的经验值相同
+    fn choose_num_probes(millibits_per_key: usize) -> usize {
+        match millibits_per_key {
+            0..=2080 => 1,
+            2081..=3580 => 2,
+            3581..=5100 => 3,
+            5101..=6640 => 4,
+            6641..=8300 => 5,
+            8301..=10070 => 6,
+            10071..=11720 => 7,
+            11721..=14001 => 8,
+            14002..=16050 => 9,
+            16051..=18300 => 10,
+            18301..=22001 => 11,
+            22002..=25501 => 12,
+            25502..=50000 => (millibits_per_key - 1) / 2000 - 1,
+            _ => 24,
+        }
+    }
+
+    /// 选择 block 的 hash 值
+    #[inline]
+    fn hash(key: &[u8]) -> u32 {
+        Hash::hash_code(key, HASH_DEFAULT_SEED)
+    }
+
+    /// 由 hash 值得到 block 的序号, 使用乘法代替取模
+    #[inline]
+    fn block_index(h: u32, num_blocks: usize) -> usize {
+        ((h as u64 * num_blocks as u64) >> 32) as usize
+    }
+
+    /// 由选择 block 的 hash 值再混合出 block 内探测位置使用的 hash 值 (murmur3 fmix32)
+    #[inline]
+    fn probe_hash(mut h: u32) -> u32 {
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x85ebca6b);
+        h ^= h >> 13;
+        h = h.wrapping_mul(0xc2b2ae35);
+        h ^ (h >> 16)
+    }
+}
+
+impl Default for BlockedBloomFilterPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// get struct  BlockedBloomFilterPolicy 属性
+impl FromPolicy for BlockedBloomFilterPolicy {
+    fn from_bits_per_key(&self) -> usize {
+        self.bits_per_key
+    }
+
+    fn from_k(&self) -> usize {
+        self.k
+    }
+}
+
+impl FilterPolicy for BlockedBloomFilterPolicy {
+
+    fn name(&self) -> String {
+        String::from("leveldb.BlockedBloomFilter")
+    }
+
+    fn create_filter(&self, keys: &[&[u8]], dst: &mut Vec<u8>) {
+        // 按 block 向上取整, 至少一个 block
+        let bits = keys.len() * self.bits_per_key;
+        let num_blocks = bits.div_ceil(CACHE_LINE_SIZE * 8).max(1);
+
+        let init_size = dst.len();
+        dst.resize(init_size + num_blocks * CACHE_LINE_SIZE, 0);
+        // 在filter的最后压入探测的个数
+        dst.push(self.k as u8);
+        let array = &mut dst[init_size..init_size + num_blocks * CACHE_LINE_SIZE];
+
+        for key in keys {
+            let h = Self::hash(key);
+            let offset = Self::block_index(h, num_blocks) * CACHE_LINE_SIZE;
+            let block = &mut array[offset..offset + CACHE_LINE_SIZE];
+
+            // 每次取 hash 值的高 9 bit 作为 block 内的位置, 再乘以黄金分割数得到下一个位置
+            let mut h = Self::probe_hash(h);
+            for _ in 0..self.k {
+                let bitpos = (h >> BIT_INDEX_SHIFT) as usize;
+                block[bitpos / 8] |= 1 << (bitpos % 8);
+                h = h.wrapping_mul(0x9e3779b9);
+            }
+        }
+    }
+
+    fn key_may_match(&self, key: &[u8], filter: &[u8]) -> bool {
+        let len = filter.len();
+        if len < CACHE_LINE_SIZE + 1 || !(len - 1).is_multiple_of(CACHE_LINE_SIZE) {
+            // 不是 BlockedBloomFilterPolicy 生成的 filter, 视为可能存在
+            return true;
+        }
+
+        let k = filter[len - 1];
+        if k == 0 || k > 30 {
+            // Reserved for potentially new encodings.  Consider it a match.
+            return true;
+        }
+
+        let num_blocks = (len - 1) / CACHE_LINE_SIZE;
+        let h = Self::hash(key);
+        let offset = Self::block_index(h, num_blocks) * CACHE_LINE_SIZE;
+        let block = &filter[offset..offset + CACHE_LINE_SIZE];
+
+        let mut h = Self::probe_hash(h);
+        for _ in 0..k {
+            let bitpos = (h >> BIT_INDEX_SHIFT) as usize;
+            if block[bitpos / 8] & (1 << (bitpos % 8)) == 0 {
+                return false;
+            }
+            h = h.wrapping_mul(0x9e3779b9);
+        }
+
+        true
+    }
+}
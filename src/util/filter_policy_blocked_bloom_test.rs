@@ -0,0 +1,117 @@
+#![cfg(test)]
+
+use crate::traits::filter_policy_trait::FilterPolicy;
+use crate::util::filter_policy::FromPolicy;
+use crate::util::filter_policy_blocked_bloom::BlockedBloomFilterPolicy;
+use crate::util::filter_policy_bloom::BloomFilterPolicy;
+
+fn key(i: u32) -> [u8; 4] {
+    i.to_le_bytes()
+}
+
+/// 使用 keys 创建 filter, 返回 10000 个不存在的 key 的误判率
+fn false_positive_rate(policy: &dyn FilterPolicy, length: u32) -> f64 {
+    let keys: Vec<[u8; 4]> = (0..length).map(key).collect();
+    let key_refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+    let mut filter: Vec<u8> = vec![];
+    policy.create_filter(&key_refs, &mut filter);
+
+    // All added keys must match
+    for k in &key_refs {
+        assert!(policy.key_may_match(k, &filter), "length {}", length);
+    }
+
+    let false_positives = (0..10000).filter(|i| policy.key_may_match(&key(i + 1000000000), &filter)).count();
+    false_positives as f64 / 10000.0
+}
+
+// ####################  BlockedBloomFilterPolicy test
+#[test]
+fn test_new() {
+    let policy = BlockedBloomFilterPolicy::new();
+    assert_eq!(policy.from_bits_per_key(), 10);
+    assert_eq!(policy.from_k(), 6);
+
+    assert_eq!(BlockedBloomFilterPolicy::new_with_bits_per_key(1).from_k(), 1);
+    assert_eq!(BlockedBloomFilterPolicy::new_with_bits_per_key(16).from_k(), 9);
+    assert_eq!(BlockedBloomFilterPolicy::new_with_bits_per_key(100).from_k(), 24);
+
+    // 与 BloomFilterPolicy 的 filter 格式不同, 名字也不同
+    assert_eq!("leveldb.BlockedBloomFilter", policy.name());
+    assert_ne!(BloomFilterPolicy::new().name(), policy.name());
+}
+
+#[test]
+fn test_empty_filter() {
+    let policy = BlockedBloomFilterPolicy::new();
+    let mut filter: Vec<u8> = vec![];
+    policy.create_filter(&[], &mut filter);
+    assert_eq!(64 + 1, filter.len());
+    assert!(!policy.key_may_match(b"hello", &filter));
+    assert!(!policy.key_may_match(b"world", &filter));
+}
+
+#[test]
+fn test_create_filter() {
+    let policy = BlockedBloomFilterPolicy::new();
+
+    // filter 追加在 dst 已有的数据之后
+    let mut dst: Vec<u8> = vec![0xff; 3];
+    policy.create_filter(&[b"hello", b"world", b"hello world"], &mut dst);
+    assert_eq!(vec![0xff; 3], dst[..3].to_vec());
+
+    let filter = &dst[3..];
+    assert!(policy.key_may_match(b"hello", filter));
+    assert!(policy.key_may_match(b"world", filter));
+    assert!(policy.key_may_match(b"hello world", filter));
+    assert!(!policy.key_may_match(b"x", filter));
+    assert!(!policy.key_may_match(b"foo", filter));
+    assert!(!policy.key_may_match(b"helloworld", filter));
+
+    // 格式错误的 filter 视为可能存在
+    assert!(policy.key_may_match(b"x", &filter[1..]));
+    assert!(policy.key_may_match(b"x", b""));
+}
+
+/// 每个 key 的探测位置都在同一个 64 字节的 block 内
+#[test]
+fn test_probes_in_one_block() {
+    // 每个 key 5120 bit, 单个 key 的 filter 有 10 个 block
+    let policy = BlockedBloomFilterPolicy::new_with_bits_per_key(5120);
+    for i in 0..100 {
+        let mut filter: Vec<u8> = vec![];
+        policy.create_filter(&[&key(i)], &mut filter);
+        assert_eq!(10 * 64 + 1, filter.len());
+
+        let blocks: Vec<usize> = filter[..filter.len() - 1].chunks(64)
+            .enumerate()
+            .filter(|(_, block)| block.iter().any(|b| *b != 0))
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(1, blocks.len(), "key {}", i);
+        assert!(policy.key_may_match(&key(i), &filter));
+    }
+}
+
+/// 不同 key 数量下的误判率, 与 BloomFilterPolicy 相差不大
+#[test]
+fn test_false_positive_rate() {
+    let blocked = BlockedBloomFilterPolicy::new();
+    let bloom = BloomFilterPolicy::new();
+
+    let mut length = 1;
+    while length <= 10000 {
+        let rate = false_positive_rate(&blocked, length);
+        assert!(rate <= 0.02, "length {} rate {}", length, rate); // Must not be over 2%
+        if length >= 1000 {
+            let bloom_rate = false_positive_rate(&bloom, length);
+            assert!(rate <= bloom_rate * 2.0, "length {} rate {} bloom rate {}", length, rate, bloom_rate);
+        }
+
+        length = if length < 10 { length + 1 } else if length < 100 { length + 10 } else if length < 1000 { length + 100 } else { length + 1000 };
+    }
+
+    // 每个 key 16 bit 时误判率低于 0.1%
+    let rate = false_positive_rate(&BlockedBloomFilterPolicy::new_with_bits_per_key(16), 10000);
+    assert!(rate <= 0.001, "rate {}", rate);
+}
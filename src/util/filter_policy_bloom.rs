@@ -1,9 +1,8 @@
 use std::ops::Mul;
 use crate::traits::filter_policy_trait::FilterPolicy;
-use crate::util::filter_policy::{AsBloomHash, FromPolicy};
-use crate::util::hash::ToHash;
+use crate::util::filter_policy::FromPolicy;
+use crate::util::hash::Hash;
 use crate::util::r#const::HASH_DEFAULT_SEED;
-use crate::util::slice::Slice;
 
 // #########################  BloomFilterPolicy
 pub struct BloomFilterPolicy {
@@ -58,9 +57,9 @@ impl BloomFilterPolicy {
     }
 }
 
-impl<'a> BloomFilterPolicy {
-    pub fn bloom_hash(key: &Slice) -> u32 {
-        key.to_hash_with_seed(HASH_DEFAULT_SEED)
+impl BloomFilterPolicy {
+    pub fn bloom_hash(key: &[u8]) -> u32 {
+        Hash::hash_code(key, HASH_DEFAULT_SEED)
     }
 }
 
@@ -81,14 +80,8 @@ impl FilterPolicy for BloomFilterPolicy {
         String::from("leveldb.BuiltinBloomFilter")
     }
 
-    fn create_filter(&self, keys: Vec<&Slice>) -> Slice {
-        let len: usize = keys.len();
-
-        self.create_filter_with_len(len, keys)
-    }
-
-    fn create_filter_with_len(&self, capacity: usize, keys: Vec<&Slice>) -> Slice {
-        let n: usize = capacity;
+    fn create_filter(&self, keys: &[&[u8]], dst: &mut Vec<u8>) {
+        let n: usize = keys.len();
 
         // Compute bloom filter size (in both bits and bytes)
         // 计算出中的需要的bits个数, n * bits_per_key, 也就是说，对于每一个key需要这么多bit
@@ -107,15 +100,15 @@ impl FilterPolicy for BloomFilterPolicy {
         bits = bytes * 8;
 
         // 扩展下要存储BloomFilter的内存空间， 并在尾部一个Byte存哈希函数的个数。
-        let mut dst_chars: Vec<u8> = vec![0; bytes + 1]; // 相当于是 append 了bytes个0
+        let init_size = dst.len();
+        dst.resize(init_size + bytes, 0); // 相当于是 append 了bytes个0
         // 在filter的最后压入哈希函数的个数。 在最后一位， 记录k 值。 这个k是位于bytes之后。
-        dst_chars[bytes] = self.k as u8;
+        dst.push(self.k as u8);
+        let array = &mut dst[init_size..init_size + bytes];
 
         // 开始依次存储每个key值。
-        // 对于每个key采用double hash的方式生成k_个bitpos，然后在 dst_chars 的相应位置设置1。
-        for i in 0..keys.len() {
-            let slice = keys[i];
-
+        // 对于每个key采用double hash的方式生成k_个bitpos，然后在 array 的相应位置设置1。
+        for key in keys {
             /* 计算哈希值 */
             // BloomFilter理论是通过多个hash计算来减少冲突，
             // 但leveldb实际上并未真正去计算多个hash，而是通过double-hashing的方式来达到同样的效果。
@@ -132,21 +125,16 @@ impl FilterPolicy for BloomFilterPolicy {
             //
             // Use double-hashing to generate a sequence of hash values.
             // See analysis in [Kirsch,Mitzenmacher 2006].
-            let mut h : u32 = slice.bloom_hash();
+            let mut h : u32 = BloomFilterPolicy::bloom_hash(key);
             // Rotate right 17 bits
             let delta : u32 = (h >> 17) | (h << 15);
 
-            for j in 0..self.k {
-                let bitpos:usize = ((h as usize) % bits);
-
-                // val ==> 1 << (bitpos % 8)
-                let mod_val: usize = bitpos % 8;
-                let val = (1 as u8).wrapping_shl(mod_val as u32);
+            for _ in 0..self.k {
+                let bitpos:usize = (h as usize) % bits;
 
                 // 本来应该直接把h bit设置为1的。但是这里总共只有bits个bit, 访问m[i] 把相应位设置为1
                 // a |= b  ==>  按位或， 后赋值给a
-                // let position: usize = bitpos / 8;
-                dst_chars[bitpos / 8] |= val;
+                array[bitpos / 8] |= 1 << (bitpos % 8);
 
                 // 累加来实现k个hash函数, h.wrapping_add(delta) ==> h += delta
                 // LevelDB中并没有真正创建k个哈希函数。而是使用旧有的哈希值累加。
@@ -154,36 +142,23 @@ impl FilterPolicy for BloomFilterPolicy {
                 h = h.wrapping_add(delta);
             }
         }
-
-        // Vec<u8> 转 Slice
-        Slice::from_buf(&dst_chars)
     }
 
-    // fn create_filter_u8(&self, keys: Vec<u8>) -> Slice {
-    //     self.create_filter_u8_with_len(keys.len(), keys)
-    // }
-    //
-    // fn create_filter_u8_with_len(&self, capacity: usize, keys: Vec<u8>) -> Slice {
-    //     todo!()
-    // }
-
-    fn key_may_match(&self, key: &Slice, bloom_filter: &Slice) -> bool {
+    fn key_may_match(&self, key: &[u8], bloom_filter: &[u8]) -> bool {
         // 1、插入时按1Byte对齐；
         // 2、尾部插入了一个Byte的hash个数
         // 所以大小不能小于2个字节
-        let len: usize = bloom_filter.size();
+        let len: usize = bloom_filter.len();
         if len < 2 {
             return false;
         }
 
-        // 获得相应的内存区域的数据: 除去尾部的1Byte对应的hash个数，就是当前位数组容器的大小
-        let bloom_filter_array:Vec<u8>  = bloom_filter.to_vec();
-        // 总共的bits数目
+        // 总共的bits数目: 除去尾部的1Byte对应的hash个数，就是当前位数组容器的大小
         let bits: usize = (len - 1) * 8;
 
         // 取得k哈希函数的数目
         // Use the encoded k so that we can read filters generated by bloom filters created using different parameters.
-        let k: u8 = bloom_filter_array[len - 1];
+        let k: u8 = bloom_filter[len - 1];
         // 对于大于30个哈希函数的情况，这里直接返回存在
         if k > 30 {
             // Reserved for potentially new encodings for short bloom filters.  Consider it a match.
@@ -194,20 +169,20 @@ impl FilterPolicy for BloomFilterPolicy {
         // 2、按插入规则去 &，只要有1bit不相同，那就不存在。
 
         // 计算哈希值
-        let mut h : u32 = key.bloom_hash();
+        let mut h : u32 = BloomFilterPolicy::bloom_hash(key);
         // Rotate right 17 bits
         let delta = (h >> 17) | (h << 15);
 
         // 计算key的hash值，重复计算阶段的步骤，循环计算k_个hash值，只要有一个结果对应的bit位为0，就认为不匹配，否则认为匹配
-        for j in 0..k {
-            let bitpos:usize = ((h as usize) % bits);
-            if (bloom_filter_array[bitpos/8] & (1 << (bitpos % 8))) == 0 {
+        for _ in 0..k {
+            let bitpos:usize = (h as usize) % bits;
+            if (bloom_filter[bitpos/8] & (1 << (bitpos % 8))) == 0 {
                 return false;
             }
 
             h = h.wrapping_add(delta);
         }
 
-        return true;
+        true
     }
-}
\ No newline at end of file
+}
@@ -37,12 +37,13 @@ fn test_create_filter() {
     let s2 = Slice::try_from(String::from("world")).unwrap();
     let s3 = Slice::try_from(String::from("hello world")).unwrap();
 
-    let mut keys : Vec<&Slice>  = Vec::new();
+    let mut keys: Vec<&[u8]> = Vec::new();
     keys.push(&s1);
     keys.push(&s2);
     keys.push(&s3);
 
-    let bloom_filter: Slice = policy.create_filter(keys);
+    let mut bloom_filter: Vec<u8> = vec![];
+    policy.create_filter(&keys, &mut bloom_filter);
 
     // 验证通过
     let mut key_may_match = policy.key_may_match(
@@ -82,9 +83,9 @@ fn test_create_filter() {
     assert!(key_may_match);
 }
 
-/// 指定超长长度。可以超过放置的值
+/// 追加到已有较长数据的 dst 中
 #[test]
-fn test_create_filter_with_long_len(){
+fn test_create_filter_append_long_prefix(){
     let policy = BloomFilterPolicy::new_with_bits_per_key(800);
 
     // 如下三个值， 存放在 BloomFilter 中
@@ -92,12 +93,16 @@ fn test_create_filter_with_long_len(){
     let s2 = Slice::try_from(String::from("world")).unwrap();
     let s3 = Slice::try_from(String::from("hello world")).unwrap();
 
-    let mut keys : Vec<&Slice>  = Vec::new();
+    let mut keys: Vec<&[u8]> = Vec::new();
     keys.push(&s1);
     keys.push(&s2);
     keys.push(&s3);
 
-    let bloom_filter: Slice = policy.create_filter_with_len(600, keys);
+    // dst 中已有 600 字节的数据, filter 追加在其后
+    let mut dst: Vec<u8> = vec![0xff; 600];
+    policy.create_filter(&keys, &mut dst);
+    assert_eq!(vec![0xff; 600], dst[..600].to_vec());
+    let bloom_filter = &dst[600..];
 
     // 验证通过
     let mut key_may_match = policy.key_may_match(
@@ -137,9 +142,9 @@ fn test_create_filter_with_long_len(){
     assert!(key_may_match);
 }
 
-/// 指定端长度。放不开放置的值。 此时对于 BloomFilterPolicy 来讲不需要扩容
+/// 追加到已有较短数据的 dst 中
 #[test]
-fn test_create_filter_with_short_len(){
+fn test_create_filter_append_short_prefix(){
     let policy = BloomFilterPolicy::new_with_bits_per_key(800);
 
     // 如下三个值， 存放在 BloomFilter 中
@@ -147,12 +152,16 @@ fn test_create_filter_with_short_len(){
     let s2 = Slice::try_from(String::from("world")).unwrap();
     let s3 = Slice::try_from(String::from("hello world")).unwrap();
 
-    let mut keys : Vec<&Slice>  = Vec::new();
+    let mut keys: Vec<&[u8]> = Vec::new();
     keys.push(&s1);
     keys.push(&s2);
     keys.push(&s3);
 
-    let bloom_filter: Slice = policy.create_filter_with_len(2, keys);
+    // dst 中已有 2 字节的数据, filter 追加在其后
+    let mut dst: Vec<u8> = vec![0xff; 2];
+    policy.create_filter(&keys, &mut dst);
+    assert_eq!(vec![0xff; 2], dst[..2].to_vec());
+    let bloom_filter = &dst[2..];
 
     // 验证通过
     let mut key_may_match = policy.key_may_match(
@@ -190,4 +199,66 @@ fn test_create_filter_with_short_len(){
         &Slice::try_from(String::from("hello")).unwrap(),
         &bloom_filter);
     assert!(key_may_match);
-}
\ No newline at end of file
+}
+#[test]
+fn test_empty_filter() {
+    let policy = BloomFilterPolicy::new();
+    let mut bloom_filter: Vec<u8> = vec![];
+    policy.create_filter(&[], &mut bloom_filter);
+    assert!(!policy.key_may_match(b"hello", &bloom_filter));
+    assert!(!policy.key_may_match(b"world", &bloom_filter));
+}
+
+/// 不同 key 数量下, filter 的大小及误判率
+#[test]
+fn test_varying_lengths() {
+    fn key(i: u32) -> [u8; 4] {
+        i.to_le_bytes()
+    }
+
+    fn next_length(length: u32) -> u32 {
+        if length < 10 {
+            length + 1
+        } else if length < 100 {
+            length + 10
+        } else if length < 1000 {
+            length + 100
+        } else {
+            length + 1000
+        }
+    }
+
+    let policy = BloomFilterPolicy::new();
+    // Count number of filters that significantly exceed the false positive rate
+    let mut mediocre_filters = 0;
+    let mut good_filters = 0;
+
+    let mut length = 1;
+    while length <= 10000 {
+        let keys: Vec<[u8; 4]> = (0..length).map(key).collect();
+        let key_refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+        let mut bloom_filter: Vec<u8> = vec![];
+        policy.create_filter(&key_refs, &mut bloom_filter);
+
+        assert!(bloom_filter.len() <= (length as usize * 10 / 8) + 40, "length {}", length);
+
+        // All added keys must match
+        for k in &key_refs {
+            assert!(policy.key_may_match(k, &bloom_filter), "length {}", length);
+        }
+
+        // Check false positive rate
+        let false_positives = (0..10000).filter(|i| policy.key_may_match(&key(i + 1000000000), &bloom_filter)).count();
+        let rate = false_positives as f64 / 10000.0;
+        // bits_per_key 为 10 时 k 为 7 (LevelDB 截断为 6), key 很少的 filter 只有 64 bit, 误判率会略高于 2%
+        assert!(rate <= 0.03, "length {} rate {}", length, rate); // Must not be over 3%
+        if rate > 0.0125 {
+            mediocre_filters += 1; // Allowed, but not too often
+        } else {
+            good_filters += 1;
+        }
+
+        length = next_length(length);
+    }
+    assert!(mediocre_filters <= good_filters / 5, "good {} mediocre {}", good_filters, mediocre_filters);
+}
@@ -1,37 +1,85 @@
-use crate::traits::filter_policy_trait::FilterPolicy;
-use crate::util::slice::Slice;
+use std::sync::Arc;
+use crate::db::db_format::ParsedInternalKey;
+use crate::traits::filter_policy_trait::{FilterPolicy, FilterPolicyPtr};
 
 // #########################  InternalFilterPolicy
+/// 包装用户的 FilterPolicy, 使其可以处理 internal key。
+///
+/// table 中保存的是 internal key (user key + 8 字节的 sequence/type),
+/// 创建及查询 filter 前先去掉末尾的 8 字节, 因此 filter 中保存的是 user key,
+/// 任意 sequence 的查询都可以命中 filter。
+///
+/// Filter policy wrapper that converts from internal keys to user keys
 pub struct InternalFilterPolicy {
-    user_policy_: dyn FilterPolicy
+    user_policy: FilterPolicyPtr
 }
 
 impl InternalFilterPolicy {
-    fn new(policy: Box<dyn FilterPolicy>) -> Box<InternalFilterPolicy> {
-        // InternalFilterPolicy{ user_policy_: policy }
-        todo!()
+    ///
+    /// 包装用户的 FilterPolicy
+    ///
+    /// # Arguments
+    ///
+    /// * `policy`: 用户的 FilterPolicy
+    ///
+    /// returns: InternalFilterPolicy
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use level_db_rust::traits::filter_policy_trait::FilterPolicy;
+    /// use level_db_rust::util::filter_policy_bloom::BloomFilterPolicy;
+    /// use level_db_rust::util::filter_policy_internal::InternalFilterPolicy;
+    ///
+    /// let policy = InternalFilterPolicy::new(Arc::new(Box::new(BloomFilterPolicy::new())));
+    /// assert_eq!("leveldb.BuiltinBloomFilter", policy.name());
+    /// ```
+    pub fn new(policy: FilterPolicyPtr) -> Self {
+        Self {
+            user_policy: policy
+        }
+    }
+
+    /// 包装用户的 FilterPolicy, 返回 FilterPolicyPtr。 policy 为 None 时返回 None
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// options.filter_policy = InternalFilterPolicy::wrap(options.filter_policy.take());
+    /// ```
+    pub fn wrap(policy: Option<FilterPolicyPtr>) -> Option<FilterPolicyPtr> {
+        policy.map(|p| -> FilterPolicyPtr { Arc::new(Box::new(InternalFilterPolicy::new(p))) })
+    }
+
+    /// 返回被包装的用户 FilterPolicy
+    pub fn user_policy(&self) -> FilterPolicyPtr {
+        self.user_policy.clone()
     }
 }
 
 impl FilterPolicy for InternalFilterPolicy {
+    /// 与用户的 FilterPolicy 同名, 打开 table 时按名字查找 filter block,
+    /// 因此 DB 内部生成的 filter 与直接使用用户 FilterPolicy 生成的 filter 格式一致
     fn name(&self) -> String {
-        todo!()
+        self.user_policy.name()
     }
 
-    fn create_filter(&self, keys: Vec<&Slice>) -> Slice {
-        self.create_filter_with_len(keys.len(), keys)
-    }
-
-    fn create_filter_with_len(&self, capacity: usize, keys: Vec<&Slice>) -> Slice {
-        // 根据指定的参数创建过滤器，并返回结果， 结果为dst的原始内容 + append结果。
+    fn create_filter(&self, keys: &[&[u8]], dst: &mut Vec<u8>) {
         // 参数keys[0,n-1]包含依据用户提供的comparator排序的key列表--可重复，
-        // 并把根据这些key创建的filter追加到 dst中。
-        //
-        todo!()
+        // 去掉 sequence/type 后交给用户的 FilterPolicy 处理。
+        // 只是截取 key 的前缀, 不需要复制 key 的数据
+        let user_keys: Vec<&[u8]> = keys.iter()
+            .map(|key| ParsedInternalKey::extract_user_key(key))
+            .collect();
+        self.user_policy.create_filter(&user_keys, dst);
     }
 
-    fn key_may_match(&self, key: &Slice, bloom_filter: &Slice) -> bool {
-        todo!()
+    fn key_may_match(&self, key: &[u8], filter: &[u8]) -> bool {
+        self.user_policy.key_may_match(ParsedInternalKey::extract_user_key(key), filter)
     }
 
-}
\ No newline at end of file
+    fn prefix_may_match(&self, key: &[u8], filter: &[u8]) -> bool {
+        self.user_policy.prefix_may_match(ParsedInternalKey::extract_user_key(key), filter)
+    }
+}
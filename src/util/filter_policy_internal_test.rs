@@ -1,5 +1,66 @@
+use std::sync::Arc;
+use crate::db::db_format::{InternalKey, ValueType};
+use crate::traits::filter_policy_trait::{FilterPolicy, FilterPolicyPtr};
+use crate::util::filter_policy_bloom::BloomFilterPolicy;
+use crate::util::filter_policy_internal::InternalFilterPolicy;
+use crate::util::slice::Slice;
+
+fn ikey(user_key: &str, seq: u64) -> Vec<u8> {
+    InternalKey::new(Slice::from(user_key), seq, ValueType::KTypeValue).encode().to_vec()
+}
+
+fn create_filter(policy: &dyn FilterPolicy, keys: &[&[u8]]) -> Vec<u8> {
+    let mut filter = vec![];
+    policy.create_filter(keys, &mut filter);
+    filter
+}
+
+// ####################  InternalFilterPolicy test
+#[test]
+fn test_name() {
+    let user_policy: FilterPolicyPtr = Arc::new(Box::new(BloomFilterPolicy::new()));
+    let policy = InternalFilterPolicy::new(user_policy.clone());
+    assert_eq!(user_policy.name(), policy.name());
+    assert_eq!("leveldb.BuiltinBloomFilter", policy.user_policy().name());
+
+    assert!(InternalFilterPolicy::wrap(None).is_none());
+    assert_eq!("leveldb.BuiltinBloomFilter", InternalFilterPolicy::wrap(Some(user_policy)).unwrap().name());
+}
 
 #[test]
-fn test__() {
+fn test_create_filter() {
+    let user_policy: FilterPolicyPtr = Arc::new(Box::new(BloomFilterPolicy::new()));
+    let policy = InternalFilterPolicy::new(user_policy.clone());
+
+    let filter = create_filter(&policy, &[&ikey("hello", 1), &ikey("world", 2)]);
+
+    // 任意 sequence 的 internal key 都可以命中
+    assert!(policy.key_may_match(&ikey("hello", 1), &filter));
+    assert!(policy.key_may_match(&ikey("hello", 100), &filter));
+    assert!(policy.key_may_match(&ikey("world", 0), &filter));
+    assert!(!policy.key_may_match(&ikey("x", 1), &filter));
+    assert!(!policy.key_may_match(&ikey("foo", 2), &filter));
+
+    // filter 中保存的是 user key, 与用户的 FilterPolicy 生成的 filter 一致
+    assert!(user_policy.key_may_match(b"hello", &filter));
+    assert!(user_policy.key_may_match(b"world", &filter));
+    let user_filter = create_filter(user_policy.as_ref().as_ref(), &[b"hello", b"world"]);
+    assert_eq!(user_filter, filter);
+}
+
+#[test]
+fn test_create_filter_append() {
+    let user_policy: FilterPolicyPtr = Arc::new(Box::new(BloomFilterPolicy::new()));
+    let policy = InternalFilterPolicy::new(user_policy.clone());
+
+    // filter 追加在 dst 已有的数据之后
+    let mut dst = b"prefix".to_vec();
+    policy.create_filter(&[&ikey("hello", 1), &ikey("hello", 2), &ikey("hello world", 3)], &mut dst);
+    assert_eq!(b"prefix", &dst[..6]);
 
+    let filter = &dst[6..];
+    assert!(policy.key_may_match(&ikey("hello", 7), filter));
+    assert!(policy.key_may_match(&ikey("hello world", 7), filter));
+    assert!(!policy.key_may_match(&ikey("helloworld", 7), filter));
+    assert_eq!(create_filter(user_policy.as_ref().as_ref(), &[b"hello", b"hello", b"hello world"]), filter);
 }
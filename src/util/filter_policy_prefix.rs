@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use crate::traits::filter_policy_trait::{FilterPolicy, FilterPolicyPtr};
+use crate::traits::slice_transform_trait::SliceTransformPtr;
+
+// #########################  PrefixFilterPolicy
+/// 包装用户的 FilterPolicy, 生成 filter 时除了 key 之外还加入 key 的前缀,
+/// 使 filter 既可以判断完整的 key, 也可以通过 prefix_may_match 判断前缀。
+///
+/// 名字中包含前缀提取器的名字: 没有保存前缀的 filter 不能用于判断前缀,
+/// 因此更换前缀提取器后, 旧 table 中的 filter 不会被使用。
+pub struct PrefixFilterPolicy {
+    policy: FilterPolicyPtr,
+    prefix_extractor: SliceTransformPtr,
+}
+
+impl PrefixFilterPolicy {
+    ///
+    /// # Arguments
+    ///
+    /// * `policy`: 用户的 FilterPolicy
+    /// * `prefix_extractor`: 前缀提取器
+    ///
+    /// returns: PrefixFilterPolicy
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use level_db_rust::traits::filter_policy_trait::FilterPolicy;
+    /// use level_db_rust::util::filter_policy_bloom::BloomFilterPolicy;
+    /// use level_db_rust::util::filter_policy_prefix::PrefixFilterPolicy;
+    /// use level_db_rust::util::slice_transform::FixedPrefixTransform;
+    ///
+    /// let policy = PrefixFilterPolicy::new(Arc::new(Box::new(BloomFilterPolicy::new())),
+    ///                                      Arc::new(Box::new(FixedPrefixTransform::new(4))));
+    /// assert_eq!("leveldb.BuiltinBloomFilter+leveldb.FixedPrefix.4", policy.name());
+    /// ```
+    pub fn new(policy: FilterPolicyPtr, prefix_extractor: SliceTransformPtr) -> Self {
+        Self {
+            policy,
+            prefix_extractor,
+        }
+    }
+
+    /// policy 及 prefix_extractor 都不为 None 时包装为 PrefixFilterPolicy, 否则返回 policy
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// options.filter_policy = PrefixFilterPolicy::wrap(options.filter_policy.take(), options.prefix_extractor.clone());
+    /// ```
+    pub fn wrap(policy: Option<FilterPolicyPtr>, prefix_extractor: Option<SliceTransformPtr>) -> Option<FilterPolicyPtr> {
+        match (policy, prefix_extractor) {
+            (Some(policy), Some(prefix_extractor)) => {
+                Some(Arc::new(Box::new(PrefixFilterPolicy::new(policy, prefix_extractor))))
+            }
+            (policy, _) => policy,
+        }
+    }
+}
+
+impl FilterPolicy for PrefixFilterPolicy {
+    fn name(&self) -> String {
+        format!("{}+{}", self.policy.name(), self.prefix_extractor.name())
+    }
+
+    fn create_filter(&self, keys: &[&[u8]], dst: &mut Vec<u8>) {
+        let mut all_keys: Vec<&[u8]> = Vec::with_capacity(keys.len() * 2);
+        let mut last_prefix: Option<&[u8]> = None;
+        for key in keys {
+            all_keys.push(key);
+            if self.prefix_extractor.in_domain(key) {
+                // keys 是有序的, 相同的前缀是连续的, 只加入一次
+                let prefix = self.prefix_extractor.transform(key);
+                if last_prefix != Some(prefix) {
+                    all_keys.push(prefix);
+                    last_prefix = Some(prefix);
+                }
+            }
+        }
+        self.policy.create_filter(&all_keys, dst);
+    }
+
+    fn key_may_match(&self, key: &[u8], filter: &[u8]) -> bool {
+        self.policy.key_may_match(key, filter)
+    }
+
+    fn prefix_may_match(&self, key: &[u8], filter: &[u8]) -> bool {
+        if !self.prefix_extractor.in_domain(key) {
+            return true;
+        }
+        self.policy.key_may_match(self.prefix_extractor.transform(key), filter)
+    }
+}
@@ -0,0 +1,70 @@
+#![cfg(test)]
+
+use std::sync::Arc;
+use crate::db::db_format::{InternalKey, ValueType};
+use crate::traits::filter_policy_trait::{FilterPolicy, FilterPolicyPtr};
+use crate::traits::slice_transform_trait::SliceTransformPtr;
+use crate::util::filter_policy_bloom::BloomFilterPolicy;
+use crate::util::filter_policy_internal::InternalFilterPolicy;
+use crate::util::filter_policy_prefix::PrefixFilterPolicy;
+use crate::util::slice::Slice;
+use crate::util::slice_transform::DelimitedPrefixTransform;
+
+fn bloom() -> FilterPolicyPtr {
+    Arc::new(Box::new(BloomFilterPolicy::new()))
+}
+
+fn tenant_prefix() -> SliceTransformPtr {
+    Arc::new(Box::new(DelimitedPrefixTransform::new(b'/', 1)))
+}
+
+// ####################  PrefixFilterPolicy test
+#[test]
+fn test_wrap() {
+    let policy = PrefixFilterPolicy::wrap(Some(bloom()), Some(tenant_prefix())).unwrap();
+    assert_eq!("leveldb.BuiltinBloomFilter+leveldb.DelimitedPrefix.47.1", policy.name());
+
+    // 没有前缀提取器时不包装
+    assert_eq!("leveldb.BuiltinBloomFilter", PrefixFilterPolicy::wrap(Some(bloom()), None).unwrap().name());
+    assert!(PrefixFilterPolicy::wrap(None, Some(tenant_prefix())).is_none());
+}
+
+#[test]
+fn test_prefix_may_match() {
+    let policy = PrefixFilterPolicy::new(bloom(), tenant_prefix());
+    let mut filter = vec![];
+    policy.create_filter(&[b"a/1", b"a/2", b"b/1", b"nodelimiter"], &mut filter);
+
+    // 完整的 key
+    assert!(policy.key_may_match(b"a/1", &filter));
+    assert!(policy.key_may_match(b"b/1", &filter));
+    assert!(policy.key_may_match(b"nodelimiter", &filter));
+    assert!(!policy.key_may_match(b"a/3", &filter));
+
+    // 前缀
+    assert!(policy.prefix_may_match(b"a/", &filter));
+    assert!(policy.prefix_may_match(b"a/zzz", &filter));
+    assert!(policy.prefix_may_match(b"b/0", &filter));
+    assert!(!policy.prefix_may_match(b"c/1", &filter));
+    assert!(!policy.prefix_may_match(b"tenant/", &filter));
+    // 没有前缀的 key 视为可能存在
+    assert!(policy.prefix_may_match(b"c", &filter));
+
+    // 没有加入前缀的 FilterPolicy 总是返回 true
+    let mut plain = vec![];
+    bloom().create_filter(&[b"a/1"], &mut plain);
+    assert!(bloom().prefix_may_match(b"c/1", &plain));
+}
+
+#[test]
+fn test_internal_prefix_may_match() {
+    let policy = InternalFilterPolicy::wrap(PrefixFilterPolicy::wrap(Some(bloom()), Some(tenant_prefix()))).unwrap();
+    let ikey = |user_key: &str| InternalKey::new(Slice::from(user_key), 7, ValueType::KTypeValue).encode().to_vec();
+
+    let mut filter = vec![];
+    policy.create_filter(&[&ikey("a/1"), &ikey("a/2"), &ikey("b/1")], &mut filter);
+    assert!(policy.key_may_match(&ikey("a/2"), &filter));
+    assert!(policy.prefix_may_match(&ikey("a/9"), &filter));
+    assert!(policy.prefix_may_match(&ikey("b/"), &filter));
+    assert!(!policy.prefix_may_match(&ikey("c/1"), &filter));
+}
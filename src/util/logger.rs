@@ -0,0 +1,139 @@
+use std::fmt::Arguments;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::traits::logger_trait::{InfoLogLevel, Logger};
+
+/// 写入运行日志, logger 为 `Option<LoggerPtr>`, 为 None 时丢弃日志
+///
+/// # Examples
+///
+/// ```ignore
+/// info_log!(self.options.info_log, InfoLogLevel::Info, "Level-0 table #{}: started", number);
+/// ```
+#[macro_export]
+macro_rules! info_log {
+    ($logger:expr, $level:expr, $($arg:tt)*) => {
+        if let Some(logger) = &$logger {
+            $crate::traits::logger_trait::Logger::log(&**logger, $level, format_args!($($arg)*));
+        }
+    };
+}
+
+/// 写入 Debug 级别的运行日志, 见 info_log!
+#[macro_export]
+macro_rules! log_debug {
+    ($logger:expr, $($arg:tt)*) => {
+        $crate::info_log!($logger, $crate::traits::logger_trait::InfoLogLevel::Debug, $($arg)*)
+    };
+}
+
+/// 写入 Info 级别的运行日志, 见 info_log!
+#[macro_export]
+macro_rules! log_info {
+    ($logger:expr, $($arg:tt)*) => {
+        $crate::info_log!($logger, $crate::traits::logger_trait::InfoLogLevel::Info, $($arg)*)
+    };
+}
+
+/// 写入 Warn 级别的运行日志, 见 info_log!
+#[macro_export]
+macro_rules! log_warn {
+    ($logger:expr, $($arg:tt)*) => {
+        $crate::info_log!($logger, $crate::traits::logger_trait::InfoLogLevel::Warn, $($arg)*)
+    };
+}
+
+/// 写入 Error 级别的运行日志, 见 info_log!
+#[macro_export]
+macro_rules! log_error {
+    ($logger:expr, $($arg:tt)*) => {
+        $crate::info_log!($logger, $crate::traits::logger_trait::InfoLogLevel::Error, $($arg)*)
+    };
+}
+
+/// 写入文件的 Logger, 由 Env::new_logger 创建, 数据库默认使用的 LOG 文件即为 FileLogger。
+///
+/// 每条日志占一行, 格式为 `时间(UTC) 线程号 [级别] 内容`, 例如
+///
+/// ```text
+/// 2024/01/02-03:04:05.123456 7 [INFO] Level-0 table #5: started
+/// ```
+///
+/// 每一行通过一次 write 写入文件, 多个线程同时写入时行之间不会交错
+pub struct FileLogger {
+    file: Mutex<File>,
+    level: InfoLogLevel,
+}
+
+impl FileLogger {
+    /// 创建 FileLogger, 低于 level 的日志会被丢弃
+    ///
+    /// # Arguments
+    ///
+    /// * `file`: 写入的文件
+    /// * `level`: 写入的最低日志级别
+    ///
+    /// returns: FileLogger
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let logger = FileLogger::new(env.new_writable_file(&fname)?, InfoLogLevel::Info);
+    /// ```
+    pub fn new(file: File, level: InfoLogLevel) -> Self {
+        Self {
+            file: Mutex::new(file),
+            level,
+        }
+    }
+}
+
+impl Logger for FileLogger {
+    fn log(&self, level: InfoLogLevel, args: Arguments) {
+        if level < self.level {
+            return;
+        }
+        let thread_id = format!("{:?}", thread::current().id());
+        let thread_id = thread_id.trim_start_matches("ThreadId(").trim_end_matches(')');
+        let mut line = format!("{} {} [{}] {}", format_time(SystemTime::now()), thread_id, level, args);
+        // Add newline if necessary
+        if !line.ends_with('\n') {
+            line.push('\n');
+        }
+        // 写入失败时没有其他地方可以报告, 忽略错误
+        let _ = self.file.lock().unwrap().write_all(line.as_bytes());
+    }
+}
+
+/// 将时间格式化为 UTC 的 `yyyy/MM/dd-HH:mm:ss.micros`
+pub(crate) fn format_time(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let secs_of_day = secs % 86400;
+    format!("{:04}/{:02}/{:02}-{:02}:{:02}:{:02}.{:06}", year, month, day,
+            secs_of_day / 3600, secs_of_day / 60 % 60, secs_of_day % 60, since_epoch.subsec_micros())
+}
+
+/// 将 1970-01-01 之后的天数转换为 (年, 月, 日)
+///
+/// 算法见 <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    // [0, 146096]
+    let doe = z.rem_euclid(146097);
+    // [0, 399]
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    // [0, 365]
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    // [0, 11], 从三月开始
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::time::{Duration, UNIX_EPOCH};
+    use crate::traits::logger_trait::{InfoLogLevel, LoggerPtr};
+    use crate::util::env::Env;
+    use crate::util::logger::format_time;
+    use crate::util::Result;
+    use crate::{log_debug, log_error, log_info, log_warn};
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!("1970/01/01-00:00:00.000000", format_time(UNIX_EPOCH));
+        assert_eq!("2023/11/14-22:13:20.123456",
+                   format_time(UNIX_EPOCH + Duration::from_micros(1700000000_123456)));
+        // 闰年的 2 月 29 日
+        assert_eq!("2024/02/29-01:02:03.000007",
+                   format_time(UNIX_EPOCH + Duration::from_micros(1709168523_000007)));
+    }
+
+    #[test]
+    fn test_file_logger() -> Result<()> {
+        let dir = std::env::temp_dir().join("level_db_rust_file_logger_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        let fname = dir.join("LOG");
+        let env = Env {};
+
+        let logger = Some(env.new_logger(&fname, InfoLogLevel::Warn)?);
+        // 低于 Warn 的日志被丢弃
+        log_debug!(logger, "debug {}", 1);
+        log_info!(logger, "info {}", 2);
+        log_warn!(logger, "warn {}", 3);
+        log_error!(logger, "error {}\n", 4);
+        let none: Option<LoggerPtr> = None;
+        log_error!(none, "dropped");
+
+        let contents = fs::read_to_string(&fname)?;
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(2, lines.len(), "{}", contents);
+        assert!(contents.ends_with('\n'));
+        // 时间 线程号 [级别] 内容
+        let fields: Vec<&str> = lines[0].splitn(3, ' ').collect();
+        assert_eq!("2026/01/01-00:00:00.000000".len(), fields[0].len(), "{}", lines[0]);
+        assert!(fields[1].parse::<u64>().is_ok(), "{}", lines[0]);
+        assert_eq!("[WARN] warn 3", fields[2]);
+        assert!(lines[1].ends_with(" [ERROR] error 4"), "{}", lines[1]);
+
+        // 重新创建时清空原有内容
+        drop(logger);
+        let logger = Some(env.new_logger(&fname, InfoLogLevel::Debug)?);
+        log_debug!(logger, "debug {}", 5);
+        let contents = fs::read_to_string(&fname)?;
+        assert_eq!(1, contents.lines().count(), "{}", contents);
+        assert!(contents.ends_with(" [DEBUG] debug 5\n"), "{}", contents);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
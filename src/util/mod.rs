@@ -21,14 +21,26 @@ pub mod comparator;
 mod comparator_test;
 pub mod crc;
 mod crc_test;
+pub mod snappy;
+mod snappy_test;
+pub mod compression;
+mod compression_test;
 // pub mod bloom_filter;
 // mod bloom_filter_test;
 pub mod filter_policy;
 mod filter_policy_test;
 pub mod filter_policy_bloom;
 mod filter_policy_bloom_test;
+pub mod filter_policy_blocked_bloom;
+mod filter_policy_blocked_bloom_test;
+pub mod filter_policy_binary_fuse;
+mod filter_policy_binary_fuse_test;
 pub mod filter_policy_internal;
 mod filter_policy_internal_test;
+pub mod filter_policy_prefix;
+mod filter_policy_prefix_test;
+pub mod slice_transform;
+mod slice_transform_test;
 
 pub mod histogram;
 mod histogram_test;
@@ -45,6 +57,8 @@ mod linked_list_test;
 pub mod unsafe_slice;
 pub mod env;
 mod env_test;
+pub mod logger;
+mod logger_test;
 pub mod mem_debug;
 // pub mod error;
 // mod error_test;
@@ -1,20 +1,88 @@
 use std::sync::Arc;
 use crate::db::db::Snapshot;
-use crate::db::db_format::InternalKeyComparator;
+use crate::table::block::Block;
 use crate::traits::comparator_trait::Comparator;
 use crate::traits::filter_policy_trait::{FilterPolicy, FilterPolicyPtr};
+use crate::traits::logger_trait::{InfoLogLevel, LoggerPtr};
+use crate::traits::slice_transform_trait::SliceTransformPtr;
+use crate::traits::table_properties_collector_trait::TablePropertiesCollectorFactory;
 use crate::util::comparator::BytewiseComparatorImpl;
+use crate::util::cache::ShardLRUCache;
 use crate::util::env::Env;
+use crate::util::slice::Slice;
+use crate::util::status::{LevelError, Status};
 
 /// Options 的 `Arc<Box<Options>>` 别名
 pub type OptionsPtr = Arc<Box<Options>>;
 
+/// block 的压缩类型, 其值会写入 block 的 trailer 中, 因此不能修改已有的值。
+/// Zstd 使用与 LevelDB 相同的值, LevelDB 没有 LZ4, 使用与 RocksDB 相同的值
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum CompressionType {
-    NoCompression,
-    SnappyCompression
+    NoCompression = 0,
+    SnappyCompression = 1,
+    ZstdCompression = 2,
+    LZ4Compression = 4,
 }
 
-pub struct Cache {}
+impl TryFrom<u8> for CompressionType {
+    type Error = Status;
+
+    /// 将 block trailer 中的类型转换为 CompressionType, 未知的类型返回 NotSupported
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: trailer 中的类型
+    ///
+    /// returns: Result<CompressionType, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::options::CompressionType;
+    ///
+    /// assert_eq!(CompressionType::SnappyCompression, CompressionType::try_from(1).unwrap());
+    /// assert!(CompressionType::try_from(3).is_err());
+    /// ```
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(CompressionType::NoCompression),
+            1 => Ok(CompressionType::SnappyCompression),
+            2 => Ok(CompressionType::ZstdCompression),
+            4 => Ok(CompressionType::LZ4Compression),
+            _ => Err(LevelError::not_supported(Slice::from("unsupported block compression type"),
+                                               Slice::from(value.to_string()))),
+        }
+    }
+}
+
+/// block trailer 中的校验算法, 其值会写入 Footer 中, 因此不能修改已有的值。
+/// 默认为 LevelDB 的 CRC32C, 旧的 table 的 Footer 中该位置为 0, 即 Crc32c
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ChecksumType {
+    /// masked CRC32C
+    #[default]
+    Crc32c = 0,
+    /// xxHash64 (seed 为 0) 的低 32 位
+    XxHash64 = 1,
+}
+
+impl TryFrom<u8> for ChecksumType {
+    type Error = Status;
+
+    /// 将 Footer 中的校验类型转换为 ChecksumType, 未知的类型返回 NotSupported
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ChecksumType::Crc32c),
+            1 => Ok(ChecksumType::XxHash64),
+            _ => Err(LevelError::not_supported(Slice::from("unsupported block checksum type"),
+                                               Slice::from(value.to_string()))),
+        }
+    }
+}
+
+/// block 缓存, 以 block 的大小作为 charge
+pub type Cache = ShardLRUCache<Block>;
 
 // 使用如下定义（后续路径会重构）
 // use crate::traits::filter_policy_trait::FilterPolicy;
@@ -31,7 +99,7 @@ pub struct Options {
     /// REQUIRES: The client must ensure that the comparator supplied
     /// here has the same name and orders keys *exactly* the same as the
     /// comparator provided to previous open calls on the same DB.
-    pub cmp: Box<dyn Comparator>,
+    pub cmp: Arc<dyn Comparator>,
     /// If true, the database will be created if it is missing.
     pub create_if_missing: bool,
     /// If true, an error is raised if the database already exists.
@@ -46,6 +114,19 @@ pub struct Options {
     /// e.g. to read/write files, schedule background work, etc.
     /// Default: Env::Default()
     pub env: Env,
+    /// Any internal progress/error information generated by the db will
+    /// be written to info_log if it is non-null, or to a file stored
+    /// in the same directory as the DB contents if info_log is null.
+    ///
+    /// 数据库目录下的 LOG 会在打开时被重命名为 LOG.old
+    ///
+    /// Default: None
+    pub info_log: Option<LoggerPtr>,
+    /// 写入数据库目录下 LOG 文件的最低日志级别, 对自定义的 info_log 无效,
+    /// 自定义的 Logger 自行决定是否丢弃日志
+    ///
+    /// Default: InfoLogLevel::Info
+    pub info_log_level: InfoLogLevel,
 
     /// Amount of data to build up in memory (backed by an unsorted log
     /// on disk) before converting to a sorted on-disk file.
@@ -64,7 +145,7 @@ pub struct Options {
 
     /// If non-null, use the specified cache for blocks.
     /// If null, leveldb will automatically create and use an 8MB internal cache.
-    pub block_cache: Option<Cache>,
+    pub block_cache: Option<Arc<Cache>>,
 
     /// Approximate size of user data packed per block.  Note that the
     /// block size specified here corresponds to uncompressed data.  The
@@ -84,11 +165,14 @@ pub struct Options {
     /// Another reason to increase this parameter might be when you are
     /// initially populating a large database.
     pub max_file_size: usize,
+    /// block 的压缩类型, 默认不压缩
+    ///
     /// Compress blocks using the specified compression algorithm.  This
     /// parameter can be changed dynamically.
-    ///     /// Default: kSnappyCompression, which gives lightweight but fast
-    /// compression.
-    ///     /// Typical speeds of kSnappyCompression on an Intel(R) Core(TM)2 2.4GHz:
+    ///
+    /// Default: NoCompression
+    ///
+    /// Typical speeds of kSnappyCompression on an Intel(R) Core(TM)2 2.4GHz:
     ///    ~200-500MB/s compression
     ///    ~400-800MB/s decompression
     /// Note that these speeds are significantly faster than most
@@ -97,6 +181,22 @@ pub struct Options {
     /// incompressible, the kSnappyCompression implementation will
     /// efficiently detect that and will switch to uncompressed mode.
     pub compression: CompressionType,
+    /// 每一层使用的压缩类型, 不为空时代替 compression。
+    /// level 超过 vec 的长度时使用最后一个元素, 例如
+    /// `[NoCompression, NoCompression, LZ4Compression, LZ4Compression, LZ4Compression, ZstdCompression]`
+    /// 表示 L0~L1 不压缩, L2~L4 使用 LZ4, L5~L6 使用 Zstd。
+    /// memtable 落盘生成的 table 使用其写入的层 (可能直接写入 level 1 或 level 2) 的压缩类型
+    ///
+    /// Default: empty
+    pub compression_per_level: Vec<CompressionType>,
+    /// Zstd 的压缩级别, 为 None 时使用 Zstd 的默认级别
+    ///
+    /// Default: None
+    pub zstd_level: Option<i32>,
+    /// Zstd 压缩及解压使用的字典, 读取 table 时必须使用与写入时相同的字典
+    ///
+    /// Default: None
+    pub zstd_dictionary: Option<Vec<u8>>,
     /// EXPERIMENTAL: If true, append to existing MANIFEST and log files
     /// when a database is opened.  This can significantly speed up open.
     ///     /// Default: currently false, but may become true later.
@@ -105,6 +205,28 @@ pub struct Options {
     /// Many applications will benefit from passing the result of
     /// NewBloomFilterPolicy() here.
     pub filter_policy: Option<FilterPolicyPtr>,
+    /// 为整个 table 的所有 key 生成一个 filter (meta block 名为 "fullfilter.<policy name>"),
+    /// 代替每 2KB data block 一个 filter, 查找时不需要读取 index block 就可以排除整个 table。
+    /// 两种方式生成的 table 在任意设置下都可以读取
+    ///
+    /// Default: false
+    pub full_filter: bool,
+    /// 前缀提取器, 不为空时每个 key 的前缀也会加入 filter,
+    /// 使用 ReadOptions::prefix_same_as_start 的迭代器可以跳过 filter 中没有 seek 目标前缀的 table 及 block。
+    /// 需要同时设置 filter_policy
+    ///
+    /// Default: None
+    pub prefix_extractor: Option<SliceTransformPtr>,
+    /// TableBuilder 写入的 block trailer 使用的校验算法。
+    /// 算法记录在 table 的 footer 中, 读取时总是使用写入时的算法校验
+    ///
+    /// Default: ChecksumType::Crc32c
+    pub checksum: ChecksumType,
+    /// 用户自定义属性收集器的工厂, 生成每个 table 时由每个工厂创建新的收集器,
+    /// 收集到的属性保存在 table 的 properties block 中
+    ///
+    /// Default: empty
+    pub table_properties_collectors: Vec<TablePropertiesCollectorFactory>,
 }
 /// Options that control read operations
 pub struct ReadOptions {
@@ -119,6 +241,13 @@ pub struct ReadOptions {
     /// not have been released).  If "snapshot" is null, use an implicit
     /// snapshot of the state at the beginning of this read operation.
     pub snapshot: Option<Box<dyn Snapshot>>,
+    /// 为 true 时, seek(target) 之后的迭代器只返回与 target 前缀相同的 key (参见 Options::prefix_extractor),
+    /// 离开该前缀后迭代器变为无效, filter 中没有该前缀的 table 及 block 不会被读取。
+    /// seek_to_first/seek_to_last 仍然按完整的顺序迭代。
+    /// Options::prefix_extractor 为 None 时无效
+    ///
+    /// Default: false
+    pub prefix_same_as_start: bool,
 }
 
 /// Options that control write operations
@@ -141,11 +270,13 @@ pub struct WriteOptions {
 impl Default for Options {
     fn default() -> Self {
         Self {
-            cmp: Box::new(InternalKeyComparator::default()),
+            cmp: Arc::new(BytewiseComparatorImpl::default()),
             create_if_missing: false,
             error_if_exists: false,
             paranoid_checks: false,
             env: Env {},
+            info_log: None,
+            info_log_level: InfoLogLevel::Info,
             write_buffer_size: 4 * 1024 * 1024,
             max_open_files: 1000,
             block_cache: None,
@@ -153,8 +284,43 @@ impl Default for Options {
             block_restart_interval: 16,
             max_file_size: 2 * 1024 * 1024,
             compression: CompressionType::NoCompression,
+            compression_per_level: vec![],
+            zstd_level: None,
+            zstd_dictionary: None,
             reuse_logs: false,
-            filter_policy: None
+            filter_policy: None,
+            full_filter: false,
+            prefix_extractor: None,
+            checksum: ChecksumType::Crc32c,
+            table_properties_collectors: vec![],
+        }
+    }
+}
+
+impl Options {
+    /// 写入 level 层的 table 使用的压缩类型
+    ///
+    /// # Arguments
+    ///
+    /// * `level`: table 所在的层
+    ///
+    /// returns: CompressionType
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::options::{CompressionType, Options};
+    ///
+    /// let mut options = Options::default();
+    /// options.compression_per_level = vec![CompressionType::NoCompression, CompressionType::SnappyCompression];
+    /// assert_eq!(CompressionType::NoCompression, options.compression_for_level(0));
+    /// // 超出 compression_per_level 的层使用最后一个
+    /// assert_eq!(CompressionType::SnappyCompression, options.compression_for_level(3));
+    /// ```
+    pub fn compression_for_level(&self, level: usize) -> CompressionType {
+        match self.compression_per_level.last() {
+            None => self.compression,
+            Some(last) => *self.compression_per_level.get(level).unwrap_or(last),
         }
     }
 }
@@ -164,7 +330,8 @@ impl Default for ReadOptions {
         Self {
             verify_checksums: false,
             fill_cache: true,
-            snapshot: None
+            snapshot: None,
+            prefix_same_as_start: false,
         }
     }
 }
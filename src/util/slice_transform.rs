@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use crate::db::db_format::ParsedInternalKey;
+use crate::traits::slice_transform_trait::{SliceTransform, SliceTransformPtr};
+
+// #########################  FixedPrefixTransform
+/// 取 key 的前 prefix_len 个字节作为前缀, 长度不足的 key 没有前缀
+pub struct FixedPrefixTransform {
+    prefix_len: usize,
+}
+
+impl FixedPrefixTransform {
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::slice_transform::FixedPrefixTransform;
+    ///
+    /// let transform = FixedPrefixTransform::new(8);
+    /// ```
+    pub fn new(prefix_len: usize) -> Self {
+        Self {
+            prefix_len
+        }
+    }
+}
+
+impl SliceTransform for FixedPrefixTransform {
+    fn name(&self) -> String {
+        format!("leveldb.FixedPrefix.{}", self.prefix_len)
+    }
+
+    fn transform<'a>(&self, key: &'a [u8]) -> &'a [u8] {
+        assert!(self.in_domain(key));
+        &key[..self.prefix_len]
+    }
+
+    fn in_domain(&self, key: &[u8]) -> bool {
+        key.len() >= self.prefix_len
+    }
+}
+
+// #########################  DelimitedPrefixTransform
+/// 取 key 中到第 count 个分隔符 (包含分隔符) 为止的部分作为前缀, 分隔符不足 count 个的 key 没有前缀。
+///
+/// 例如 key 为 `tenant/entity/...` 时, 分隔符为 `/`, count 为 1 得到 `tenant/`, count 为 2 得到 `tenant/entity/`
+pub struct DelimitedPrefixTransform {
+    delimiter: u8,
+    count: usize,
+}
+
+impl DelimitedPrefixTransform {
+    ///
+    /// # Arguments
+    ///
+    /// * `delimiter`: 分隔符
+    /// * `count`: 前缀中分隔符的个数, 至少为 1
+    ///
+    /// returns: DelimitedPrefixTransform
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::slice_transform::DelimitedPrefixTransform;
+    ///
+    /// // "tenant/"
+    /// let transform = DelimitedPrefixTransform::new(b'/', 1);
+    /// ```
+    pub fn new(delimiter: u8, count: usize) -> Self {
+        Self {
+            delimiter,
+            count: count.max(1),
+        }
+    }
+
+    /// 第 count 个分隔符之后的位置, 分隔符不足时返回 None
+    fn prefix_len(&self, key: &[u8]) -> Option<usize> {
+        key.iter()
+            .enumerate()
+            .filter(|(_, b)| **b == self.delimiter)
+            .nth(self.count - 1)
+            .map(|(i, _)| i + 1)
+    }
+}
+
+impl SliceTransform for DelimitedPrefixTransform {
+    fn name(&self) -> String {
+        format!("leveldb.DelimitedPrefix.{}.{}", self.delimiter, self.count)
+    }
+
+    fn transform<'a>(&self, key: &'a [u8]) -> &'a [u8] {
+        let len = self.prefix_len(key).expect("key is not in domain");
+        &key[..len]
+    }
+
+    fn in_domain(&self, key: &[u8]) -> bool {
+        self.prefix_len(key).is_some()
+    }
+}
+
+// #########################  InternalSliceTransform
+/// 包装用户的 SliceTransform, 使其可以处理 internal key。
+///
+/// 与 InternalFilterPolicy 相同, 先去掉 internal key 末尾的 8 字节, 再提取 user key 的前缀
+pub struct InternalSliceTransform {
+    user_transform: SliceTransformPtr,
+}
+
+impl InternalSliceTransform {
+    pub fn new(transform: SliceTransformPtr) -> Self {
+        Self {
+            user_transform: transform
+        }
+    }
+
+    /// 包装用户的 SliceTransform, 返回 SliceTransformPtr。 transform 为 None 时返回 None
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// options.prefix_extractor = InternalSliceTransform::wrap(options.prefix_extractor.take());
+    /// ```
+    pub fn wrap(transform: Option<SliceTransformPtr>) -> Option<SliceTransformPtr> {
+        transform.map(|t| -> SliceTransformPtr { Arc::new(Box::new(InternalSliceTransform::new(t))) })
+    }
+}
+
+impl SliceTransform for InternalSliceTransform {
+    fn name(&self) -> String {
+        self.user_transform.name()
+    }
+
+    fn transform<'a>(&self, key: &'a [u8]) -> &'a [u8] {
+        self.user_transform.transform(ParsedInternalKey::extract_user_key(key))
+    }
+
+    fn in_domain(&self, key: &[u8]) -> bool {
+        self.user_transform.in_domain(ParsedInternalKey::extract_user_key(key))
+    }
+}
@@ -0,0 +1,54 @@
+#![cfg(test)]
+
+use std::sync::Arc;
+use crate::db::db_format::{InternalKey, ValueType};
+use crate::traits::slice_transform_trait::{SliceTransform, SliceTransformPtr};
+use crate::util::slice::Slice;
+use crate::util::slice_transform::{DelimitedPrefixTransform, FixedPrefixTransform, InternalSliceTransform};
+
+// ####################  FixedPrefixTransform test
+#[test]
+fn test_fixed_prefix() {
+    let transform = FixedPrefixTransform::new(3);
+    assert_eq!("leveldb.FixedPrefix.3", transform.name());
+    assert!(transform.in_domain(b"abc"));
+    assert!(transform.in_domain(b"abcdef"));
+    assert!(!transform.in_domain(b"ab"));
+    assert_eq!(b"abc", transform.transform(b"abc"));
+    assert_eq!(b"abc", transform.transform(b"abcdef"));
+}
+
+// ####################  DelimitedPrefixTransform test
+#[test]
+fn test_delimited_prefix() {
+    let tenant = DelimitedPrefixTransform::new(b'/', 1);
+    assert_eq!(b"tenant/", tenant.transform(b"tenant/entity/1"));
+    assert_eq!(b"tenant/", tenant.transform(b"tenant/"));
+    assert!(!tenant.in_domain(b"tenant"));
+    assert!(!tenant.in_domain(b""));
+
+    let entity = DelimitedPrefixTransform::new(b'/', 2);
+    assert_eq!(b"tenant/entity/", entity.transform(b"tenant/entity/1"));
+    assert!(!entity.in_domain(b"tenant/entity"));
+    assert_ne!(tenant.name(), entity.name());
+
+    // count 至少为 1
+    assert_eq!(b"a/", DelimitedPrefixTransform::new(b'/', 0).transform(b"a/b"));
+}
+
+// ####################  InternalSliceTransform test
+#[test]
+fn test_internal_transform() {
+    let user_transform: SliceTransformPtr = Arc::new(Box::new(DelimitedPrefixTransform::new(b'/', 1)));
+    let transform = InternalSliceTransform::wrap(Some(user_transform.clone())).unwrap();
+    assert_eq!(user_transform.name(), transform.name());
+    assert!(InternalSliceTransform::wrap(None).is_none());
+
+    // 去掉 internal key 末尾的 8 字节后再提取前缀
+    let key = InternalKey::new(Slice::from("tenant/entity"), 100, ValueType::KTypeValue);
+    assert!(transform.in_domain(key.encode()));
+    assert_eq!(b"tenant/", transform.transform(key.encode()));
+
+    let key = InternalKey::new(Slice::from("tenant"), 0x2f2f2f, ValueType::KTypeValue);
+    assert!(!transform.in_domain(key.encode()));
+}
@@ -0,0 +1,318 @@
+use crate::util::coding::Encoder;
+use crate::util::Result;
+use crate::util::status::{LevelError, Status};
+
+/// 压缩时按 64KB 分块处理, copy 的 offset 不会超过一个块, 哈希表中可以只保存 u16 的块内偏移
+const K_BLOCK_SIZE: usize = 1 << 16;
+const K_MAX_HASH_TABLE_BITS: usize = 14;
+const K_MAX_HASH_TABLE_SIZE: usize = 1 << K_MAX_HASH_TABLE_BITS;
+const K_MIN_HASH_TABLE_SIZE: usize = 1 << 8;
+/// 距离块末尾不足 15 字节时不再查找匹配, 保证查找时可以安全地读取 4 个字节
+const K_INPUT_MARGIN_BYTES: usize = 15;
+
+/// element 的类型, 保存在 tag 的低 2 位
+const LITERAL: u8 = 0;
+const COPY_1_BYTE_OFFSET: u8 = 1;
+const COPY_2_BYTE_OFFSET: u8 = 2;
+const COPY_4_BYTE_OFFSET: u8 = 3;
+
+/// Snappy raw 格式的压缩与解压, 与 C++ snappy::RawCompress / RawUncompress 的格式兼容
+///
+/// 压缩数据以 varint32 编码的原始长度开头, 之后是若干 element, 每个 element 以一个 tag 字节开始:
+///    tag & 3 == 0: literal, 长度 - 1 保存在 tag 的高 6 位, 大于等于 60 时表示之后 1~4 个字节保存长度 - 1
+///    tag & 3 == 1: copy, 长度 4~11, offset 为 11 位, 高 3 位保存在 tag 中, 低 8 位在之后的 1 个字节
+///    tag & 3 == 2: copy, 长度 1~64 保存在 tag 的高 6 位, offset 为之后的 2 个字节
+///    tag & 3 == 3: copy, 长度 1~64 保存在 tag 的高 6 位, offset 为之后的 4 个字节
+pub struct Snappy {}
+
+impl Snappy {
+    /// 压缩后数据的最大长度
+    pub fn max_compressed_length(source_len: usize) -> usize {
+        32 + source_len + source_len / 6
+    }
+
+    /// 压缩数据
+    ///
+    /// # Arguments
+    ///
+    /// * `input`: 原始数据
+    ///
+    /// returns: Vec<u8>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::snappy::Snappy;
+    /// let compressed = Snappy::compress(b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+    /// assert_eq!(b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_vec(), Snappy::decompress(&compressed).unwrap());
+    /// ```
+    pub fn compress(input: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::max_compressed_length(input.len()));
+        Encoder::with_vec_append(&mut out).put_varint32(input.len() as u32).expect("put varint32");
+
+        let mut table = vec![0_u16; K_MAX_HASH_TABLE_SIZE];
+        for block in input.chunks(K_BLOCK_SIZE) {
+            let table_size = hash_table_size(block.len());
+            let table = &mut table[..table_size];
+            table.fill(0);
+            compress_block(block, table, &mut out);
+        }
+        out
+    }
+
+    /// 读取压缩数据中保存的原始数据长度
+    ///
+    /// # Arguments
+    ///
+    /// * `compressed`: 压缩数据
+    ///
+    /// returns: Result<usize, Status>
+    pub fn uncompressed_length(compressed: &[u8]) -> Result<usize> {
+        parse_varint32(compressed).map(|(len, _)| len as usize)
+    }
+
+    /// 解压数据, 数据损坏时返回 Corruption
+    ///
+    /// # Arguments
+    ///
+    /// * `compressed`: 压缩数据
+    ///
+    /// returns: Result<Vec<u8>, Status>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use level_db_rust::util::snappy::Snappy;
+    /// let data = Snappy::decompress(&Snappy::compress(b"hello")).unwrap();
+    /// assert_eq!(b"hello".to_vec(), data);
+    /// ```
+    pub fn decompress(compressed: &[u8]) -> Result<Vec<u8>> {
+        let (len, mut pos) = parse_varint32(compressed)?;
+        let len = len as usize;
+        // 原始长度来自输入数据, 数据损坏时可能很大, 预分配的空间不超过合法数据的最大压缩比
+        let mut out: Vec<u8> = Vec::with_capacity(len.min(compressed.len().saturating_mul(32)));
+
+        while pos < compressed.len() {
+            let tag = compressed[pos];
+            pos += 1;
+            if tag & 3 == LITERAL {
+                let mut literal_len = (tag >> 2) as usize;
+                if literal_len >= 60 {
+                    let bytes = literal_len - 59;
+                    literal_len = read_le(compressed, pos, bytes)?;
+                    pos += bytes;
+                }
+                literal_len += 1;
+                if compressed.len() - pos < literal_len || len - out.len() < literal_len {
+                    return Err(corruption("literal out of range"));
+                }
+                out.extend_from_slice(&compressed[pos..pos + literal_len]);
+                pos += literal_len;
+                continue;
+            }
+
+            let (copy_len, offset) = match tag & 3 {
+                COPY_1_BYTE_OFFSET => {
+                    let offset = ((tag as usize >> 5) << 8) | read_le(compressed, pos, 1)?;
+                    pos += 1;
+                    (((tag >> 2) & 7) as usize + 4, offset)
+                }
+                COPY_2_BYTE_OFFSET => {
+                    let offset = read_le(compressed, pos, 2)?;
+                    pos += 2;
+                    ((tag >> 2) as usize + 1, offset)
+                }
+                _ => {
+                    debug_assert_eq!(COPY_4_BYTE_OFFSET, tag & 3);
+                    let offset = read_le(compressed, pos, 4)?;
+                    pos += 4;
+                    ((tag >> 2) as usize + 1, offset)
+                }
+            };
+            if offset == 0 || offset > out.len() || len - out.len() < copy_len {
+                return Err(corruption("copy out of range"));
+            }
+            let start = out.len() - offset;
+            if offset >= copy_len {
+                out.extend_from_within(start..start + copy_len);
+            } else {
+                // 源与目标重叠, 例如 offset 为 1 时重复最后一个字节, 需要逐字节复制
+                for i in 0..copy_len {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+
+        if out.len() != len {
+            return Err(corruption("uncompressed length mismatch"));
+        }
+        Ok(out)
+    }
+}
+
+fn corruption(msg: &str) -> Status {
+    LevelError::corruption_string("corrupted snappy data", msg)
+}
+
+/// 解析开头的 varint32, 返回值与其占用的字节数
+fn parse_varint32(data: &[u8]) -> Result<(u32, usize)> {
+    let mut value: u32 = 0;
+    for (i, &byte) in data.iter().take(5).enumerate() {
+        let bits = (byte & 0x7f) as u32;
+        if i == 4 && bits > 0x0f {
+            break;
+        }
+        value |= bits << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    Err(corruption("bad uncompressed length"))
+}
+
+/// 读取 n 个字节的小端整数
+fn read_le(data: &[u8], pos: usize, n: usize) -> Result<usize> {
+    if data.len() < pos + n {
+        return Err(corruption("truncated element"));
+    }
+    Ok(data[pos..pos + n].iter().rev().fold(0, |acc, &b| (acc << 8) | b as usize))
+}
+
+/// 哈希表大小为不小于块大小的 2 的幂, 取值范围为 [256, 16384]
+fn hash_table_size(input_len: usize) -> usize {
+    input_len.next_power_of_two().clamp(K_MIN_HASH_TABLE_SIZE, K_MAX_HASH_TABLE_SIZE)
+}
+
+#[inline]
+fn load32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+#[inline]
+fn hash(bytes: u32, shift: u32) -> usize {
+    (bytes.wrapping_mul(0x1e35a7bd) >> shift) as usize
+}
+
+/// 压缩一个不超过 K_BLOCK_SIZE 的块
+///
+/// 以 4 个字节的哈希查找之前出现过的相同数据, 找到时输出之前未匹配的 literal 及 copy,
+/// 连续未找到匹配时逐渐增大步长, 以加快不可压缩数据的处理
+fn compress_block(input: &[u8], table: &mut [u16], out: &mut Vec<u8>) {
+    let len = input.len();
+    let mut next_emit = 0;
+    if len >= K_INPUT_MARGIN_BYTES {
+        let shift = 32 - table.len().trailing_zeros();
+        let ip_limit = len - K_INPUT_MARGIN_BYTES;
+        let mut ip = 1;
+        let mut next_hash = hash(load32(input, ip), shift);
+
+        'outer: loop {
+            // Heuristic match skipping: If 32 bytes are scanned with no matches
+            // found, start looking only at every other byte. If 32 more bytes are
+            // scanned, look at every third byte, etc.. When a match is found,
+            // immediately go back to looking at every byte.
+            let mut skip = 32;
+            let mut next_ip = ip;
+            let mut candidate;
+            loop {
+                ip = next_ip;
+                let h = next_hash;
+                let bytes_between_hash_lookups = skip >> 5;
+                skip += 1;
+                next_ip = ip + bytes_between_hash_lookups;
+                if next_ip > ip_limit {
+                    break 'outer;
+                }
+                next_hash = hash(load32(input, next_ip), shift);
+                candidate = table[h] as usize;
+                table[h] = ip as u16;
+                if load32(input, ip) == load32(input, candidate) {
+                    break;
+                }
+            }
+
+            // Step 2: A 4-byte match has been found.  We'll later see if more
+            // than 4 bytes match.  But, prior to the match, input
+            // bytes [next_emit, ip) are unmatched.  Emit them as "literal bytes."
+            emit_literal(&input[next_emit..ip], out);
+
+            // Step 3: Call EmitCopy, and then see if another EmitCopy could
+            // be our next move.  Repeat until we find no match for the
+            // input immediately after what was consumed by the last EmitCopy call.
+            loop {
+                let base = ip;
+                let matched = 4 + find_match_length(input, candidate + 4, ip + 4);
+                ip += matched;
+                emit_copy(base - candidate, matched, out);
+                next_emit = ip;
+                if ip >= ip_limit {
+                    break 'outer;
+                }
+                // We could immediately start working at ip now, but to improve
+                // compression we first update table[Hash(ip - 1, ...)].
+                table[hash(load32(input, ip - 1), shift)] = (ip - 1) as u16;
+                let h = hash(load32(input, ip), shift);
+                candidate = table[h] as usize;
+                table[h] = ip as u16;
+                if load32(input, ip) != load32(input, candidate) {
+                    break;
+                }
+            }
+
+            ip += 1;
+            next_hash = hash(load32(input, ip), shift);
+        }
+    }
+
+    // Emit the remaining bytes as a literal
+    if next_emit < len {
+        emit_literal(&input[next_emit..], out);
+    }
+}
+
+/// 从 s1、s2 开始相同的字节数, s1 < s2
+#[inline]
+fn find_match_length(input: &[u8], s1: usize, s2: usize) -> usize {
+    input[s2..].iter().zip(&input[s1..]).take_while(|(a, b)| a == b).count()
+}
+
+fn emit_literal(literal: &[u8], out: &mut Vec<u8>) {
+    let n = literal.len() - 1;
+    if n < 60 {
+        out.push(LITERAL | ((n as u8) << 2));
+    } else {
+        let bytes = (usize::BITS - n.leading_zeros()).div_ceil(8) as usize;
+        out.push(LITERAL | (((59 + bytes) as u8) << 2));
+        out.extend_from_slice(&(n as u32).to_le_bytes()[..bytes]);
+    }
+    out.extend_from_slice(literal);
+}
+
+fn emit_copy(offset: usize, mut len: usize, out: &mut Vec<u8>) {
+    // A special case for len <= 64 might help, but so far measurements suggest
+    // it's in the noise.
+
+    // Emit 64 byte copies but make sure to keep at least four bytes reserved.
+    while len >= 68 {
+        emit_copy_upto64(offset, 64, out);
+        len -= 64;
+    }
+    // Emit an extra 60 byte copy if have too much data to fit in one copy.
+    if len > 64 {
+        emit_copy_upto64(offset, 60, out);
+        len -= 60;
+    }
+    emit_copy_upto64(offset, len, out);
+}
+
+fn emit_copy_upto64(offset: usize, len: usize, out: &mut Vec<u8>) {
+    debug_assert!((4..=64).contains(&len));
+    debug_assert!(offset < K_BLOCK_SIZE);
+    if len < 12 && offset < 2048 {
+        out.push(COPY_1_BYTE_OFFSET | (((len - 4) as u8) << 2) | (((offset >> 8) as u8) << 5));
+        out.push(offset as u8);
+    } else {
+        out.push(COPY_2_BYTE_OFFSET | (((len - 1) as u8) << 2));
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+    }
+}
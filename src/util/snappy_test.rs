@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::{Rng, RngCore, SeedableRng};
+    use crate::util::snappy::Snappy;
+
+    fn round_trip(input: &[u8]) -> Vec<u8> {
+        let compressed = Snappy::compress(input);
+        assert!(compressed.len() <= Snappy::max_compressed_length(input.len()));
+        assert_eq!(input.len(), Snappy::uncompressed_length(&compressed).unwrap());
+        assert_eq!(input, Snappy::decompress(&compressed).unwrap().as_slice());
+        compressed
+    }
+
+    fn random_bytes(rnd: &mut StdRng, len: usize) -> Vec<u8> {
+        let mut data = vec![0; len];
+        rnd.fill_bytes(&mut data);
+        data
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(b"");
+        round_trip(b"a");
+        round_trip(b"hello world, hello world, hello world");
+
+        let mut rnd = StdRng::seed_from_u64(301);
+        for len in [1, 14, 15, 16, 59, 60, 61, 255, 256, 257, 4095, 65535, 65536, 65537, 200000] {
+            round_trip(&random_bytes(&mut rnd, len));
+            // 可压缩的数据: 由少量字符组成
+            let data: Vec<u8> = (0..len).map(|_| b'a' + rnd.gen_range(0..4)).collect();
+            round_trip(&data);
+        }
+    }
+
+    #[test]
+    fn test_compressible() {
+        let data = vec![b'x'; 100000];
+        let compressed = round_trip(&data);
+        assert!(compressed.len() < data.len() / 10, "{}", compressed.len());
+
+        let data: Vec<u8> = "leveldb snappy block ".repeat(1000).into_bytes();
+        let compressed = round_trip(&data);
+        assert!(compressed.len() < data.len() / 8, "{}", compressed.len());
+    }
+
+    #[test]
+    fn test_incompressible() {
+        let mut rnd = StdRng::seed_from_u64(301);
+        let data = random_bytes(&mut rnd, 10000);
+        let compressed = round_trip(&data);
+        assert!(compressed.len() > data.len(), "{}", compressed.len());
+    }
+
+    #[test]
+    fn test_known_encoding() {
+        assert_eq!(vec![0x00], Snappy::compress(b""));
+        assert_eq!(vec![0x05, 0x10, b'h', b'e', b'l', b'l', b'o'], Snappy::compress(b"hello"));
+        // literal "a" + copy(offset 1, len 19)
+        assert_eq!(vec![0x14, 0x00, b'a', 0x4a, 0x01, 0x00], Snappy::compress(&[b'a'; 20]));
+
+        // 各种 element 的解码: literal 使用 1 个字节的长度, 1/2/4 字节 offset 的 copy
+        let mut compressed = vec![0x81, 0x01, 60 << 2, 61];
+        compressed.extend(0..62);
+        compressed.extend([0x01 | (3 << 2), 62]);
+        compressed.extend([0x02 | (9 << 2), 10, 0]);
+        compressed.extend([0x03 | (49 << 2), 1, 0, 0, 0]);
+        let mut expected: Vec<u8> = (0..62).collect();
+        expected.extend(0..7);
+        expected.extend([59, 60, 61]);
+        expected.extend(0..7);
+        expected.extend([6; 50]);
+        assert_eq!(129, expected.len());
+        assert_eq!(expected, Snappy::decompress(&compressed).unwrap());
+    }
+
+    #[test]
+    fn test_corruption() {
+        let data = "0123456789".repeat(100).into_bytes();
+        let compressed = Snappy::compress(&data);
+
+        // 截断
+        for len in [0, 1, compressed.len() / 2, compressed.len() - 1] {
+            assert!(Snappy::decompress(&compressed[..len]).err().unwrap().is_corruption());
+        }
+        // 原始长度不一致
+        let mut bad = compressed.clone();
+        bad[0] += 1;
+        assert!(Snappy::decompress(&bad).is_err());
+        // 长度的 varint 不完整
+        assert!(Snappy::uncompressed_length(&[0x80, 0x80]).is_err());
+        assert!(Snappy::uncompressed_length(&[0xff, 0xff, 0xff, 0xff, 0xff]).is_err());
+        // copy 的 offset 超出已解压的数据
+        assert!(Snappy::decompress(&[0x04, 0x02 | (3 << 2), 1, 0]).is_err());
+        assert!(Snappy::decompress(&[0x04, 0x00, b'a', 0x02 | (2 << 2), 0, 0]).is_err());
+        // copy 超过原始长度
+        assert!(Snappy::decompress(&[0x02, 0x00, b'a', 0x02 | (2 << 2), 1, 0]).is_err());
+    }
+}
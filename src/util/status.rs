@@ -9,7 +9,7 @@ use crate::util::status::LevelError::{KCorruption, KIOError, KInvalidArgument, K
 /// db 中的返回状态，将错误号和错误信息封装成Status类，统一进行处理。
 /// 在 leveldb的实现里， 为了节省空间Status将返回码(code), 错误信息message及长度打包存储于一个字符串数组中， 来存储错误信息。
 /// 在该项目中， 使用LevelError 和 Slice 存储错误信息
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Status {
     err: LevelError,
     msg: Slice
@@ -201,7 +201,7 @@ impl Status {
 // }
 
 /// Status 的状态
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum LevelError {
     KOk,
     KNotFound,
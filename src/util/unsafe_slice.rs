@@ -37,6 +37,18 @@ impl UnsafeSlice {
 
 
 
+    /// 直接使用一段已有内存构造 UnsafeSlice, 不会拷贝数据
+    ///
+    /// # Safety
+    /// 调用方需要保证 UnsafeSlice 使用期间, ptr 指向的 len 字节内存一直有效且不被修改
+    #[inline]
+    pub unsafe fn from_raw_parts(ptr: *const u8, len: usize) -> Self {
+        Self {
+            ptr: ptr as *mut u8,
+            len,
+        }
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.len